@@ -11,14 +11,14 @@ use rubato::sinc_interpolator::sinc_interpolator_neon::NeonInterpolator;
 use rubato::sinc_interpolator::sinc_interpolator_sse::SseInterpolator;
 
 use rubato::{
-    FastFixedIn, FftFixedIn, PolynomialDegree, Resampler, SincFixedIn, SincInterpolationType,
-    WindowFunction,
+    FastFixedIn, FftFixedIn, PolynomialDegree, Resampler, SincFixedIn, SincInterpolationParameters,
+    SincInterpolationType, WindowFunction,
 };
 
 fn bench_fftfixedin(c: &mut Criterion) {
     let chunksize = 1024;
     let mut resampler = FftFixedIn::<f64>::new(44100, 192000, 1024, 2, 1).unwrap();
-    let waveform = vec![vec![0.0 as f64; chunksize]; 1];
+    let waveform = vec![vec![0.0_f64; chunksize]; 1];
     c.bench_function("FftFixedIn f64", |b| {
         b.iter(|| resampler.process(black_box(&waveform), None).unwrap())
     });
@@ -27,12 +27,121 @@ fn bench_fftfixedin(c: &mut Criterion) {
 fn bench_fftfixedin_32(c: &mut Criterion) {
     let chunksize = 1024;
     let mut resampler = FftFixedIn::<f32>::new(44100, 192000, 1024, 2, 1).unwrap();
-    let waveform = vec![vec![0.0 as f32; chunksize]; 1];
+    let waveform = vec![vec![0.0_f32; chunksize]; 1];
     c.bench_function("FftFixedIn f32", |b| {
         b.iter(|| resampler.process(black_box(&waveform), None).unwrap())
     });
 }
 
+/// A logger that accepts all records and discards them.
+struct NullLogger;
+
+impl log::Log for NullLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, _record: &log::Record) {}
+
+    fn flush(&self) {}
+}
+
+static NULL_LOGGER: NullLogger = NullLogger;
+
+/// Measure the steady-state processing time with all log levels enabled,
+/// using f64 samples and a scalar interpolator with sinc_len 256.
+/// Compare against "scalar async cubic   64", which uses the same settings,
+/// to see the overhead from logging.
+/// This only logs anything when the `log` feature is enabled:
+/// `cargo bench --features log -- "async cubic"`
+fn bench_sinc_logging(c: &mut Criterion) {
+    let _ = log::set_logger(&NULL_LOGGER);
+    log::set_max_level(log::LevelFilter::Trace);
+    let chunksize = 1024;
+    let interpolator =
+        ScalarInterpolator::<f64>::new(256, 256, 0.947_337_15, WindowFunction::BlackmanHarris2);
+    let mut resampler = SincFixedIn::<f64>::new_with_interpolator(
+        192000_f64 / 44100_f64,
+        1.1,
+        SincInterpolationType::Cubic,
+        Box::new(interpolator),
+        chunksize,
+        1,
+    )
+    .unwrap();
+    let waveform = vec![vec![0.0_f64; chunksize]; 1];
+    c.bench_function("logging async cubic f64 sinc_len 256", |b| {
+        b.iter(|| resampler.process(black_box(&waveform), None).unwrap())
+    });
+    log::set_max_level(log::LevelFilter::Off);
+}
+
+/// Measure the processing time for stereo input, where both channels are interpolated in a single pass.
+/// Compare against twice the time of the mono "avx async cubic   64"
+/// (or the best interpolator for the platform) to see the gain from the interleaved layout.
+fn bench_sinc_stereo(c: &mut Criterion) {
+    let chunksize = 1024;
+    let parameters = SincInterpolationParameters {
+        sinc_len: 256,
+        f_cutoff: 0.947_337_15,
+        interpolation: SincInterpolationType::Cubic,
+        oversampling_factor: 256,
+        window: WindowFunction::BlackmanHarris2,
+    };
+    let mut resampler =
+        SincFixedIn::<f64>::new(192000_f64 / 44100_f64, 1.1, parameters, chunksize, 2).unwrap();
+    let waveform = vec![vec![0.0_f64; chunksize]; 2];
+    c.bench_function("stereo async cubic   64", |b| {
+        b.iter(|| resampler.process(black_box(&waveform), None).unwrap())
+    });
+}
+
+/// Measure a 32-channel resampler with the separate per-channel buffers
+/// and with the interleaved buffer, to see the gain from the interleaved layout.
+fn bench_sinc_multichannel(c: &mut Criterion) {
+    let chunksize = 1024;
+    let channels = 32;
+    let parameters = SincInterpolationParameters {
+        sinc_len: 256,
+        f_cutoff: 0.947_337_15,
+        interpolation: SincInterpolationType::Cubic,
+        oversampling_factor: 256,
+        window: WindowFunction::BlackmanHarris2,
+    };
+    let waveform = vec![vec![0.0_f32; chunksize]; channels];
+    for (interleaved, desc) in [
+        (false, "32 channels async cubic   32"),
+        (true, "32 channels interleaved async cubic   32"),
+    ] {
+        let mut resampler = SincFixedIn::<f32>::new(
+            192000_f64 / 44100_f64,
+            1.1,
+            parameters.clone(),
+            chunksize,
+            channels,
+        )
+        .unwrap();
+        resampler.set_interleaved_buffer(interleaved);
+        c.bench_function(desc, |b| {
+            b.iter(|| resampler.process(black_box(&waveform), None).unwrap())
+        });
+    }
+}
+
+/// Measure the generation of the sinc table, which dominates the time for creating a resampler.
+fn bench_sinc_table(c: &mut Criterion) {
+    c.bench_function("sinc table 256x256", |b| {
+        b.iter(|| {
+            ScalarInterpolator::<f64>::new(
+                black_box(256),
+                black_box(256),
+                0.947_337_15,
+                WindowFunction::BlackmanHarris2,
+            )
+        })
+    });
+}
+
 /// Helper to unwrap the constructed interpolator if appropriate.
 macro_rules! unwrap_helper {
     (infallible $var:ident) => {
@@ -48,7 +157,7 @@ macro_rules! bench_async_resampler {
         fn $f(c: &mut Criterion) {
             let chunksize = 1024;
             let sinc_len = 256;
-            let f_cutoff = 0.9473371669037001;
+            let f_cutoff = 0.947_337_15;
             let oversampling_factor = 256;
             let window = WindowFunction::BlackmanHarris2;
             let resample_ratio = 192000 as f64 / 44100 as f64;
@@ -355,6 +464,10 @@ criterion_group!(
     benches,
     bench_fftfixedin,
     bench_fftfixedin_32,
+    bench_sinc_logging,
+    bench_sinc_stereo,
+    bench_sinc_multichannel,
+    bench_sinc_table,
     bench_fast_async_septic_32,
     bench_fast_async_quintic_32,
     bench_fast_async_cubic_32,
@@ -390,6 +503,10 @@ criterion_group!(
     benches,
     bench_fftfixedin,
     bench_fftfixedin_32,
+    bench_sinc_logging,
+    bench_sinc_stereo,
+    bench_sinc_multichannel,
+    bench_sinc_table,
     bench_fast_async_septic_32,
     bench_fast_async_quintic_32,
     bench_fast_async_cubic_32,
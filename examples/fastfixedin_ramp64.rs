@@ -1,3 +1,19 @@
+//! A resampler app that reads a raw file of little-endian 64 bit floats, and writes the output in the same format.
+//! The command line arguments are input filename, output filename, input samplerate, output samplerate,
+//! number of channels, final relative ratio in percent, and ramp duration in seconds.
+//! To resample the file `sine_f64_2ch.raw` from 44.1kHz to 192kHz, and assuming the file has two channels,
+//! and that the resampling ratio should be ramped to 150% during 3 seconds, the command is:
+//! ```
+//! cargo run --release --example fastfixedin_ramp64 sine_f64_2ch.raw test.raw 44100 192000 2 150 3
+//! ```
+//! There are two helper python scripts for testing. `makesineraw.py` simply writes a stereo file
+//! with a 1 second long 1kHz tone (at 44.1kHz). This script takes no aruments. Modify as needed to create other test files.
+//! To analyze the result, use the `analyze_result.py` script. This takes three arguments: number of channels, samplerate, and number of bits per sample (32 or 64).
+//! Example, to analyze the file created above:
+//! ```
+//! python examples/analyze_result.py test.raw 2 192000 64
+//! ```
+
 extern crate rubato;
 use rubato::{FastFixedIn, PolynomialDegree, Resampler};
 use std::convert::TryInto;
@@ -12,22 +28,6 @@ extern crate log;
 use env_logger::Builder;
 use log::LevelFilter;
 
-///! A resampler app that reads a raw file of little-endian 64 bit floats, and writes the output in the same format.
-///! The command line arguments are input filename, output filename, input samplerate, output samplerate,
-///! number of channels, final relative ratio in percent, and ramp duration in seconds.
-///! To resample the file `sine_f64_2ch.raw` from 44.1kHz to 192kHz, and assuming the file has two channels,
-///  and that the resampling ratio should be ramped to 150% during 3 seconds, the command is:
-///! ```
-///! cargo run --release --example fastfixedin_ramp64 sine_f64_2ch.raw test.raw 44100 192000 2 150 3
-///! ```
-///! There are two helper python scripts for testing. `makesineraw.py` simply writes a stereo file
-///! with a 1 second long 1kHz tone (at 44.1kHz). This script takes no aruments. Modify as needed to create other test files.
-///! To analyze the result, use the `analyze_result.py` script. This takes three arguments: number of channels, samplerate, and number of bits per sample (32 or 64).
-///! Example, to analyze the file created above:
-///! ```
-///! python examples/analyze_result.py test.raw 2 192000 64
-///! ```
-
 /// Helper to read frames from a buffer
 fn read_frames<R: Read + Seek>(inbuffer: &mut R, nbr: usize, channels: usize) -> Vec<Vec<f64>> {
     let mut buffer = vec![0u8; 8];
@@ -0,0 +1,135 @@
+extern crate rubato;
+use rubato::{
+    calculate_cutoff, Endianness, Resampler, SampleEncoding, SampleFormat, SincFixedIn,
+    SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+use std::convert::TryInto;
+use std::env;
+use std::fs::File;
+use std::io::prelude::{Read, Write};
+use std::io::{BufReader, BufWriter};
+
+const CHUNK_SIZE: usize = 1024;
+
+// An app that transcodes a raw file of little-endian 64 bit floats to a new sample rate and sample format.
+// The command line arguments are input filename, output filename, input samplerate, output samplerate,
+// number of channels, and output format.
+// The output format is one of S8, U8, S16LE, S16BE, S24LE, S24BE, S32LE, S32BE, F32LE, F32BE, F64LE, F64BE.
+// To transcode the file `sine_f64_2ch.raw` from 44.1kHz to 48kHz 16-bit integers, assuming the file has two channels, the command is:
+// ```
+// cargo run --release --example transcode_raw sine_f64_2ch.raw test.raw 44100 48000 2 S16LE
+// ```
+// The input file can be created with the `makesineraw.py` script.
+
+/// Helper to parse a format name like "S24LE" to a SampleFormat.
+fn parse_format(name: &str) -> SampleFormat {
+    let (encoding, rest) = match name.split_at(1) {
+        ("S", rest) => (SampleEncoding::SignedInt, rest),
+        ("U", rest) => (SampleEncoding::UnsignedInt, rest),
+        ("F", rest) => (SampleEncoding::Float, rest),
+        _ => panic!("Unknown sample format {}", name),
+    };
+    let (bits, endianness) = if let Some(bits) = rest.strip_suffix("LE") {
+        (bits, Endianness::Little)
+    } else if let Some(bits) = rest.strip_suffix("BE") {
+        (bits, Endianness::Big)
+    } else {
+        (rest, Endianness::Little)
+    };
+    let bits = bits
+        .parse::<usize>()
+        .expect("Can't parse the number of bits");
+    SampleFormat::new(bits, encoding, endianness)
+}
+
+/// Helper to read an entire file of interleaved 64 bit floats to memory, as one vector per channel.
+fn read_file<R: Read>(input: &mut R, channels: usize) -> Vec<Vec<f64>> {
+    let mut raw = Vec::new();
+    input.read_to_end(&mut raw).unwrap();
+    let mut waves = vec![Vec::new(); channels];
+    for frame in raw.chunks_exact(8 * channels) {
+        for (wave, bytes) in waves.iter_mut().zip(frame.chunks_exact(8)) {
+            wave.push(f64::from_le_bytes(bytes.try_into().unwrap()));
+        }
+    }
+    waves
+}
+
+fn main() {
+    let file_in = env::args().nth(1).expect("Please specify an input file.");
+    let file_out = env::args().nth(2).expect("Please specify an output file.");
+    let fs_in = env::args()
+        .nth(3)
+        .expect("Please specify an input sample rate")
+        .parse::<usize>()
+        .unwrap();
+    let fs_out = env::args()
+        .nth(4)
+        .expect("Please specify an output sample rate")
+        .parse::<usize>()
+        .unwrap();
+    let channels = env::args()
+        .nth(5)
+        .expect("Please specify number of channels")
+        .parse::<usize>()
+        .unwrap();
+    let format = parse_format(
+        &env::args()
+            .nth(6)
+            .expect("Please specify an output format, for example S16LE"),
+    );
+    println!(
+        "Transcoding {} to {}, from {} Hz to {} Hz, output format {:?}",
+        file_in, file_out, fs_in, fs_out, format
+    );
+
+    let mut reader = BufReader::new(File::open(file_in).expect("Can't open file"));
+    let mut indata = read_file(&mut reader, channels);
+    let nbr_input_frames = indata[0].len();
+
+    let window = WindowFunction::BlackmanHarris2;
+    let params = SincInterpolationParameters {
+        sinc_len: 256,
+        f_cutoff: calculate_cutoff(256, window),
+        interpolation: SincInterpolationType::Cubic,
+        oversampling_factor: 256,
+        window,
+    };
+    let mut resampler = SincFixedIn::<f64>::new(
+        fs_out as f64 / fs_in as f64,
+        1.0,
+        params,
+        CHUNK_SIZE,
+        channels,
+    )
+    .unwrap();
+
+    // Pad the input with silence to a whole number of chunks.
+    // The padding also flushes the end of the input out of the resampler.
+    let padded_frames = (nbr_input_frames + 2 * CHUNK_SIZE) / CHUNK_SIZE * CHUNK_SIZE;
+    for wave in indata.iter_mut() {
+        wave.resize(padded_frames, 0.0);
+    }
+    let nbr_output_frames = (nbr_input_frames as f64 * fs_out as f64 / fs_in as f64) as usize;
+
+    let frame_bytes = channels * format.bytes_per_sample();
+    let mut raw = vec![0u8; resampler.output_frames_max() * frame_bytes];
+    let mut scratch = Vec::new();
+    let mut writer = BufWriter::new(File::create(file_out).expect("Can't create file"));
+    let mut frames_to_write = nbr_output_frames;
+    for start in (0..padded_frames).step_by(CHUNK_SIZE) {
+        let chunk = indata
+            .iter()
+            .map(|wave| &wave[start..start + CHUNK_SIZE])
+            .collect::<Vec<_>>();
+        let frames = resampler
+            .process_into_bytes(&chunk, &mut raw, format, channels, &mut scratch)
+            .unwrap();
+        // The first output frame is aligned with the first input frame,
+        // and only the output from the padding at the end is dropped.
+        let write = frames.min(frames_to_write);
+        writer.write_all(&raw[..write * frame_bytes]).unwrap();
+        frames_to_write -= write;
+    }
+    println!("Wrote {} frames", nbr_output_frames - frames_to_write);
+}
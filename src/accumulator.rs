@@ -0,0 +1,225 @@
+use crate::error::ResampleResult;
+use crate::{Resampler, Sample};
+
+/// A helper that owns a resampler and collects its output
+/// in one growing vector per channel.
+///
+/// This is useful when the complete output is needed at the end,
+/// for example when resampling a file.
+/// Each call to [process_append](Accumulator::process_append) resamples one chunk
+/// and appends the result, and [finish](Accumulator::finish) returns the collected output.
+///
+/// For gapless playback, a number of leading and trailing input frames can be trimmed
+/// from the output with [set_input_trim](Accumulator::set_input_trim).
+pub struct Accumulator<T, R> {
+    resampler: R,
+    wave_out: Vec<Vec<T>>,
+    trim: Option<(usize, usize)>,
+    frames_in: usize,
+    stream_start: usize,
+}
+
+impl<T, R> Accumulator<T, R>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    /// Create a new Accumulator, wrapping the given resampler.
+    pub fn new(resampler: R) -> Self {
+        let wave_out = vec![Vec::new(); resampler.nbr_channels()];
+        Accumulator {
+            resampler,
+            wave_out,
+            trim: None,
+            frames_in: 0,
+            stream_start: 0,
+        }
+    }
+
+    /// Trim `leading` frames from the start and `trailing` frames from the end of the input,
+    /// for example the encoder delay and padding of a gapless encoded file.
+    ///
+    /// The trim is applied when the stream is [flushed](Accumulator::flush),
+    /// since the end of the input is only known then.
+    /// It is given in input frames, and is scaled by the resampling ratio.
+    /// For a stream of `N` input frames, the resampler gives `ceil(N * ratio)` output frames
+    /// after its initial delay, see [finish](Resampler::finish),
+    /// and the delay is taken as the number of frames in excess of this.
+    /// The delay is removed, together with the first `ceil(leading * ratio)` frames,
+    /// and the stream is then cut to `ceil((N - trailing) * ratio) - ceil(leading * ratio)` frames.
+    /// This keeps the frames that lie within the untrimmed input,
+    /// with the first output frame at the input frame `leading`,
+    /// or the closest following output frame when `leading * ratio` is not an integer.
+    /// The trim is calculated with the current ratio of the resampler,
+    /// and is only exact when the ratio is not changed during the stream.
+    pub fn set_input_trim(&mut self, leading: usize, trailing: usize) {
+        self.trim = Some((leading, trailing));
+    }
+
+    /// End the current stream, by appending the remaining output from
+    /// [finish](Resampler::finish) of the resampler, and applying the trailing trim if one is set.
+    /// This resets the resampler, and a new stream can be started,
+    /// with the same trim as the previous one.
+    /// Returns the number of frames of the finished stream.
+    /// Note that this method allocates.
+    pub fn flush(&mut self) -> ResampleResult<usize> {
+        let remaining = self.resampler.finish()?;
+        for (chan_out, chan_remaining) in self.wave_out.iter_mut().zip(remaining.iter()) {
+            chan_out.extend_from_slice(chan_remaining);
+        }
+        if let Some((leading, trailing)) = self.trim {
+            let stream_frames = self.frames() - self.stream_start;
+            let delay = stream_frames.saturating_sub(self.output_frames_before(self.frames_in));
+            let end = self.frames_in.saturating_sub(trailing).max(leading);
+            let start = self.stream_start + delay + self.output_frames_before(leading);
+            let stop = self.stream_start + delay + self.output_frames_before(end);
+            for chan_out in self.wave_out.iter_mut() {
+                chan_out.truncate(stop.min(chan_out.len()));
+                chan_out.drain(self.stream_start..start.min(chan_out.len()));
+            }
+        }
+        let stream_frames = self.frames() - self.stream_start;
+        self.frames_in = 0;
+        self.stream_start = self.frames();
+        Ok(stream_frames)
+    }
+
+    /// Resample a chunk of audio, and append the output to the collected output.
+    /// The input and `active_channels_mask` are given like for
+    /// [process_into_buffer](Resampler::process_into_buffer).
+    ///
+    /// Inactive channels are extended with silence,
+    /// so that all channels keep the same length.
+    /// The number of input frames consumed and the number of output frames appended
+    /// is returned in a tuple, `(input_frames, output_frames)`.
+    pub fn process_append<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_max = self.resampler.output_frames_max();
+        let start = self.frames();
+        for chan_out in self.wave_out.iter_mut() {
+            chan_out.resize(start + frames_max, T::zero());
+        }
+        let mut buffers = self
+            .wave_out
+            .iter_mut()
+            .map(|chan_out| &mut chan_out[start..])
+            .collect::<Vec<_>>();
+        let result =
+            self.resampler
+                .process_into_buffer(wave_in, &mut buffers, active_channels_mask);
+        let frames_out = result.as_ref().map(|(_, frames)| *frames).unwrap_or(0);
+        for chan_out in self.wave_out.iter_mut() {
+            chan_out.truncate(start + frames_out);
+        }
+        if let Ok((frames_in, _)) = result {
+            self.frames_in += frames_in;
+        }
+        result
+    }
+
+    /// Get the number of output frames before the input position `frames`,
+    /// not counting the delay.
+    fn output_frames_before(&self, frames: usize) -> usize {
+        (frames as f64 * self.resampler.target_ratio()).ceil() as usize
+    }
+
+    /// Get the number of frames collected so far.
+    pub fn frames(&self) -> usize {
+        self.wave_out.first().map(|chan| chan.len()).unwrap_or(0)
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn resampler(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler,
+    /// for example for changing the resampling ratio.
+    pub fn resampler_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Consume the Accumulator and return the collected output, as one vector per channel.
+    /// Any remaining output of the resampler is not included, see [flush](Accumulator::flush).
+    pub fn finish(self) -> Vec<Vec<T>> {
+        self.wave_out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Accumulator;
+    use crate::{FastFixedIn, PolynomialDegree, Resampler};
+    use approx::assert_abs_diff_eq;
+    use rand::Rng;
+
+    #[test]
+    fn matches_concatenated() {
+        let mut rng = rand::thread_rng();
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.3, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mut accumulator = Accumulator::new(
+            FastFixedIn::<f64>::new(1.3, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap(),
+        );
+        let mut expected = vec![Vec::new(); 2];
+        for n in 0..10 {
+            let waves = (0..2)
+                .map(|_| (0..256).map(|_| rng.gen::<f64>() - 0.5).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            if n == 5 {
+                resampler.set_resample_ratio_relative(1.05, true).unwrap();
+                accumulator
+                    .resampler_mut()
+                    .set_resample_ratio_relative(1.05, true)
+                    .unwrap();
+            }
+            let output = resampler.process(&waves, None).unwrap();
+            let (frames_in, frames_out) = accumulator.process_append(&waves, None).unwrap();
+            assert_eq!(frames_in, 256);
+            assert_eq!(frames_out, output[0].len());
+            for (exp, out) in expected.iter_mut().zip(output) {
+                exp.extend_from_slice(&out);
+            }
+            assert_eq!(accumulator.frames(), expected[0].len());
+        }
+        assert_eq!(accumulator.finish(), expected);
+    }
+
+    #[test]
+    fn input_trim() {
+        let resampler = FastFixedIn::<f64>::new(2.0, 1.1, PolynomialDegree::Cubic, 256, 1).unwrap();
+        let mut accumulator = Accumulator::new(resampler);
+        accumulator.set_input_trim(100, 60);
+        let ramp = (0..1024).map(|n| n as f64).collect::<Vec<_>>();
+        for chunk in ramp.chunks(256) {
+            accumulator.process_append(&[chunk], None).unwrap();
+        }
+        assert_eq!(accumulator.flush().unwrap(), 1928 - 200);
+        let output = accumulator.finish();
+        // ceil(964 * 2.0) - ceil(100 * 2.0)
+        assert_eq!(output[0].len(), 1928 - 200);
+        // The output starts at the first untrimmed input frame,
+        // and the ramp is reproduced exactly by the cubic interpolation.
+        for (n, value) in output[0].iter().enumerate().take(1200) {
+            assert_abs_diff_eq!(*value, 100.0 + n as f64 / 2.0, epsilon = 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn error_appends_nothing() {
+        let mut accumulator = Accumulator::new(
+            FastFixedIn::<f64>::new(1.3, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap(),
+        );
+        accumulator
+            .process_append(&vec![vec![0.0f64; 256]; 2], None)
+            .unwrap();
+        let frames = accumulator.frames();
+        assert!(accumulator
+            .process_append(&vec![vec![0.0f64; 100]; 2], None)
+            .is_err());
+        assert_eq!(accumulator.frames(), frames);
+    }
+}
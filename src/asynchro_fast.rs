@@ -1,5 +1,9 @@
 use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
-use crate::{update_mask_from_buffers, validate_buffers, Resampler, Sample};
+use crate::{
+    change_nbr_channels, frames_before_end, next_frame_phase, reactivated_channels,
+    reserve_channels, update_mask_from_buffers, validate_buffers, validate_channels, Resampler,
+    Sample,
+};
 
 const POLYNOMIAL_LEN_U: usize = 8;
 const POLYNOMIAL_LEN_I: isize = 8;
@@ -51,6 +55,7 @@ pub struct FastFixedIn<T> {
     buffer: Vec<Vec<T>>,
     interpolation: PolynomialDegree,
     channel_mask: Vec<bool>,
+    max_output_frames: usize,
 }
 
 /// An asynchronous resampler that returns a fixed number of audio frames.
@@ -81,6 +86,7 @@ pub struct FastFixedOut<T> {
     buffer: Vec<Vec<T>>,
     interpolation: PolynomialDegree,
     channel_mask: Vec<bool>,
+    max_output_frames: usize,
 }
 
 /// Perform septic polynomial interpolation to get value at x.
@@ -207,7 +213,7 @@ where
     /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio * 10.0` and `resample_ratio / 10.0`.
     /// - `interpolation_type`: Degree of polynomial used for interpolation, see [PolynomialDegree].
     /// - `chunk_size`: Size of input data in frames.
-    /// - `nbr_channels`: Number of channels in input/output.
+    /// - `nbr_channels`: Number of channels in input/output, must be > 0.
     pub fn new(
         resample_ratio: f64,
         max_resample_ratio_relative: f64,
@@ -221,6 +227,7 @@ where
         );
 
         validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        validate_channels(nbr_channels)?;
 
         let buffer = vec![vec![T::zero(); chunk_size + 2 * POLYNOMIAL_LEN_U]; nbr_channels];
 
@@ -237,42 +244,88 @@ where
             buffer,
             interpolation: interpolation_type,
             channel_mask,
+            max_output_frames: usize::MAX,
         })
     }
+
+    /// Allocate the internal buffers for up to `max_channels` channels,
+    /// see [SincFixedIn::set_max_channels](crate::SincFixedIn::set_max_channels).
+    pub fn set_max_channels(&mut self, max_channels: usize) {
+        reserve_channels(&mut self.buffer, &mut self.channel_mask, max_channels);
+    }
+
+    /// Get the maximum number of channels that the internal buffers are allocated for.
+    pub fn max_channels(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Change the number of channels without allocating,
+    /// see [SincFixedIn::set_nbr_channels](crate::SincFixedIn::set_nbr_channels).
+    pub fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        change_nbr_channels(&mut self.buffer, &mut self.channel_mask, nbr_channels)?;
+        self.nbr_channels = nbr_channels;
+        Ok(())
+    }
+
+    /// Get the fractional phase of the resampler, as a value in the range `[0, 1)`.
+    /// See [SincFixedIn::fractional_phase](crate::SincFixedIn::fractional_phase).
+    pub fn fractional_phase(&self) -> f64 {
+        next_frame_phase(self.last_index, self.resample_ratio)
+    }
 }
 
-impl<T> Resampler<T> for FastFixedIn<T>
+impl<T> FastFixedIn<T>
 where
     T: Sample,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    /// Process a chunk, optionally skipping the validation of the buffers.
+    fn process_buffers<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
         &mut self,
         wave_in: &[Vin],
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
+        checked: bool,
     ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
-        } else {
-            update_mask_from_buffers(&mut self.channel_mask);
-        };
-
         // Set length to chunksize*ratio plus a safety margin of 10 elements.
         let needed_len = (self.chunk_size as f64
             * (0.5 * self.resample_ratio + 0.5 * self.target_ratio)
             + 10.0) as usize;
 
-        validate_buffers(
-            wave_in,
-            wave_out,
-            &self.channel_mask,
-            self.nbr_channels,
-            self.chunk_size,
-            needed_len,
-        )?;
+        if checked {
+            validate_buffers(
+                wave_in,
+                wave_out,
+                active_channels_mask,
+                self.nbr_channels,
+                self.chunk_size,
+                needed_len,
+            )?;
+        } else {
+            debug_assert!(
+                validate_buffers(
+                    wave_in,
+                    wave_out,
+                    active_channels_mask,
+                    self.nbr_channels,
+                    self.chunk_size,
+                    needed_len,
+                )
+                .is_ok(),
+                "Invalid buffers passed to process_into_buffer_unchecked"
+            );
+        }
+
+        for chan in reactivated_channels(&self.channel_mask, active_channels_mask) {
+            self.buffer[chan].iter_mut().for_each(|s| *s = T::zero());
+        }
+        if let Some(mask) = active_channels_mask {
+            self.channel_mask.copy_from_slice(mask);
+        } else {
+            update_mask_from_buffers(&mut self.channel_mask);
+        };
 
         // Update buffer with new data.
-        for buf in self.buffer.iter_mut() {
+        for buf in self.buffer[..self.nbr_channels].iter_mut() {
             buf.copy_within(self.chunk_size..self.chunk_size + 2 * POLYNOMIAL_LEN_U, 0);
         }
 
@@ -439,6 +492,29 @@ where
         );
         Ok((self.chunk_size, n))
     }
+}
+
+impl<T> Resampler<T> for FastFixedIn<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_buffers(wave_in, wave_out, active_channels_mask, true)
+    }
+
+    unsafe fn process_into_buffer_unchecked<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_buffers(wave_in, wave_out, active_channels_mask, false)
+    }
 
     fn output_frames_max(&self) -> usize {
         // Set length to chunksize*ratio plus a safety margin of 10 elements.
@@ -451,10 +527,33 @@ where
             as usize
     }
 
+    fn output_frames_remaining(&self) -> usize {
+        frames_before_end(self.last_index, self.resample_ratio)
+    }
+
     fn output_delay(&self) -> usize {
         (POLYNOMIAL_LEN_U as f64 * self.resample_ratio / 2.0) as usize
     }
 
+    fn set_max_output_frames(&mut self, max_frames: usize) {
+        self.max_output_frames = max_frames;
+    }
+
+    fn max_output_frames(&self) -> usize {
+        self.max_output_frames
+    }
+
+    fn target_ratio(&self) -> f64 {
+        self.target_ratio
+    }
+
+    fn ratio_bounds(&self) -> (f64, f64) {
+        (
+            self.resample_ratio_original / self.max_relative_ratio,
+            self.resample_ratio_original * self.max_relative_ratio,
+        )
+    }
+
     fn nbr_channels(&self) -> usize {
         self.nbr_channels
     }
@@ -513,7 +612,7 @@ where
     /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio * 10.0` and `resample_ratio / 10.0`.
     /// - `interpolation_type`: Degree of polynomial used for interpolation, see [PolynomialDegree].
     /// - `chunk_size`: Size of output data in frames.
-    /// - `nbr_channels`: Number of channels in input/output.
+    /// - `nbr_channels`: Number of channels in input/output, must be > 0.
     pub fn new(
         resample_ratio: f64,
         max_resample_ratio_relative: f64,
@@ -526,6 +625,7 @@ where
             resample_ratio, chunk_size, nbr_channels,
         );
         validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        validate_channels(nbr_channels)?;
 
         let needed_input_size =
             (chunk_size as f64 / resample_ratio).ceil() as usize + POLYNOMIAL_LEN_U / 2;
@@ -548,35 +648,81 @@ where
             buffer,
             interpolation: interpolation_type,
             channel_mask,
+            max_output_frames: usize::MAX,
         })
     }
+
+    /// Allocate the internal buffers for up to `max_channels` channels,
+    /// see [SincFixedIn::set_max_channels](crate::SincFixedIn::set_max_channels).
+    pub fn set_max_channels(&mut self, max_channels: usize) {
+        reserve_channels(&mut self.buffer, &mut self.channel_mask, max_channels);
+    }
+
+    /// Get the maximum number of channels that the internal buffers are allocated for.
+    pub fn max_channels(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Change the number of channels without allocating,
+    /// see [SincFixedIn::set_nbr_channels](crate::SincFixedIn::set_nbr_channels).
+    pub fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        change_nbr_channels(&mut self.buffer, &mut self.channel_mask, nbr_channels)?;
+        self.nbr_channels = nbr_channels;
+        Ok(())
+    }
+
+    /// Get the fractional phase of the resampler, as a value in the range `[0, 1)`.
+    /// See [SincFixedIn::fractional_phase](crate::SincFixedIn::fractional_phase).
+    pub fn fractional_phase(&self) -> f64 {
+        next_frame_phase(self.last_index, self.resample_ratio)
+    }
 }
 
-impl<T> Resampler<T> for FastFixedOut<T>
+impl<T> FastFixedOut<T>
 where
     T: Sample,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    /// Process a chunk, optionally skipping the validation of the buffers.
+    fn process_buffers<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
         &mut self,
         wave_in: &[Vin],
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
+        checked: bool,
     ) -> ResampleResult<(usize, usize)> {
+        if checked {
+            validate_buffers(
+                wave_in,
+                wave_out,
+                active_channels_mask,
+                self.nbr_channels,
+                self.needed_input_size,
+                self.chunk_size,
+            )?;
+        } else {
+            debug_assert!(
+                validate_buffers(
+                    wave_in,
+                    wave_out,
+                    active_channels_mask,
+                    self.nbr_channels,
+                    self.needed_input_size,
+                    self.chunk_size,
+                )
+                .is_ok(),
+                "Invalid buffers passed to process_into_buffer_unchecked"
+            );
+        }
+
+        for chan in reactivated_channels(&self.channel_mask, active_channels_mask) {
+            self.buffer[chan].iter_mut().for_each(|s| *s = T::zero());
+        }
         if let Some(mask) = active_channels_mask {
             self.channel_mask.copy_from_slice(mask);
         } else {
             update_mask_from_buffers(&mut self.channel_mask);
         };
-
-        validate_buffers(
-            wave_in,
-            wave_out,
-            &self.channel_mask,
-            self.nbr_channels,
-            self.needed_input_size,
-            self.chunk_size,
-        )?;
-        for buf in self.buffer.iter_mut() {
+        for buf in self.buffer[..self.nbr_channels].iter_mut() {
             buf.copy_within(
                 self.current_buffer_fill..self.current_buffer_fill + 2 * POLYNOMIAL_LEN_U,
                 0,
@@ -737,6 +883,29 @@ where
         );
         Ok((input_frames_used, self.chunk_size))
     }
+}
+
+impl<T> Resampler<T> for FastFixedOut<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_buffers(wave_in, wave_out, active_channels_mask, true)
+    }
+
+    unsafe fn process_into_buffer_unchecked<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_buffers(wave_in, wave_out, active_channels_mask, false)
+    }
 
     fn input_frames_max(&self) -> usize {
         (self.chunk_size as f64 / self.resample_ratio_original * self.max_relative_ratio).ceil()
@@ -761,10 +930,37 @@ where
         self.chunk_size
     }
 
+    fn output_frames_remaining(&self) -> usize {
+        frames_before_end(self.last_index, self.resample_ratio)
+    }
+
+    fn min_input_for_output(&self) -> usize {
+        (self.last_index + 1.0 / self.resample_ratio + POLYNOMIAL_LEN_U as f64).ceil() as usize
+    }
+
     fn output_delay(&self) -> usize {
         (POLYNOMIAL_LEN_U as f64 * self.resample_ratio / 2.0) as usize
     }
 
+    fn set_max_output_frames(&mut self, max_frames: usize) {
+        self.max_output_frames = max_frames;
+    }
+
+    fn max_output_frames(&self) -> usize {
+        self.max_output_frames
+    }
+
+    fn target_ratio(&self) -> f64 {
+        self.target_ratio
+    }
+
+    fn ratio_bounds(&self) -> (f64, f64) {
+        (
+            self.resample_ratio_original / self.max_relative_ratio,
+            self.resample_ratio_original * self.max_relative_ratio,
+        )
+    }
+
     fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
         trace!("Change resample ratio to {}", new_ratio);
         if (new_ratio / self.resample_ratio_original >= 1.0 / self.max_relative_ratio)
@@ -814,9 +1010,44 @@ mod tests {
     use crate::PolynomialDegree;
     use crate::Resampler;
     use crate::{check_output, check_ratio};
-    use crate::{FastFixedIn, FastFixedOut};
+    use crate::{FastFixedIn, FastFixedOut, ResamplerConstructionError};
     use rand::Rng;
 
+    #[test]
+    fn fractional_phase() {
+        // Each output frame advances the position by 1.25 input frames.
+        let mut resampler =
+            FastFixedIn::<f64>::new(0.8, 1.0, PolynomialDegree::Cubic, 1024, 1).unwrap();
+        assert!((resampler.fractional_phase() - 0.25).abs() < 1.0e-12);
+        let waves = vec![vec![0.0; 1024]; 1];
+        let mut frames = 0;
+        for _ in 0..5 {
+            frames += resampler.process(&waves, None).unwrap()[0].len();
+            let expected = ((frames + 1) as f64 * 1.25).fract();
+            assert!((resampler.fractional_phase() - expected).abs() < 1.0e-9);
+        }
+        let mut resampler =
+            FastFixedOut::<f64>::new(0.8, 1.0, PolynomialDegree::Cubic, 1024, 1).unwrap();
+        for n in 1..5 {
+            let waves = vec![vec![0.0; resampler.input_frames_next()]; 1];
+            resampler.process(&waves, None).unwrap();
+            let expected = ((n * 1024 + 1) as f64 * 1.25).fract();
+            assert!((resampler.fractional_phase() - expected).abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn reject_zero_channels() {
+        assert!(matches!(
+            FastFixedIn::<f64>::new(1.2, 1.0, PolynomialDegree::Cubic, 1024, 0),
+            Err(ResamplerConstructionError::InvalidChannelCount(0))
+        ));
+        assert!(matches!(
+            FastFixedOut::<f64>::new(1.2, 1.0, PolynomialDegree::Cubic, 1024, 0),
+            Err(ResamplerConstructionError::InvalidChannelCount(0))
+        ));
+    }
+
     #[test]
     fn make_resampler_fi() {
         let mut resampler =
@@ -842,6 +1073,137 @@ mod tests {
         );
     }
 
+    #[test]
+    fn interleaved_i16() {
+        let mut resampler =
+            FastFixedIn::<f32>::new(1.5, 1.0, PolynomialDegree::Cubic, 512, 2).unwrap();
+        let mut reference =
+            FastFixedIn::<f32>::new(1.5, 1.0, PolynomialDegree::Cubic, 512, 2).unwrap();
+        // The right channel is loud enough to clip.
+        let waves = vec![
+            (0..512)
+                .map(|n| (n as f32 * 0.05).sin())
+                .collect::<Vec<f32>>(),
+            (0..512)
+                .map(|n| 1.5 * (n as f32 * 0.03).sin())
+                .collect::<Vec<f32>>(),
+        ];
+        let mut out = vec![0i16; 2 * resampler.output_frames_next()];
+        let mut scratch = Vec::new();
+        let frames = resampler
+            .process_into_interleaved_i16(&waves, &mut out, 2, &mut scratch)
+            .unwrap();
+        let out_ref = reference.process(&waves, None).unwrap();
+        assert_eq!(frames, out_ref[0].len());
+        for frame in 0..frames {
+            for chan in 0..2 {
+                let expected = (out_ref[chan][frame] as f64 * 32768.0)
+                    .round()
+                    .clamp(-32768.0, 32767.0) as i16;
+                assert_eq!(out[2 * frame + chan], expected);
+            }
+        }
+        assert!(out.contains(&i16::MAX));
+        let mut short = vec![0i16; 10];
+        assert!(resampler
+            .process_into_interleaved_i16(&waves, &mut short, 2, &mut scratch)
+            .is_err());
+    }
+
+    #[test]
+    fn min_input_for_output() {
+        let ratio = 48000.0 / 44100.0;
+        let mut resampler =
+            FastFixedOut::<f64>::new(ratio, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mut single =
+            FastFixedOut::<f64>::new(ratio, 1.0, PolynomialDegree::Cubic, 1, 2).unwrap();
+        assert_eq!(resampler.min_input_for_output(), single.input_frames_next());
+        for _ in 0..3 {
+            let frames = resampler.input_frames_next();
+            resampler
+                .process(&vec![vec![0.0f64; frames]; 2], None)
+                .unwrap();
+            let frames = single.min_input_for_output();
+            assert_eq!(frames, single.input_frames_next());
+            let out = single
+                .process(&vec![vec![0.0f64; frames]; 2], None)
+                .unwrap();
+            assert_eq!(out[0].len(), 1);
+        }
+        assert!(resampler.min_input_for_output() < resampler.input_frames_next());
+    }
+
+    #[test]
+    fn output_sample_rate() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(48000.0 / 44100.0, 1.1, PolynomialDegree::Cubic, 1024, 1)
+                .unwrap();
+        resampler.set_resample_ratio_relative(1.01, false).unwrap();
+        assert!((resampler.target_ratio() - 1.01 * 48000.0 / 44100.0).abs() < 1.0e-12);
+        let rate = resampler.output_sample_rate(44100.0);
+        assert!((rate - 48480.0).abs() < 1.0e-6);
+        let waves = vec![vec![0.0f64; 1024]];
+        let mut frames_out = 0;
+        for _ in 0..100 {
+            frames_out += resampler.process(&waves, None).unwrap()[0].len();
+        }
+        let measured = 44100.0 * frames_out as f64 / (100 * 1024) as f64;
+        assert!((measured - rate).abs() / rate < 1.0e-3);
+    }
+
+    #[test]
+    fn process_in_place() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(2.0, 1.0, PolynomialDegree::Cubic, 64, 2).unwrap();
+        let mut resampler_in_place =
+            FastFixedIn::<f64>::new(2.0, 1.0, PolynomialDegree::Cubic, 64, 2).unwrap();
+        let mut wave_out = resampler.output_buffer_allocate(true);
+        for block in 0..4 {
+            let wave_in = vec![
+                (0..64)
+                    .map(|n| (block * 64 + n) as f64)
+                    .collect::<Vec<f64>>();
+                2
+            ];
+            let mut buffer = wave_in.clone();
+            for chan in buffer.iter_mut() {
+                chan.resize(resampler_in_place.output_frames_max(), 0.0);
+            }
+            let (nbr_in, nbr_out) = resampler
+                .process_into_buffer(&wave_in, &mut wave_out, None)
+                .unwrap();
+            let (nbr_in_place, nbr_out_in_place) = resampler_in_place
+                .process_in_place_safe(&mut buffer, None)
+                .unwrap();
+            assert_eq!(nbr_in, nbr_in_place);
+            assert_eq!(nbr_out, nbr_out_in_place);
+            assert_eq!(wave_out[0][..nbr_out], buffer[0][..nbr_out]);
+            assert_eq!(wave_out[1][..nbr_out], buffer[1][..nbr_out]);
+        }
+    }
+
+    #[test]
+    fn finish_fi() {
+        let ratio = 48000.0 / 44100.0;
+        let mut resampler =
+            FastFixedIn::<f64>::new(ratio, 1.0, PolynomialDegree::Cubic, 1024, 2).unwrap();
+        let waves = vec![vec![0.5f64; 1024]; 2];
+        let mut frames_out = 0;
+        for _ in 0..5 {
+            frames_out += resampler.process(&waves, None).unwrap()[0].len();
+        }
+        let tail = resampler.finish().unwrap();
+        let total = frames_out + tail[0].len();
+        // The first output frame is centered 3.5 input frames before the first input frame.
+        let expected = ((5.0 * 1024.0 + 3.5) * ratio).ceil() as usize;
+        assert!(
+            (total as isize - expected as isize).abs() <= 1,
+            "got {} frames, expected {}",
+            total,
+            expected
+        );
+    }
+
     #[test]
     fn reset_resampler_fi() {
         let mut resampler =
@@ -907,14 +1269,9 @@ mod tests {
     #[test]
     fn make_resampler_fi_downsample() {
         // Replicate settings from reported issue.
-        let mut resampler = FastFixedIn::<f64>::new(
-            16000 as f64 / 96000 as f64,
-            1.0,
-            PolynomialDegree::Cubic,
-            1024,
-            2,
-        )
-        .unwrap();
+        let mut resampler =
+            FastFixedIn::<f64>::new(16000_f64 / 96000_f64, 1.0, PolynomialDegree::Cubic, 1024, 2)
+                .unwrap();
         let waves = vec![vec![0.0f64; 1024]; 2];
         let out = resampler.process(&waves, None).unwrap();
         assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
@@ -940,7 +1297,7 @@ mod tests {
     fn make_resampler_fi_upsample() {
         // Replicate settings from reported issue.
         let mut resampler = FastFixedIn::<f64>::new(
-            192000 as f64 / 44100 as f64,
+            192000_f64 / 44100_f64,
             1.0,
             PolynomialDegree::Cubic,
             1024,
@@ -981,6 +1338,21 @@ mod tests {
         assert_eq!(out[0].len(), 1024);
     }
 
+    #[test]
+    fn ratio_bounds() {
+        let mut resampler =
+            FastFixedOut::<f64>::new(1.5, 2.0, PolynomialDegree::Cubic, 1024, 2).unwrap();
+        assert_eq!(resampler.ratio_bounds(), (0.75, 3.0));
+        let (min, max) = resampler.ratio_bounds();
+        assert!(resampler.set_resample_ratio(min, false).is_ok());
+        assert!(resampler.set_resample_ratio(max, false).is_ok());
+        assert!(resampler.set_resample_ratio(0.99 * min, false).is_err());
+        assert!(resampler.set_resample_ratio(1.01 * max, false).is_err());
+        let resampler =
+            FastFixedIn::<f64>::new(1.5, 2.0, PolynomialDegree::Cubic, 1024, 2).unwrap();
+        assert_eq!(resampler.ratio_bounds(), (0.75, 3.0));
+    }
+
     #[test]
     fn reset_resampler_fo() {
         let mut resampler =
@@ -1227,4 +1599,47 @@ mod tests {
             FastFixedIn::<f32>::new(ratio, 100.0, PolynomialDegree::Cubic, 1024, 2).unwrap();
         check_ratio!(resampler, ratio, 1000);
     }
+
+    #[test]
+    fn unchecked_matches_checked() {
+        let ratio = 48000.0 / 44100.0;
+        let mut resampler =
+            FastFixedOut::<f64>::new(ratio, 1.1, PolynomialDegree::Cubic, 512, 2).unwrap();
+        let mut reference =
+            FastFixedOut::<f64>::new(ratio, 1.1, PolynomialDegree::Cubic, 512, 2).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut wave_out = resampler.output_buffer_allocate(true);
+        let mut wave_out_ref = reference.output_buffer_allocate(true);
+        for n in 0..10 {
+            resampler
+                .set_resample_ratio_relative(1.0 + 0.01 * n as f64, true)
+                .unwrap();
+            reference
+                .set_resample_ratio_relative(1.0 + 0.01 * n as f64, true)
+                .unwrap();
+            let frames = resampler.input_frames_next();
+            let waves = vec![(0..frames).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>(); 2];
+            let result = unsafe {
+                resampler
+                    .process_into_buffer_unchecked(&waves, &mut wave_out, None)
+                    .unwrap()
+            };
+            let result_ref = reference
+                .process_into_buffer(&waves, &mut wave_out_ref, None)
+                .unwrap();
+            assert_eq!(result, result_ref);
+            assert_eq!(wave_out, wave_out_ref);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn unchecked_asserts_in_debug() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.0, 1.0, PolynomialDegree::Cubic, 512, 2).unwrap();
+        let waves = vec![vec![0.0f64; 100]; 2];
+        let mut wave_out = resampler.output_buffer_allocate(true);
+        let _ = unsafe { resampler.process_into_buffer_unchecked(&waves, &mut wave_out, None) };
+    }
 }
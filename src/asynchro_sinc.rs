@@ -1,5 +1,12 @@
-use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::emphasis::{Emphasis, EmphasisMode};
+use crate::error::{
+    CpuFeature, MissingCpuFeature, ResampleError, ResampleResult, ResamplerConstructionError,
+};
+use crate::gain::{AutoScale, RmsMatcher};
 use crate::interpolation::*;
+use crate::multichannel::InterleavedBuffer;
+use crate::output_stage::OutputStage;
+use crate::sinc::make_sincs;
 #[cfg(target_arch = "x86_64")]
 use crate::sinc_interpolator::sinc_interpolator_avx::AvxInterpolator;
 #[cfg(target_arch = "aarch64")]
@@ -7,11 +14,17 @@ use crate::sinc_interpolator::sinc_interpolator_neon::NeonInterpolator;
 #[cfg(target_arch = "x86_64")]
 use crate::sinc_interpolator::sinc_interpolator_sse::SseInterpolator;
 use crate::sinc_interpolator::{ScalarInterpolator, SincInterpolator};
-use crate::windows::WindowFunction;
-use crate::{update_mask_from_buffers, validate_buffers, Resampler, Sample};
+use crate::windows::{make_window, WindowFunction};
+use crate::{
+    change_nbr_channels, frames_before_end, make_buffer, next_frame_phase, reactivated_channels,
+    reserve_channels, update_mask_from_buffers, validate_buffers, validate_channels, ChannelLayout,
+    Resampler, Sample,
+};
+use num_integer as integer;
+use std::time::{Duration, Instant};
 
 /// A struct holding the parameters for sinc interpolation.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SincInterpolationParameters {
     /// Length of the windowed sinc interpolation filter.
     /// Higher values can allow a higher cut-off frequency leading to less high frequency roll-off
@@ -20,6 +33,15 @@ pub struct SincInterpolationParameters {
     pub sinc_len: usize,
     /// Relative cutoff frequency of the sinc interpolation filter
     /// (relative to the lowest one of fs_in/2 or fs_out/2). Start at 0.95, and increase if needed.
+    ///
+    /// The same cutoff serves as the anti-imaging filter when upsampling,
+    /// and as the anti-aliasing filter when downsampling.
+    /// When upsampling, the cutoff is relative to fs_in/2, and the filter suppresses
+    /// the images of the input spectrum that appear above it.
+    /// When downsampling, the cutoff is relative to fs_out/2, and the filter removes
+    /// the frequencies that would otherwise alias.
+    /// Only one of the two is needed for a given ratio, so there is no separate setting.
+    /// The attenuation of the images or the aliases is given by the `window` and `sinc_len`.
     pub f_cutoff: f32,
     /// The number of intermediate points to use for interpolation.
     /// Higher values use more memory for storing the sinc filters.
@@ -33,6 +55,99 @@ pub struct SincInterpolationParameters {
     pub window: WindowFunction,
 }
 
+impl SincInterpolationParameters {
+    /// Estimate the peak-to-peak ripple in the passband of the sinc filter, in dB.
+    ///
+    /// The frequency response of the filter is evaluated from zero up to 90% of the cutoff frequency.
+    /// For short filters the transition band may begin below this frequency,
+    /// and then the start of the roll-off is included in the ripple.
+    /// Shorter filters and windows with low attenuation give larger ripple.
+    pub fn passband_ripple_db(&self) -> f64 {
+        let sinc_len = 8 * (((self.sinc_len as f32) / 8.0).ceil() as usize);
+        let taps = make_sincs::<f64>(sinc_len, 1, self.f_cutoff, self.window).remove(0);
+        let passband_edge = 0.9 * self.f_cutoff as f64;
+
+        // Evaluate the response on a grid dense enough to resolve the ripple.
+        let nbr_points = 8 * sinc_len;
+        let mut min_gain = f64::INFINITY;
+        let mut max_gain = 0.0f64;
+        for point in 0..=nbr_points {
+            let omega = std::f64::consts::PI * passband_edge * point as f64 / nbr_points as f64;
+            let (re, im) = taps
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(re, im), (n, tap)| {
+                    let phase = omega * n as f64;
+                    (re + tap * phase.cos(), im - tap * phase.sin())
+                });
+            let gain = (re * re + im * im).sqrt();
+            min_gain = min_gain.min(gain);
+            max_gain = max_gain.max(gain);
+        }
+        20.0 * (max_gain / min_gain).log10()
+    }
+
+    /// Estimate the number of bytes used by a [SincFixedIn] resampler with these parameters,
+    /// for samples of type `T`.
+    ///
+    /// The estimate includes:
+    /// - The sinc filter table, of `oversampling_factor * sinc_len * size_of::<T>()` bytes.
+    /// - One buffer per channel, holding the input chunk and a history of `2 * sinc_len` frames.
+    /// - The channel mask.
+    ///
+    /// The sinc length is rounded up to a multiple of 8, as done by the resampler.
+    /// The estimate doesn't include the small fixed size of the resampler structs,
+    /// or any buffers allocated by the caller, such as the ones from
+    /// [output_buffer_allocate](Resampler::output_buffer_allocate).
+    pub fn memory_footprint<T: Sample>(&self, chunk_size: usize, channels: usize) -> usize {
+        let sample = std::mem::size_of::<T>();
+        let sinc_len = 8 * (((self.sinc_len as f32) / 8.0).ceil() as usize);
+        let sincs = self.oversampling_factor * sinc_len * sample;
+        let buffer_len = chunk_size + 2 * sinc_len;
+        sincs + channels * (buffer_len * sample + std::mem::size_of::<bool>())
+    }
+
+    /// Get the smallest oversampling factor that keeps the error of the interpolation
+    /// between the intermediate points below `target_thd_db`,
+    /// given as a negative value in dB relative to full scale, like -120.0.
+    ///
+    /// The estimate is analytical. A polynomial through `p` points, spaced `h` apart,
+    /// interpolates a sine of angular frequency `w` with a peak error of at most `C * (w * h)^p`,
+    /// where `C` depends on the polynomial degree:
+    ///
+    /// | Interpolation | `p` | `C` |
+    /// |---------------|-----|-----|
+    /// | Nearest       | 1   | 1/2 |
+    /// | Linear        | 2   | 1/8 |
+    /// | Quadratic     | 3   | 1/(9 * sqrt(3)) |
+    /// | Cubic         | 4   | 3/128 |
+    ///
+    /// The spacing is `h = 1 / oversampling_factor` input frames,
+    /// and the worst case is a sine at the Nyquist frequency, `w = pi`.
+    /// The returned factor is the smallest one where the peak error is at or below the target.
+    /// The peak error is an upper bound for the distortion, and the actual THD is usually
+    /// a few dB lower. The error of the sinc filter itself is not included.
+    /// [SincInterpolationType::Adaptive] is treated as linear interpolation,
+    /// since it uses that in the smooth regions where the error matters most.
+    ///
+    /// The error of linear interpolation falls much slower with the oversampling factor,
+    /// which is why it needs a far larger factor than cubic interpolation for the same target.
+    /// For example, -120 dB needs 1111 for linear, but only 39 for cubic interpolation.
+    pub fn optimal_oversampling(interpolation: SincInterpolationType, target_thd_db: f64) -> usize {
+        let (order, constant) = match interpolation {
+            SincInterpolationType::Nearest => (1, 0.5),
+            SincInterpolationType::Linear | SincInterpolationType::Adaptive { .. } => {
+                (2, 1.0 / 8.0)
+            }
+            SincInterpolationType::Quadratic => (3, 1.0 / (9.0 * 3.0f64.sqrt())),
+            SincInterpolationType::Cubic => (4, 3.0 / 128.0),
+        };
+        let max_error = 10.0f64.powf(target_thd_db / 20.0);
+        let max_step = (max_error / constant).powf(1.0 / order as f64) / std::f64::consts::PI;
+        ((1.0 / max_step).ceil() as usize).max(1)
+    }
+}
+
 /// Interpolation methods that can be selected. For asynchronous interpolation where the
 /// ratio between input and output sample rates can be any number, it's not possible to
 /// pre-calculate all the needed interpolation filters.
@@ -41,7 +156,7 @@ pub struct SincInterpolationParameters {
 /// It's more efficient to combine the sinc filters with some other interpolation technique.
 /// Then, sinc filters are used to provide a fixed number of interpolated points between input samples,
 /// and then, the new value is calculated by interpolation between those points.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SincInterpolationType {
     /// For cubic interpolation, the four nearest intermediate points are calculated
     /// using sinc interpolation.
@@ -68,7 +183,145 @@ pub enum SincInterpolationType {
     /// no unnecessary computations are performed and the result is equivalent to that of synchronous resampling.
     /// This also works for other ratios that can be expressed by a fraction. For 44.1kHz -> 48 kHz,
     /// setting oversampling_factor to 160 gives the desired result (since 48kHz = 160/147 * 44.1kHz).
+    /// The constructors of [SincFixedIn] and [SincFixedOut] check this, and return
+    /// [ResamplerConstructionError::NearestNotExact] with a suitable oversampling factor
+    /// if the nearest points would not be the correct ones.
     Nearest,
+    /// The Adaptive mode chooses between cubic and linear interpolation for each output sample.
+    /// Around transients it uses cubic interpolation,
+    /// and in smooth or quiet regions it uses the cheaper linear interpolation.
+    /// A region counts as a transient when the absolute second difference of the input,
+    /// measured at the two input frames nearest to the output sample, exceeds `threshold`.
+    /// For a sine with amplitude `A` and angular frequency `w` (in radians per sample),
+    /// the second difference is about `A * w^2`.
+    ///
+    /// Since linear interpolation needs many intermediate points, the oversampling factor
+    /// should be chosen as for [SincInterpolationType::Linear].
+    /// The number of samples calculated with each method can be read with
+    /// [SincFixedIn::adaptive_counts] and [SincFixedOut::adaptive_counts].
+    Adaptive {
+        /// The curvature above which cubic interpolation is used.
+        threshold: f64,
+    },
+}
+
+/// Quality levels for the sinc resamplers, see [SincFixedIn::set_quality].
+///
+/// Changing the quality doesn't reallocate or recalculate anything,
+/// which makes it possible to switch for example between a fast preview while scrubbing
+/// and the full quality for playback, using the same resampler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quality {
+    /// Use the interpolation type and sinc length given to the constructor.
+    /// This is the default.
+    Full,
+    /// Use linear interpolation, and only the central quarter of each sinc
+    /// (but at least 32 taps, and at most the full sinc length).
+    /// The shorter sincs give a wider transition band and weaker suppression of aliasing.
+    /// This is approximately four times faster than cubic interpolation with the full sinc length.
+    /// When the resampler was created with [SincInterpolationType::Nearest],
+    /// that interpolation type is kept and only the sincs are shortened.
+    Preview,
+}
+
+/// How the number of output frames of [SincFixedIn] is rounded,
+/// see [SincFixedIn::set_output_rounding].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputRounding {
+    /// Return all frames that can be calculated from the input.
+    /// Whether a chunk gives the floor or the ceil of `chunk_size * resample_ratio` frames
+    /// depends on where the output frames fall relative to the end of the chunk.
+    /// This is the default.
+    Natural,
+    /// Return the exact number of frames for the chunk rounded down,
+    /// and hold back the remaining frames.
+    AlwaysFloor,
+    /// Round the total number of returned frames up,
+    /// so that it is never less than the exact number of frames for the input so far.
+    AlwaysCeil,
+}
+
+/// How the sinc resamplers fill the history of the filter at the start of a stream,
+/// see [SincFixedIn::set_edge_mode].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeMode {
+    /// The input is preceded by silence. A signal that doesn't start at zero
+    /// then starts with a step, which the filter turns into a short ramp with some ringing.
+    /// This is the default.
+    Zero,
+    /// The input is preceded by the value of its first sample, so that the filter sees
+    /// a constant instead of a step. This avoids the transient for signals that start
+    /// with a DC offset, or in the middle of a slowly varying waveform.
+    Hold,
+}
+
+/// The number of frames per channel that [OutputRounding::AlwaysCeil] may hold back.
+const ROUNDING_CARRY_LEN: usize = 4;
+
+/// Level measurements of a block of input, see [OutputStage::last_loudness].
+///
+/// The levels are linear, with 1.0 for full scale.
+/// Convert them to dBFS and dBTP with `20 * log10(level)`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LoudnessStats {
+    /// The RMS level of the samples of all active channels.
+    pub rms: f64,
+    /// The largest magnitude of the samples of all active channels.
+    pub sample_peak: f64,
+    /// The largest magnitude of the signal oversampled by a factor four,
+    /// an estimate of the true peak that includes the peaks in between the samples.
+    pub true_peak: f64,
+    /// The number of frames in the block.
+    pub frames: usize,
+}
+
+/// Get the interpolation type and the number of sinc taps to use for a quality level.
+fn quality_settings(
+    quality: Quality,
+    interpolation: &SincInterpolationType,
+    sinc_len: usize,
+) -> (SincInterpolationType, usize) {
+    match quality {
+        Quality::Full => (interpolation.clone(), sinc_len),
+        Quality::Preview => {
+            let interpolation = match interpolation {
+                SincInterpolationType::Nearest => SincInterpolationType::Nearest,
+                _ => SincInterpolationType::Linear,
+            };
+            (interpolation, (sinc_len / 4).max(32))
+        }
+    }
+}
+
+/// The number of output frames between the checks of the elapsed time,
+/// when processing with a time budget.
+const BUDGET_CHECK_INTERVAL: usize = 64;
+
+/// Tracks the processing time of a chunk against a time budget,
+/// see [SincFixedIn::set_time_budget].
+struct BudgetClock {
+    start: Instant,
+    budget: f64,
+    expected_frames: f64,
+}
+
+impl BudgetClock {
+    fn new(budget: Duration, expected_frames: f64) -> Self {
+        BudgetClock {
+            start: Instant::now(),
+            budget: budget.as_secs_f64(),
+            expected_frames,
+        }
+    }
+
+    /// Check if the chunk is projected to exceed the budget, after calculating `frames` frames.
+    /// The time is only checked at intervals, to keep the overhead low.
+    fn behind(&self, frames: usize) -> bool {
+        frames > 0
+            && frames % BUDGET_CHECK_INTERVAL == 0
+            && self.start.elapsed().as_secs_f64() * self.expected_frames / frames as f64
+                > self.budget
+    }
 }
 
 /// An asynchronous resampler that accepts a fixed number of audio frames for input
@@ -83,6 +336,17 @@ pub enum SincInterpolationType {
 /// However, when decreasing more than a few percent (or speeding up the output),
 /// the filters can no longer suppress all aliasing and this may lead to some artefacts.
 /// Higher maximum ratios require more memory to be allocated by [Resampler::output_buffer_allocate].
+///
+/// With two channels, both channels are interpolated in a single pass over each sinc,
+/// see [SincInterpolator::get_sinc_interpolated_pair].
+/// This is used automatically, and gives output identical to processing the channels separately.
+/// The [SincInterpolationType::Adaptive] mode always processes the channels separately.
+///
+/// The SIMD interpolator is selected once, from the CPU features detected when the resampler
+/// is created, and is used for the lifetime of the resampler, see [cpu_feature](SincFixedIn::cpu_feature).
+/// On systems with cores that support different instruction sets, a resampler must therefore
+/// only be used on cores with the same features as the one it was created on.
+/// Otherwise it may execute an instruction that the core doesn't support.
 pub struct SincFixedIn<T> {
     nbr_channels: usize,
     chunk_size: usize,
@@ -94,7 +358,32 @@ pub struct SincFixedIn<T> {
     interpolator: Box<dyn SincInterpolator<T>>,
     buffer: Vec<Vec<T>>,
     interpolation: SincInterpolationType,
+    full_interpolation: SincInterpolationType,
     channel_mask: Vec<bool>,
+    lookahead: usize,
+    lookahead_used: usize,
+    fixed_output_size: bool,
+    fixed_size_threshold: Option<f64>,
+    output_rounding: OutputRounding,
+    rounding_remainder: f64,
+    rounding_carry: Vec<Vec<T>>,
+    carried_frames: usize,
+    parameters: Option<SincInterpolationParameters>,
+    cpu_feature: Option<CpuFeature>,
+    deterministic: bool,
+    rms_matcher: RmsMatcher,
+    output_stage: OutputStage<T>,
+    auto_scale: AutoScale,
+    emphasis: Emphasis,
+    adaptive_counts: [usize; 2],
+    time_budget: Option<Duration>,
+    degraded_chunks: usize,
+    channel_layout: Option<ChannelLayout>,
+    edge_mode: EdgeMode,
+    at_start: bool,
+    flush_subnormals: bool,
+    max_output_frames: usize,
+    interleaved: Option<InterleavedBuffer<T>>,
 }
 
 /// An asynchronous resampler that returns a fixed number of audio frames.
@@ -111,6 +400,8 @@ pub struct SincFixedIn<T> {
 /// the filters can no longer suppress all aliasing and this may lead to some artefacts.
 /// Higher maximum ratios require more memory to be allocated by
 /// [input_buffer_allocate](Resampler::input_buffer_allocate) and an internal buffer.
+///
+/// The SIMD interpolator is selected when the resampler is created, like for [SincFixedIn].
 pub struct SincFixedOut<T> {
     nbr_channels: usize,
     chunk_size: usize,
@@ -124,47 +415,101 @@ pub struct SincFixedOut<T> {
     interpolator: Box<dyn SincInterpolator<T>>,
     buffer: Vec<Vec<T>>,
     interpolation: SincInterpolationType,
+    full_interpolation: SincInterpolationType,
     channel_mask: Vec<bool>,
+    parameters: Option<SincInterpolationParameters>,
+    cpu_feature: Option<CpuFeature>,
+    deterministic: bool,
+    rms_matcher: RmsMatcher,
+    output_stage: OutputStage<T>,
+    auto_scale: AutoScale,
+    emphasis: Emphasis,
+    adaptive_counts: [usize; 2],
+    time_budget: Option<Duration>,
+    degraded_chunks: usize,
+    channel_layout: Option<ChannelLayout>,
+    edge_mode: EdgeMode,
+    at_start: bool,
+    flush_subnormals: bool,
+    max_output_frames: usize,
+    interleaved: Option<InterleavedBuffer<T>>,
 }
 
-pub fn make_interpolator<T>(
+/// Get the sinc length rounded up to a multiple of 8,
+/// and the cutoff relative to the lower of the two Nyquist frequencies.
+pub(crate) fn interpolator_settings(
     sinc_len: usize,
     resample_ratio: f64,
     f_cutoff: f32,
-    oversampling_factor: usize,
-    window: WindowFunction,
-) -> Box<dyn SincInterpolator<T>>
-where
-    T: Sample,
-{
+) -> (usize, f32) {
     let sinc_len = 8 * (((sinc_len as f32) / 8.0).ceil() as usize);
     let f_cutoff = if resample_ratio >= 1.0 {
         f_cutoff
     } else {
         f_cutoff * resample_ratio as f32
     };
+    (sinc_len, f_cutoff)
+}
+
+/// Make the fastest interpolator supported by the CPU,
+/// and return it together with the CPU feature it uses, or None for the scalar interpolator.
+/// The interpolator can be shared between threads, see [SegmentedResampler](crate::segmented::SegmentedResampler).
+pub fn make_interpolator<T>(
+    sinc_len: usize,
+    resample_ratio: f64,
+    f_cutoff: f32,
+    oversampling_factor: usize,
+    window: WindowFunction,
+) -> (Box<dyn SincInterpolator<T> + Sync>, Option<CpuFeature>)
+where
+    T: Sample,
+{
+    let (sinc_len, f_cutoff) = interpolator_settings(sinc_len, resample_ratio, f_cutoff);
 
     #[cfg(target_arch = "x86_64")]
     if let Ok(interpolator) =
         AvxInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
     {
-        return Box::new(interpolator);
+        return (Box::new(interpolator), Some(CpuFeature::Avx));
     }
 
     #[cfg(target_arch = "x86_64")]
     if let Ok(interpolator) =
         SseInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
     {
-        return Box::new(interpolator);
+        return (Box::new(interpolator), Some(CpuFeature::Sse3));
     }
 
     #[cfg(target_arch = "aarch64")]
     if let Ok(interpolator) =
         NeonInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
     {
-        return Box::new(interpolator);
+        return (Box::new(interpolator), Some(CpuFeature::Neon));
     }
 
+    (
+        Box::new(ScalarInterpolator::<T>::new(
+            sinc_len,
+            oversampling_factor,
+            f_cutoff,
+            window,
+        )),
+        None,
+    )
+}
+
+/// Make the scalar interpolator, regardless of the available CPU features.
+fn make_scalar_interpolator<T>(
+    sinc_len: usize,
+    resample_ratio: f64,
+    f_cutoff: f32,
+    oversampling_factor: usize,
+    window: WindowFunction,
+) -> Box<dyn SincInterpolator<T> + Sync>
+where
+    T: Sample,
+{
+    let (sinc_len, f_cutoff) = interpolator_settings(sinc_len, resample_ratio, f_cutoff);
     Box::new(ScalarInterpolator::<T>::new(
         sinc_len,
         oversampling_factor,
@@ -173,9 +518,62 @@ where
     ))
 }
 
+/// Make the interpolator that uses the given CPU feature,
+/// and return it together with the main feature it uses.
+/// [CpuFeature::Fma] selects the AVX interpolator, which needs both AVX and FMA.
+#[allow(unused_variables)]
+fn make_interpolator_for_feature<T>(
+    feature: CpuFeature,
+    sinc_len: usize,
+    resample_ratio: f64,
+    f_cutoff: f32,
+    oversampling_factor: usize,
+    window: WindowFunction,
+) -> Result<(Box<dyn SincInterpolator<T>>, CpuFeature), MissingCpuFeature>
+where
+    T: Sample,
+{
+    let (sinc_len, f_cutoff) = interpolator_settings(sinc_len, resample_ratio, f_cutoff);
+    match feature {
+        #[cfg(target_arch = "x86_64")]
+        CpuFeature::Avx | CpuFeature::Fma => {
+            let interpolator =
+                AvxInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)?;
+            Ok((Box::new(interpolator), CpuFeature::Avx))
+        }
+        #[cfg(target_arch = "x86_64")]
+        CpuFeature::Sse3 => {
+            let interpolator =
+                SseInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)?;
+            Ok((Box::new(interpolator), CpuFeature::Sse3))
+        }
+        #[cfg(target_arch = "aarch64")]
+        CpuFeature::Neon => {
+            let interpolator =
+                NeonInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)?;
+            Ok((Box::new(interpolator), CpuFeature::Neon))
+        }
+    }
+}
+
+/// Values with a smaller magnitude than this are flushed to zero
+/// when [SincFixedIn::set_flush_subnormals] is enabled.
+/// This is about -400 dB, far below anything audible,
+/// and high enough that the products with the sinc coefficients stay normal.
+const FLUSH_THRESHOLD: f64 = 1.0e-20;
+
+/// Replace tiny values by zero, to keep subnormal numbers out of the scalar products.
+fn flush_tiny_values<T: Sample>(data: &mut [T]) {
+    for value in data.iter_mut() {
+        if value.to_f64().abs() < FLUSH_THRESHOLD {
+            *value = T::zero();
+        }
+    }
+}
+
 /// Perform cubic polynomial interpolation to get value at x.
 /// Input points are assumed to be at x = -1, 0, 1, 2.
-fn interp_cubic<T>(x: T, yvals: &[T; 4]) -> T
+pub(crate) fn interp_cubic<T>(x: T, yvals: &[T; 4]) -> T
 where
     T: Sample,
 {
@@ -192,7 +590,7 @@ where
 
 /// Perform quadratic polynomial interpolation to get value at x.
 /// Input points are assumed to be at x = 0, 1, 2.
-fn interp_quad<T>(x: T, yvals: &[T; 3]) -> T
+pub(crate) fn interp_quad<T>(x: T, yvals: &[T; 3]) -> T
 where
     T: Sample,
 {
@@ -204,13 +602,267 @@ where
 }
 
 /// Perform linear interpolation between two points at x=0 and x=1.
-fn interp_lin<T>(x: T, yvals: &[T; 2]) -> T
+pub(crate) fn interp_lin<T>(x: T, yvals: &[T; 2]) -> T
 where
     T: Sample,
 {
     yvals[0] + x * (yvals[1] - yvals[0])
 }
 
+/// Choose the interpolation type for [SincInterpolationType::Adaptive] at the time `idx`,
+/// relative to the start of the new data in `buf`.
+/// The curvature is the largest absolute second difference of the input
+/// at the two input frames nearest to the center of the sinc filter.
+fn adaptive_interpolation<T>(
+    buf: &[T],
+    sinc_len: usize,
+    idx: f64,
+    threshold: f64,
+) -> SincInterpolationType
+where
+    T: Sample,
+{
+    let center = (idx.floor() as isize + (2 * sinc_len + sinc_len / 2) as isize) as usize;
+    let curvature = buf[center - 1..center + 3]
+        .windows(3)
+        .map(|w| (w[0] - T::coerce(2.0) * w[1] + w[2]).to_f64().abs())
+        .fold(0.0, f64::max);
+    if curvature > threshold {
+        SincInterpolationType::Cubic
+    } else {
+        SincInterpolationType::Linear
+    }
+}
+
+/// Calculate a single output value for the time `idx`, relative to the start of the new data in `buf`.
+/// This performs the same steps as the processing loops, and gives identical results.
+fn interpolate_at<T>(
+    interpolator: &dyn SincInterpolator<T>,
+    interpolation: &SincInterpolationType,
+    buf: &[T],
+    idx: f64,
+) -> T
+where
+    T: Sample,
+{
+    let sinc_len = interpolator.len() as isize;
+    let oversampling_factor = interpolator.nbr_sincs();
+    let frac = idx * oversampling_factor as f64 - (idx * oversampling_factor as f64).floor();
+    let frac_offset = T::coerce(frac);
+    match interpolation {
+        SincInterpolationType::Cubic => {
+            let mut points = [T::zero(); 4];
+            let mut nearest = [(0isize, 0isize); 4];
+            get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
+            for (n, p) in nearest.iter().zip(points.iter_mut()) {
+                *p = interpolator.get_sinc_interpolated(
+                    buf,
+                    (n.0 + 2 * sinc_len) as usize,
+                    n.1 as usize,
+                );
+            }
+            interp_cubic(frac_offset, &points)
+        }
+        SincInterpolationType::Quadratic => {
+            let mut points = [T::zero(); 3];
+            let mut nearest = [(0isize, 0isize); 3];
+            get_nearest_times_3(idx, oversampling_factor as isize, &mut nearest);
+            for (n, p) in nearest.iter().zip(points.iter_mut()) {
+                *p = interpolator.get_sinc_interpolated(
+                    buf,
+                    (n.0 + 2 * sinc_len) as usize,
+                    n.1 as usize,
+                );
+            }
+            interp_quad(frac_offset, &points)
+        }
+        SincInterpolationType::Linear => {
+            let mut points = [T::zero(); 2];
+            let mut nearest = [(0isize, 0isize); 2];
+            get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
+            for (n, p) in nearest.iter().zip(points.iter_mut()) {
+                *p = interpolator.get_sinc_interpolated(
+                    buf,
+                    (n.0 + 2 * sinc_len) as usize,
+                    n.1 as usize,
+                );
+            }
+            interp_lin(frac_offset, &points)
+        }
+        SincInterpolationType::Nearest => {
+            let nearest = get_nearest_time(idx, oversampling_factor as isize);
+            interpolator.get_sinc_interpolated(
+                buf,
+                (nearest.0 + 2 * sinc_len) as usize,
+                nearest.1 as usize,
+            )
+        }
+        SincInterpolationType::Adaptive { threshold } => {
+            let interpolation = adaptive_interpolation(buf, sinc_len as usize, idx, *threshold);
+            interpolate_at(interpolator, &interpolation, buf, idx)
+        }
+    }
+}
+
+/// Calculate a single output frame at position `idx` for both channels of a stereo buffer.
+/// The result is identical to that of [interpolate_at] for each channel.
+fn interpolate_pair_at<T>(
+    interpolator: &dyn SincInterpolator<T>,
+    interpolation: &SincInterpolationType,
+    waves: [&[T]; 2],
+    idx: f64,
+) -> [T; 2]
+where
+    T: Sample,
+{
+    let sinc_len = interpolator.len() as isize;
+    let oversampling_factor = interpolator.nbr_sincs();
+    let frac = idx * oversampling_factor as f64 - (idx * oversampling_factor as f64).floor();
+    let frac_offset = T::coerce(frac);
+    match interpolation {
+        SincInterpolationType::Cubic => {
+            let mut points = [[T::zero(); 4]; 2];
+            let mut nearest = [(0isize, 0isize); 4];
+            get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
+            for (i, n) in nearest.iter().enumerate() {
+                let values = interpolator.get_sinc_interpolated_pair(
+                    waves,
+                    (n.0 + 2 * sinc_len) as usize,
+                    n.1 as usize,
+                );
+                points[0][i] = values[0];
+                points[1][i] = values[1];
+            }
+            [
+                interp_cubic(frac_offset, &points[0]),
+                interp_cubic(frac_offset, &points[1]),
+            ]
+        }
+        SincInterpolationType::Quadratic => {
+            let mut points = [[T::zero(); 3]; 2];
+            let mut nearest = [(0isize, 0isize); 3];
+            get_nearest_times_3(idx, oversampling_factor as isize, &mut nearest);
+            for (i, n) in nearest.iter().enumerate() {
+                let values = interpolator.get_sinc_interpolated_pair(
+                    waves,
+                    (n.0 + 2 * sinc_len) as usize,
+                    n.1 as usize,
+                );
+                points[0][i] = values[0];
+                points[1][i] = values[1];
+            }
+            [
+                interp_quad(frac_offset, &points[0]),
+                interp_quad(frac_offset, &points[1]),
+            ]
+        }
+        SincInterpolationType::Linear => {
+            let mut points = [[T::zero(); 2]; 2];
+            let mut nearest = [(0isize, 0isize); 2];
+            get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
+            for (i, n) in nearest.iter().enumerate() {
+                let values = interpolator.get_sinc_interpolated_pair(
+                    waves,
+                    (n.0 + 2 * sinc_len) as usize,
+                    n.1 as usize,
+                );
+                points[0][i] = values[0];
+                points[1][i] = values[1];
+            }
+            [
+                interp_lin(frac_offset, &points[0]),
+                interp_lin(frac_offset, &points[1]),
+            ]
+        }
+        SincInterpolationType::Nearest => {
+            let nearest = get_nearest_time(idx, oversampling_factor as isize);
+            interpolator.get_sinc_interpolated_pair(
+                waves,
+                (nearest.0 + 2 * sinc_len) as usize,
+                nearest.1 as usize,
+            )
+        }
+        SincInterpolationType::Adaptive { .. } => {
+            unreachable!("The adaptive interpolation chooses the type per channel")
+        }
+    }
+}
+
+/// Calculate a single output value like [interpolate_at],
+/// and choose the interpolation type first if it is [SincInterpolationType::Adaptive].
+fn interpolate_any_at<T>(
+    interpolator: &dyn SincInterpolator<T>,
+    interpolation: &SincInterpolationType,
+    buf: &[T],
+    idx: f64,
+    adaptive_counts: &mut [usize; 2],
+) -> T
+where
+    T: Sample,
+{
+    if let SincInterpolationType::Adaptive { threshold } = interpolation {
+        let interpolation = adaptive_interpolation(buf, interpolator.len(), idx, *threshold);
+        let cubic = interpolation == SincInterpolationType::Cubic;
+        adaptive_counts[cubic as usize] += 1;
+        interpolate_at(interpolator, &interpolation, buf, idx)
+    } else {
+        interpolate_at(interpolator, interpolation, buf, idx)
+    }
+}
+
+/// Calculate one output frame at position `idx` for the active channels,
+/// and store it as frame `frame` of `wave_out`.
+///
+/// This is the inner step of the processing loops of the sinc resamplers.
+/// The interleaved buffer is used if there is one, stereo input is interpolated in pairs,
+/// and otherwise the channels are interpolated separately.
+/// The [SincInterpolationType::Adaptive] mode always processes the channels separately.
+#[allow(clippy::too_many_arguments)]
+fn interpolate_frame<T, Vout>(
+    interpolator: &dyn SincInterpolator<T>,
+    interpolation: &SincInterpolationType,
+    buffer: &[Vec<T>],
+    interleaved: Option<&mut InterleavedBuffer<T>>,
+    channel_mask: &[bool],
+    adaptive_counts: &mut [usize; 2],
+    idx: f64,
+    wave_out: &mut [Vout],
+    frame: usize,
+) where
+    T: Sample,
+    Vout: AsMut<[T]>,
+{
+    let adaptive = matches!(interpolation, SincInterpolationType::Adaptive { .. });
+    if let (Some(interleaved), false) = (interleaved, adaptive) {
+        let values = interleaved.interpolate(interpolation, interpolator.len(), idx);
+        for (chan, active) in channel_mask.iter().enumerate() {
+            if *active {
+                wave_out[chan].as_mut()[frame] = values[chan];
+            }
+        }
+    } else if channel_mask.len() == 2 && !adaptive {
+        let values =
+            interpolate_pair_at(interpolator, interpolation, [&buffer[0], &buffer[1]], idx);
+        for (chan, active) in channel_mask.iter().enumerate() {
+            if *active {
+                wave_out[chan].as_mut()[frame] = values[chan];
+            }
+        }
+    } else {
+        for (chan, active) in channel_mask.iter().enumerate() {
+            if *active {
+                wave_out[chan].as_mut()[frame] = interpolate_any_at(
+                    interpolator,
+                    interpolation,
+                    &buffer[chan],
+                    idx,
+                    adaptive_counts,
+                );
+            }
+        }
+    }
+}
+
 fn validate_ratios(
     resample_ratio: f64,
     max_resample_ratio_relative: f64,
@@ -226,331 +878,528 @@ fn validate_ratios(
     Ok(())
 }
 
-impl<T> SincFixedIn<T>
-where
-    T: Sample,
-{
-    /// Create a new SincFixedIn.
-    ///
-    /// Parameters are:
-    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
-    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio * 10.0` and `resample_ratio / 10.0`.
-    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
-    /// - `chunk_size`: Size of input data in frames.
-    /// - `nbr_channels`: Number of channels in input/output.
-    pub fn new(
-        resample_ratio: f64,
-        max_resample_ratio_relative: f64,
-        parameters: SincInterpolationParameters,
-        chunk_size: usize,
-        nbr_channels: usize,
-    ) -> Result<Self, ResamplerConstructionError> {
-        debug!(
-            "Create new SincFixedIn, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
-            resample_ratio, chunk_size, nbr_channels, parameters
-        );
+/// Check that a set of parameters is valid for constructing a sinc resampler,
+/// without actually constructing it.
+///
+/// This performs the same checks as [SincFixedIn::new],
+/// but does not allocate any buffers or generate any sinc tables.
+/// This makes it cheap enough to call for example when validating user input.
+/// This includes the minimum sinc length for the given ratio range,
+/// see [ResamplerConstructionError::SincLenTooShort].
+/// [SincFixedOut::new] performs the same checks except this one, and also accepts shorter sinc lengths.
+/// Parameters that pass this check are therefore valid for both resamplers.
+///
+/// Parameters are:
+/// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+/// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+/// - `max_resample_ratio_relative`: Maximum relative ratio, must be >= 1.0.
+///   Use 1.0 for a fixed ratio.
+/// - `chunk_size`: Size of input data in frames for [SincFixedIn],
+///   or of output data for [SincFixedOut], must be > 0.
+/// - `nbr_channels`: Number of channels in input/output, must be > 0.
+pub fn validate_sinc_config(
+    parameters: &SincInterpolationParameters,
+    resample_ratio: f64,
+    max_resample_ratio_relative: f64,
+    chunk_size: usize,
+    nbr_channels: usize,
+) -> Result<(), ResamplerConstructionError> {
+    validate_parameters(
+        parameters,
+        resample_ratio,
+        max_resample_ratio_relative,
+        chunk_size,
+        nbr_channels,
+    )?;
+    let (sinc_len, _) =
+        interpolator_settings(parameters.sinc_len, resample_ratio, parameters.f_cutoff);
+    let minimum = min_sinc_len_fixed_in(resample_ratio, max_resample_ratio_relative);
+    if sinc_len < minimum {
+        return Err(ResamplerConstructionError::SincLenTooShort {
+            provided: sinc_len,
+            minimum,
+        });
+    }
+    Ok(())
+}
 
-        let interpolator = make_interpolator(
+/// Check the parameters that are common to [SincFixedIn] and [SincFixedOut].
+fn validate_parameters(
+    parameters: &SincInterpolationParameters,
+    resample_ratio: f64,
+    max_resample_ratio_relative: f64,
+    chunk_size: usize,
+    nbr_channels: usize,
+) -> Result<(), ResamplerConstructionError> {
+    validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+    validate_channels(nbr_channels)?;
+    if chunk_size == 0 {
+        return Err(ResamplerConstructionError::InvalidChunkSize(chunk_size));
+    }
+    if parameters.sinc_len == 0 {
+        return Err(ResamplerConstructionError::InvalidSincLength(
             parameters.sinc_len,
-            resample_ratio,
-            parameters.f_cutoff,
+        ));
+    }
+    if parameters.oversampling_factor == 0 {
+        return Err(ResamplerConstructionError::InvalidOversamplingFactor(
             parameters.oversampling_factor,
-            parameters.window,
-        );
+        ));
+    }
+    if !(parameters.f_cutoff > 0.0 && parameters.f_cutoff <= 1.0) {
+        return Err(ResamplerConstructionError::InvalidCutoff(
+            parameters.f_cutoff,
+        ));
+    }
+    if matches!(parameters.interpolation, SincInterpolationType::Nearest) {
+        validate_nearest(resample_ratio, parameters.oversampling_factor)?;
+    }
+    Ok(())
+}
 
-        Self::new_with_interpolator(
-            resample_ratio,
-            max_resample_ratio_relative,
-            parameters.interpolation,
-            interpolator,
-            chunk_size,
-            nbr_channels,
-        )
+/// Design the anti-aliasing low-pass filter that a sinc resampler would use,
+/// as a single FIR filter at the input sample rate.
+///
+/// This is the windowed sinc given by the `sinc_len`, `f_cutoff` and `window` fields of the parameters,
+/// before it is split into the polyphase branches used for the interpolation.
+/// The other fields are not used.
+/// When downsampling, meaning that `resample_ratio` is below 1.0, the cutoff is scaled
+/// by the ratio in the same way as in the resamplers.
+///
+/// The sinc length is rounded up to a multiple of 8, and the filter has one more tap than that.
+/// The odd length gives a linear-phase filter, centred on the middle tap at index `(len - 1) / 2`.
+/// The taps are scaled to give a gain of 1.0 at zero frequency.
+pub fn design_antialias_fir(
+    parameters: &SincInterpolationParameters,
+    resample_ratio: f64,
+) -> Vec<f64> {
+    let sinc_len = 8 * (((parameters.sinc_len as f32) / 8.0).ceil() as usize);
+    let f_cutoff = if resample_ratio >= 1.0 {
+        parameters.f_cutoff
+    } else {
+        parameters.f_cutoff * resample_ratio as f32
+    };
+    let len = sinc_len + 1;
+    let center = (len - 1) / 2;
+    // The first half of a periodic window of length `len - 1` is the first half
+    // of the symmetric window of length `len`.
+    let window = make_window::<f64>(len - 1, parameters.window);
+    let mut taps = vec![0.0; len];
+    for n in 0..=center {
+        let x = std::f64::consts::PI * f_cutoff as f64 * (n as f64 - center as f64);
+        let value = if x == 0.0 {
+            window[n]
+        } else {
+            window[n] * x.sin() / x
+        };
+        taps[n] = value;
+        taps[len - 1 - n] = value;
     }
+    let sum: f64 = taps.iter().sum();
+    taps.iter_mut().for_each(|tap| *tap /= sum);
+    taps
+}
 
-    /// Create a new SincFixedIn using an existing Interpolator.
-    ///
-    /// Parameters are:
-    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
-    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio` * 10.0 and `resample_ratio` / 10.0.
-    /// - `interpolation_type`: Parameters for interpolation, see `SincInterpolationParameters`.
-    /// - `interpolator`: The interpolator to use.
-    /// - `chunk_size`: Size of output data in frames.
-    /// - `nbr_channels`: Number of channels in input/output.
-    pub fn new_with_interpolator(
-        resample_ratio: f64,
-        max_resample_ratio_relative: f64,
-        interpolation_type: SincInterpolationType,
-        interpolator: Box<dyn SincInterpolator<T>>,
-        chunk_size: usize,
-        nbr_channels: usize,
-    ) -> Result<Self, ResamplerConstructionError> {
-        validate_ratios(resample_ratio, max_resample_ratio_relative)?;
-        let buffer = vec![vec![T::zero(); chunk_size + 2 * interpolator.len()]; nbr_channels];
+/// Relative tolerance used when comparing resample ratios in `config_eq`.
+const CONFIG_RATIO_TOLERANCE: f64 = 1.0e-9;
 
-        let channel_mask = vec![true; nbr_channels];
+fn config_matches(
+    own_parameters: &Option<SincInterpolationParameters>,
+    own_ratio: f64,
+    own_channels: usize,
+    parameters: &SincInterpolationParameters,
+    resample_ratio: f64,
+    nbr_channels: usize,
+) -> bool {
+    own_parameters.as_ref() == Some(parameters)
+        && own_channels == nbr_channels
+        && (own_ratio - resample_ratio).abs() <= CONFIG_RATIO_TOLERANCE * own_ratio
+}
 
-        Ok(SincFixedIn {
-            nbr_channels,
-            chunk_size,
-            last_index: -((interpolator.len() / 2) as f64),
-            resample_ratio,
-            resample_ratio_original: resample_ratio,
-            target_ratio: resample_ratio,
-            max_relative_ratio: max_resample_ratio_relative,
-            interpolator,
-            buffer,
-            interpolation: interpolation_type,
-            channel_mask,
+/// Read through all sincs of an interpolator once, for [Resampler::warmup].
+/// The sum of the results is returned, so that the reads can't be optimized away.
+fn touch_sincs<T: Sample>(interpolator: &dyn SincInterpolator<T>, buffer: &[T]) -> T {
+    (0..interpolator.nbr_sincs()).fold(T::zero(), |acc, subindex| {
+        acc + interpolator.get_sinc_interpolated(buffer, 0, subindex)
+    })
+}
+
+/// Get the mean DC gain of the sincs of an interpolator, using the taps that are active.
+fn filter_dc_gain<T: Sample>(interpolator: &dyn SincInterpolator<T>) -> f64 {
+    let ones = vec![T::one(); interpolator.len() + 1];
+    let nbr_sincs = interpolator.nbr_sincs();
+    (0..nbr_sincs)
+        .map(|subindex| {
+            interpolator
+                .get_sinc_interpolated(&ones, 0, subindex)
+                .to_f64()
         })
-    }
+        .sum::<f64>()
+        / nbr_sincs as f64
 }
 
-impl<T> Resampler<T> for SincFixedIn<T>
-where
-    T: Sample,
-{
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
-        &mut self,
-        wave_in: &[Vin],
-        wave_out: &mut [Vout],
-        active_channels_mask: Option<&[bool]>,
-    ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
-        } else {
-            update_mask_from_buffers(&mut self.channel_mask);
-        };
+/// Fill the history before the new data at `start` with the first new sample
+/// of each active channel, for [EdgeMode::Hold].
+fn hold_first_sample<T: Sample>(buffers: &mut [Vec<T>], mask: &[bool], start: usize) {
+    for (buf, active) in buffers.iter_mut().zip(mask.iter()) {
+        if *active {
+            let first = buf[start];
+            buf[..start].iter_mut().for_each(|s| *s = first);
+        }
+    }
+}
 
-        // Set length to chunksize*ratio plus a safety margin of 10 elements.
-        let needed_len = (self.chunk_size as f64
-            * (0.5 * self.resample_ratio + 0.5 * self.target_ratio)
-            + 10.0) as usize;
+/// Record the input positions of `frames` output frames, for `last_input_indices`.
+/// The positions are stepped in the same way as when processing, starting from `idx` and `t_ratio`.
+/// The sinc used for a position is centered `sinc_len / 2 - 1 + 1 / oversampling_factor`
+/// input frames after it.
+fn record_input_indices<T: Sample>(
+    indices: &mut Vec<f64>,
+    frames: usize,
+    mut idx: f64,
+    mut t_ratio: f64,
+    t_ratio_increment: f64,
+    interpolator: &dyn SincInterpolator<T>,
+) {
+    let offset = (interpolator.len() / 2) as f64 - 1.0 + 1.0 / interpolator.nbr_sincs() as f64;
+    indices.clear();
+    for _ in 0..frames {
+        t_ratio += t_ratio_increment;
+        idx += t_ratio;
+        indices.push(idx + offset);
+    }
+}
 
-        validate_buffers(
-            wave_in,
-            wave_out,
-            &self.channel_mask,
-            self.nbr_channels,
-            self.chunk_size,
-            needed_len,
-        )?;
+/// Perform fifth order Lagrange interpolation to get value at x.
+/// Input points are assumed to be at x = -2, -1, 0, 1, 2, 3.
+fn interp_quintic(x: f64, yvals: &[f64; 6]) -> f64 {
+    let mut value = 0.0;
+    for (k, y) in yvals.iter().enumerate() {
+        let weight = (0..6)
+            .filter(|j| *j != k)
+            .map(|j| (x + 2.0 - j as f64) / (k as f64 - j as f64))
+            .product::<f64>();
+        value += weight * y;
+    }
+    value
+}
 
-        let sinc_len = self.interpolator.len();
-        let oversampling_factor = self.interpolator.nbr_sincs();
-        let mut t_ratio = 1.0 / self.resample_ratio;
-        let t_ratio_end = 1.0 / self.target_ratio;
-        let approximate_nbr_frames =
-            self.chunk_size as f64 * (0.5 * self.resample_ratio + 0.5 * self.target_ratio);
-        let t_ratio_increment = (t_ratio_end - t_ratio) / approximate_nbr_frames;
-        let end_idx =
-            self.chunk_size as isize - (sinc_len as isize + 1) - t_ratio_end.ceil() as isize;
+/// Calculate the reference value for the error estimates at the time `idx`,
+/// by fifth order interpolation between the six nearest sincs.
+fn interpolate_reference<T>(interpolator: &dyn SincInterpolator<T>, buf: &[T], idx: f64) -> f64
+where
+    T: Sample,
+{
+    let sinc_len = interpolator.len() as isize;
+    let oversampling_factor = interpolator.nbr_sincs();
+    let frac = idx * oversampling_factor as f64 - (idx * oversampling_factor as f64).floor();
+    let mut points = [0.0; 6];
+    let mut nearest = [(0isize, 0isize); 6];
+    get_nearest_times_6(idx, oversampling_factor as isize, &mut nearest);
+    for (n, p) in nearest.iter().zip(points.iter_mut()) {
+        *p = interpolator
+            .get_sinc_interpolated(buf, (n.0 + 2 * sinc_len) as usize, n.1 as usize)
+            .to_f64();
+    }
+    interp_quintic(frac, &points)
+}
 
-        // Update buffer with new data.
-        for buf in self.buffer.iter_mut() {
-            buf.copy_within(self.chunk_size..self.chunk_size + 2 * sinc_len, 0);
+/// Record the error estimates of `frames` output frames of the active channels,
+/// for `last_error_estimates`.
+/// The positions are stepped in the same way as when processing, starting from `idx` and `t_ratio`.
+#[allow(clippy::too_many_arguments)]
+fn record_error_estimates<T: Sample>(
+    estimates: &mut [Vec<T>],
+    buffer: &[Vec<T>],
+    mask: &[bool],
+    frames: usize,
+    start_idx: f64,
+    start_t_ratio: f64,
+    t_ratio_increment: f64,
+    interpolator: &dyn SincInterpolator<T>,
+    interpolation: &SincInterpolationType,
+) {
+    for ((estimate, buf), active) in estimates.iter_mut().zip(buffer.iter()).zip(mask.iter()) {
+        estimate.clear();
+        if !*active {
+            continue;
         }
-
-        for (chan, active) in self.channel_mask.iter().enumerate() {
-            if *active {
-                debug_assert!(needed_len <= wave_out[chan].as_mut().len());
-                self.buffer[chan][2 * sinc_len..2 * sinc_len + self.chunk_size]
-                    .copy_from_slice(&wave_in[chan].as_ref()[..self.chunk_size]);
-            }
+        let mut idx = start_idx;
+        let mut t_ratio = start_t_ratio;
+        for _ in 0..frames {
+            t_ratio += t_ratio_increment;
+            idx += t_ratio;
+            let value = interpolate_at(interpolator, interpolation, buf, idx).to_f64();
+            let reference = interpolate_reference(interpolator, buf, idx);
+            estimate.push(T::coerce(value - reference));
         }
+    }
+}
 
-        let mut idx = self.last_index;
-
-        let mut n = 0;
+/// Get the number of intermediate points that are combined for each output sample
+/// with the given interpolation type, see [OutputStage::last_intermediate_points].
+fn intermediate_points_per_frame(interpolation: &SincInterpolationType) -> usize {
+    match interpolation {
+        SincInterpolationType::Cubic | SincInterpolationType::Adaptive { .. } => 4,
+        SincInterpolationType::Quadratic => 3,
+        SincInterpolationType::Linear => 2,
+        SincInterpolationType::Nearest => 1,
+    }
+}
 
-        match self.interpolation {
-            SincInterpolationType::Cubic => {
-                let mut points = [T::zero(); 4];
-                let mut nearest = [(0isize, 0isize); 4];
-                while idx < end_idx as f64 {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[n] = interp_cubic(frac_offset, &points);
-                        }
-                    }
-                    n += 1;
+/// Record the intermediate points of `frames` output frames of the active channels,
+/// for `last_intermediate_points`, and the fractional positions between them.
+/// The positions are stepped in the same way as when processing, starting from `idx` and `t_ratio`.
+#[allow(clippy::too_many_arguments)]
+fn record_intermediate_points<T: Sample>(
+    points: &mut [Vec<T>],
+    positions: &mut Vec<f64>,
+    buffer: &[Vec<T>],
+    mask: &[bool],
+    frames: usize,
+    start_idx: f64,
+    start_t_ratio: f64,
+    t_ratio_increment: f64,
+    interpolator: &dyn SincInterpolator<T>,
+    interpolation: &SincInterpolationType,
+) {
+    let sinc_len = interpolator.len() as isize;
+    let factor = interpolator.nbr_sincs() as isize;
+    let mut nearest_4 = [(0isize, 0isize); 4];
+    let mut nearest_3 = [(0isize, 0isize); 3];
+    let mut nearest_2 = [(0isize, 0isize); 2];
+    let mut nearest_1 = [(0isize, 0isize); 1];
+    positions.clear();
+    let mut idx = start_idx;
+    let mut t_ratio = start_t_ratio;
+    for _ in 0..frames {
+        t_ratio += t_ratio_increment;
+        idx += t_ratio;
+        let scaled = idx * factor as f64;
+        positions.push(scaled - scaled.floor());
+    }
+    for ((chan_points, buf), active) in points.iter_mut().zip(buffer.iter()).zip(mask.iter()) {
+        chan_points.clear();
+        if !*active {
+            continue;
+        }
+        let mut idx = start_idx;
+        let mut t_ratio = start_t_ratio;
+        for _ in 0..frames {
+            t_ratio += t_ratio_increment;
+            idx += t_ratio;
+            let nearest: &[(isize, isize)] = match interpolation {
+                SincInterpolationType::Cubic | SincInterpolationType::Adaptive { .. } => {
+                    get_nearest_times_4(idx, factor, &mut nearest_4);
+                    &nearest_4
                 }
-            }
-            SincInterpolationType::Quadratic => {
-                let mut points = [T::zero(); 3];
-                let mut nearest = [(0isize, 0isize); 3];
-                while idx < end_idx as f64 {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    get_nearest_times_3(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[n] = interp_quad(frac_offset, &points);
-                        }
-                    }
-                    n += 1;
+                SincInterpolationType::Quadratic => {
+                    get_nearest_times_3(idx, factor, &mut nearest_3);
+                    &nearest_3
                 }
-            }
-            SincInterpolationType::Linear => {
-                let mut points = [T::zero(); 2];
-                let mut nearest = [(0isize, 0isize); 2];
-                while idx < end_idx as f64 {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[n] = interp_lin(frac_offset, &points);
-                        }
-                    }
-                    n += 1;
+                SincInterpolationType::Linear => {
+                    get_nearest_times_2(idx, factor, &mut nearest_2);
+                    &nearest_2
                 }
-            }
-            SincInterpolationType::Nearest => {
-                let mut point;
-                let mut nearest;
-                while idx < end_idx as f64 {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    nearest = get_nearest_time(idx, oversampling_factor as isize);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            point = self.interpolator.get_sinc_interpolated(
-                                buf,
-                                (nearest.0 + 2 * sinc_len as isize) as usize,
-                                nearest.1 as usize,
-                            );
-                            wave_out[chan].as_mut()[n] = point;
-                        }
-                    }
-                    n += 1;
+                SincInterpolationType::Nearest => {
+                    nearest_1[0] = get_nearest_time(idx, factor);
+                    &nearest_1
                 }
+            };
+            for n in nearest.iter() {
+                chan_points.push(interpolator.get_sinc_interpolated(
+                    buf,
+                    (n.0 + 2 * sinc_len) as usize,
+                    n.1 as usize,
+                ));
             }
         }
-
-        // Store last index for next iteration.
-        self.last_index = idx - self.chunk_size as f64;
-        self.resample_ratio = self.target_ratio;
-        trace!(
-            "Resampling channels {:?}, {} frames in, {} frames out",
-            active_channels_mask,
-            self.chunk_size,
-            n,
-        );
-        Ok((self.chunk_size, n))
     }
+}
 
-    fn output_frames_max(&self) -> usize {
-        // Set length to chunksize*ratio plus a safety margin of 10 elements.
-        (self.chunk_size as f64 * self.resample_ratio_original * self.max_relative_ratio + 10.0)
-            as usize
-    }
+/// The factor of the oversampling used to estimate the true peak.
+const TRUE_PEAK_OVERSAMPLING: usize = 4;
 
-    fn output_frames_next(&self) -> usize {
-        (self.chunk_size as f64 * (0.5 * self.resample_ratio + 0.5 * self.target_ratio) + 10.0)
-            as usize
+/// Measure the levels of `frames` frames of the active channels of the buffer,
+/// using the sincs to oversample the signal for the true peak.
+/// The block ends `sinc_len / 2` frames before the end of the new data,
+/// since the sincs for the last frames need input that is not yet available.
+/// The measured values are multiplied by `gain`.
+fn measure_loudness<T: Sample>(
+    interpolator: &dyn SincInterpolator<T>,
+    buffer: &[Vec<T>],
+    mask: &[bool],
+    frames: usize,
+    gain: f64,
+) -> LoudnessStats {
+    let sinc_len = interpolator.len();
+    let nbr_sincs = interpolator.nbr_sincs();
+    let mut stats = LoudnessStats {
+        frames,
+        ..LoudnessStats::default()
+    };
+    let mut sum = 0.0;
+    let mut samples = 0;
+    for (buf, _) in buffer
+        .iter()
+        .zip(mask.iter())
+        .filter(|(_, active)| **active)
+    {
+        for index in sinc_len..sinc_len + frames {
+            // The last sinc is centered `sinc_len / 2` frames after the index.
+            let value = buf[index + sinc_len / 2].to_f64().abs();
+            sum += value * value;
+            stats.sample_peak = stats.sample_peak.max(value);
+            stats.true_peak = stats.true_peak.max(value);
+            // The other points step back towards the previous frame.
+            for step in 1..TRUE_PEAK_OVERSAMPLING {
+                let subindex = nbr_sincs - 1 - step * nbr_sincs / TRUE_PEAK_OVERSAMPLING;
+                let point = interpolator.get_sinc_interpolated(buf, index, subindex);
+                stats.true_peak = stats.true_peak.max(point.to_f64().abs());
+            }
+        }
+        samples += frames;
     }
-
-    fn output_delay(&self) -> usize {
-        (self.interpolator.len() as f64 * self.resample_ratio / 2.0) as usize
+    if samples > 0 {
+        stats.rms = gain * (sum / samples as f64).sqrt();
     }
+    stats.sample_peak *= gain;
+    stats.true_peak *= gain;
+    stats
+}
 
-    fn nbr_channels(&self) -> usize {
-        self.nbr_channels
+/// Get the energy of the prototype filter of an interpolator, using the taps that are active,
+/// divided by the number of sincs.
+/// The taps are found by interpolating unit impulses, so that this works for any interpolator.
+fn filter_energy<T: Sample>(interpolator: &dyn SincInterpolator<T>) -> f64 {
+    let sinc_len = interpolator.len();
+    let nbr_sincs = interpolator.nbr_sincs();
+    let mut impulse = vec![T::zero(); sinc_len + 1];
+    let mut energy = 0.0;
+    for n in 0..sinc_len {
+        impulse[n] = T::one();
+        for subindex in 0..nbr_sincs {
+            let tap = interpolator
+                .get_sinc_interpolated(&impulse, 0, subindex)
+                .to_f64();
+            energy += tap * tap;
+        }
+        impulse[n] = T::zero();
     }
+    energy / nbr_sincs as f64
+}
 
-    fn input_frames_max(&self) -> usize {
-        self.chunk_size
+/// Calculate the group delay in input frames of the filter used by an interpolator,
+/// at a frequency relative to the Nyquist frequency of the input.
+/// The taps are found by interpolating unit impulses, so that this works for any interpolator.
+/// The branch where a linear phase filter is centered on an input frame is used.
+fn filter_group_delay<T: Sample>(
+    interpolator: &dyn SincInterpolator<T>,
+    normalized_freq: f64,
+) -> f64 {
+    let sinc_len = interpolator.len();
+    let subindex = interpolator.nbr_sincs() - 1;
+    let omega = std::f64::consts::PI * normalized_freq;
+    let mut impulse = vec![T::zero(); sinc_len + 1];
+    // The group delay is the real part of the transform of n * h[n] divided by that of h[n].
+    let (mut num_re, mut num_im, mut den_re, mut den_im) = (0.0, 0.0, 0.0, 0.0);
+    for n in 0..sinc_len {
+        impulse[n] = T::one();
+        let tap = interpolator
+            .get_sinc_interpolated(&impulse, 0, subindex)
+            .to_f64();
+        impulse[n] = T::zero();
+        let (sin, cos) = (omega * n as f64).sin_cos();
+        den_re += tap * cos;
+        den_im -= tap * sin;
+        num_re += n as f64 * tap * cos;
+        num_im -= n as f64 * tap * sin;
     }
+    (num_re * den_re + num_im * den_im) / (den_re * den_re + den_im * den_im)
+}
 
-    fn input_frames_next(&self) -> usize {
-        self.chunk_size
-    }
+/// Get the largest step backwards of the first output frame of a chunk,
+/// for a [SincFixedIn] with the given ratio range.
+///
+/// The last output frame of a chunk may overshoot the end by up to one time step.
+/// When the ratio is then increased, the first frame of the next chunk lands closer to
+/// that position, and the interpolation reaches further back into the buffered history.
+fn max_index_jump(resample_ratio: f64, max_resample_ratio_relative: f64) -> f64 {
+    (max_resample_ratio_relative / resample_ratio).ceil()
+        - 1.0 / (resample_ratio * max_resample_ratio_relative)
+}
 
-    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
-        trace!("Change resample ratio to {}", new_ratio);
-        if (new_ratio / self.resample_ratio_original >= 1.0 / self.max_relative_ratio)
-            && (new_ratio / self.resample_ratio_original <= self.max_relative_ratio)
-        {
-            if !ramp {
-                self.resample_ratio = new_ratio;
-            }
-            self.target_ratio = new_ratio;
-            Ok(())
-        } else {
-            Err(ResampleError::RatioOutOfBounds {
-                provided: new_ratio,
-                original: self.resample_ratio_original,
-                max_relative_ratio: self.max_relative_ratio,
-            })
-        }
-    }
+/// Get the shortest sinc length that a [SincFixedIn] supports for the given ratio range.
+/// The buffer holds `2 * sinc_len` frames of history, and the neighbor points used by
+/// the interpolation extend up to two frames further back than the output frame.
+fn min_sinc_len_fixed_in(resample_ratio: f64, max_resample_ratio_relative: f64) -> usize {
+    let frames = max_index_jump(resample_ratio, max_resample_ratio_relative) + 3.0;
+    8 * ((frames / 8.0).ceil() as usize)
+}
 
-    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
-        let new_ratio = self.resample_ratio_original * rel_ratio;
-        self.set_resample_ratio(new_ratio, ramp)
+/// Find the fraction `numerator / denominator` closest to `value`,
+/// with a denominator no larger than `max_denominator`.
+fn approximate_fraction(value: f64, max_denominator: usize) -> (usize, usize) {
+    // Walk the continued fraction expansion, keeping the last two convergents.
+    let (mut num_prev, mut num) = (0, 1);
+    let (mut den_prev, mut den) = (1, 0);
+    let mut remainder = value;
+    loop {
+        let whole = remainder.floor();
+        let next_num = whole as usize * num + num_prev;
+        let next_den = whole as usize * den + den_prev;
+        if next_den > max_denominator {
+            break;
+        }
+        (num_prev, num) = (num, next_num);
+        (den_prev, den) = (den, next_den);
+        let fract = remainder - whole;
+        if fract < 1.0e-9 || (num as f64 / den as f64 - value).abs() < 1.0e-12 * value {
+            break;
+        }
+        remainder = 1.0 / fract;
     }
+    (num, den)
+}
 
-    fn reset(&mut self) {
-        self.buffer
-            .iter_mut()
-            .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
-        self.channel_mask.iter_mut().for_each(|val| *val = true);
-        self.last_index = -((self.interpolator.len() / 2) as f64);
-        self.resample_ratio = self.resample_ratio_original;
-        self.target_ratio = self.resample_ratio_original;
+/// Check that the Nearest interpolation type gives exact results for the given ratio.
+/// The positions of the output samples are multiples of `1 / resample_ratio`.
+/// Writing the ratio as `p / q`, these all fall on intermediate points only when
+/// `p` divides the oversampling factor.
+fn validate_nearest(
+    resample_ratio: f64,
+    oversampling_factor: usize,
+) -> Result<(), ResamplerConstructionError> {
+    let (numerator, _) = approximate_fraction(resample_ratio, 1_000_000);
+    if numerator == 0 || oversampling_factor % numerator != 0 {
+        let suggested_oversampling = integer::lcm(oversampling_factor, numerator.max(1));
+        warn!(
+            "Nearest interpolation is not exact for ratio {} with oversampling_factor {}, use {} instead",
+            resample_ratio, oversampling_factor, suggested_oversampling
+        );
+        return Err(ResamplerConstructionError::NearestNotExact {
+            suggested_oversampling,
+        });
     }
+    Ok(())
 }
 
-impl<T> SincFixedOut<T>
+impl<T> SincFixedIn<T>
 where
     T: Sample,
 {
-    /// Create a new SincFixedOut.
+    /// Create a new SincFixedIn.
     ///
     /// Parameters are:
     /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
     /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio * 10.0` and `resample_ratio / 10.0`.
     /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
-    /// - `chunk_size`: Size of output data in frames.
-    /// - `nbr_channels`: Number of channels in input/output.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output, must be > 0.
+    ///
+    /// Strong downsampling combined with a large `max_resample_ratio_relative` requires a
+    /// longer sinc filter. A too short `sinc_len` gives a [ResamplerConstructionError::SincLenTooShort]
+    /// error with the minimum supported length.
     pub fn new(
         resample_ratio: f64,
         max_resample_ratio_relative: f64,
@@ -562,7 +1411,15 @@ where
             "Create new SincFixedIn, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
             resample_ratio, chunk_size, nbr_channels, parameters
         );
-        let interpolator = make_interpolator(
+        validate_sinc_config(
+            &parameters,
+            resample_ratio,
+            max_resample_ratio_relative,
+            chunk_size,
+            nbr_channels,
+        )?;
+
+        let (interpolator, cpu_feature) = make_interpolator(
             parameters.sinc_len,
             resample_ratio,
             parameters.f_cutoff,
@@ -570,17 +1427,20 @@ where
             parameters.window,
         );
 
-        Self::new_with_interpolator(
+        let mut resampler = Self::new_with_interpolator(
             resample_ratio,
             max_resample_ratio_relative,
-            parameters.interpolation,
+            parameters.interpolation.clone(),
             interpolator,
             chunk_size,
             nbr_channels,
-        )
+        )?;
+        resampler.parameters = Some(parameters);
+        resampler.cpu_feature = cpu_feature;
+        Ok(resampler)
     }
 
-    /// Create a new SincFixedOut using an existing Interpolator.
+    /// Create a new SincFixedIn using an existing Interpolator.
     ///
     /// Parameters are:
     /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
@@ -588,7 +1448,7 @@ where
     /// - `interpolation_type`: Parameters for interpolation, see `SincInterpolationParameters`.
     /// - `interpolator`: The interpolator to use.
     /// - `chunk_size`: Size of output data in frames.
-    /// - `nbr_channels`: Number of channels in input/output.
+    /// - `nbr_channels`: Number of channels in input/output, must be > 0.
     pub fn new_with_interpolator(
         resample_ratio: f64,
         max_resample_ratio_relative: f64,
@@ -598,401 +1458,2278 @@ where
         nbr_channels: usize,
     ) -> Result<Self, ResamplerConstructionError> {
         validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        validate_channels(nbr_channels)?;
+        let minimum = min_sinc_len_fixed_in(resample_ratio, max_resample_ratio_relative);
+        if interpolator.len() < minimum {
+            return Err(ResamplerConstructionError::SincLenTooShort {
+                provided: interpolator.len(),
+                minimum,
+            });
+        }
+        Ok(Self::from_validated_parts(
+            resample_ratio,
+            max_resample_ratio_relative,
+            interpolation_type,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        ))
+    }
+
+    /// Create a new SincFixedIn like [new_with_interpolator](SincFixedIn::new_with_interpolator),
+    /// from parameters that have already been validated.
+    pub(crate) fn from_validated_parts(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        interpolation_type: SincInterpolationType,
+        interpolator: Box<dyn SincInterpolator<T>>,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Self {
+        let buffer = vec![vec![T::zero(); chunk_size + 2 * interpolator.len()]; nbr_channels];
 
-        let needed_input_size =
-            (chunk_size as f64 / resample_ratio).ceil() as usize + interpolator.len() / 2;
-        let buffer_channel_length = ((max_resample_ratio_relative + 1.0) * needed_input_size as f64)
-            as usize
-            + 2 * interpolator.len();
-        let buffer = vec![vec![T::zero(); buffer_channel_length]; nbr_channels];
         let channel_mask = vec![true; nbr_channels];
 
-        Ok(SincFixedOut {
+        // Set length to chunksize*ratio plus a safety margin of 10 elements.
+        let max_frames =
+            (chunk_size as f64 * resample_ratio * max_resample_ratio_relative + 10.0) as usize;
+        let points_per_frame = intermediate_points_per_frame(&interpolation_type);
+        SincFixedIn {
             nbr_channels,
             chunk_size,
-            needed_input_size,
             last_index: -((interpolator.len() / 2) as f64),
-            current_buffer_fill: needed_input_size,
             resample_ratio,
             resample_ratio_original: resample_ratio,
             target_ratio: resample_ratio,
             max_relative_ratio: max_resample_ratio_relative,
             interpolator,
             buffer,
-            interpolation: interpolation_type,
+            interpolation: interpolation_type.clone(),
+            full_interpolation: interpolation_type,
             channel_mask,
-        })
+            lookahead: 0,
+            lookahead_used: 0,
+            fixed_output_size: false,
+            fixed_size_threshold: None,
+            output_rounding: OutputRounding::Natural,
+            rounding_remainder: 0.0,
+            rounding_carry: vec![Vec::new(); nbr_channels],
+            carried_frames: 0,
+            parameters: None,
+            cpu_feature: None,
+            deterministic: false,
+            rms_matcher: RmsMatcher::new(),
+            output_stage: OutputStage::new(nbr_channels, max_frames, points_per_frame),
+            auto_scale: AutoScale::new(),
+            emphasis: Emphasis::new(),
+            adaptive_counts: [0; 2],
+            time_budget: None,
+            degraded_chunks: 0,
+            channel_layout: None,
+            edge_mode: EdgeMode::Zero,
+            at_start: true,
+            flush_subnormals: false,
+            max_output_frames: usize::MAX,
+            interleaved: None,
+        }
     }
-}
 
-impl<T> Resampler<T> for SincFixedOut<T>
-where
-    T: Sample,
-{
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
-        &mut self,
-        wave_in: &[Vin],
-        wave_out: &mut [Vout],
-        active_channels_mask: Option<&[bool]>,
-    ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
+    /// Get the layout of the polyphase filter bank, as `(branches, taps_per_branch)`.
+    ///
+    /// The number of branches equals the oversampling factor,
+    /// and the number of taps per branch is the sinc length after rounding up to a multiple of 8.
+    /// With [Quality::Preview], the number of taps is the reduced number actually in use.
+    /// The filter bank always stores the full sinc length,
+    /// using `branches * sinc_len` samples of memory.
+    pub fn polyphase_info(&self) -> (usize, usize) {
+        (
+            self.interpolator.nbr_sincs(),
+            self.interpolator.active_len(),
+        )
+    }
+
+    /// Get the CPU feature used by the interpolator, or None for the scalar interpolator.
+    ///
+    /// This is the fastest feature detected when the resampler was created,
+    /// or the one selected with [force_isa](SincFixedIn::force_isa).
+    /// Resamplers created with [new_with_interpolator](SincFixedIn::new_with_interpolator)
+    /// return None, since the type of a custom interpolator is not known.
+    pub fn cpu_feature(&self) -> Option<CpuFeature> {
+        self.cpu_feature
+    }
+
+    /// Replace the interpolator with the one using the given CPU feature,
+    /// for example to test the SSE interpolator on a machine that supports AVX.
+    ///
+    /// [CpuFeature::Fma] selects the AVX interpolator, which needs both AVX and FMA.
+    /// Returns a [MissingCpuFeature] error if the feature is not detected,
+    /// and the current interpolator is then kept.
+    /// The sincs are calculated anew, and the buffered input and the quality level are kept.
+    /// The output may differ from that of other interpolators in the last bits,
+    /// because of different rounding.
+    /// Resamplers created with [new_with_interpolator](SincFixedIn::new_with_interpolator)
+    /// keep their interpolator, and this does nothing.
+    /// Note that this method allocates the new sincs.
+    pub fn force_isa(&mut self, feature: CpuFeature) -> Result<(), MissingCpuFeature> {
+        let parameters = match self.parameters.as_ref() {
+            Some(parameters) => parameters,
+            None => return Ok(()),
+        };
+        let (interpolator, feature) = make_interpolator_for_feature(
+            feature,
+            parameters.sinc_len,
+            self.resample_ratio_original,
+            parameters.f_cutoff,
+            parameters.oversampling_factor,
+            parameters.window,
+        )?;
+        self.replace_interpolator(interpolator, Some(feature));
+        self.deterministic = false;
+        Ok(())
+    }
+
+    /// Enable or disable the deterministic mode, where the output is the same on all platforms.
+    ///
+    /// The SIMD interpolators sum the products of the input and the sincs in an order that depends
+    /// on the instruction set, and the output then differs in the last bits between platforms.
+    /// In deterministic mode, the plain scalar interpolator is used regardless of the available
+    /// CPU features, which sums the products in a single well-defined order.
+    /// The compiler may vectorize this code, but does not reorder the floating point operations
+    /// or fuse them into multiply-add instructions, so the results are not affected.
+    /// The processing is slower than with the SIMD interpolators.
+    /// [cpu_feature](SincFixedIn::cpu_feature) returns None while the mode is enabled.
+    ///
+    /// The output is bit-identical for the same input and settings on all platforms,
+    /// provided that the sincs are identical. The sincs are calculated once, in double precision,
+    /// using the sine and cosine functions of the platform math library.
+    /// These are correctly rounded for practically all arguments on the common platforms,
+    /// but this is not guaranteed by Rust. The other settings, like the
+    /// [interleaved buffer](SincFixedIn::set_interleaved_buffer), also use a fixed order of operations.
+    ///
+    /// Disabling switches back to the fastest interpolator. Calling [force_isa](SincFixedIn::force_isa)
+    /// also disables the mode. The mode is kept by [rebuild_with](SincFixedIn::rebuild_with).
+    /// The sincs are calculated anew, and the buffered input and the quality level are kept.
+    /// Resamplers created with [new_with_interpolator](SincFixedIn::new_with_interpolator)
+    /// keep their interpolator, and this does nothing.
+    /// Note that this method allocates the new sincs.
+    pub fn set_deterministic(&mut self, enable: bool) {
+        let parameters = match self.parameters.as_ref() {
+            Some(parameters) => parameters,
+            None => return,
+        };
+        let (interpolator, feature) = if enable {
+            (
+                make_scalar_interpolator(
+                    parameters.sinc_len,
+                    self.resample_ratio_original,
+                    parameters.f_cutoff,
+                    parameters.oversampling_factor,
+                    parameters.window,
+                ),
+                None,
+            )
         } else {
-            update_mask_from_buffers(&mut self.channel_mask);
+            make_interpolator(
+                parameters.sinc_len,
+                self.resample_ratio_original,
+                parameters.f_cutoff,
+                parameters.oversampling_factor,
+                parameters.window,
+            )
         };
+        self.replace_interpolator(interpolator, feature);
+        self.deterministic = enable;
+    }
 
-        validate_buffers(
-            wave_in,
-            wave_out,
-            &self.channel_mask,
-            self.nbr_channels,
-            self.needed_input_size,
-            self.chunk_size,
-        )?;
-        let sinc_len = self.interpolator.len();
-        let oversampling_factor = self.interpolator.nbr_sincs();
+    /// Check if the deterministic mode is enabled, see [set_deterministic](SincFixedIn::set_deterministic).
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
 
-        for buf in self.buffer.iter_mut() {
-            buf.copy_within(
-                self.current_buffer_fill..self.current_buffer_fill + 2 * sinc_len,
-                0,
-            );
+    /// Replace the interpolator, keeping the number of active taps, and update the buffers that depend on it.
+    fn replace_interpolator(
+        &mut self,
+        mut interpolator: Box<dyn SincInterpolator<T>>,
+        feature: Option<CpuFeature>,
+    ) {
+        interpolator.set_active_len(self.interpolator.active_len());
+        self.interpolator = interpolator;
+        self.cpu_feature = feature;
+        if let Some(interleaved) = self.interleaved.as_mut() {
+            interleaved.update_sincs(&*self.interpolator);
         }
-        self.current_buffer_fill = self.needed_input_size;
+    }
 
-        for (chan, active) in self.channel_mask.iter().enumerate() {
-            if *active {
-                debug_assert!(self.chunk_size <= wave_out[chan].as_mut().len());
-                self.buffer[chan][2 * sinc_len..2 * sinc_len + self.needed_input_size]
-                    .copy_from_slice(&wave_in[chan].as_ref()[..self.needed_input_size]);
-            }
-        }
+    /// Get the number of frames of input history kept per channel between chunks.
+    ///
+    /// The history is `2 * sinc_len` frames, where `sinc_len` is the sinc length
+    /// after rounding up to a multiple of 8.
+    /// The output is delayed by half of the sinc length, see [output_delay](Resampler::output_delay).
+    /// The history does not depend on the resampling ratio.
+    /// Note that the internal buffer also holds the current chunk,
+    /// which for [SincFixedOut] is sized for the largest allowed ratio.
+    pub fn delay_buffer_len(&self) -> usize {
+        2 * self.interpolator.len()
+    }
 
-        let mut idx = self.last_index;
-        let mut t_ratio = 1.0 / self.resample_ratio;
-        let t_ratio_end = 1.0 / self.target_ratio;
-        let t_ratio_increment = (t_ratio_end - t_ratio) / self.chunk_size as f64;
+    /// Get the group delay of the anti-aliasing filter at a given frequency, in output frames.
+    ///
+    /// The frequency `normalized_freq` is relative to the Nyquist frequency of the input,
+    /// with 1.0 meaning half the input sample rate.
+    /// The delay is calculated from the phase response of the filter,
+    /// and is scaled by the current resampling ratio.
+    /// For the linear phase filters made from [SincInterpolationParameters],
+    /// the delay is the same at all frequencies, and equals half the sinc length.
+    /// A custom interpolator, see [new_with_interpolator](SincFixedIn::new_with_interpolator),
+    /// may use filters where the delay depends on the frequency.
+    /// The result is only meaningful in the passband,
+    /// since the phase is poorly defined where the filter response is close to zero.
+    pub fn group_delay_at(&self, normalized_freq: f64) -> f64 {
+        filter_group_delay(&*self.interpolator, normalized_freq) * self.resample_ratio
+    }
 
-        match self.interpolation {
-            SincInterpolationType::Cubic => {
-                let mut points = [T::zero(); 4];
-                let mut nearest = [(0isize, 0isize); 4];
-                for frame in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[frame] = interp_cubic(frac_offset, &points);
-                        }
-                    }
-                }
-            }
-            SincInterpolationType::Quadratic => {
-                let mut points = [T::zero(); 3];
-                let mut nearest = [(0isize, 0isize); 3];
-                for frame in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    get_nearest_times_3(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[frame] = interp_quad(frac_offset, &points);
-                        }
-                    }
-                }
-            }
-            SincInterpolationType::Linear => {
-                let mut points = [T::zero(); 2];
-                let mut nearest = [(0isize, 0isize); 2];
-                for frame in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                                *p = self.interpolator.get_sinc_interpolated(
-                                    buf,
-                                    (n.0 + 2 * sinc_len as isize) as usize,
-                                    n.1 as usize,
-                                );
-                            }
-                            wave_out[chan].as_mut()[frame] = interp_lin(frac_offset, &points);
-                        }
-                    }
-                }
-            }
-            SincInterpolationType::Nearest => {
-                let mut point;
-                let mut nearest;
-                for frame in 0..self.chunk_size {
-                    t_ratio += t_ratio_increment;
-                    idx += t_ratio;
-                    nearest = get_nearest_time(idx, oversampling_factor as isize);
-                    for (chan, active) in self.channel_mask.iter().enumerate() {
-                        if *active {
-                            let buf = &self.buffer[chan];
-                            point = self.interpolator.get_sinc_interpolated(
-                                buf,
-                                (nearest.0 + 2 * sinc_len as isize) as usize,
-                                nearest.1 as usize,
-                            );
-                            wave_out[chan].as_mut()[frame] = point;
-                        }
-                    }
-                }
-            }
-        }
+    /// Get the DC gain of the anti-aliasing filter.
+    ///
+    /// The taps of each sinc in the filter bank are normalized to sum to one,
+    /// so that a constant input gives the same constant output at every output frame,
+    /// without any ripple or droop. The returned value is the mean of the sums of the sincs,
+    /// and should equal 1.0 to within the rounding errors of the sample type.
+    /// With [Quality::Preview], only the central taps are used, and the gain deviates slightly from one.
+    /// Interpolators created from custom sincs return the gain of those.
+    pub fn dc_gain(&self) -> f64 {
+        filter_dc_gain(&*self.interpolator)
+    }
 
-        // Store last index for next iteration.
-        let input_frames_used = self.needed_input_size;
-        self.last_index = idx - self.current_buffer_fill as f64;
-        self.resample_ratio = self.target_ratio;
-        self.needed_input_size = (self.last_index as f32
-            + self.chunk_size as f32 / self.resample_ratio as f32
-            + sinc_len as f32)
-            .ceil() as usize;
-        trace!(
-            "Resampling channels {:?}, {} frames in, {} frames out. Next needed length: {} frames, last index {}",
-            active_channels_mask,
-            self.current_buffer_fill,
-            self.chunk_size,
-            self.needed_input_size,
-            self.last_index
-        );
-        Ok((input_frames_used, self.chunk_size))
+    /// Get the energy of the anti-aliasing filter, as the sum of the squares of the taps
+    /// of the prototype filter, divided by the oversampling factor.
+    ///
+    /// The prototype filter is the long filter that the sincs of the filter bank are taken from.
+    /// Dividing by the oversampling factor gives the mean of the energies of the individual sincs,
+    /// which is the energy of the filter at the input sample rate,
+    /// and does not depend on the oversampling factor.
+    /// Since the sincs are normalized to unity gain at DC, by Parseval's theorem
+    /// the energy of an ideal low-pass filter equals its relative cutoff frequency.
+    /// For the windowed sincs, the energy is slightly lower than the cutoff
+    /// given by [SincInterpolationParameters::f_cutoff], because of the transition band.
+    /// When downsampling, the cutoff is scaled by the resampling ratio the resampler
+    /// was created with, and the energy is scaled by approximately the same factor.
+    /// A value that is far from the expected one means that the configuration
+    /// attenuates or boosts the signal.
+    /// Note that this method allocates, and evaluates every tap of the filter bank.
+    pub fn filter_energy(&self) -> f64 {
+        filter_energy(&*self.interpolator)
     }
 
-    fn input_frames_max(&self) -> usize {
-        (self.chunk_size as f64 / self.resample_ratio_original * self.max_relative_ratio).ceil()
-            as usize
-            + 2
-            + self.interpolator.len() / 2
+    /// Get the fractional phase of the resampler, as a value in the range `[0, 1)`.
+    ///
+    /// This is the sub-sample position between two input frames, where the next output frame
+    /// will be calculated. It advances by `1 / resample_ratio` for each output frame,
+    /// and wraps around at each input frame.
+    /// Comparing the phases of two resamplers that process the same input
+    /// shows how far apart their output frames are, in fractions of an input frame.
+    /// A new or reset resampler has the phase of `1 / resample_ratio`, modulo 1.
+    pub fn fractional_phase(&self) -> f64 {
+        next_frame_phase(self.last_index, self.resample_ratio)
     }
 
-    fn input_frames_next(&self) -> usize {
-        self.needed_input_size
+    /// Get the number of output samples calculated with linear and with cubic interpolation,
+    /// as a tuple `(linear, cubic)`.
+    /// The counts are only updated with [SincInterpolationType::Adaptive],
+    /// and each active channel is counted separately.
+    /// They are cleared by [reset](Resampler::reset).
+    pub fn adaptive_counts(&self) -> (usize, usize) {
+        (self.adaptive_counts[0], self.adaptive_counts[1])
     }
 
-    fn nbr_channels(&self) -> usize {
-        self.nbr_channels
+    /// Check if the resampler was created with the given configuration.
+    ///
+    /// This is intended for deciding if an existing resampler can be reused,
+    /// instead of creating a new one.
+    /// The interpolation parameters and number of channels must be equal,
+    /// and the original resample ratio must match `resample_ratio` within a relative tolerance of 1e-9.
+    /// The chunk size and the maximum relative ratio are not compared.
+    /// A resampler created with [new_with_interpolator](SincFixedIn::new_with_interpolator)
+    /// never matches, since the parameters of a custom interpolator are not known.
+    pub fn config_eq(
+        &self,
+        parameters: &SincInterpolationParameters,
+        resample_ratio: f64,
+        nbr_channels: usize,
+    ) -> bool {
+        config_matches(
+            &self.parameters,
+            self.resample_ratio_original,
+            self.nbr_channels,
+            parameters,
+            resample_ratio,
+            nbr_channels,
+        )
     }
 
-    fn output_frames_max(&self) -> usize {
-        self.chunk_size
+    /// Create a new resampler with different interpolation parameters,
+    /// for example when tweaking the window or sinc length interactively.
+    ///
+    /// The new resampler gets the original resample ratio, maximum relative ratio,
+    /// chunk size and number of channels of this one, and only the sinc table is calculated anew.
+    /// The buffers are allocated for the same maximum number of channels,
+    /// see [set_max_channels](SincFixedIn::set_max_channels), and the settings made with
+    /// [set_flush_subnormals](SincFixedIn::set_flush_subnormals),
+    /// [set_interleaved_buffer](SincFixedIn::set_interleaved_buffer),
+    /// [set_deterministic](SincFixedIn::set_deterministic) and
+    /// [set_max_output_frames](Resampler::set_max_output_frames) are kept.
+    /// Other settings, and the buffered input, start out as for a resampler created with
+    /// [new](SincFixedIn::new), and the output is identical to that of such a resampler.
+    /// This resampler is left unchanged, so that it can continue to be used if the new
+    /// parameters are rejected with a [ResamplerConstructionError].
+    pub fn rebuild_with(
+        &self,
+        parameters: SincInterpolationParameters,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let mut resampler = Self::new(
+            self.resample_ratio_original,
+            self.max_relative_ratio,
+            parameters,
+            self.chunk_size,
+            self.nbr_channels,
+        )?;
+        resampler.set_max_channels(self.buffer.len());
+        resampler.flush_subnormals = self.flush_subnormals;
+        resampler.max_output_frames = self.max_output_frames;
+        if self.deterministic {
+            resampler.set_deterministic(true);
+        }
+        resampler.set_interleaved_buffer(self.interleaved.is_some());
+        Ok(resampler)
     }
 
-    fn output_frames_next(&self) -> usize {
-        self.chunk_size
+    /// Consume a chunk of input, and return the oversampled signal instead of the resampled one.
+    ///
+    /// The sinc filter bank interpolates the input at `oversampling_factor` points per input frame,
+    /// and the normal output is obtained by interpolating between these points.
+    /// This returns the points themselves, for use in a custom filter or decimator.
+    /// The sample rate of the result is `oversampling_factor` times the input sample rate,
+    /// and each channel has exactly `chunk_size * oversampling_factor` samples.
+    /// Sample `j` of the block returned for chunk number `c` (counted from 0 after creating
+    /// or resetting the resampler) is centered at input frame
+    /// `c * chunk_size + j / oversampling_factor - sinc_len / 2 - 1`,
+    /// which means that the block is delayed by `sinc_len / 2 + 1` input frames,
+    /// with `sinc_len` the length after rounding up to a multiple of 8.
+    ///
+    /// All channels are processed. The input filtering, flushing of tiny values
+    /// and [automatic scaling](SincFixedIn::set_auto_scale) apply as usual,
+    /// while the output stage, RMS matching and output emphasis, is not used.
+    /// Afterwards the resampler is in the same state as if the chunk had been processed with
+    /// [process_into_buffer](Resampler::process_into_buffer), so that the calls can be mixed.
+    /// Note that this method allocates the returned vectors.
+    pub fn oversampled_block<Vin: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+    ) -> ResampleResult<Vec<Vec<T>>> {
+        let sinc_len = self.interpolator.len();
+        let oversampling_factor = self.interpolator.nbr_sincs();
+        let frames = self.chunk_size * oversampling_factor;
+        let mut wave_out = make_buffer(self.nbr_channels, frames, true);
+        validate_buffers(
+            wave_in,
+            &mut wave_out,
+            None,
+            self.nbr_channels,
+            self.chunk_size,
+            frames,
+        )?;
+        self.update_channel_mask(None);
+        self.skip_chunk();
+        self.store_chunk(wave_in);
+        // The points start at time `-sinc_len`, the earliest that the buffer covers.
+        for (buf, out) in self.buffer.iter().zip(wave_out.iter_mut()) {
+            for (j, value) in out.iter_mut().enumerate() {
+                *value = self.interpolator.get_sinc_interpolated(
+                    buf,
+                    sinc_len + j / oversampling_factor,
+                    j % oversampling_factor,
+                );
+            }
+        }
+        self.auto_scale
+            .apply_output(&mut wave_out, frames, &self.channel_mask);
+        Ok(wave_out)
     }
 
-    fn output_delay(&self) -> usize {
-        (self.interpolator.len() as f64 * self.resample_ratio / 2.0) as usize
+    /// Enable or disable storing the delay lines of all channels in a single interleaved buffer.
+    ///
+    /// Normally each channel has its own buffer, and the scalar products with the sincs
+    /// are calculated one channel at a time.
+    /// For many channels, this gives a poor cache behavior since each output frame
+    /// reads from as many separate buffers as there are channels.
+    /// When enabled, the input is also stored in one buffer where the samples of each frame
+    /// are next to each other, and the scalar products are calculated for all channels at once,
+    /// with the channels in the inner loop. This is contiguous in memory,
+    /// and is vectorized by the compiler instead of using the SIMD interpolators.
+    /// Whether it is faster depends on the platform and the number of channels,
+    /// compare using the `bench_sinc_multichannel` benchmark.
+    ///
+    /// The output matches that of the normal processing to within rounding errors,
+    /// since the products are summed in a different order.
+    /// The [SincInterpolationType::Adaptive] mode always processes the channels separately.
+    /// Enabling allocates the buffer and reads the sincs out of the interpolator,
+    /// and should not be done from a realtime thread. It is disabled by default.
+    pub fn set_interleaved_buffer(&mut self, enable: bool) {
+        self.interleaved = if enable {
+            Some(InterleavedBuffer::new(&*self.interpolator, &self.buffer))
+        } else {
+            None
+        };
     }
 
-    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
-        trace!("Change resample ratio to {}", new_ratio);
-        if (new_ratio / self.resample_ratio_original >= 1.0 / self.max_relative_ratio)
-            && (new_ratio / self.resample_ratio_original <= self.max_relative_ratio)
-        {
-            if !ramp {
-                self.resample_ratio = new_ratio;
-            }
-            self.target_ratio = new_ratio;
+    /// Enable or disable flushing of tiny values in the input to zero.
+    ///
+    /// Long decaying tails, like the end of a reverb, eventually reach subnormal numbers.
+    /// Arithmetic with subnormals is very slow on many CPUs, and processing such tails
+    /// can then take many times longer than processing normal audio.
+    /// When enabled, input values with a magnitude below 1e-20 (about -400 dB)
+    /// are replaced by zero as they are copied into the delay line,
+    /// which keeps the scalar products free of subnormals.
+    /// The input is processed as usual once it rises above the threshold again.
+    /// This is disabled by default, which keeps the output bit-exact to the input data.
+    pub fn set_flush_subnormals(&mut self, enable: bool) {
+        self.flush_subnormals = enable;
+    }
 
-            self.needed_input_size = (self.last_index as f32
-                + self.chunk_size as f32
-                    / (0.5 * self.resample_ratio as f32 + 0.5 * self.target_ratio as f32)
-                + self.interpolator.len() as f32)
-                .ceil() as usize;
-            Ok(())
-        } else {
-            Err(ResampleError::RatioOutOfBounds {
-                provided: new_ratio,
-                original: self.resample_ratio_original,
-                max_relative_ratio: self.max_relative_ratio,
-            })
-        }
+    /// Choose how the history of the filter is filled at the start of a stream.
+    ///
+    /// The history holds the input before the first chunk, which the filter needs
+    /// for calculating the first output frames. With [EdgeMode::Zero] it is silence,
+    /// and with [EdgeMode::Hold] it is filled with the first sample of each channel
+    /// from the first chunk, after any [emphasis](SincFixedIn::set_emphasis) filtering.
+    /// The mode only affects the start of a stream, after creating or resetting the resampler,
+    /// and a change takes effect at the next start.
+    /// The history of channels that become active again is always cleared to silence.
+    /// The default is [EdgeMode::Zero], and the mode is not changed by [reset](Resampler::reset).
+    pub fn set_edge_mode(&mut self, mode: EdgeMode) {
+        self.edge_mode = mode;
     }
 
-    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
-        let new_ratio = self.resample_ratio_original * rel_ratio;
-        self.set_resample_ratio(new_ratio, ramp)
+    /// Enable or disable automatic scaling of very quiet input.
+    ///
+    /// The scalar products with the sincs lose precision when the input is so small
+    /// that the products become subnormal, which for `f32` happens for values below about 1e-38.
+    /// When enabled, the buffered input is multiplied by a power of two that brings its peak
+    /// to between 0.5 and 1.0 before filtering, and the output is scaled back by the inverse.
+    /// The factor is updated for each chunk, from the peak of the new input and the buffered history.
+    /// Scaling is only applied when the peak is below 2^-32, about -193 dB.
+    /// Normal audio in the range [-1, 1] is therefore not scaled,
+    /// and since multiplying by a power of two is exact, its output is identical
+    /// to that without automatic scaling.
+    /// This is disabled by default.
+    pub fn set_auto_scale(&mut self, enable: bool) {
+        self.auto_scale.set_enabled(enable);
     }
 
-    fn reset(&mut self) {
+    /// Enable or disable matching the RMS level of the output to that of the input.
+    ///
+    /// When enabled, the RMS levels of the input and output of each chunk are measured,
+    /// and a gain is applied to the output to keep the levels equal.
+    /// This compensates for any level change caused by the anti-aliasing filter,
+    /// for example when part of the signal lies above the cutoff frequency.
+    /// The gain is smoothed over several chunks, and ramped within each chunk,
+    /// to avoid pumping on transients. Silent chunks leave the gain unchanged.
+    /// Changing the setting resets the gain to unity. It is disabled by default.
+    pub fn set_match_rms(&mut self, enable: bool) {
+        self.rms_matcher.set_enabled(enable);
+    }
+
+    /// Get the output stage, with the settings and results of the processing
+    /// that is applied to the output after the interpolation, see [OutputStage].
+    pub fn output_stage(&self) -> &OutputStage<T> {
+        &self.output_stage
+    }
+
+    /// Get the output stage for changing its settings, see [OutputStage].
+    pub fn output_stage_mut(&mut self) -> &mut OutputStage<T> {
+        &mut self.output_stage
+    }
+
+    /// Reset the state of the resampler, like [reset](Resampler::reset), but keep the statistics.
+    ///
+    /// This clears the delay line, the fractional position, any ratio change and the
+    /// state of the output stage, so that a new stream can be processed.
+    /// The statistics that are accumulated over several chunks are kept:
+    /// the [output checksum](OutputStage::output_checksum),
+    /// the [adaptive interpolation counts](SincFixedIn::adaptive_counts)
+    /// and the number of [degraded chunks](SincFixedIn::degraded_chunks).
+    /// The [clipped samples](OutputStage::last_clips) and [levels](OutputStage::last_loudness)
+    /// of the last chunk are also kept until the next chunk is processed.
+    /// This allows the metering to be aggregated over several streams processed by one resampler.
+    /// A full [reset](Resampler::reset) clears the statistics as well.
+    pub fn reset_state_only(&mut self) {
         self.buffer
             .iter_mut()
             .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
-        self.needed_input_size = (self.chunk_size as f64 / self.resample_ratio_original).ceil()
-            as usize
-            + self.interpolator.len() / 2;
-        self.current_buffer_fill = self.needed_input_size;
-        self.last_index = -((self.interpolator.len() / 2) as f64);
         self.channel_mask.iter_mut().for_each(|val| *val = true);
+        self.last_index = -((self.interpolator.len() / 2) as f64);
         self.resample_ratio = self.resample_ratio_original;
         self.target_ratio = self.resample_ratio_original;
+        self.lookahead_used = self.lookahead;
+        self.rounding_remainder = 0.0;
+        self.carried_frames = 0;
+        self.fixed_size_threshold = None;
+        self.rms_matcher.reset();
+        self.auto_scale.reset();
+        self.at_start = true;
+        self.emphasis.reset();
+        self.output_stage.reset_state();
+        if let Some(interleaved) = self.interleaved.as_mut() {
+            interleaved.clear();
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{interp_cubic, interp_lin};
-    use crate::Resampler;
-    use crate::SincInterpolationParameters;
-    use crate::SincInterpolationType;
-    use crate::WindowFunction;
-    use crate::{check_output, check_ratio};
-    use crate::{SincFixedIn, SincFixedOut};
-    use rand::Rng;
+    /// Set a time budget for processing each chunk, or disable it with a zero duration.
+    ///
+    /// With a budget, the elapsed time is checked at regular intervals while a chunk is processed.
+    /// When the chunk is projected to take longer than the budget, the remainder of the chunk
+    /// is calculated at the [Quality::Preview] level, and the next chunk starts again at the
+    /// current quality level. The number of chunks where this happened can be read with
+    /// [degraded_chunks](SincFixedIn::degraded_chunks).
+    ///
+    /// This is best effort, and the processing never fails because of the budget.
+    /// A chunk can still take longer than the budget, for example when the thread is preempted,
+    /// or when the lower quality level is also too slow.
+    /// While a budget is set, the output values are calculated one by one for each channel,
+    /// without the faster paths for two channels or an interleaved buffer.
+    /// The budget is disabled by default.
+    pub fn set_time_budget(&mut self, budget: Duration) {
+        self.time_budget = if budget.is_zero() { None } else { Some(budget) };
+    }
 
-    fn basic_params() -> SincInterpolationParameters {
-        SincInterpolationParameters {
-            sinc_len: 64,
-            f_cutoff: 0.95,
-            interpolation: SincInterpolationType::Cubic,
-            oversampling_factor: 16,
-            window: WindowFunction::BlackmanHarris2,
-        }
+    /// Get the number of chunks that were partly processed at a lower quality level
+    /// to stay within the time budget, see [set_time_budget](SincFixedIn::set_time_budget).
+    /// The count is cleared by [reset](Resampler::reset).
+    pub fn degraded_chunks(&self) -> usize {
+        self.degraded_chunks
     }
 
-    #[test]
-    fn int_cubic() {
-        let params = basic_params();
-        let _resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        let yvals = [0.0f64, 2.0f64, 4.0f64, 6.0f64];
-        let interp = interp_cubic(0.5f64, &yvals);
-        assert_eq!(interp, 3.0f64);
+    /// Set the quality level, see [Quality].
+    ///
+    /// This changes the interpolation type and the number of sinc taps that are used,
+    /// without reallocating or recalculating the sincs.
+    /// The change takes effect from the next processed chunk.
+    /// The output delay is unchanged, since the sincs are shortened equally at both ends.
+    /// The reduced number of taps can be read with [polyphase_info](SincFixedIn::polyphase_info).
+    pub fn set_quality(&mut self, quality: Quality) {
+        let (interpolation, taps) =
+            quality_settings(quality, &self.full_interpolation, self.interpolator.len());
+        self.set_active_taps(taps);
+        self.output_stage
+            .set_points_per_frame(intermediate_points_per_frame(&interpolation));
+        self.interpolation = interpolation;
     }
 
-    #[test]
-    fn int_lin_32() {
-        let params = basic_params();
-        let _resampler = SincFixedIn::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        let yvals = [1.0f32, 5.0f32];
-        let interp = interp_lin(0.25f32, &yvals);
-        assert_eq!(interp, 2.0f32);
+    /// Limit the scalar products to `taps` taps of each sinc, also for the interleaved buffer.
+    fn set_active_taps(&mut self, taps: usize) {
+        self.interpolator.set_active_len(taps);
+        if let Some(interleaved) = self.interleaved.as_mut() {
+            interleaved.update_sincs(&*self.interpolator);
+        }
     }
 
-    #[test]
-    fn int_cubic_32() {
-        let params = basic_params();
-        let _resampler = SincFixedIn::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        let yvals = [0.0f32, 2.0f32, 4.0f32, 6.0f32];
-        let interp = interp_cubic(0.5f32, &yvals);
-        assert_eq!(interp, 3.0f32);
+    /// Set the emphasis filtering, see [EmphasisMode].
+    ///
+    /// This is intended for audio with emphasis, like some CDs, where the
+    /// emphasis must be removed before resampling and applied again afterwards.
+    /// Doing this inside the resampler saves two extra passes over the data.
+    /// The filters are first-order, with the input filter designed for `input_sample_rate`,
+    /// and the output filter for the output rate at the current resampling ratio.
+    /// The filter states persist between chunks, and are cleared by [reset](Resampler::reset).
+    /// Setting the mode clears the filter states. Emphasis is off by default.
+    pub fn set_emphasis(&mut self, mode: EmphasisMode, input_sample_rate: f64) {
+        self.emphasis.set_mode(
+            mode,
+            input_sample_rate,
+            input_sample_rate * self.resample_ratio,
+            self.buffer.len(),
+        );
     }
 
-    #[test]
-    fn int_lin() {
-        let params = basic_params();
-        let _resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        let yvals = [1.0f64, 5.0f64];
-        let interp = interp_lin(0.25f64, &yvals);
-        assert_eq!(interp, 2.0f64);
+    /// Allocate the internal buffers for up to `max_channels` channels.
+    ///
+    /// The number of channels can then be changed with
+    /// [set_nbr_channels](SincFixedIn::set_nbr_channels) without allocating,
+    /// which makes it safe to do from a realtime thread.
+    /// This allocates, and should be called right after creating the resampler.
+    /// By default the buffers are allocated for the number of channels given to the constructor.
+    /// The buffers are never shrunk, so a value below the current maximum has no effect.
+    pub fn set_max_channels(&mut self, max_channels: usize) {
+        reserve_channels(&mut self.buffer, &mut self.channel_mask, max_channels);
+        self.emphasis.set_max_channels(max_channels);
+        self.output_stage.set_max_channels(max_channels);
+        let capacity = self.rounding_carry[0].capacity();
+        self.rounding_carry
+            .resize_with(self.buffer.len(), || Vec::with_capacity(capacity));
+        if self.interleaved.is_some() {
+            self.interleaved = Some(InterleavedBuffer::new(&*self.interpolator, &self.buffer));
+        }
     }
 
-    #[test]
-    fn make_resampler_fi() {
-        let params = basic_params();
-        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        let waves = vec![vec![0.0f64; 1024]; 2];
-        let out = resampler.process(&waves, None).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 1150 && out[0].len() < 1229,
-            "expected {} - {} samples, got {}",
-            1150,
-            1229,
-            out[0].len()
-        );
-        let out2 = resampler.process(&waves, None).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 1226 && out2[0].len() < 1232,
-            "expected {} - {} samples, got {}",
-            1226,
-            1232,
-            out2[0].len()
-        );
+    /// Get the maximum number of channels that the internal buffers are allocated for.
+    pub fn max_channels(&self) -> usize {
+        self.buffer.len()
     }
 
-    #[test]
-    fn reset_resampler_fi() {
-        let params = basic_params();
-        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+    /// Change the number of channels, without allocating.
+    ///
+    /// The new number of channels must be between 1 and [max_channels](SincFixedIn::max_channels),
+    /// otherwise a [ResampleError::ChannelCountOutOfBounds] is returned.
+    /// Removed channels are dropped, and added channels start from silence.
+    /// The remaining channels continue without interruption,
+    /// and the position and ratio of the resampler are unchanged.
+    pub fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        change_nbr_channels(&mut self.buffer, &mut self.channel_mask, nbr_channels)?;
+        for chan in self.nbr_channels..nbr_channels {
+            self.emphasis.reset_channel(chan);
+        }
+        self.nbr_channels = nbr_channels;
+        self.output_stage.set_nbr_channels(nbr_channels);
+        if self.channel_layout.map(|layout| layout.nbr_channels()) != Some(nbr_channels) {
+            self.channel_layout = None;
+        }
+        Ok(())
+    }
 
-        let mut rng = rand::thread_rng();
-        let mut waves = vec![vec![0.0f64; 1024]; 2];
-        waves
-            .iter_mut()
-            .for_each(|ch| ch.iter_mut().for_each(|s| *s = rng.gen()));
-        let out1 = resampler.process(&waves, None).unwrap();
-        resampler.reset();
-        let out2 = resampler.process(&waves, None).unwrap();
-        assert_eq!(
-            out1, out2,
-            "Resampler gives different output when new and after a reset."
-        );
+    /// Declare the layout of the channels, or remove it with `None`.
+    ///
+    /// The layout is only used for bookkeeping and validation, and doesn't change the output.
+    /// The layout must have the current number of channels,
+    /// otherwise a [ResampleError::WrongNumberOfLayoutChannels] is returned.
+    /// Changing the number of channels with [set_nbr_channels](SincFixedIn::set_nbr_channels)
+    /// removes a layout that no longer matches.
+    /// There is no layout by default, and it is not changed by [reset](Resampler::reset).
+    pub fn set_channel_layout(&mut self, layout: Option<ChannelLayout>) -> ResampleResult<()> {
+        if let Some(layout) = layout {
+            if layout.nbr_channels() != self.nbr_channels {
+                return Err(ResampleError::WrongNumberOfLayoutChannels {
+                    expected: layout.nbr_channels(),
+                    actual: self.nbr_channels,
+                });
+            }
+        }
+        self.channel_layout = layout;
+        Ok(())
     }
 
-    #[test]
-    fn make_resampler_fi_32() {
-        let params = basic_params();
-        let mut resampler = SincFixedIn::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        let waves = vec![vec![0.0f32; 1024]; 2];
-        let out = resampler.process(&waves, None).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 1150 && out[0].len() < 1229,
-            "expected {} - {} samples, got {}",
-            1150,
-            1229,
-            out[0].len()
-        );
-        let out2 = resampler.process(&waves, None).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 1226 && out2[0].len() < 1232,
-            "expected {} - {} samples, got {}",
-            1226,
-            1232,
-            out2[0].len()
-        );
+    /// Get the declared channel layout, see [set_channel_layout](SincFixedIn::set_channel_layout).
+    pub fn channel_layout(&self) -> Option<ChannelLayout> {
+        self.channel_layout
     }
 
-    #[test]
-    fn make_resampler_fi_skipped() {
-        let params = basic_params();
-        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        let waves = vec![vec![0.0f64; 1024], Vec::new()];
+    /// Check that a mixing matrix for the output matches the channels of this resampler,
+    /// see [ChannelLayout::validate_mix_matrix].
+    /// Without a declared layout, the rows must have one coefficient per channel.
+    pub fn validate_mix_matrix(&self, matrix: &[Vec<f64>]) -> ResampleResult<()> {
+        self.channel_layout
+            .unwrap_or(ChannelLayout::Discrete(self.nbr_channels))
+            .validate_mix_matrix(matrix)
+    }
+
+    /// Enable or disable returning the same number of frames from each call to
+    /// [process_into_buffer](Resampler::process_into_buffer).
+    ///
+    /// Normally the number of output frames varies by one from call to call,
+    /// depending on where the output frames happen to fall relative to the end of the input chunk.
+    /// Even when `chunk_size * resample_ratio` is an integer, the size can then jump
+    /// between one more and one less than that, if the output frames fall close to the end of the chunk.
+    ///
+    /// When enabled, the resampler holds back at most one calculated frame in a reservoir,
+    /// which is returned first in the next call. Whether the last calculated frame is held back
+    /// depends on its position relative to the end of the chunk, so that the fractional
+    /// part of `chunk_size * resample_ratio` is carried from call to call.
+    /// Once the delay of the resampler has passed, which normally happens in the first call,
+    /// each call then returns `chunk_size * resample_ratio` frames rounded to the floor or the ceil,
+    /// and the total number of returned frames follows the exact number within one frame.
+    /// When `chunk_size * resample_ratio` is an integer, every call from then on
+    /// returns exactly that number of frames.
+    /// The cost is a latency of at most one output frame on top of
+    /// [output_delay](Resampler::output_delay).
+    /// The output size only stays constant as long as the resample ratio is not changed.
+    /// When enabled, this replaces the rounding set by [set_output_rounding](SincFixedIn::set_output_rounding).
+    ///
+    /// This resets the resampler, and should be called before processing starts.
+    /// It also allocates the buffer for the held back frame.
+    pub fn set_fixed_output_size(&mut self, enable: bool) {
+        self.fixed_output_size = enable;
+        self.reset();
+        let capacity = self.output_frames_max() + 1;
+        for carry in self.rounding_carry.iter_mut() {
+            carry.reserve(capacity);
+        }
+    }
+
+    /// Choose how the number of frames returned by each call to
+    /// [process_into_buffer](Resampler::process_into_buffer) is rounded.
+    ///
+    /// The exact number of output frames for a chunk is `chunk_size * resample_ratio`,
+    /// which is usually not an integer. With [OutputRounding::Natural], each call returns
+    /// the frames that can be calculated from the input, and whether that is the floor
+    /// or the ceil of the exact number depends on the phase of the output frames relative to
+    /// the chunk, which in turn depends on the sinc length, the look-ahead and earlier ratio changes.
+    ///
+    /// With [OutputRounding::AlwaysFloor], each call returns the exact number of frames
+    /// for the chunk rounded down, and never more. The frames that are calculated
+    /// but not returned are held back, and are returned first in the next call.
+    /// Since the average number of calculated frames is the exact number, the held back frames
+    /// accumulate by the fractional part for each call. This adds a latency on top of
+    /// [output_delay](Resampler::output_delay) that grows by that fraction per call,
+    /// and the buffer holding the frames grows as needed. This mode is therefore meant for
+    /// streams of limited length. No frames are lost, [finish](Resampler::finish)
+    /// returns the held back frames together with the rest of the output.
+    ///
+    /// With [OutputRounding::AlwaysCeil], the fractional part is instead carried from call to call,
+    /// and each call returns the exact total number of frames so far rounded up, minus the frames
+    /// returned before. The total number of returned frames is then never less than
+    /// `total_chunks * chunk_size * resample_ratio`, not counting the first calls
+    /// that only return the delayed start of the output. Since the average must equal the exact number,
+    /// the size still alternates between the floor and the ceil when the exact number is
+    /// not an integer. To do this, the resampler holds back at most four calculated frames,
+    /// which are returned first in the next call. This adds a latency of up to four output frames
+    /// on top of [output_delay](Resampler::output_delay), and the latency varies by up to four frames
+    /// from call to call, a jitter that the natural rounding doesn't have.
+    ///
+    /// This resets the resampler, and should be called before processing starts.
+    /// It also allocates the buffer for the held back frames.
+    pub fn set_output_rounding(&mut self, rounding: OutputRounding) {
+        self.output_rounding = rounding;
+        self.reset();
+        let capacity = self.output_frames_max() + ROUNDING_CARRY_LEN;
+        for carry in self.rounding_carry.iter_mut() {
+            carry.reserve(capacity);
+        }
+    }
+
+    /// Set the look-ahead, as a number of input frames.
+    ///
+    /// The resampler then holds back the output frames that correspond to the last
+    /// `frames` input frames of each chunk. This adds a latency of approximately `frames * ratio`
+    /// output frames, but allows these frames to be retrieved in advance
+    /// with [peek_into_buffer](SincFixedIn::peek_into_buffer).
+    /// The look-ahead is limited by the length of the sinc filter,
+    /// and may be at most `sinc_len / 2` input frames.
+    /// The limit is lower for short filters combined with a wide range of allowed ratios.
+    ///
+    /// The look-ahead is 0 by default. Decreasing the look-ahead releases the held back frames,
+    /// which means that the next call to [process_into_buffer](Resampler::process_into_buffer)
+    /// returns more frames than usual. The number is given by [output_frames_next](Resampler::output_frames_next).
+    pub fn set_lookahead(&mut self, frames: usize) -> ResampleResult<()> {
+        let sinc_len = self.interpolator.len();
+        let margin = sinc_len as f64
+            - 3.0
+            - max_index_jump(self.resample_ratio_original, self.max_relative_ratio);
+        let max = (sinc_len / 2).min(margin as usize);
+        if frames > max {
+            return Err(ResampleError::LookaheadOutOfBounds {
+                provided: frames,
+                max,
+            });
+        }
+        self.lookahead = frames;
+        Ok(())
+    }
+
+    /// Calculate the first frames of the next output chunk, without consuming any input.
+    ///
+    /// This can be used to get a look-ahead of the resampled signal, for example for a limiter.
+    /// The frames are calculated from the input that is already stored in the internal buffer,
+    /// and are identical to the first frames that will be returned by the next call to
+    /// [process_into_buffer](Resampler::process_into_buffer), provided that the resampling ratio
+    /// is not changed in between.
+    /// They are calculated in the same way as in processing, and go through the same output stages,
+    /// including the emphasis, the channel gains and the output rounding.
+    /// There are two exceptions. The RMS matching, see [set_match_rms](SincFixedIn::set_match_rms),
+    /// ramps the gain within each chunk towards a value that depends on the input of that chunk.
+    /// The peeked frames instead use the gain at the end of the last chunk, which differs slightly
+    /// from the gain that the frames get when they are processed.
+    /// And with a [time budget](SincFixedIn::set_time_budget), the frames are always
+    /// calculated at full quality.
+    /// Peeking doesn't update the statistics, like the clip detection and the checksum.
+    ///
+    /// Only the frames held back because of the look-ahead can be calculated,
+    /// see [set_lookahead](SincFixedIn::set_lookahead), together with any frames held back
+    /// by the [output rounding](SincFixedIn::set_output_rounding).
+    /// At most `max_frames` frames are written to `wave_out`.
+    /// The channels marked as active in the last call to `process_into_buffer` are written.
+    /// Returns the number of frames written per channel.
+    pub fn peek_into_buffer<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+        max_frames: usize,
+    ) -> ResampleResult<usize> {
+        let carried = self.carried_frames.min(max_frames);
+        let nbr_new = self.output_available().min(max_frames - carried);
+        self.check_output_buffer(wave_out, carried + nbr_new)?;
+        // Peeking doesn't count as processing for the statistics.
+        let adaptive_counts = self.adaptive_counts;
+        self.interpolate_buffered(wave_out, nbr_new);
+        self.adaptive_counts = adaptive_counts;
+        self.auto_scale
+            .apply_output(wave_out, nbr_new, &self.channel_mask);
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                let out = wave_out[chan].as_mut();
+                self.emphasis.preview_output(chan, &mut out[..nbr_new]);
+                // The held back frames are returned first.
+                out.copy_within(..nbr_new, carried);
+                out[..carried].copy_from_slice(&self.rounding_carry[chan][..carried]);
+            }
+        }
+        let nbr_frames = carried + nbr_new;
+        self.rms_matcher
+            .apply_gain(wave_out, nbr_frames, &self.channel_mask);
+        self.output_stage
+            .apply_gains(wave_out, nbr_frames, &self.channel_mask);
+        Ok(nbr_frames)
+    }
+
+    /// Get the number of output frames that can be calculated from the input
+    /// that is already stored in the internal buffer.
+    ///
+    /// Each call to [process_into_buffer](Resampler::process_into_buffer) leaves a small margin
+    /// of input that is only used by the next call. When the resample ratio is increased,
+    /// more output frames fit in the same input, and some of them can be retrieved
+    /// with [pull_available](SincFixedIn::pull_available) before providing more input.
+    /// The frames are calculated using the target ratio, without any ramping.
+    pub fn output_available(&self) -> usize {
+        let t_ratio = 1.0 / self.target_ratio;
+        let end_idx = self.buffered_end_idx();
+        let mut idx = self.last_index + self.chunk_size as f64;
+        let mut n = 0;
+        while ((idx + t_ratio).floor() as isize) < end_idx {
+            idx += t_ratio;
+            n += 1;
+        }
+        n
+    }
+
+    /// Retrieve the output frames that can be calculated from the input
+    /// that is already stored in the internal buffer,
+    /// see [output_available](SincFixedIn::output_available).
+    ///
+    /// Unlike [peek_into_buffer](SincFixedIn::peek_into_buffer), this consumes the frames,
+    /// and the next call to [process_into_buffer](Resampler::process_into_buffer)
+    /// continues after the last retrieved frame.
+    /// The frames go through the same output processing as those from `process_into_buffer`,
+    /// and are included in the clip detection, checksum and preview.
+    /// Since there is no new input, the RMS matching keeps its current gain.
+    /// Any frames held back by the [output rounding](SincFixedIn::set_output_rounding)
+    /// are returned first, and the output is padded like for `process_into_buffer`.
+    /// The output buffer must have room for [output_available](SincFixedIn::output_available)
+    /// frames, plus the held back frames and the padding.
+    /// The channels marked as active in the last call to `process_into_buffer` are written.
+    /// Returns the number of frames written per channel.
+    pub fn pull_available<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+    ) -> ResampleResult<usize> {
+        let nbr_frames = self.output_available();
+        self.check_output_buffer(
+            wave_out,
+            self.carried_frames + nbr_frames + self.output_stage.max_padding(),
+        )?;
+        let idx = self.interpolate_buffered(wave_out, nbr_frames);
+        self.last_index = idx - self.chunk_size as f64;
+        Ok(self.process_output(None::<&[Vec<T>]>, wave_out, nbr_frames, None))
+    }
+
+    /// Get the first index after the last one that can be calculated from the buffered input.
+    /// The data is shifted by one chunk at the start of the next call,
+    /// and the last usable time is limited by the sinc length and the interpolation points.
+    fn buffered_end_idx(&self) -> isize {
+        self.chunk_size as isize - self.interpolator.len() as isize - 2
+    }
+
+    /// Check that there is one output buffer per channel,
+    /// and that the active ones can hold `frames` frames.
+    fn check_output_buffer<Vout: AsMut<[T]>>(
+        &self,
+        wave_out: &mut [Vout],
+        frames: usize,
+    ) -> ResampleResult<()> {
+        if wave_out.len() != self.nbr_channels {
+            return Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: self.nbr_channels,
+                actual: wave_out.len(),
+            });
+        }
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            let actual = wave_out[chan].as_mut().len();
+            if *active && actual < frames {
+                return Err(ResampleError::InsufficientOutputBufferSize {
+                    channel: chan,
+                    expected: frames,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Calculate `nbr_frames` output frames from the buffered input, without consuming it.
+    /// The frames are calculated in the same way as in [process_buffers](SincFixedIn::process_buffers),
+    /// but without any output processing.
+    /// Returns the index of the last calculated frame.
+    fn interpolate_buffered<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+        nbr_frames: usize,
+    ) -> f64 {
+        let t_ratio = 1.0 / self.target_ratio;
+        let mut idx = self.last_index + self.chunk_size as f64;
+        for n in 0..nbr_frames {
+            idx += t_ratio;
+            interpolate_frame(
+                &*self.interpolator,
+                &self.interpolation,
+                &self.buffer,
+                self.interleaved.as_mut(),
+                &self.channel_mask,
+                &mut self.adaptive_counts,
+                idx,
+                wave_out,
+                n,
+            );
+        }
+        idx
+    }
+}
+
+impl<T> SincFixedIn<T>
+where
+    T: Sample,
+{
+    /// Apply the output rounding to `nbr_frames` newly calculated frames in `wave_out`,
+    /// for a chunk with `exact_frames` output frames, or None to return all frames.
+    /// The held back frames are placed first, and the new ones that are not returned are held back.
+    /// Returns the number of frames to return.
+    fn round_output<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+        nbr_frames: usize,
+        exact_frames: Option<f64>,
+    ) -> usize {
+        let available = self.carried_frames + nbr_frames;
+        let returned = match (self.output_rounding, exact_frames) {
+            (_, Some(_)) if self.fixed_output_size => {
+                // Hold back the last frame when it is past the threshold. The number of calculated
+                // frames drops by one when the phase wraps from 1 to 0, and so does the held back frame.
+                // The threshold is placed half a frame step from the first phase, which stays
+                // constant when the number of output frames per chunk is an integer.
+                let phase = self.output_phase();
+                let threshold = *self
+                    .fixed_size_threshold
+                    .get_or_insert_with(|| (phase + 0.5).fract().max(f64::EPSILON));
+                available - (available > 0 && phase >= threshold) as usize
+            }
+            (_, None) if self.fixed_output_size => available,
+            (OutputRounding::Natural, _) => return nbr_frames,
+            (OutputRounding::AlwaysCeil, None) => {
+                self.rounding_remainder -= available as f64;
+                available
+            }
+            (_, None) => available,
+            (OutputRounding::AlwaysFloor, Some(exact_frames)) => {
+                (exact_frames.floor().max(0.0) as usize).min(available)
+            }
+            (OutputRounding::AlwaysCeil, Some(exact_frames)) => {
+                self.rounding_remainder += exact_frames;
+                let wanted = self.rounding_remainder.ceil();
+                self.rounding_remainder -= wanted;
+                // The frames missing at the start are the delay, and are not made up for later.
+                (wanted.max(0.0) as usize)
+                    .min(available)
+                    .max(available.saturating_sub(ROUNDING_CARRY_LEN))
+            }
+        };
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                let out = wave_out[chan].as_mut();
+                let carry = &mut self.rounding_carry[chan];
+                carry.resize(self.carried_frames, T::zero());
+                carry.extend_from_slice(&out[..nbr_frames]);
+                out[..returned].copy_from_slice(&carry[..returned]);
+                carry.drain(..returned);
+            }
+        }
+        self.carried_frames = available - returned;
+        returned
+    }
+
+    /// Apply the output processing to `nbr_frames` newly calculated frames in `wave_out`,
+    /// and update the statistics. Returns the number of frames to return.
+    ///
+    /// The RMS matching uses the input chunk `wave_in`, or keeps its gain when there is none.
+    /// The output rounding is done for a chunk with `exact_frames` output frames,
+    /// see [round_output](SincFixedIn::round_output).
+    fn process_output<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[Vin]>,
+        wave_out: &mut [Vout],
+        nbr_frames: usize,
+        exact_frames: Option<f64>,
+    ) -> usize {
+        self.auto_scale
+            .apply_output(wave_out, nbr_frames, &self.channel_mask);
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                self.emphasis
+                    .apply_output(chan, &mut wave_out[chan].as_mut()[..nbr_frames]);
+            }
+        }
+        let n = self.round_output(wave_out, nbr_frames, exact_frames);
+        if let Some(wave_in) = wave_in {
+            self.rms_matcher
+                .apply(wave_in, self.chunk_size, wave_out, n, &self.channel_mask);
+        } else {
+            self.rms_matcher.apply_gain(wave_out, n, &self.channel_mask);
+        }
+        self.output_stage.process(wave_out, n, &self.channel_mask)
+    }
+
+    /// Get the position of the last calculated output frame after the end of the usable input,
+    /// as a fraction of the step between output frames, in the range 0 to 1.
+    fn output_phase(&self) -> f64 {
+        let end_idx = -((self.interpolator.len() + 1) as f64)
+            - (1.0 / self.target_ratio).ceil()
+            - self.lookahead as f64;
+        (self.last_index - end_idx) * self.target_ratio
+    }
+
+    /// Get the number of held back frames that the next call may return on top of the new ones.
+    /// With [OutputRounding::AlwaysFloor] a call never returns more than the new frames.
+    fn carried_ceil_frames(&self) -> usize {
+        if self.output_rounding == OutputRounding::AlwaysFloor && !self.fixed_output_size {
+            0
+        } else {
+            self.carried_frames
+        }
+    }
+
+    /// Advance the position as if a chunk had been processed, without touching the buffer.
+    ///
+    /// The index arithmetic is identical to that of [process_buffers](SincFixedIn::process_buffers),
+    /// so that the position afterwards is bit-exact to that of a resampler that processed the chunk.
+    pub(crate) fn skip_chunk(&mut self) {
+        let sinc_len = self.interpolator.len();
+        let mut t_ratio = 1.0 / self.resample_ratio;
+        let t_ratio_end = 1.0 / self.target_ratio;
+        let approximate_nbr_frames =
+            self.chunk_size as f64 * (0.5 * self.resample_ratio + 0.5 * self.target_ratio);
+        let t_ratio_increment = (t_ratio_end - t_ratio) / approximate_nbr_frames;
+        let end_idx = self.chunk_size as isize
+            - (sinc_len as isize + 1)
+            - t_ratio_end.ceil() as isize
+            - self.lookahead as isize;
+        let mut idx = self.last_index;
+        while idx < end_idx as f64 {
+            t_ratio += t_ratio_increment;
+            idx += t_ratio;
+        }
+        self.last_index = idx - self.chunk_size as f64;
+        self.resample_ratio = self.target_ratio;
+        self.lookahead_used = self.lookahead;
+        self.at_start = false;
+    }
+
+    /// Clear the history of channels that become active, and update the channel mask.
+    fn update_channel_mask(&mut self, active_channels_mask: Option<&[bool]>) {
+        for chan in reactivated_channels(&self.channel_mask, active_channels_mask) {
+            self.buffer[chan].iter_mut().for_each(|s| *s = T::zero());
+            self.emphasis.reset_channel(chan);
+        }
+        if let Some(mask) = active_channels_mask {
+            self.channel_mask.copy_from_slice(mask);
+        } else {
+            update_mask_from_buffers(&mut self.channel_mask);
+        };
+    }
+
+    /// Shift the buffer, and store a chunk of new data for the active channels.
+    fn store_chunk<Vin: AsRef<[T]>>(&mut self, wave_in: &[Vin]) {
+        let sinc_len = self.interpolator.len();
+        for buf in self.buffer[..self.nbr_channels].iter_mut() {
+            buf.copy_within(self.chunk_size..self.chunk_size + 2 * sinc_len, 0);
+        }
+
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                let new_data = &mut self.buffer[chan][2 * sinc_len..2 * sinc_len + self.chunk_size];
+                new_data.copy_from_slice(&wave_in[chan].as_ref()[..self.chunk_size]);
+                self.emphasis.apply_input(chan, new_data);
+            }
+        }
+        if self.at_start && self.edge_mode == EdgeMode::Hold {
+            hold_first_sample(&mut self.buffer, &self.channel_mask, 2 * sinc_len);
+        }
+        self.at_start = false;
+        self.auto_scale.scale_input(
+            &mut self.buffer,
+            &self.channel_mask,
+            2 * sinc_len,
+            2 * sinc_len + self.chunk_size,
+        );
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active && self.flush_subnormals {
+                flush_tiny_values(
+                    &mut self.buffer[chan][2 * sinc_len..2 * sinc_len + self.chunk_size],
+                );
+            }
+        }
+        if let Some(interleaved) = self.interleaved.as_mut() {
+            interleaved.load(
+                &self.buffer,
+                &self.channel_mask,
+                2 * sinc_len + self.chunk_size,
+            );
+        }
+    }
+
+    /// Process a chunk of input into the output buffers.
+    fn process_buffers<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        // Set length to chunksize*ratio plus a safety margin of 10 elements.
+        let needed_len = self.output_frames_next();
+
+        validate_buffers(
+            wave_in,
+            wave_out,
+            active_channels_mask,
+            self.nbr_channels,
+            self.chunk_size,
+            needed_len,
+        )?;
+
+        self.update_channel_mask(active_channels_mask);
+
+        let sinc_len = self.interpolator.len();
+        let mut t_ratio = 1.0 / self.resample_ratio;
+        let t_ratio_end = 1.0 / self.target_ratio;
+        let approximate_nbr_frames =
+            self.chunk_size as f64 * (0.5 * self.resample_ratio + 0.5 * self.target_ratio);
+        let t_ratio_increment = (t_ratio_end - t_ratio) / approximate_nbr_frames;
+        let end_idx = self.chunk_size as isize
+            - (sinc_len as isize + 1)
+            - t_ratio_end.ceil() as isize
+            - self.lookahead as isize;
+
+        self.store_chunk(wave_in);
+        if let Some(loudness) = self.output_stage.loudness_mut() {
+            *loudness = measure_loudness(
+                &*self.interpolator,
+                &self.buffer,
+                &self.channel_mask,
+                self.chunk_size,
+                self.auto_scale.inverse_gain(),
+            );
+        }
+
+        let mut idx = self.last_index;
+        let (start_idx, start_t_ratio) = (idx, t_ratio);
+
+        let mut n = 0;
+
+        let clock = self
+            .time_budget
+            .map(|budget| BudgetClock::new(budget, approximate_nbr_frames));
+        let full_taps = self.interpolator.active_len();
+        let mut interpolation = self.interpolation.clone();
+        let mut degraded = false;
+        while idx < end_idx as f64 {
+            if !degraded && clock.as_ref().map_or(false, |clock| clock.behind(n)) {
+                let (reduced, taps) = quality_settings(Quality::Preview, &interpolation, sinc_len);
+                self.set_active_taps(taps.min(full_taps));
+                interpolation = reduced;
+                degraded = true;
+            }
+            t_ratio += t_ratio_increment;
+            idx += t_ratio;
+            interpolate_frame(
+                &*self.interpolator,
+                &interpolation,
+                &self.buffer,
+                self.interleaved.as_mut(),
+                &self.channel_mask,
+                &mut self.adaptive_counts,
+                idx,
+                wave_out,
+                n,
+            );
+            n += 1;
+        }
+        if degraded {
+            self.set_active_taps(full_taps);
+            self.degraded_chunks += 1;
+        }
+
+        if let Some(indices) = self.output_stage.input_indices_mut() {
+            record_input_indices(
+                indices,
+                n,
+                start_idx,
+                start_t_ratio,
+                t_ratio_increment,
+                &*self.interpolator,
+            );
+        }
+        if let Some(estimates) = self.output_stage.error_estimates_mut() {
+            record_error_estimates(
+                estimates,
+                &self.buffer,
+                &self.channel_mask,
+                n,
+                start_idx,
+                start_t_ratio,
+                t_ratio_increment,
+                &*self.interpolator,
+                &self.interpolation,
+            );
+            self.auto_scale
+                .apply_output(estimates, n, &self.channel_mask);
+        }
+        if let Some((points, positions)) = self.output_stage.intermediate_points_mut() {
+            record_intermediate_points(
+                points,
+                positions,
+                &self.buffer,
+                &self.channel_mask,
+                n,
+                start_idx,
+                start_t_ratio,
+                t_ratio_increment,
+                &*self.interpolator,
+                &self.interpolation,
+            );
+        }
+
+        // Store last index for next iteration.
+        self.last_index = idx - self.chunk_size as f64;
+        self.resample_ratio = self.target_ratio;
+        self.lookahead_used = self.lookahead;
+        let n = self.process_output(Some(wave_in), wave_out, n, Some(approximate_nbr_frames));
+        trace!(
+            "Resampling channels {:?}, {} frames in, {} frames out",
+            active_channels_mask,
+            self.chunk_size,
+            n,
+        );
+        Ok((self.chunk_size, n))
+    }
+}
+
+impl<T> Resampler<T> for SincFixedIn<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_buffers(wave_in, wave_out, active_channels_mask)
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.output_stage.max_frames() + self.output_stage.max_padding()
+    }
+
+    fn output_frames_next(&self) -> usize {
+        // Frames held back by a previously larger look-ahead are released in the next chunk.
+        let released = self.lookahead_used.saturating_sub(self.lookahead);
+        ((self.chunk_size + released) as f64
+            * (0.5 * self.resample_ratio + 0.5 * self.target_ratio)
+            + 10.0) as usize
+            + self.carried_ceil_frames()
+            + self.output_stage.max_padding()
+    }
+
+    fn output_frames_remaining(&self) -> usize {
+        // The output frame at index `idx` is centered at `idx + sinc_len/2 - 1`.
+        let offset = (self.interpolator.len() / 2) as f64 - 1.0;
+        frames_before_end(self.last_index + offset, self.resample_ratio) + self.carried_frames
+    }
+
+    fn output_delay(&self) -> usize {
+        (self.interpolator.len() as f64 * self.resample_ratio / 2.0) as usize
+    }
+
+    fn set_max_output_frames(&mut self, max_frames: usize) {
+        self.max_output_frames = max_frames;
+    }
+
+    fn max_output_frames(&self) -> usize {
+        self.max_output_frames
+    }
+
+    fn target_ratio(&self) -> f64 {
+        self.target_ratio
+    }
+
+    fn ratio_bounds(&self) -> (f64, f64) {
+        (
+            self.resample_ratio_original / self.max_relative_ratio,
+            self.resample_ratio_original * self.max_relative_ratio,
+        )
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn input_frames_max(&self) -> usize {
+        self.chunk_size
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.chunk_size
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        trace!("Change resample ratio to {}", new_ratio);
+        if (new_ratio / self.resample_ratio_original >= 1.0 / self.max_relative_ratio)
+            && (new_ratio / self.resample_ratio_original <= self.max_relative_ratio)
+        {
+            if !ramp {
+                self.resample_ratio = new_ratio;
+            }
+            self.target_ratio = new_ratio;
+            Ok(())
+        } else {
+            Err(ResampleError::RatioOutOfBounds {
+                provided: new_ratio,
+                original: self.resample_ratio_original,
+                max_relative_ratio: self.max_relative_ratio,
+            })
+        }
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        let new_ratio = self.resample_ratio_original * rel_ratio;
+        self.set_resample_ratio(new_ratio, ramp)
+    }
+
+    fn warmup(&mut self) {
+        let _ = touch_sincs(&*self.interpolator, &self.buffer[0]);
+        let wave_in = self.input_buffer_allocate(true);
+        let mut wave_out = self.output_buffer_allocate(true);
+        let _ = self.process_into_buffer(&wave_in, &mut wave_out, None);
+        self.reset();
+    }
+
+    fn reset(&mut self) {
+        self.reset_state_only();
+        self.output_stage.reset();
+        self.adaptive_counts = [0; 2];
+        self.degraded_chunks = 0;
+    }
+}
+
+impl<T> SincFixedOut<T>
+where
+    T: Sample,
+{
+    /// Create a new SincFixedOut.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio * 10.0` and `resample_ratio / 10.0`.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of output data in frames.
+    /// - `nbr_channels`: Number of channels in input/output, must be > 0.
+    ///
+    /// Unlike [SincFixedIn::new], this accepts any `sinc_len` above zero,
+    /// also those that [validate_sinc_config] rejects as too short.
+    pub fn new(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        debug!(
+            "Create new SincFixedIn, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
+            resample_ratio, chunk_size, nbr_channels, parameters
+        );
+        validate_parameters(
+            &parameters,
+            resample_ratio,
+            max_resample_ratio_relative,
+            chunk_size,
+            nbr_channels,
+        )?;
+        let (interpolator, cpu_feature) = make_interpolator(
+            parameters.sinc_len,
+            resample_ratio,
+            parameters.f_cutoff,
+            parameters.oversampling_factor,
+            parameters.window,
+        );
+
+        let mut resampler = Self::new_with_interpolator(
+            resample_ratio,
+            max_resample_ratio_relative,
+            parameters.interpolation.clone(),
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        )?;
+        resampler.parameters = Some(parameters);
+        resampler.cpu_feature = cpu_feature;
+        Ok(resampler)
+    }
+
+    /// Create a new SincFixedOut using an existing Interpolator.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Starting ratio between output and input sample rates, must be > 0.
+    /// - `max_resample_ratio_relative`: Maximum ratio that can be set with [Resampler::set_resample_ratio] relative to `resample_ratio`, must be >= 1.0. The minimum relative ratio is the reciprocal of the maximum. For example, with `max_resample_ratio_relative` of 10.0, the ratio can be set between `resample_ratio` * 10.0 and `resample_ratio` / 10.0.
+    /// - `interpolation_type`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `interpolator`: The interpolator to use.
+    /// - `chunk_size`: Size of output data in frames.
+    /// - `nbr_channels`: Number of channels in input/output, must be > 0.
+    pub fn new_with_interpolator(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        interpolation_type: SincInterpolationType,
+        interpolator: Box<dyn SincInterpolator<T>>,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        validate_ratios(resample_ratio, max_resample_ratio_relative)?;
+        validate_channels(nbr_channels)?;
+
+        let needed_input_size =
+            (chunk_size as f64 / resample_ratio).ceil() as usize + interpolator.len() / 2;
+        let buffer_channel_length = ((max_resample_ratio_relative + 1.0) * needed_input_size as f64)
+            as usize
+            + 2 * interpolator.len();
+        let buffer = vec![vec![T::zero(); buffer_channel_length]; nbr_channels];
+        let channel_mask = vec![true; nbr_channels];
+        let points_per_frame = intermediate_points_per_frame(&interpolation_type);
+
+        Ok(SincFixedOut {
+            nbr_channels,
+            chunk_size,
+            needed_input_size,
+            last_index: -((interpolator.len() / 2) as f64),
+            current_buffer_fill: needed_input_size,
+            resample_ratio,
+            resample_ratio_original: resample_ratio,
+            target_ratio: resample_ratio,
+            max_relative_ratio: max_resample_ratio_relative,
+            interpolator,
+            buffer,
+            interpolation: interpolation_type.clone(),
+            full_interpolation: interpolation_type,
+            channel_mask,
+            parameters: None,
+            cpu_feature: None,
+            deterministic: false,
+            rms_matcher: RmsMatcher::new(),
+            output_stage: OutputStage::new(nbr_channels, chunk_size, points_per_frame),
+            auto_scale: AutoScale::new(),
+            emphasis: Emphasis::new(),
+            adaptive_counts: [0; 2],
+            time_budget: None,
+            degraded_chunks: 0,
+            channel_layout: None,
+            edge_mode: EdgeMode::Zero,
+            at_start: true,
+            flush_subnormals: false,
+            max_output_frames: usize::MAX,
+            interleaved: None,
+        })
+    }
+
+    /// Get the layout of the polyphase filter bank, as `(branches, taps_per_branch)`.
+    /// See [SincFixedIn::polyphase_info].
+    pub fn polyphase_info(&self) -> (usize, usize) {
+        (
+            self.interpolator.nbr_sincs(),
+            self.interpolator.active_len(),
+        )
+    }
+
+    /// Get the CPU feature used by the interpolator, or None for the scalar interpolator.
+    /// See [SincFixedIn::cpu_feature].
+    pub fn cpu_feature(&self) -> Option<CpuFeature> {
+        self.cpu_feature
+    }
+
+    /// Replace the interpolator with the one using the given CPU feature.
+    /// See [SincFixedIn::force_isa].
+    pub fn force_isa(&mut self, feature: CpuFeature) -> Result<(), MissingCpuFeature> {
+        let parameters = match self.parameters.as_ref() {
+            Some(parameters) => parameters,
+            None => return Ok(()),
+        };
+        let (interpolator, feature) = make_interpolator_for_feature(
+            feature,
+            parameters.sinc_len,
+            self.resample_ratio_original,
+            parameters.f_cutoff,
+            parameters.oversampling_factor,
+            parameters.window,
+        )?;
+        self.replace_interpolator(interpolator, Some(feature));
+        self.deterministic = false;
+        Ok(())
+    }
+
+    /// Enable or disable the deterministic mode, where the output is the same on all platforms.
+    /// See [SincFixedIn::set_deterministic].
+    pub fn set_deterministic(&mut self, enable: bool) {
+        let parameters = match self.parameters.as_ref() {
+            Some(parameters) => parameters,
+            None => return,
+        };
+        let (interpolator, feature) = if enable {
+            (
+                make_scalar_interpolator(
+                    parameters.sinc_len,
+                    self.resample_ratio_original,
+                    parameters.f_cutoff,
+                    parameters.oversampling_factor,
+                    parameters.window,
+                ),
+                None,
+            )
+        } else {
+            make_interpolator(
+                parameters.sinc_len,
+                self.resample_ratio_original,
+                parameters.f_cutoff,
+                parameters.oversampling_factor,
+                parameters.window,
+            )
+        };
+        self.replace_interpolator(interpolator, feature);
+        self.deterministic = enable;
+    }
+
+    /// Check if the deterministic mode is enabled. See [SincFixedIn::is_deterministic].
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Replace the interpolator, keeping the number of active taps, and update the buffers that depend on it.
+    fn replace_interpolator(
+        &mut self,
+        mut interpolator: Box<dyn SincInterpolator<T>>,
+        feature: Option<CpuFeature>,
+    ) {
+        interpolator.set_active_len(self.interpolator.active_len());
+        self.interpolator = interpolator;
+        self.cpu_feature = feature;
+        if let Some(interleaved) = self.interleaved.as_mut() {
+            interleaved.update_sincs(&*self.interpolator);
+        }
+    }
+
+    /// Get the number of frames of input history kept per channel between chunks.
+    /// See [SincFixedIn::delay_buffer_len].
+    pub fn delay_buffer_len(&self) -> usize {
+        2 * self.interpolator.len()
+    }
+
+    /// Get the group delay of the anti-aliasing filter at a given frequency, in output frames.
+    /// See [SincFixedIn::group_delay_at].
+    pub fn group_delay_at(&self, normalized_freq: f64) -> f64 {
+        filter_group_delay(&*self.interpolator, normalized_freq) * self.resample_ratio
+    }
+
+    /// Get the DC gain of the anti-aliasing filter.
+    /// See [SincFixedIn::dc_gain].
+    pub fn dc_gain(&self) -> f64 {
+        filter_dc_gain(&*self.interpolator)
+    }
+
+    /// Get the energy of the anti-aliasing filter.
+    /// See [SincFixedIn::filter_energy].
+    pub fn filter_energy(&self) -> f64 {
+        filter_energy(&*self.interpolator)
+    }
+
+    /// Get the fractional phase of the resampler, as a value in the range `[0, 1)`.
+    /// See [SincFixedIn::fractional_phase].
+    pub fn fractional_phase(&self) -> f64 {
+        next_frame_phase(self.last_index, self.resample_ratio)
+    }
+
+    /// Get the number of output samples calculated with linear and with cubic interpolation.
+    /// See [SincFixedIn::adaptive_counts].
+    pub fn adaptive_counts(&self) -> (usize, usize) {
+        (self.adaptive_counts[0], self.adaptive_counts[1])
+    }
+
+    /// Enable or disable storing the delay lines of all channels in a single interleaved buffer.
+    /// See [SincFixedIn::set_interleaved_buffer].
+    pub fn set_interleaved_buffer(&mut self, enable: bool) {
+        self.interleaved = if enable {
+            Some(InterleavedBuffer::new(&*self.interpolator, &self.buffer))
+        } else {
+            None
+        };
+    }
+
+    /// Enable or disable flushing of tiny values in the input to zero.
+    /// See [SincFixedIn::set_flush_subnormals].
+    pub fn set_flush_subnormals(&mut self, enable: bool) {
+        self.flush_subnormals = enable;
+    }
+
+    /// Choose how the history of the filter is filled at the start of a stream.
+    /// See [SincFixedIn::set_edge_mode].
+    pub fn set_edge_mode(&mut self, mode: EdgeMode) {
+        self.edge_mode = mode;
+    }
+
+    /// Enable or disable automatic scaling of very quiet input.
+    /// See [SincFixedIn::set_auto_scale].
+    pub fn set_auto_scale(&mut self, enable: bool) {
+        self.auto_scale.set_enabled(enable);
+    }
+
+    /// Enable or disable matching the RMS level of the output to that of the input.
+    /// See [SincFixedIn::set_match_rms].
+    pub fn set_match_rms(&mut self, enable: bool) {
+        self.rms_matcher.set_enabled(enable);
+    }
+
+    /// Get the output stage, see [SincFixedIn::output_stage].
+    pub fn output_stage(&self) -> &OutputStage<T> {
+        &self.output_stage
+    }
+
+    /// Get the output stage for changing its settings, see [SincFixedIn::output_stage_mut].
+    pub fn output_stage_mut(&mut self) -> &mut OutputStage<T> {
+        &mut self.output_stage
+    }
+
+    /// Reset the state of the resampler, but keep the statistics.
+    /// See [SincFixedIn::reset_state_only].
+    pub fn reset_state_only(&mut self) {
+        self.buffer
+            .iter_mut()
+            .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
+        self.needed_input_size = (self.chunk_size as f64 / self.resample_ratio_original).ceil()
+            as usize
+            + self.interpolator.len() / 2;
+        self.current_buffer_fill = self.needed_input_size;
+        self.last_index = -((self.interpolator.len() / 2) as f64);
+        self.channel_mask.iter_mut().for_each(|val| *val = true);
+        self.resample_ratio = self.resample_ratio_original;
+        self.target_ratio = self.resample_ratio_original;
+        self.rms_matcher.reset();
+        self.auto_scale.reset();
+        self.at_start = true;
+        self.emphasis.reset();
+        self.output_stage.reset_state();
+        if let Some(interleaved) = self.interleaved.as_mut() {
+            interleaved.clear();
+        }
+    }
+
+    /// Set a time budget for processing each chunk, or disable it with a zero duration.
+    /// See [SincFixedIn::set_time_budget].
+    pub fn set_time_budget(&mut self, budget: Duration) {
+        self.time_budget = if budget.is_zero() { None } else { Some(budget) };
+    }
+
+    /// Get the number of chunks that were partly processed at a lower quality level.
+    /// See [SincFixedIn::degraded_chunks].
+    pub fn degraded_chunks(&self) -> usize {
+        self.degraded_chunks
+    }
+
+    /// Set the quality level, see [SincFixedIn::set_quality].
+    pub fn set_quality(&mut self, quality: Quality) {
+        let (interpolation, taps) =
+            quality_settings(quality, &self.full_interpolation, self.interpolator.len());
+        self.set_active_taps(taps);
+        self.output_stage
+            .set_points_per_frame(intermediate_points_per_frame(&interpolation));
+        self.interpolation = interpolation;
+    }
+
+    /// Limit the scalar products to `taps` taps of each sinc, also for the interleaved buffer.
+    fn set_active_taps(&mut self, taps: usize) {
+        self.interpolator.set_active_len(taps);
+        if let Some(interleaved) = self.interleaved.as_mut() {
+            interleaved.update_sincs(&*self.interpolator);
+        }
+    }
+
+    /// Set the emphasis filtering, see [SincFixedIn::set_emphasis].
+    pub fn set_emphasis(&mut self, mode: EmphasisMode, input_sample_rate: f64) {
+        self.emphasis.set_mode(
+            mode,
+            input_sample_rate,
+            input_sample_rate * self.resample_ratio,
+            self.buffer.len(),
+        );
+    }
+
+    /// Allocate the internal buffers for up to `max_channels` channels,
+    /// see [SincFixedIn::set_max_channels].
+    pub fn set_max_channels(&mut self, max_channels: usize) {
+        reserve_channels(&mut self.buffer, &mut self.channel_mask, max_channels);
+        self.emphasis.set_max_channels(max_channels);
+        self.output_stage.set_max_channels(max_channels);
+        if self.interleaved.is_some() {
+            self.interleaved = Some(InterleavedBuffer::new(&*self.interpolator, &self.buffer));
+        }
+    }
+
+    /// Get the maximum number of channels that the internal buffers are allocated for.
+    pub fn max_channels(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Change the number of channels without allocating, see [SincFixedIn::set_nbr_channels].
+    pub fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        change_nbr_channels(&mut self.buffer, &mut self.channel_mask, nbr_channels)?;
+        for chan in self.nbr_channels..nbr_channels {
+            self.emphasis.reset_channel(chan);
+        }
+        self.nbr_channels = nbr_channels;
+        self.output_stage.set_nbr_channels(nbr_channels);
+        if self.channel_layout.map(|layout| layout.nbr_channels()) != Some(nbr_channels) {
+            self.channel_layout = None;
+        }
+        Ok(())
+    }
+
+    /// Declare the layout of the channels. See [SincFixedIn::set_channel_layout].
+    pub fn set_channel_layout(&mut self, layout: Option<ChannelLayout>) -> ResampleResult<()> {
+        if let Some(layout) = layout {
+            if layout.nbr_channels() != self.nbr_channels {
+                return Err(ResampleError::WrongNumberOfLayoutChannels {
+                    expected: layout.nbr_channels(),
+                    actual: self.nbr_channels,
+                });
+            }
+        }
+        self.channel_layout = layout;
+        Ok(())
+    }
+
+    /// Get the declared channel layout. See [SincFixedIn::channel_layout].
+    pub fn channel_layout(&self) -> Option<ChannelLayout> {
+        self.channel_layout
+    }
+
+    /// Check that a mixing matrix for the output matches the channels of this resampler.
+    /// See [SincFixedIn::validate_mix_matrix].
+    pub fn validate_mix_matrix(&self, matrix: &[Vec<f64>]) -> ResampleResult<()> {
+        self.channel_layout
+            .unwrap_or(ChannelLayout::Discrete(self.nbr_channels))
+            .validate_mix_matrix(matrix)
+    }
+
+    /// Check if the resampler was created with the given configuration.
+    /// See [SincFixedIn::config_eq].
+    pub fn config_eq(
+        &self,
+        parameters: &SincInterpolationParameters,
+        resample_ratio: f64,
+        nbr_channels: usize,
+    ) -> bool {
+        config_matches(
+            &self.parameters,
+            self.resample_ratio_original,
+            self.nbr_channels,
+            parameters,
+            resample_ratio,
+            nbr_channels,
+        )
+    }
+
+    /// Create a new resampler with different interpolation parameters.
+    /// See [SincFixedIn::rebuild_with].
+    pub fn rebuild_with(
+        &self,
+        parameters: SincInterpolationParameters,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let mut resampler = Self::new(
+            self.resample_ratio_original,
+            self.max_relative_ratio,
+            parameters,
+            self.chunk_size,
+            self.nbr_channels,
+        )?;
+        resampler.set_max_channels(self.buffer.len());
+        resampler.flush_subnormals = self.flush_subnormals;
+        resampler.max_output_frames = self.max_output_frames;
+        if self.deterministic {
+            resampler.set_deterministic(true);
+        }
+        resampler.set_interleaved_buffer(self.interleaved.is_some());
+        Ok(resampler)
+    }
+}
+
+impl<T> SincFixedOut<T>
+where
+    T: Sample,
+{
+    /// Process a chunk of input into the output buffers.
+    fn process_buffers<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        validate_buffers(
+            wave_in,
+            wave_out,
+            active_channels_mask,
+            self.nbr_channels,
+            self.needed_input_size,
+            self.chunk_size,
+        )?;
+
+        for chan in reactivated_channels(&self.channel_mask, active_channels_mask) {
+            self.buffer[chan].iter_mut().for_each(|s| *s = T::zero());
+        }
+        if let Some(mask) = active_channels_mask {
+            self.channel_mask.copy_from_slice(mask);
+        } else {
+            update_mask_from_buffers(&mut self.channel_mask);
+        };
+        let sinc_len = self.interpolator.len();
+
+        for buf in self.buffer[..self.nbr_channels].iter_mut() {
+            buf.copy_within(
+                self.current_buffer_fill..self.current_buffer_fill + 2 * sinc_len,
+                0,
+            );
+        }
+        self.current_buffer_fill = self.needed_input_size;
+
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                debug_assert!(self.chunk_size <= wave_out[chan].as_mut().len());
+                let new_data =
+                    &mut self.buffer[chan][2 * sinc_len..2 * sinc_len + self.needed_input_size];
+                new_data.copy_from_slice(&wave_in[chan].as_ref()[..self.needed_input_size]);
+                self.emphasis.apply_input(chan, new_data);
+            }
+        }
+        if self.at_start && self.edge_mode == EdgeMode::Hold {
+            hold_first_sample(&mut self.buffer, &self.channel_mask, 2 * sinc_len);
+        }
+        self.at_start = false;
+        self.auto_scale.scale_input(
+            &mut self.buffer,
+            &self.channel_mask,
+            2 * sinc_len,
+            2 * sinc_len + self.needed_input_size,
+        );
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active && self.flush_subnormals {
+                flush_tiny_values(
+                    &mut self.buffer[chan][2 * sinc_len..2 * sinc_len + self.needed_input_size],
+                );
+            }
+        }
+        if let Some(interleaved) = self.interleaved.as_mut() {
+            interleaved.load(
+                &self.buffer,
+                &self.channel_mask,
+                2 * sinc_len + self.needed_input_size,
+            );
+        }
+
+        if let Some(loudness) = self.output_stage.loudness_mut() {
+            *loudness = measure_loudness(
+                &*self.interpolator,
+                &self.buffer,
+                &self.channel_mask,
+                self.needed_input_size,
+                self.auto_scale.inverse_gain(),
+            );
+        }
+
+        let mut idx = self.last_index;
+        let mut t_ratio = 1.0 / self.resample_ratio;
+        let t_ratio_end = 1.0 / self.target_ratio;
+        let t_ratio_increment = (t_ratio_end - t_ratio) / self.chunk_size as f64;
+        if let Some(indices) = self.output_stage.input_indices_mut() {
+            record_input_indices(
+                indices,
+                self.chunk_size,
+                idx,
+                t_ratio,
+                t_ratio_increment,
+                &*self.interpolator,
+            );
+        }
+        if let Some(estimates) = self.output_stage.error_estimates_mut() {
+            record_error_estimates(
+                estimates,
+                &self.buffer,
+                &self.channel_mask,
+                self.chunk_size,
+                idx,
+                t_ratio,
+                t_ratio_increment,
+                &*self.interpolator,
+                &self.interpolation,
+            );
+            self.auto_scale
+                .apply_output(estimates, self.chunk_size, &self.channel_mask);
+        }
+        if let Some((points, positions)) = self.output_stage.intermediate_points_mut() {
+            record_intermediate_points(
+                points,
+                positions,
+                &self.buffer,
+                &self.channel_mask,
+                self.chunk_size,
+                idx,
+                t_ratio,
+                t_ratio_increment,
+                &*self.interpolator,
+                &self.interpolation,
+            );
+        }
+
+        let clock = self
+            .time_budget
+            .map(|budget| BudgetClock::new(budget, self.chunk_size as f64));
+        let full_taps = self.interpolator.active_len();
+        let mut interpolation = self.interpolation.clone();
+        let mut degraded = false;
+        for frame in 0..self.chunk_size {
+            if !degraded && clock.as_ref().map_or(false, |clock| clock.behind(frame)) {
+                let (reduced, taps) = quality_settings(Quality::Preview, &interpolation, sinc_len);
+                self.set_active_taps(taps.min(full_taps));
+                interpolation = reduced;
+                degraded = true;
+            }
+            t_ratio += t_ratio_increment;
+            idx += t_ratio;
+            interpolate_frame(
+                &*self.interpolator,
+                &interpolation,
+                &self.buffer,
+                self.interleaved.as_mut(),
+                &self.channel_mask,
+                &mut self.adaptive_counts,
+                idx,
+                wave_out,
+                frame,
+            );
+        }
+        if degraded {
+            self.set_active_taps(full_taps);
+            self.degraded_chunks += 1;
+        }
+
+        // Store last index for next iteration.
+        let input_frames_used = self.needed_input_size;
+        self.last_index = idx - self.current_buffer_fill as f64;
+        self.resample_ratio = self.target_ratio;
+        self.needed_input_size = (self.last_index as f32
+            + self.chunk_size as f32 / self.resample_ratio as f32
+            + sinc_len as f32)
+            .ceil() as usize;
+        self.auto_scale
+            .apply_output(wave_out, self.chunk_size, &self.channel_mask);
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                self.emphasis
+                    .apply_output(chan, &mut wave_out[chan].as_mut()[..self.chunk_size]);
+            }
+        }
+        self.rms_matcher.apply(
+            wave_in,
+            input_frames_used,
+            wave_out,
+            self.chunk_size,
+            &self.channel_mask,
+        );
+        let nbr_frames = self
+            .output_stage
+            .process(wave_out, self.chunk_size, &self.channel_mask);
+        trace!(
+            "Resampling channels {:?}, {} frames in, {} frames out. Next needed length: {} frames, last index {}",
+            active_channels_mask,
+            self.current_buffer_fill,
+            self.chunk_size,
+            self.needed_input_size,
+            self.last_index
+        );
+        Ok((input_frames_used, nbr_frames))
+    }
+}
+
+impl<T> Resampler<T> for SincFixedOut<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_buffers(wave_in, wave_out, active_channels_mask)
+    }
+
+    fn input_frames_max(&self) -> usize {
+        (self.chunk_size as f64 / self.resample_ratio_original * self.max_relative_ratio).ceil()
+            as usize
+            + 2
+            + self.interpolator.len() / 2
+    }
+
+    fn input_frames_next(&self) -> usize {
+        self.needed_input_size
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn output_frames_max(&self) -> usize {
+        self.chunk_size + self.output_stage.max_padding()
+    }
+
+    fn output_frames_next(&self) -> usize {
+        self.chunk_size + self.output_stage.max_padding()
+    }
+
+    fn min_input_for_output(&self) -> usize {
+        (self.last_index + 1.0 / self.resample_ratio + self.interpolator.len() as f64).ceil()
+            as usize
+    }
+
+    fn output_frames_remaining(&self) -> usize {
+        // The output frame at index `idx` is centered at `idx + sinc_len/2 - 1`.
+        let offset = (self.interpolator.len() / 2) as f64 - 1.0;
+        frames_before_end(self.last_index + offset, self.resample_ratio)
+    }
+
+    fn output_delay(&self) -> usize {
+        (self.interpolator.len() as f64 * self.resample_ratio / 2.0) as usize
+    }
+
+    fn set_max_output_frames(&mut self, max_frames: usize) {
+        self.max_output_frames = max_frames;
+    }
+
+    fn max_output_frames(&self) -> usize {
+        self.max_output_frames
+    }
+
+    fn target_ratio(&self) -> f64 {
+        self.target_ratio
+    }
+
+    fn ratio_bounds(&self) -> (f64, f64) {
+        (
+            self.resample_ratio_original / self.max_relative_ratio,
+            self.resample_ratio_original * self.max_relative_ratio,
+        )
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        trace!("Change resample ratio to {}", new_ratio);
+        if (new_ratio / self.resample_ratio_original >= 1.0 / self.max_relative_ratio)
+            && (new_ratio / self.resample_ratio_original <= self.max_relative_ratio)
+        {
+            if !ramp {
+                self.resample_ratio = new_ratio;
+            }
+            self.target_ratio = new_ratio;
+
+            self.needed_input_size = (self.last_index as f32
+                + self.chunk_size as f32
+                    / (0.5 * self.resample_ratio as f32 + 0.5 * self.target_ratio as f32)
+                + self.interpolator.len() as f32)
+                .ceil() as usize;
+            Ok(())
+        } else {
+            Err(ResampleError::RatioOutOfBounds {
+                provided: new_ratio,
+                original: self.resample_ratio_original,
+                max_relative_ratio: self.max_relative_ratio,
+            })
+        }
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        let new_ratio = self.resample_ratio_original * rel_ratio;
+        self.set_resample_ratio(new_ratio, ramp)
+    }
+
+    fn warmup(&mut self) {
+        let _ = touch_sincs(&*self.interpolator, &self.buffer[0]);
+        let wave_in = self.input_buffer_allocate(true);
+        let mut wave_out = self.output_buffer_allocate(true);
+        let _ = self.process_into_buffer(&wave_in, &mut wave_out, None);
+        self.reset();
+    }
+
+    fn reset(&mut self) {
+        self.reset_state_only();
+        self.output_stage.reset();
+        self.adaptive_counts = [0; 2];
+        self.degraded_chunks = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        design_antialias_fir, interp_cubic, interp_lin, interpolator_settings, validate_sinc_config,
+    };
+    use crate::sinc_interpolator::{ScalarInterpolator, SincInterpolator};
+    use crate::Resampler;
+    use crate::Sample;
+    use crate::SincInterpolationParameters;
+    use crate::SincInterpolationType;
+    use crate::WindowFunction;
+    use crate::{check_output, check_ratio};
+    use crate::{EdgeMode, EmphasisCurve, EmphasisMode, LoudnessStats, OutputRounding, Quality};
+    use crate::{ResampleError, ResamplerConstructionError};
+    use crate::{SincFixedIn, SincFixedOut};
+    use approx::assert_abs_diff_eq;
+    use rand::Rng;
+    use std::f64::consts::PI;
+    use std::time::Duration;
+
+    fn basic_params() -> SincInterpolationParameters {
+        SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        }
+    }
+
+    #[test]
+    fn int_cubic() {
+        let params = basic_params();
+        let _resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        let yvals = [0.0f64, 2.0f64, 4.0f64, 6.0f64];
+        let interp = interp_cubic(0.5f64, &yvals);
+        assert_eq!(interp, 3.0f64);
+    }
+
+    #[test]
+    fn int_lin_32() {
+        let params = basic_params();
+        let _resampler = SincFixedIn::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        let yvals = [1.0f32, 5.0f32];
+        let interp = interp_lin(0.25f32, &yvals);
+        assert_eq!(interp, 2.0f32);
+    }
+
+    #[test]
+    fn int_cubic_32() {
+        let params = basic_params();
+        let _resampler = SincFixedIn::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        let yvals = [0.0f32, 2.0f32, 4.0f32, 6.0f32];
+        let interp = interp_cubic(0.5f32, &yvals);
+        assert_eq!(interp, 3.0f32);
+    }
+
+    #[test]
+    fn int_lin() {
+        let params = basic_params();
+        let _resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        let yvals = [1.0f64, 5.0f64];
+        let interp = interp_lin(0.25f64, &yvals);
+        assert_eq!(interp, 2.0f64);
+    }
+
+    #[test]
+    fn make_resampler_fi() {
+        let params = basic_params();
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        let out = resampler.process(&waves, None).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 1150 && out[0].len() < 1229,
+            "expected {} - {} samples, got {}",
+            1150,
+            1229,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves, None).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 1226 && out2[0].len() < 1232,
+            "expected {} - {} samples, got {}",
+            1226,
+            1232,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn reset_resampler_fi() {
+        let params = basic_params();
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+
+        let mut rng = rand::thread_rng();
+        let mut waves = vec![vec![0.0f64; 1024]; 2];
+        waves
+            .iter_mut()
+            .for_each(|ch| ch.iter_mut().for_each(|s| *s = rng.gen()));
+        let out1 = resampler.process(&waves, None).unwrap();
+        resampler.reset();
+        let out2 = resampler.process(&waves, None).unwrap();
+        assert_eq!(
+            out1, out2,
+            "Resampler gives different output when new and after a reset."
+        );
+    }
+
+    #[test]
+    fn make_resampler_fi_32() {
+        let params = basic_params();
+        let mut resampler = SincFixedIn::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f32; 1024]; 2];
+        let out = resampler.process(&waves, None).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 1150 && out[0].len() < 1229,
+            "expected {} - {} samples, got {}",
+            1150,
+            1229,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves, None).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 1226 && out2[0].len() < 1232,
+            "expected {} - {} samples, got {}",
+            1226,
+            1232,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn make_resampler_fi_skipped() {
+        let params = basic_params();
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f64; 1024], Vec::new()];
         let mask = vec![true, false];
         let out = resampler.process(&waves, Some(&mask)).unwrap();
         assert_eq!(out.len(), 2);
@@ -1007,340 +3744,2398 @@ mod tests {
     }
 
     #[test]
-    fn make_resampler_fi_downsample() {
-        // Replicate settings from reported issue
-        let params = SincInterpolationParameters {
-            sinc_len: 256,
-            f_cutoff: 0.95,
-            interpolation: SincInterpolationType::Cubic,
-            oversampling_factor: 160,
-            window: WindowFunction::BlackmanHarris2,
+    fn make_resampler_fi_downsample() {
+        // Replicate settings from reported issue
+        let params = SincInterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 160,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler =
+            SincFixedIn::<f64>::new(16000_f64 / 96000_f64, 1.0, params, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        let out = resampler.process(&waves, None).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 140 && out[0].len() < 200,
+            "expected {} - {} samples, got {}",
+            140,
+            200,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves, None).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 167 && out2[0].len() < 173,
+            "expected {} - {} samples, got {}",
+            167,
+            173,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn make_resampler_fi_upsample() {
+        // Replicate settings from reported issue
+        let params = SincInterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 160,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler =
+            SincFixedIn::<f64>::new(192000_f64 / 44100_f64, 1.0, params, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        let out = resampler.process(&waves, None).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 3800 && out[0].len() < 4458,
+            "expected {} - {} samples, got {}",
+            3800,
+            4458,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves, None).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 4455 && out2[0].len() < 4461,
+            "expected {} - {} samples, got {}",
+            4455,
+            4461,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn make_resampler_fo() {
+        let params = basic_params();
+        let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        let frames = resampler.input_frames_next();
+        println!("{}", frames);
+        assert!(frames > 800 && frames < 900);
+        let waves = vec![vec![0.0f64; frames]; 2];
+        let out = resampler.process(&waves, None).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].len(), 1024);
+    }
+
+    #[test]
+    fn reset_resampler_fo() {
+        let params = basic_params();
+        let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        let frames = resampler.input_frames_next();
+
+        let mut rng = rand::thread_rng();
+        let mut waves = vec![vec![0.0f64; frames]; 2];
+        waves
+            .iter_mut()
+            .for_each(|ch| ch.iter_mut().for_each(|s| *s = rng.gen()));
+        let out1 = resampler.process(&waves, None).unwrap();
+        resampler.reset();
+        assert_eq!(
+            frames,
+            resampler.input_frames_next(),
+            "Resampler requires different number of frames when new and after a reset."
+        );
+        let out2 = resampler.process(&waves, None).unwrap();
+        assert_eq!(
+            out1, out2,
+            "Resampler gives different output when new and after a reset."
+        );
+    }
+
+    #[test]
+    fn make_resampler_fo_32() {
+        let params = basic_params();
+        let mut resampler = SincFixedOut::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        let frames = resampler.input_frames_next();
+        println!("{}", frames);
+        assert!(frames > 800 && frames < 900);
+        let waves = vec![vec![0.0f32; frames]; 2];
+        let out = resampler.process(&waves, None).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].len(), 1024);
+    }
+
+    #[test]
+    fn make_resampler_fo_skipped() {
+        let params = basic_params();
+        let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        let frames = resampler.input_frames_next();
+        println!("{}", frames);
+        assert!(frames > 800 && frames < 900);
+        let mut waves = vec![vec![0.0f64; frames], Vec::new()];
+        let mask = vec![true, false];
+        waves[0][100] = 3.0;
+        let out = resampler.process(&waves, Some(&mask)).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].len(), 1024);
+        assert!(out[1].is_empty());
+        println!("{:?}", out[0]);
+        let summed = out[0].iter().sum::<f64>();
+        println!("sum: {}", summed);
+        assert!(summed < 4.0);
+        assert!(summed > 2.0);
+
+        let frames = resampler.input_frames_next();
+        let mut waves = vec![Vec::new(), vec![0.0f64; frames]];
+        let mask = vec![false, true];
+        waves[1][10] = 3.0;
+        let out = resampler.process(&waves, Some(&mask)).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[1].len(), 1024);
+        assert!(out[0].is_empty());
+        let summed = out[1].iter().sum::<f64>();
+        assert!(summed < 4.0);
+        assert!(summed > 2.0);
+    }
+
+    #[test]
+    fn make_resampler_fo_downsample() {
+        let params = SincInterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 160,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(0.125, 1.0, params, 1024, 2).unwrap();
+        let frames = resampler.input_frames_next();
+        println!("{}", frames);
+        assert!(
+            frames > 8192 && frames < 9000,
+            "expected {}..{} samples, got {}",
+            8192,
+            9000,
+            frames
+        );
+        let waves = vec![vec![0.0f64; frames]; 2];
+        let out = resampler.process(&waves, None).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert_eq!(
+            out[0].len(),
+            1024,
+            "Expected {} frames, got {}",
+            1024,
+            out[0].len()
+        );
+        let frames2 = resampler.input_frames_next();
+        assert!(
+            frames2 > 8189 && frames2 < 8195,
+            "expected {}..{} samples, got {}",
+            8189,
+            8195,
+            frames2
+        );
+        let waves2 = vec![vec![0.0f64; frames2]; 2];
+        let out2 = resampler.process(&waves2, None).unwrap();
+        assert_eq!(
+            out2[0].len(),
+            1024,
+            "Expected {} frames, got {}",
+            1024,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn make_resampler_fo_upsample() {
+        let params = SincInterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 160,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(8.0, 1.0, params, 1024, 2).unwrap();
+        let frames = resampler.input_frames_next();
+        println!("{}", frames);
+        assert!(
+            frames > 128 && frames < 300,
+            "expected {}..{} samples, got {}",
+            140,
+            200,
+            frames
+        );
+        let waves = vec![vec![0.0f64; frames]; 2];
+        let out = resampler.process(&waves, None).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert_eq!(
+            out[0].len(),
+            1024,
+            "Expected {} frames, got {}",
+            1024,
+            out[0].len()
+        );
+        let frames2 = resampler.input_frames_next();
+        assert!(
+            frames2 > 125 && frames2 < 131,
+            "expected {}..{} samples, got {}",
+            125,
+            131,
+            frames2
+        );
+        let waves2 = vec![vec![0.0f64; frames2]; 2];
+        let out2 = resampler.process(&waves2, None).unwrap();
+        assert_eq!(
+            out2[0].len(),
+            1024,
+            "Expected {} frames, got {}",
+            1024,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn check_fo_output_up() {
+        let params = basic_params();
+        let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        check_output!(resampler);
+    }
+
+    #[test]
+    fn check_fo_output_down() {
+        let params = basic_params();
+        let mut resampler = SincFixedOut::<f64>::new(0.8, 1.0, params, 1024, 2).unwrap();
+        check_output!(resampler);
+    }
+
+    #[test]
+    fn check_fi_output_up() {
+        let params = basic_params();
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        check_output!(resampler);
+    }
+
+    #[test]
+    fn check_fi_output_down() {
+        let params = basic_params();
+        let mut resampler = SincFixedIn::<f64>::new(0.8, 1.0, params, 1024, 2).unwrap();
+        check_output!(resampler);
+    }
+
+    #[test]
+    fn resample_small_fo_up() {
+        let ratio = 96000.0 / 44100.0;
+        let params = basic_params();
+        let mut resampler = SincFixedOut::<f32>::new(ratio, 1.0, params, 1, 2).unwrap();
+        check_ratio!(resampler, ratio, 100000);
+    }
+
+    #[test]
+    fn resample_big_fo_up() {
+        let ratio = 96000.0 / 44100.0;
+        let params = basic_params();
+        let mut resampler = SincFixedOut::<f32>::new(ratio, 1.0, params, 1024, 2).unwrap();
+        check_ratio!(resampler, ratio, 100);
+    }
+
+    #[test]
+    fn resample_small_fo_down() {
+        let ratio = 44100.0 / 96000.0;
+        let params = basic_params();
+        let mut resampler = SincFixedOut::<f32>::new(ratio, 1.0, params, 1, 2).unwrap();
+        check_ratio!(resampler, ratio, 100000);
+    }
+
+    #[test]
+    fn resample_big_fo_down() {
+        let ratio = 44100.0 / 96000.0;
+        let params = basic_params();
+        let mut resampler = SincFixedOut::<f32>::new(ratio, 1.0, params, 1024, 2).unwrap();
+        check_ratio!(resampler, ratio, 100);
+    }
+
+    #[test]
+    fn resample_small_fi_up() {
+        let ratio = 96000.0 / 44100.0;
+        let params = basic_params();
+        let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, 1, 2).unwrap();
+        check_ratio!(resampler, ratio, 100000);
+    }
+
+    #[test]
+    fn resample_big_fi_up() {
+        let ratio = 96000.0 / 44100.0;
+        let params = basic_params();
+        let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, 1024, 2).unwrap();
+        check_ratio!(resampler, ratio, 100);
+    }
+
+    #[test]
+    fn resample_small_fi_down() {
+        let ratio = 44100.0 / 96000.0;
+        let params = basic_params();
+        let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, 1, 2).unwrap();
+        check_ratio!(resampler, ratio, 100000);
+    }
+
+    #[test]
+    fn resample_big_fi_down() {
+        let ratio = 44100.0 / 96000.0;
+        let params = basic_params();
+        let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, 1024, 2).unwrap();
+        check_ratio!(resampler, ratio, 100);
+    }
+
+    #[test]
+    fn finish_fi() {
+        let ratio = 48000.0 / 44100.0;
+        let mut resampler = SincFixedIn::<f64>::new(ratio, 1.0, basic_params(), 1024, 2).unwrap();
+        let waves = vec![vec![0.5f64; 1024]; 2];
+        let mut frames_out = 0;
+        for _ in 0..5 {
+            frames_out += resampler.process(&waves, None).unwrap()[0].len();
+        }
+        let tail = resampler.finish().unwrap();
+        assert_eq!(tail.len(), 2);
+        let total = frames_out + tail[0].len();
+        let expected = (5.0 * 1024.0 * ratio).ceil() as usize;
+        assert!(
+            (total as isize - expected as isize).abs() <= 1,
+            "got {} frames, expected {}",
+            total,
+            expected
+        );
+        // The tail should end at the step down to the zero padding.
+        let last = tail[0].len() - 1;
+        assert!((tail[0][last - 10] - 0.5).abs() < 0.05);
+        assert!(tail[0][last] > 0.1 && tail[0][last] < 0.4);
+    }
+
+    #[test]
+    fn finish_with_fade() {
+        let ratio = 48000.0 / 44100.0;
+        let mut resampler = SincFixedIn::<f64>::new(ratio, 1.0, basic_params(), 256, 2).unwrap();
+        let mut resampler_faded =
+            SincFixedIn::<f64>::new(ratio, 1.0, basic_params(), 256, 2).unwrap();
+        let waves = vec![vec![0.5f64; 256]; 2];
+        for _ in 0..5 {
+            resampler.process(&waves, None).unwrap();
+            resampler_faded.process(&waves, None).unwrap();
+        }
+        let tail = resampler.finish().unwrap();
+        let fade_frames = 20;
+        let faded = resampler_faded.finish_with_fade(fade_frames).unwrap();
+        assert_eq!(faded[0].len(), tail[0].len());
+        assert!(tail[0].len() > fade_frames, "tail {}", tail[0].len());
+        let start = tail[0].len() - fade_frames;
+        assert_eq!(faded[0][..start], tail[0][..start]);
+        assert_eq!(faded[1][..start], tail[1][..start]);
+        assert_eq!(faded[0][tail[0].len() - 1], 0.0);
+        // The fade ramps down smoothly, without a step at its start.
+        let max_step = 0.5 * std::f64::consts::PI / fade_frames as f64;
+        for n in start..tail[0].len() {
+            let step = faded[0][n] - faded[0][n - 1];
+            assert!(step <= 0.01 && step > -max_step, "step {} at {}", step, n);
+        }
+        assert!((faded[0][start] - faded[0][start - 1]).abs() < 0.01);
+    }
+
+    #[test]
+    fn adaptive_interpolation() {
+        let ratio = 48000.0 / 44100.0;
+        let make = |interpolation| {
+            let mut params = basic_params();
+            params.oversampling_factor = 256;
+            params.interpolation = interpolation;
+            SincFixedIn::<f64>::new(ratio, 1.0, params, 1024, 1).unwrap()
+        };
+        let mut linear = make(SincInterpolationType::Linear);
+        let mut cubic = make(SincInterpolationType::Cubic);
+        let mut adaptive = make(SincInterpolationType::Adaptive { threshold: 0.01 });
+        // Silence with two clicks.
+        let mut wave = vec![0.0f64; 4096];
+        wave[1000] = 0.5;
+        wave[3000] = -0.5;
+        let mut out_linear = Vec::new();
+        let mut out_cubic = Vec::new();
+        let mut out_adaptive = Vec::new();
+        for chunk in wave.chunks(1024) {
+            out_linear.extend(linear.process(&[chunk], None).unwrap().remove(0));
+            out_cubic.extend(cubic.process(&[chunk], None).unwrap().remove(0));
+            out_adaptive.extend(adaptive.process(&[chunk], None).unwrap().remove(0));
+        }
+        assert_eq!(linear.adaptive_counts(), (0, 0));
+        let (nbr_linear, nbr_cubic) = adaptive.adaptive_counts();
+        assert_eq!(nbr_linear + nbr_cubic, out_adaptive.len());
+        // Each click affects the curvature at three input frames.
+        assert!(nbr_cubic > 0 && nbr_cubic <= 2 * 4, "{} cubic", nbr_cubic);
+        let mut cubic_frames = Vec::new();
+        for (n, value) in out_adaptive.iter().enumerate() {
+            if *value == out_cubic[n] && *value != out_linear[n] {
+                cubic_frames.push(n);
+            } else {
+                assert_eq!(*value, out_linear[n]);
+            }
+        }
+        assert_eq!(cubic_frames.len(), nbr_cubic);
+        // Output frame n is placed at input frame (n + 1) / ratio - 1.
+        for n in cubic_frames {
+            let time = (n + 1) as f64 / ratio - 1.0;
+            assert!(
+                (time - 1000.0).abs() < 3.0 || (time - 3000.0).abs() < 3.0,
+                "cubic at {}",
+                n
+            );
+        }
+        adaptive.reset();
+        assert_eq!(adaptive.adaptive_counts(), (0, 0));
+    }
+
+    #[test]
+    fn emphasis_round_trip() {
+        let ratio = 48000.0 / 44100.0;
+        let wave = (0..8192)
+            .map(|n| {
+                let t = n as f64 / 44100.0;
+                0.5 * (2.0 * PI * 1000.0 * t).sin() + 0.3 * (2.0 * PI * 6000.0 * t).sin()
+            })
+            .collect::<Vec<_>>();
+        let mut plain = SincFixedIn::<f64>::new(ratio, 1.0, basic_params(), 1024, 1).unwrap();
+        let mut emphasized = SincFixedIn::<f64>::new(ratio, 1.0, basic_params(), 1024, 1).unwrap();
+        emphasized.set_emphasis(EmphasisMode::PreemphasizeInput(EmphasisCurve::Cd), 44100.0);
+        let mut out_plain = Vec::new();
+        let mut out_emphasized = Vec::new();
+        for chunk in wave.chunks(1024) {
+            out_plain.extend(plain.process(&[chunk], None).unwrap().remove(0));
+            out_emphasized.extend(emphasized.process(&[chunk], None).unwrap().remove(0));
+        }
+        let max_diff = out_plain[200..]
+            .iter()
+            .zip(out_emphasized[200..].iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        // The filters differ slightly at the two sample rates.
+        assert!(max_diff < 0.005, "max difference {}", max_diff);
+    }
+
+    #[test]
+    fn finish_fo() {
+        let ratio = 44100.0 / 48000.0;
+        let mut resampler = SincFixedOut::<f64>::new(ratio, 1.0, basic_params(), 1024, 2).unwrap();
+        let mut frames_in = 0;
+        let mut frames_out = 0;
+        for _ in 0..5 {
+            let waves = vec![vec![0.5f64; resampler.input_frames_next()]; 2];
+            frames_in += waves[0].len();
+            frames_out += resampler.process(&waves, None).unwrap()[0].len();
+        }
+        let tail = resampler.finish().unwrap();
+        let total = frames_out + tail[0].len();
+        let expected = (frames_in as f64 * ratio).ceil() as usize;
+        assert!(
+            (total as isize - expected as isize).abs() <= 1,
+            "got {} frames, expected {}",
+            total,
+            expected
+        );
+    }
+
+    #[test]
+    fn fixed_output_size() {
+        let ratio = 48000.0 / 44100.0;
+        let mut resampler = SincFixedIn::<f64>::new(ratio, 1.0, basic_params(), 1176, 2).unwrap();
+        resampler.set_fixed_output_size(true);
+        let mut rng = rand::thread_rng();
+        for n in 0..100 {
+            let waves = vec![(0..1176).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>(); 2];
+            let out = resampler.process(&waves, None).unwrap();
+            if n > 0 {
+                assert_eq!(out[0].len(), 1280);
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_output_size_non_integer() {
+        // Each chunk gives 1024 * 48000 / 44100 = 1114.56 output frames.
+        let ratio = 48000.0 / 44100.0;
+        let exact = 1024.0 * ratio;
+        let mut resampler = SincFixedIn::<f64>::new(ratio, 1.0, basic_params(), 1024, 1).unwrap();
+        let mut natural = SincFixedIn::<f64>::new(ratio, 1.0, basic_params(), 1024, 1).unwrap();
+        resampler.set_fixed_output_size(true);
+        let mut rng = rand::thread_rng();
+        let mut output = Vec::new();
+        let mut output_natural = Vec::new();
+        let mut first = 0;
+        for n in 0..200 {
+            let waves = vec![(0..1024).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>()];
+            let out = resampler.process(&waves, None).unwrap();
+            output.extend_from_slice(&out[0]);
+            output_natural.extend_from_slice(&natural.process(&waves, None).unwrap()[0]);
+            if n == 0 {
+                first = out[0].len();
+            } else {
+                assert!(out[0].len() == 1114 || out[0].len() == 1115);
+                let expected = first as f64 + n as f64 * exact;
+                assert!((output.len() as f64 - expected).abs() <= 1.0);
+            }
+            // At most one frame is held back, and the frames are the same.
+            assert!(output_natural.len() - output.len() <= 1);
+            assert_eq!(output[..], output_natural[..output.len()]);
+        }
+    }
+
+    #[test]
+    fn output_rounding() {
+        // 100 * 1.1015625 = 110.15625 is exact, so the expected sizes are exact too.
+        let ratio = 1.1015625;
+        let mut rng = rand::thread_rng();
+        let wave = (0..100 * 200)
+            .map(|_| rng.gen::<f64>() - 0.5)
+            .collect::<Vec<f64>>();
+        let mut natural = Vec::new();
+        let mut resampler = SincFixedIn::<f64>::new(ratio, 1.0, basic_params(), 100, 1).unwrap();
+        for chunk in wave.chunks(100) {
+            natural.extend(resampler.process(&[chunk], None).unwrap().remove(0));
+        }
+        natural.extend(resampler.finish().unwrap().remove(0));
+        for rounding in [OutputRounding::AlwaysFloor, OutputRounding::AlwaysCeil] {
+            resampler.set_output_rounding(rounding);
+            let mut output = Vec::new();
+            for (n, chunk) in wave.chunks(100).enumerate() {
+                let frames = resampler.process(&[chunk], None).unwrap().remove(0);
+                let exact = |chunks: usize| 110.15625 * chunks as f64;
+                if rounding == OutputRounding::AlwaysFloor {
+                    // No call returns more than the exact number rounded down.
+                    assert!(frames.len() <= 110);
+                    assert!(output.len() + frames.len() <= exact(n + 1) as usize);
+                }
+                // The first chunks only contain the delayed start of the output.
+                if n > 1 {
+                    let expected = match rounding {
+                        OutputRounding::AlwaysFloor => 110.0,
+                        _ => exact(n + 1).ceil() - exact(n).ceil(),
+                    };
+                    assert_eq!(frames.len(), expected as usize);
+                }
+                output.extend(frames);
+            }
+            // The frames are the same, and the held back ones are returned when finishing.
+            assert_eq!(output[..], natural[..output.len()]);
+            output.extend(resampler.finish().unwrap().remove(0));
+            assert_eq!(output, natural);
+        }
+        resampler.set_output_rounding(OutputRounding::Natural);
+        let mut output = Vec::new();
+        for chunk in wave.chunks(100) {
+            output.extend(resampler.process(&[chunk], None).unwrap().remove(0));
+        }
+        output.extend(resampler.finish().unwrap().remove(0));
+        assert_eq!(output, natural);
+    }
+
+    #[test]
+    fn pad_output() {
+        let mut rng = rand::thread_rng();
+        let mut resampler = SincFixedIn::<f64>::new(1.1, 1.0, basic_params(), 1000, 2).unwrap();
+        let mut padded = SincFixedIn::<f64>::new(1.1, 1.0, basic_params(), 1000, 2).unwrap();
+        padded.output_stage_mut().pad_output_to(8);
+        for _ in 0..10 {
+            let waves = vec![(0..1000).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>(); 2];
+            let expected = resampler.process(&waves, None).unwrap();
+            let output = padded.process(&waves, None).unwrap();
+            assert_eq!(output[0].len() % 8, 0);
+            assert_eq!(
+                padded.output_stage().valid_output_frames(),
+                expected[0].len()
+            );
+            for (out, exp) in output.iter().zip(expected.iter()) {
+                assert_eq!(out[..exp.len()], exp[..]);
+                assert!(out[exp.len()..].iter().all(|s| *s == 0.0));
+            }
+        }
+
+        let mut resampler = SincFixedOut::<f64>::new(1.1, 1.0, basic_params(), 100, 1).unwrap();
+        resampler.output_stage_mut().pad_output_to(8);
+        assert_eq!(resampler.output_frames_next(), 107);
+        let frames = resampler.input_frames_next();
+        let output = resampler.process(&[vec![1.0; frames]], None).unwrap();
+        assert_eq!(output[0].len(), 104);
+        assert_eq!(resampler.output_stage().valid_output_frames(), 100);
+    }
+
+    #[test]
+    fn edge_mode_hold() {
+        // The largest deviation from the DC level in the output of the first chunks.
+        let transient = |mode: EdgeMode, fixed_out: bool| {
+            let mut resampler: Box<dyn crate::VecResampler<f64>> = if fixed_out {
+                let mut resampler =
+                    SincFixedOut::<f64>::new(1.5, 1.0, basic_params(), 256, 2).unwrap();
+                resampler.set_edge_mode(mode);
+                Box::new(resampler)
+            } else {
+                let mut resampler =
+                    SincFixedIn::<f64>::new(1.5, 1.0, basic_params(), 256, 2).unwrap();
+                resampler.set_edge_mode(mode);
+                Box::new(resampler)
+            };
+            let mut max_dev: f64 = 0.0;
+            for _ in 0..3 {
+                let frames = resampler.input_frames_next();
+                let output = resampler
+                    .process(&vec![vec![0.5; frames]; 2], None)
+                    .unwrap();
+                for chan in output.iter() {
+                    for value in chan.iter() {
+                        max_dev = max_dev.max((value - 0.5).abs());
+                    }
+                }
+            }
+            max_dev
+        };
+        for fixed_out in [false, true] {
+            let zero = transient(EdgeMode::Zero, fixed_out);
+            let hold = transient(EdgeMode::Hold, fixed_out);
+            // The step from silence gives a ramp at the start of the output.
+            assert!(zero > 0.1);
+            assert!(hold < 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn short_sinc_len() {
+        for n_interp in 0..4 {
+            for sinc_len in [1, 8, 16] {
+                for ratio in [0.125, 0.5, 2.0, 8.0] {
+                    let params = || SincInterpolationParameters {
+                        sinc_len,
+                        f_cutoff: 0.95,
+                        interpolation: match n_interp {
+                            0 => SincInterpolationType::Nearest,
+                            1 => SincInterpolationType::Linear,
+                            2 => SincInterpolationType::Quadratic,
+                            _ => SincInterpolationType::Cubic,
+                        },
+                        oversampling_factor: 16,
+                        window: WindowFunction::BlackmanHarris2,
+                    };
+                    let mut resampler = match SincFixedIn::<f64>::new(ratio, 2.0, params(), 64, 1) {
+                        Ok(resampler) => resampler,
+                        Err(ResamplerConstructionError::SincLenTooShort { minimum, .. }) => {
+                            assert!(minimum > sinc_len);
+                            // The shortest supported length must work.
+                            let mut params = params();
+                            params.sinc_len = minimum;
+                            SincFixedIn::<f64>::new(ratio, 2.0, params, 64, 1).unwrap()
+                        }
+                        Err(err) => panic!("Unexpected error: {}", err),
+                    };
+                    let half_len = resampler.polyphase_info().1 / 2;
+                    if let Err(ResampleError::LookaheadOutOfBounds { max, .. }) =
+                        resampler.set_lookahead(half_len)
+                    {
+                        resampler.set_lookahead(max).unwrap();
+                    }
+                    resampler.reset();
+                    let mut resampler_fo =
+                        SincFixedOut::<f64>::new(ratio, 2.0, params(), 64, 1).unwrap();
+                    for n in 0..20 {
+                        let rel = if n % 2 == 0 { 0.5 } else { 2.0 };
+                        resampler.set_resample_ratio_relative(rel, false).unwrap();
+                        resampler_fo
+                            .set_resample_ratio_relative(rel, false)
+                            .unwrap();
+                        let waves = vec![vec![1.0f64; 64]];
+                        let out = resampler.process(&waves, None).unwrap();
+                        assert!(out[0].iter().all(|v| v.is_finite()));
+                        let frames = resampler_fo.input_frames_next();
+                        let waves = vec![vec![1.0f64; frames]];
+                        let out = resampler_fo.process(&waves, None).unwrap();
+                        assert!(out[0].iter().all(|v| v.is_finite()));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pull_after_ratio_increase() {
+        let mut resampler = SincFixedIn::<f64>::new(0.25, 4.0, basic_params(), 1024, 2).unwrap();
+        let mut reference = SincFixedIn::<f64>::new(0.25, 4.0, basic_params(), 1024, 2).unwrap();
+        let mut rng = rand::thread_rng();
+        for _ in 0..3 {
+            let waves = vec![(0..1024).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>(); 2];
+            resampler.process(&waves, None).unwrap();
+            reference.process(&waves, None).unwrap();
+        }
+        assert_eq!(resampler.output_available(), 0);
+        resampler.set_resample_ratio(1.0, false).unwrap();
+        reference.set_resample_ratio(1.0, false).unwrap();
+        let available = resampler.output_available();
+        assert!(available > 0);
+        let mut pulled = vec![vec![0.0; 100]; 2];
+        let nbr_pulled = resampler.pull_available(&mut pulled).unwrap();
+        assert_eq!(nbr_pulled, available);
+        assert_eq!(resampler.output_available(), 0);
+
+        // The pulled frames followed by the next chunk must match the reference.
+        let waves = vec![(0..1024).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>(); 2];
+        let out = resampler.process(&waves, None).unwrap();
+        let out_ref = reference.process(&waves, None).unwrap();
+        assert_eq!(nbr_pulled + out[0].len(), out_ref[0].len());
+        assert_eq!(&pulled[0][..nbr_pulled], &out_ref[0][..nbr_pulled]);
+        assert_eq!(&out[1][..], &out_ref[1][nbr_pulled..]);
+    }
+
+    #[test]
+    fn pull_output_processing() {
+        let make = || {
+            let mut resampler =
+                SincFixedIn::<f64>::new(0.25, 4.0, basic_params(), 1024, 2).unwrap();
+            resampler.set_emphasis(EmphasisMode::PreemphasizeInput(EmphasisCurve::Cd), 44100.0);
+            resampler
+                .output_stage_mut()
+                .set_channel_gains(&[0.5, 16.0])
+                .unwrap();
+            resampler.output_stage_mut().set_output_checksum(true);
+            resampler.output_stage_mut().set_clip_detection(4096);
+            resampler
+        };
+        let mut resampler = make();
+        let mut reference = make();
+        let mut rng = rand::thread_rng();
+        // Keep the input well away from zero, so that every output frame of the second channel clips.
+        let mut chunk = || {
+            vec![
+                (0..1024)
+                    .map(|_| 0.5 + 0.5 * rng.gen::<f64>())
+                    .collect::<Vec<f64>>();
+                2
+            ]
+        };
+        for _ in 0..3 {
+            let waves = chunk();
+            resampler.process(&waves, None).unwrap();
+            reference.process(&waves, None).unwrap();
+        }
+        resampler.set_resample_ratio(1.0, false).unwrap();
+        reference.set_resample_ratio(1.0, false).unwrap();
+        let mut pulled = vec![vec![0.0; 100]; 2];
+        let nbr_pulled = resampler.pull_available(&mut pulled).unwrap();
+        assert!(nbr_pulled > 0);
+        let clips = resampler.output_stage().last_clips().len();
+        assert!(clips > 0);
+
+        // The pulled frames get the same processing as those returned by the reference.
+        let waves = chunk();
+        let out = resampler.process(&waves, None).unwrap();
+        let out_ref = reference.process(&waves, None).unwrap();
+        for chan in 0..2 {
+            assert_eq!(&pulled[chan][..nbr_pulled], &out_ref[chan][..nbr_pulled]);
+            assert_eq!(&out[chan][..], &out_ref[chan][nbr_pulled..]);
+        }
+        assert_eq!(
+            resampler.output_stage().output_checksum(),
+            reference.output_stage().output_checksum()
+        );
+        assert_eq!(
+            clips + resampler.output_stage().last_clips().len(),
+            reference.output_stage().last_clips().len()
+        );
+
+        // The buffer must also have room for the padding.
+        let mut resampler = make();
+        resampler.output_stage_mut().pad_output_to(64);
+        resampler.process(&chunk(), None).unwrap();
+        resampler.set_resample_ratio(1.0, false).unwrap();
+        let available = resampler.output_available();
+        let mut pulled = vec![vec![0.0; available]; 2];
+        assert!(matches!(
+            resampler.pull_available(&mut pulled),
+            Err(ResampleError::InsufficientOutputBufferSize { .. })
+        ));
+    }
+
+    #[test]
+    fn min_input_for_output() {
+        let ratio = 48000.0 / 44100.0;
+        let resampler = SincFixedOut::<f64>::new(ratio, 1.0, basic_params(), 256, 2).unwrap();
+        let mut single = SincFixedOut::<f64>::new(ratio, 1.0, basic_params(), 1, 2).unwrap();
+        let frames = resampler.min_input_for_output();
+        assert!(frames < resampler.input_frames_next());
+        assert_eq!(frames, single.input_frames_next());
+        let waves = vec![vec![0.0f64; frames]; 2];
+        let out = single.process(&waves, None).unwrap();
+        assert_eq!(out[0].len(), 1);
+
+        let resampler = SincFixedIn::<f64>::new(ratio, 1.0, basic_params(), 256, 2).unwrap();
+        assert_eq!(resampler.min_input_for_output(), 256);
+    }
+
+    #[test]
+    fn config_eq() {
+        let ratio = 48000.0 / 44100.0;
+        let resampler = SincFixedIn::<f64>::new(ratio, 1.1, basic_params(), 1024, 2).unwrap();
+        assert!(resampler.config_eq(&basic_params(), 48000.0 / 44100.0, 2));
+        assert!(resampler.config_eq(&basic_params(), ratio * (1.0 + 1.0e-12), 2));
+        assert!(!resampler.config_eq(&basic_params(), ratio * 1.001, 2));
+        assert!(!resampler.config_eq(&basic_params(), ratio, 1));
+        let mut params = basic_params();
+        params.sinc_len = 128;
+        assert!(!resampler.config_eq(&params, ratio, 2));
+        let mut params = basic_params();
+        params.window = WindowFunction::Hann;
+        assert!(!resampler.config_eq(&params, ratio, 2));
+
+        let resampler = SincFixedOut::<f64>::new(ratio, 1.1, basic_params(), 1024, 2).unwrap();
+        assert!(resampler.config_eq(&basic_params(), ratio, 2));
+        let mut params = basic_params();
+        params.interpolation = SincInterpolationType::Linear;
+        assert!(!resampler.config_eq(&params, ratio, 2));
+    }
+
+    #[test]
+    fn rebuild_with() {
+        let mut rng = rand::thread_rng();
+        let waves: Vec<Vec<Vec<f64>>> = (0..3)
+            .map(|_| vec![(0..1024).map(|_| rng.gen::<f64>() - 0.5).collect(); 2])
+            .collect();
+        let mut params = basic_params();
+        params.window = WindowFunction::Hann;
+        params.sinc_len = 128;
+
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.1, basic_params(), 1024, 2).unwrap();
+        resampler.set_interleaved_buffer(true);
+        resampler.process(&waves[0], None).unwrap();
+        let mut rebuilt = resampler.rebuild_with(params.clone()).unwrap();
+        let mut fresh = SincFixedIn::<f64>::new(1.2, 1.1, params.clone(), 1024, 2).unwrap();
+        fresh.set_interleaved_buffer(true);
+        for wave in waves.iter() {
+            let out_rebuilt = rebuilt.process(wave, None).unwrap();
+            let out_fresh = fresh.process(wave, None).unwrap();
+            assert_eq!(out_rebuilt, out_fresh);
+        }
+
+        let mut resampler = SincFixedOut::<f64>::new(1.2, 1.1, basic_params(), 1024, 2).unwrap();
+        resampler.set_max_channels(4);
+        let mut rebuilt = resampler.rebuild_with(params.clone()).unwrap();
+        assert_eq!(rebuilt.max_channels(), 4);
+        let mut fresh = SincFixedOut::<f64>::new(1.2, 1.1, params, 1024, 2).unwrap();
+        for wave in waves.iter() {
+            let frames = fresh.input_frames_next();
+            assert_eq!(rebuilt.input_frames_next(), frames);
+            let chunk: Vec<&[f64]> = wave.iter().map(|ch| &ch[..frames]).collect();
+            let out_rebuilt = rebuilt.process(&chunk, None).unwrap();
+            let out_fresh = fresh.process(&chunk, None).unwrap();
+            assert_eq!(out_rebuilt, out_fresh);
+        }
+
+        let mut params = basic_params();
+        params.sinc_len = 0;
+        assert!(resampler.rebuild_with(params).is_err());
+    }
+
+    #[test]
+    fn oversampled_block() {
+        // With a ratio of 2 and nearest interpolation, the output frames fall exactly on
+        // every 8th point of the 16 times oversampled signal.
+        let mut params = basic_params();
+        params.interpolation = SincInterpolationType::Nearest;
+        let mut resampler = SincFixedIn::<f64>::new(2.0, 1.0, params.clone(), 256, 2).unwrap();
+        let mut oversampler = SincFixedIn::<f64>::new(2.0, 1.0, params, 256, 2).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut resampled = vec![Vec::new(); 2];
+        let mut oversampled = vec![Vec::new(); 2];
+        for _ in 0..4 {
+            let wave: Vec<Vec<f64>> = vec![(0..256).map(|_| rng.gen::<f64>() - 0.5).collect(); 2];
+            let out = resampler.process(&wave, None).unwrap();
+            let block = oversampler.oversampled_block(&wave).unwrap();
+            assert_eq!(block[0].len(), 256 * 16);
+            for chan in 0..2 {
+                resampled[chan].extend_from_slice(&out[chan]);
+                oversampled[chan].extend_from_slice(&block[chan]);
+            }
+        }
+        // Output frame m is at time `(m + 1) / 2 - sinc_len / 2`, and point j of the first block at
+        // time `j / 16 - sinc_len`.
+        let offset = 64 / 2 * 16;
+        for chan in 0..2 {
+            let decimated: Vec<f64> = oversampled[chan][offset + 8..]
+                .iter()
+                .step_by(8)
+                .copied()
+                .collect();
+            assert!(resampled[chan].len() > 900);
+            assert_eq!(resampled[chan][..], decimated[..resampled[chan].len()]);
+        }
+
+        // Blocks and normal processing can be mixed.
+        let wave = vec![vec![0.25; 256]; 2];
+        assert_eq!(
+            resampler.process(&wave, None).unwrap(),
+            oversampler.process(&wave, None).unwrap()
+        );
+        assert!(oversampler.oversampled_block(&wave[..1]).is_err());
+    }
+
+    #[test]
+    fn match_rms() {
+        // A sine with a frequency near the cutoff is attenuated by the filter.
+        let mut params = basic_params();
+        params.f_cutoff = 0.5;
+        let ratio = 48000.0 / 44100.0;
+        let mut resampler = SincFixedIn::<f64>::new(ratio, 1.0, params, 1024, 1).unwrap();
+        let rms = |values: &[f64]| {
+            (values.iter().map(|v| v * v).sum::<f64>() / values.len() as f64).sqrt()
         };
-        let mut resampler =
-            SincFixedIn::<f64>::new(16000 as f64 / 96000 as f64, 1.0, params, 1024, 2).unwrap();
-        let waves = vec![vec![0.0f64; 1024]; 2];
-        let out = resampler.process(&waves, None).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 140 && out[0].len() < 200,
-            "expected {} - {} samples, got {}",
-            140,
-            200,
-            out[0].len()
-        );
-        let out2 = resampler.process(&waves, None).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 167 && out2[0].len() < 173,
-            "expected {} - {} samples, got {}",
-            167,
-            173,
-            out2[0].len()
-        );
+        let sine = |block: usize| {
+            (0..1024)
+                .map(|n| (0.5 * std::f64::consts::PI * (block * 1024 + n) as f64).sin())
+                .collect::<Vec<f64>>()
+        };
+        let mut rms_out = 0.0;
+        for block in 0..10 {
+            let out = resampler.process(&[sine(block)], None).unwrap();
+            rms_out = rms(&out[0][..]);
+        }
+        let rms_in = rms(&sine(0));
+        assert!(rms_out < 0.9 * rms_in);
+
+        resampler.set_match_rms(true);
+        for block in 0..50 {
+            let out = resampler.process(&[sine(block)], None).unwrap();
+            rms_out = rms(&out[0][..]);
+        }
+        assert_abs_diff_eq!(rms_out, rms_in, epsilon = 0.01 * rms_in);
     }
 
     #[test]
-    fn make_resampler_fi_upsample() {
-        // Replicate settings from reported issue
-        let params = SincInterpolationParameters {
-            sinc_len: 256,
-            f_cutoff: 0.95,
-            interpolation: SincInterpolationType::Cubic,
-            oversampling_factor: 160,
-            window: WindowFunction::BlackmanHarris2,
+    fn channel_gains() {
+        let mut rng = rand::thread_rng();
+        let waves: Vec<Vec<Vec<f64>>> = (0..3)
+            .map(|_| vec![(0..1024).map(|_| rng.gen::<f64>() - 0.5).collect(); 2])
+            .collect();
+        let mut plain = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 1024, 2).unwrap();
+        let mut gained = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 1024, 2).unwrap();
+        gained
+            .output_stage_mut()
+            .set_channel_gains(&[1.0, 0.5])
+            .unwrap();
+        for wave in waves.iter() {
+            let out_plain = plain.process(wave, None).unwrap();
+            let out_gained = gained.process(wave, None).unwrap();
+            assert_eq!(out_gained[0], out_plain[0]);
+            for (value, reference) in out_gained[1].iter().zip(out_plain[1].iter()) {
+                assert_eq!(*value, 0.5 * reference);
+            }
+        }
+
+        let mut plain = SincFixedOut::<f64>::new(1.2, 1.0, basic_params(), 512, 2).unwrap();
+        let mut gained = SincFixedOut::<f64>::new(1.2, 1.0, basic_params(), 512, 2).unwrap();
+        gained
+            .output_stage_mut()
+            .set_channel_gains(&[0.5, 1.0])
+            .unwrap();
+        for wave in waves.iter() {
+            let frames = plain.input_frames_next();
+            let chunk: Vec<&[f64]> = wave.iter().map(|ch| &ch[..frames]).collect();
+            let out_plain = plain.process(&chunk, None).unwrap();
+            let out_gained = gained.process(&chunk, None).unwrap();
+            assert_eq!(out_gained[1], out_plain[1]);
+            for (value, reference) in out_gained[0].iter().zip(out_plain[0].iter()) {
+                assert_eq!(*value, 0.5 * reference);
+            }
+        }
+
+        assert!(matches!(
+            gained.output_stage_mut().set_channel_gains(&[0.5]),
+            Err(ResampleError::WrongNumberOfGains {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn clip_detection() {
+        // A sine at a quarter of the sample rate, with samples at 45 degrees from the peaks.
+        // The samples stay below full scale, while the waveform peaks at 1.3.
+        let sine = |n: usize| 1.3 * (0.5 * PI * n as f64 + 0.25 * PI).sin();
+        let wave = vec![(0..1024).map(sine).collect::<Vec<f64>>(), vec![0.5; 1024]];
+        assert!(wave[0].iter().all(|v| v.abs() < 1.0));
+        let mut resampler = SincFixedIn::<f64>::new(2.0, 1.0, basic_params(), 1024, 2).unwrap();
+        resampler.process(&wave, None).unwrap();
+        assert!(resampler.output_stage().last_clips().is_empty());
+
+        resampler.output_stage_mut().set_clip_detection(10000);
+        let out = resampler.process(&wave, None).unwrap();
+        let expected: Vec<(usize, usize)> = out[0]
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| value.abs() > 1.0)
+            .map(|(frame, _)| (0, frame))
+            .collect();
+        assert!(expected.len() > 500);
+        assert_eq!(resampler.output_stage().last_clips(), &expected[..]);
+
+        resampler.output_stage_mut().set_clip_detection(10);
+        resampler.process(&wave, None).unwrap();
+        assert_eq!(resampler.output_stage().last_clips(), &expected[..10]);
+        resampler.reset();
+        assert!(resampler.output_stage().last_clips().is_empty());
+    }
+
+    #[test]
+    fn output_checksum() {
+        let mut rng = rand::thread_rng();
+        let wave: Vec<Vec<f64>> = vec![(0..1024).map(|_| rng.gen::<f64>() - 0.5).collect(); 2];
+        let mut other = wave.clone();
+        other[1][500] += 1.0e-6;
+        let checksum = |wave: &[Vec<f64>]| {
+            let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 1024, 2).unwrap();
+            resampler.output_stage_mut().set_output_checksum(true);
+            for _ in 0..3 {
+                resampler.process(wave, None).unwrap();
+            }
+            resampler.output_stage().output_checksum()
         };
-        let mut resampler =
-            SincFixedIn::<f64>::new(192000 as f64 / 44100 as f64, 1.0, params, 1024, 2).unwrap();
-        let waves = vec![vec![0.0f64; 1024]; 2];
-        let out = resampler.process(&waves, None).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 3800 && out[0].len() < 4458,
-            "expected {} - {} samples, got {}",
-            3800,
-            4458,
-            out[0].len()
-        );
-        let out2 = resampler.process(&waves, None).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 4455 && out2[0].len() < 4461,
-            "expected {} - {} samples, got {}",
-            4455,
-            4461,
-            out2[0].len()
-        );
+        assert_eq!(checksum(&wave), checksum(&wave));
+        assert_ne!(checksum(&wave), checksum(&other));
+
+        let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, basic_params(), 512, 2).unwrap();
+        let initial = resampler.output_stage().output_checksum();
+        resampler.process(&wave, None).unwrap();
+        assert_eq!(resampler.output_stage().output_checksum(), initial);
+        resampler.output_stage_mut().set_output_checksum(true);
+        resampler.process(&wave, None).unwrap();
+        assert_ne!(resampler.output_stage().output_checksum(), initial);
+        resampler.output_stage_mut().reset_output_checksum();
+        assert_eq!(resampler.output_stage().output_checksum(), initial);
     }
 
     #[test]
-    fn make_resampler_fo() {
-        let params = basic_params();
-        let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        let frames = resampler.input_frames_next();
-        println!("{}", frames);
-        assert!(frames > 800 && frames < 900);
-        let waves = vec![vec![0.0f64; frames]; 2];
-        let out = resampler.process(&waves, None).unwrap();
-        assert_eq!(out.len(), 2);
-        assert_eq!(out[0].len(), 1024);
+    fn time_budget() {
+        let mut rng = rand::thread_rng();
+        let waves: Vec<Vec<Vec<f64>>> = (0..6)
+            .map(|_| vec![(0..1024).map(|_| rng.gen::<f64>() - 0.5).collect(); 2])
+            .collect();
+        // A generous budget gives the same output as no budget,
+        // also when processing continues without a budget.
+        let mut reference = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 1024, 2).unwrap();
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 1024, 2).unwrap();
+        resampler.set_time_budget(Duration::from_secs(100));
+        for (n, wave) in waves.iter().enumerate() {
+            if n == 3 {
+                resampler.set_time_budget(Duration::ZERO);
+            }
+            let expected = reference.process(wave, None).unwrap();
+            assert_eq!(resampler.process(wave, None).unwrap(), expected);
+        }
+        assert_eq!(resampler.degraded_chunks(), 0);
+
+        // A tiny budget lowers the quality instead of failing.
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 1024, 2).unwrap();
+        resampler.set_time_budget(Duration::from_nanos(1));
+        let mut reference = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 1024, 2).unwrap();
+        reference.set_quality(Quality::Preview);
+        for wave in waves.iter() {
+            let output = resampler.process(wave, None).unwrap();
+            let expected = reference.process(wave, None).unwrap();
+            // The quality is lowered at the first check.
+            assert_eq!(output[0][64..], expected[0][64..]);
+        }
+        assert_eq!(resampler.degraded_chunks(), waves.len());
+        assert_eq!(resampler.polyphase_info().1, 64);
+
+        let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, basic_params(), 512, 2).unwrap();
+        resampler.set_time_budget(Duration::from_nanos(1));
+        for wave in waves.iter() {
+            let frames = resampler.input_frames_next();
+            let chunk: Vec<&[f64]> = wave.iter().map(|ch| &ch[..frames]).collect();
+            resampler.process(&chunk, None).unwrap();
+        }
+        assert_eq!(resampler.degraded_chunks(), waves.len());
+        resampler.reset();
+        assert_eq!(resampler.degraded_chunks(), 0);
     }
 
     #[test]
-    fn reset_resampler_fo() {
+    fn unchecked_matches_checked() {
+        let ratio = 48000.0 / 44100.0;
+        let mut resampler = SincFixedOut::<f64>::new(ratio, 1.1, basic_params(), 512, 2).unwrap();
+        let mut reference = SincFixedOut::<f64>::new(ratio, 1.1, basic_params(), 512, 2).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut wave_out = resampler.output_buffer_allocate(true);
+        let mut wave_out_ref = reference.output_buffer_allocate(true);
+        for n in 0..10 {
+            resampler
+                .set_resample_ratio_relative(1.0 + 0.01 * n as f64, true)
+                .unwrap();
+            reference
+                .set_resample_ratio_relative(1.0 + 0.01 * n as f64, true)
+                .unwrap();
+            let frames = resampler.input_frames_next();
+            let waves = vec![(0..frames).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>(); 2];
+            let result = unsafe {
+                resampler
+                    .process_into_buffer_unchecked(&waves, &mut wave_out, None)
+                    .unwrap()
+            };
+            let result_ref = reference
+                .process_into_buffer(&waves, &mut wave_out_ref, None)
+                .unwrap();
+            assert_eq!(result, result_ref);
+            assert_eq!(wave_out, wave_out_ref);
+        }
+    }
+
+    #[test]
+    fn unchecked_still_validates() {
+        let mut resampler = SincFixedIn::<f64>::new(1.0, 1.0, basic_params(), 512, 2).unwrap();
+        let waves = vec![vec![0.0f64; 100]; 2];
+        let mut wave_out = resampler.output_buffer_allocate(true);
+        let result =
+            unsafe { resampler.process_into_buffer_unchecked(&waves, &mut wave_out, None) };
+        assert!(result.is_err());
+    }
+
+    /// Resample random stereo input, first with a single stereo resampler,
+    /// and then with one resampler per channel, and check that the results are identical.
+    fn assert_stereo_matches_mono<T, F>(make_interpolator: F)
+    where
+        T: Sample + std::fmt::Debug,
+        F: Fn() -> Box<dyn SincInterpolator<T>>,
+    {
+        let mut rng = rand::thread_rng();
+        let chunk_size = 500;
+        let interpolation_types = [
+            SincInterpolationType::Cubic,
+            SincInterpolationType::Quadratic,
+            SincInterpolationType::Linear,
+            SincInterpolationType::Nearest,
+        ];
+        for interpolation in interpolation_types {
+            let mut stereo = SincFixedIn::<T>::new_with_interpolator(
+                1.3,
+                1.5,
+                interpolation.clone(),
+                make_interpolator(),
+                chunk_size,
+                2,
+            )
+            .unwrap();
+            let mut mono = (0..2)
+                .map(|_| {
+                    SincFixedIn::<T>::new_with_interpolator(
+                        1.3,
+                        1.5,
+                        interpolation.clone(),
+                        make_interpolator(),
+                        chunk_size,
+                        1,
+                    )
+                    .unwrap()
+                })
+                .collect::<Vec<_>>();
+            for chunk in 0..6 {
+                if chunk == 3 {
+                    stereo.set_resample_ratio_relative(1.2, true).unwrap();
+                    for resampler in mono.iter_mut() {
+                        resampler.set_resample_ratio_relative(1.2, true).unwrap();
+                    }
+                }
+                let waves = (0..2)
+                    .map(|_| {
+                        (0..chunk_size)
+                            .map(|_| T::coerce(rng.gen::<f64>() - 0.5))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                let out = stereo.process(&waves, None).unwrap();
+                for (chan, resampler) in mono.iter_mut().enumerate() {
+                    let out_mono = resampler.process(&[&waves[chan]], None).unwrap();
+                    assert_eq!(out[chan], out_mono[0], "{:?}", interpolation);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stereo_frames_scalar() {
+        let window = WindowFunction::BlackmanHarris2;
+        assert_stereo_matches_mono::<f64, _>(|| {
+            Box::new(ScalarInterpolator::new(64, 16, 0.95, window))
+        });
+        assert_stereo_matches_mono::<f32, _>(|| {
+            Box::new(ScalarInterpolator::new(64, 16, 0.95, window))
+        });
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn stereo_frames_sse() {
+        use crate::sinc_interpolator::sinc_interpolator_sse::SseInterpolator;
+        let window = WindowFunction::BlackmanHarris2;
+        if SseInterpolator::<f64>::new(64, 16, 0.95, window).is_err() {
+            return;
+        }
+        assert_stereo_matches_mono::<f64, _>(|| {
+            Box::new(SseInterpolator::new(64, 16, 0.95, window).unwrap())
+        });
+        assert_stereo_matches_mono::<f32, _>(|| {
+            Box::new(SseInterpolator::new(64, 16, 0.95, window).unwrap())
+        });
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn stereo_frames_avx() {
+        use crate::sinc_interpolator::sinc_interpolator_avx::AvxInterpolator;
+        let window = WindowFunction::BlackmanHarris2;
+        if AvxInterpolator::<f64>::new(64, 16, 0.95, window).is_err() {
+            return;
+        }
+        assert_stereo_matches_mono::<f64, _>(|| {
+            Box::new(AvxInterpolator::new(64, 16, 0.95, window).unwrap())
+        });
+        assert_stereo_matches_mono::<f32, _>(|| {
+            Box::new(AvxInterpolator::new(64, 16, 0.95, window).unwrap())
+        });
+    }
+
+    #[test]
+    fn antialias_fir() {
+        let gain_at = |taps: &[f64], freq: f64| {
+            let omega = std::f64::consts::PI * freq;
+            let (re, im) = taps
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(re, im), (n, tap)| {
+                    (
+                        re + tap * (omega * n as f64).cos(),
+                        im - tap * (omega * n as f64).sin(),
+                    )
+                });
+            (re * re + im * im).sqrt()
+        };
+        for ratio in [2.0, 0.5] {
+            let taps = design_antialias_fir(&basic_params(), ratio);
+            assert_eq!(taps.len(), 65);
+            for (first, last) in taps.iter().zip(taps.iter().rev()) {
+                assert_eq!(first, last);
+            }
+            assert_abs_diff_eq!(taps.iter().sum::<f64>(), 1.0, epsilon = 1.0e-12);
+            assert_abs_diff_eq!(gain_at(&taps, 0.0), 1.0, epsilon = 1.0e-12);
+        }
+        // Downsampling by two moves the cutoff to half the input Nyquist frequency.
+        let taps = design_antialias_fir(&basic_params(), 0.5);
+        assert!(gain_at(&taps, 0.3) > 0.99);
+        assert!(gain_at(&taps, 0.7) < 1.0e-3);
+    }
+
+    #[test]
+    fn upsampling_suppresses_images() {
+        // Level in dB of a frequency, relative to the output Nyquist frequency,
+        // measured with a Blackman-Harris window.
+        let level_at = |wave: &[f64], freq: f64| {
+            let window =
+                crate::windows::make_window::<f64>(wave.len(), WindowFunction::BlackmanHarris);
+            let omega = PI * freq;
+            let (re, im) = wave.iter().zip(window.iter()).enumerate().fold(
+                (0.0, 0.0),
+                |(re, im), (n, (value, w))| {
+                    (
+                        re + value * w * (omega * n as f64).cos(),
+                        im - value * w * (omega * n as f64).sin(),
+                    )
+                },
+            );
+            let gain = window.iter().sum::<f64>() / 2.0;
+            20.0 * ((re * re + im * im).sqrt() / gain).log10()
+        };
+        // The attenuation of the images is set by the window and the sinc length.
+        for (window, sinc_len, max_level) in [
+            (WindowFunction::Hann, 64, -90.0),
+            (WindowFunction::Blackman, 64, -100.0),
+            (WindowFunction::BlackmanHarris2, 256, -140.0),
+        ] {
+            let params = SincInterpolationParameters {
+                sinc_len,
+                f_cutoff: crate::calculate_cutoff(sinc_len, window),
+                interpolation: SincInterpolationType::Cubic,
+                oversampling_factor: 256,
+                window,
+            };
+            let mut resampler = SincFixedIn::<f64>::new(4.0, 1.0, params, 1024, 1).unwrap();
+            // A tone at 0.4 of the input Nyquist frequency, with images at
+            // 1.6, 2.4 and 3.6 of the input Nyquist frequency.
+            let input = (0..8 * 1024)
+                .map(|n| (0.4 * PI * n as f64).sin())
+                .collect::<Vec<_>>();
+            let mut output = Vec::new();
+            for chunk in input.chunks(1024) {
+                output.extend(resampler.process(&[chunk], None).unwrap().remove(0));
+            }
+            let wave = &output[1024..1024 + 16384];
+            assert_abs_diff_eq!(level_at(wave, 0.1), 0.0, epsilon = 0.01);
+            for image in [0.4, 0.6, 0.9] {
+                let level = level_at(wave, image);
+                assert!(
+                    level < max_level,
+                    "{:?}: image at {} is {} dB",
+                    window,
+                    image,
+                    level
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn filter_energy() {
+        let window = WindowFunction::BlackmanHarris2;
+        let f_cutoff = crate::calculate_cutoff(256, window);
+        let energy_with = |oversampling_factor: usize, ratio: f64| {
+            let params = SincInterpolationParameters {
+                sinc_len: 256,
+                f_cutoff,
+                interpolation: SincInterpolationType::Linear,
+                oversampling_factor,
+                window,
+            };
+            SincFixedIn::<f64>::new(ratio, 1.0, params, 1024, 1)
+                .unwrap()
+                .filter_energy()
+        };
+        let energy = energy_with(64, 1.5);
+        assert!(energy < f_cutoff as f64);
+        assert!(energy > 0.95 * f_cutoff as f64);
+        for oversampling_factor in [16, 128] {
+            assert_abs_diff_eq!(
+                energy_with(oversampling_factor, 1.5),
+                energy,
+                epsilon = 1.0e-6
+            );
+        }
+        // Downsampling by two halves the cutoff, while the transition band keeps its width.
+        assert_abs_diff_eq!(energy_with(64, 0.5), 0.5 * energy, epsilon = 0.01);
+        let resampler = SincFixedOut::<f32>::new(1.5, 1.0, basic_params(), 1024, 1).unwrap();
+        assert_abs_diff_eq!(resampler.filter_energy(), 0.95, epsilon = 0.05);
+    }
+
+    #[test]
+    fn input_indices() {
+        // With a ramp as input, each output value equals the input position it was calculated at.
+        let ramp = (0..8192).map(|n| n as f64).collect::<Vec<_>>();
+        let mut resampler = SincFixedIn::<f64>::new(2.0, 1.1, basic_params(), 1024, 1).unwrap();
+        assert!(resampler.output_stage().last_input_indices().is_empty());
+        resampler.output_stage_mut().set_record_input_indices(true);
+        for (chunk, input) in ramp.chunks(1024).take(4).enumerate() {
+            let output = resampler.process(&[input], None).unwrap().remove(0);
+            let indices = resampler.output_stage().last_input_indices();
+            assert_eq!(indices.len(), output.len());
+            for pair in indices.windows(2) {
+                assert_abs_diff_eq!(pair[1] - pair[0], 0.5, epsilon = 1.0e-9);
+            }
+            if chunk > 0 {
+                for (index, value) in indices.iter().zip(output.iter()) {
+                    assert_abs_diff_eq!(*value, (chunk * 1024) as f64 + index, epsilon = 1.0e-3);
+                }
+            }
+        }
+        resampler.output_stage_mut().set_record_input_indices(false);
+        resampler.process(&[&ramp[..1024]], None).unwrap();
+        assert!(resampler.output_stage().last_input_indices().is_empty());
+
+        let mut resampler = SincFixedOut::<f64>::new(0.5, 1.1, basic_params(), 512, 1).unwrap();
+        resampler.output_stage_mut().set_record_input_indices(true);
+        let mut start = 0;
+        for chunk in 0..4 {
+            let frames = resampler.input_frames_next();
+            let output = resampler
+                .process(&[&ramp[start..start + frames]], None)
+                .unwrap()
+                .remove(0);
+            let indices = resampler.output_stage().last_input_indices();
+            assert_eq!(indices.len(), 512);
+            for pair in indices.windows(2) {
+                assert_abs_diff_eq!(pair[1] - pair[0], 2.0, epsilon = 1.0e-9);
+            }
+            if chunk > 0 {
+                for (index, value) in indices.iter().zip(output.iter()) {
+                    assert_abs_diff_eq!(*value, start as f64 + index, epsilon = 1.0e-3);
+                }
+            }
+            start += frames;
+        }
+    }
+
+    #[test]
+    fn group_delay_constant() {
+        let resampler = SincFixedIn::<f64>::new(1.5, 1.0, basic_params(), 1024, 1).unwrap();
+        let expected = 64.0 / 2.0 * 1.5;
+        for freq in [0.0, 0.1, 0.3, 0.5, 0.7, 0.85] {
+            assert_abs_diff_eq!(resampler.group_delay_at(freq), expected, epsilon = 1.0e-3);
+        }
+        let resampler = SincFixedOut::<f32>::new(0.5, 1.0, basic_params(), 1024, 1).unwrap();
+        for freq in [0.0, 0.2, 0.4] {
+            assert_abs_diff_eq!(resampler.group_delay_at(freq), 16.0, epsilon = 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn change_nbr_channels() {
+        let mut rng = rand::thread_rng();
+        let mut reference = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 256, 2).unwrap();
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 256, 2).unwrap();
+        resampler.set_max_channels(4);
+        assert_eq!(resampler.max_channels(), 4);
+        // The first two channels continue unchanged while a third is added and removed again.
+        for nbr_channels in [2, 3, 3, 2, 2] {
+            resampler.set_nbr_channels(nbr_channels).unwrap();
+            assert_eq!(resampler.nbr_channels(), nbr_channels);
+            let mut waves = (0..2)
+                .map(|_| (0..256).map(|_| rng.gen::<f64>() - 0.5).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let expected = reference.process(&waves, None).unwrap();
+            waves.resize(nbr_channels, vec![0.0; 256]);
+            let output = resampler.process(&waves, None).unwrap();
+            assert_eq!(output[..2], expected[..]);
+            assert!(output[2..].iter().flatten().all(|v| *v == 0.0));
+        }
+        assert!(matches!(
+            resampler.set_nbr_channels(5),
+            Err(ResampleError::ChannelCountOutOfBounds {
+                provided: 5,
+                max: 4
+            })
+        ));
+        assert!(resampler.set_nbr_channels(0).is_err());
+    }
+
+    #[test]
+    fn polyphase_layout() {
+        let mut params = basic_params();
+        params.sinc_len = 60;
+        params.oversampling_factor = 32;
+        let resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
+        assert_eq!(resampler.polyphase_info(), (32, 64));
+        let resampler = SincFixedOut::<f64>::new(1.2, 1.0, basic_params(), 1024, 2).unwrap();
+        assert_eq!(resampler.polyphase_info(), (16, 64));
+    }
+
+    #[test]
+    fn preview_quality() {
+        let mut params = basic_params();
+        params.sinc_len = 256;
+        params.oversampling_factor = 128;
+        let wave = (0..1024)
+            .map(|n| (n as f64 * 0.05).sin())
+            .collect::<Vec<_>>();
+        let mut full = SincFixedIn::<f64>::new(1.5, 1.0, params.clone(), 1024, 1).unwrap();
+        let mut resampler = SincFixedIn::<f64>::new(1.5, 1.0, params.clone(), 1024, 1).unwrap();
+        resampler.set_quality(Quality::Preview);
+        assert_eq!(resampler.polyphase_info(), (128, 64));
+        let expected = full.process(&[&wave], None).unwrap();
+        let output = resampler.process(&[&wave], None).unwrap();
+        assert_eq!(output[0].len(), expected[0].len());
+        // A low frequency sine is reproduced well also in preview.
+        for (value, reference) in output[0].iter().zip(expected[0].iter()).skip(256) {
+            assert!((value - reference).abs() < 5.0e-3);
+        }
+        resampler.set_quality(Quality::Full);
+        assert_eq!(resampler.polyphase_info(), (128, 256));
+        let mut resampler = SincFixedOut::<f64>::new(1.5, 1.0, params, 1024, 1).unwrap();
+        resampler.set_quality(Quality::Preview);
+        assert_eq!(resampler.polyphase_info(), (128, 64));
+        resampler.set_quality(Quality::Full);
+        assert_eq!(resampler.polyphase_info(), (128, 256));
+    }
+
+    #[test]
+    fn flush_subnormals() {
+        // A decaying tail that ends far below the smallest normal f32.
+        let wave = (0..8192)
+            .map(|n| (n as f32 * 0.3).sin() * (-0.02 * n as f32).exp())
+            .collect::<Vec<f32>>();
+        assert!(wave.iter().any(|v| v.is_subnormal()));
+        let run = |flush: bool| {
+            let mut resampler = SincFixedOut::<f32>::new(1.2, 1.0, basic_params(), 512, 1).unwrap();
+            resampler.set_flush_subnormals(flush);
+            let mut output = Vec::new();
+            let mut offset = 0;
+            while offset + resampler.input_frames_next() <= wave.len() {
+                let frames = resampler.input_frames_next();
+                let chunk = resampler
+                    .process(&[&wave[offset..offset + frames]], None)
+                    .unwrap();
+                output.extend_from_slice(&chunk[0]);
+                offset += frames;
+            }
+            output
+        };
+        let plain = run(false);
+        let flushed = run(true);
+        assert!(plain.iter().any(|v| v.is_subnormal()));
+        assert!(!flushed.iter().any(|v| v.is_subnormal()));
+        // The audible part is unchanged, and the tail becomes exactly zero.
+        for (a, b) in plain.iter().zip(flushed.iter()) {
+            assert!((a - b).abs() < 1.0e-18);
+        }
+        assert!(flushed[flushed.len() - 1000..].iter().all(|v| *v == 0.0));
+    }
+
+    #[test]
+    fn auto_scale() {
+        // A sine with a subnormal f32 peak, where all products with the sincs are subnormal.
+        let amplitude = 1.0e-40;
+        let wave = |scale: f64| {
+            (0..4096)
+                .map(|n| (scale * (n as f64 * 0.3).sin()) as f32)
+                .collect::<Vec<f32>>()
+        };
+        let run_fixed_in = |input: &[f32], auto: bool| {
+            let mut resampler = SincFixedIn::<f32>::new(1.2, 1.0, basic_params(), 512, 1).unwrap();
+            resampler.set_auto_scale(auto);
+            input
+                .chunks(512)
+                .flat_map(|chunk| resampler.process(&[chunk], None).unwrap().remove(0))
+                .collect::<Vec<f32>>()
+        };
+        let run_fixed_out = |input: &[f32], auto: bool| {
+            let mut resampler = SincFixedOut::<f32>::new(1.2, 1.0, basic_params(), 512, 1).unwrap();
+            resampler.set_auto_scale(auto);
+            let mut output = Vec::new();
+            let mut offset = 0;
+            while offset + resampler.input_frames_next() <= input.len() {
+                let frames = resampler.input_frames_next();
+                let chunk = &input[offset..offset + frames];
+                output.extend(resampler.process(&[chunk], None).unwrap().remove(0));
+                offset += frames;
+            }
+            output
+        };
+        // The reference is the same input resampled in f64, where the products stay normal.
+        let input = wave(amplitude);
+        let input_f64 = input.iter().map(|v| *v as f64).collect::<Vec<f64>>();
+        let mut fixed_in = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 512, 1).unwrap();
+        let mut fixed_out = SincFixedOut::<f64>::new(1.2, 1.0, basic_params(), 512, 1).unwrap();
+        let expected_in = input_f64
+            .chunks(512)
+            .flat_map(|chunk| fixed_in.process(&[chunk], None).unwrap().remove(0))
+            .collect::<Vec<f64>>();
+        let mut expected_out = Vec::new();
+        let mut offset = 0;
+        while offset + fixed_out.input_frames_next() <= input_f64.len() {
+            let frames = fixed_out.input_frames_next();
+            let chunk = &input_f64[offset..offset + frames];
+            expected_out.extend(fixed_out.process(&[chunk], None).unwrap().remove(0));
+            offset += frames;
+        }
+        let relative_error = |output: &[f32], expected: &[f64]| {
+            output
+                .iter()
+                .zip(expected.iter())
+                .skip(1000)
+                .map(|(a, b)| (*a as f64 - b).abs() / amplitude)
+                .fold(0.0, f64::max)
+        };
+        let plain = relative_error(&run_fixed_in(&input, false), &expected_in);
+        let scaled = relative_error(&run_fixed_in(&input, true), &expected_in);
+        // With scaling, only the rounding of the subnormal output remains.
+        let output_ulp = f32::from_bits(1) as f64 / amplitude;
+        assert!(scaled < 0.2 * plain);
+        assert!(scaled < output_ulp);
+        let plain = relative_error(&run_fixed_out(&input, false), &expected_out);
+        let scaled = relative_error(&run_fixed_out(&input, true), &expected_out);
+        assert!(scaled < 0.2 * plain);
+        assert!(scaled < output_ulp);
+
+        // Audio in the normal range is not scaled, and the output is identical.
+        let input = wave(0.5);
+        assert_eq!(run_fixed_in(&input, false), run_fixed_in(&input, true));
+        assert_eq!(run_fixed_out(&input, false), run_fixed_out(&input, true));
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn deterministic() {
+        let mut rng = rand::thread_rng();
+        let waves = (0..4)
+            .map(|_| {
+                (0..2)
+                    .map(|_| (0..512).map(|_| rng.gen::<f32>() - 0.5).collect())
+                    .collect::<Vec<Vec<f32>>>()
+            })
+            .collect::<Vec<_>>();
         let params = basic_params();
-        let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        let frames = resampler.input_frames_next();
+        let (sinc_len, f_cutoff) = interpolator_settings(params.sinc_len, 1.2, params.f_cutoff);
+        let scalar = ScalarInterpolator::<f32>::new(
+            sinc_len,
+            params.oversampling_factor,
+            f_cutoff,
+            params.window,
+        );
+        let mut reference = SincFixedIn::<f32>::new_with_interpolator(
+            1.2,
+            1.0,
+            params.interpolation.clone(),
+            Box::new(scalar),
+            512,
+            2,
+        )
+        .unwrap();
+        let mut resampler = SincFixedIn::<f32>::new(1.2, 1.0, params.clone(), 512, 2).unwrap();
+        assert!(!resampler.is_deterministic());
+        resampler.set_deterministic(true);
+        assert!(resampler.is_deterministic());
+        assert_eq!(resampler.cpu_feature(), None);
+        let mut rebuilt = resampler.rebuild_with(params.clone()).unwrap();
+        assert!(rebuilt.is_deterministic());
+        // Forcing another instruction set first makes no difference.
+        let mut forced = SincFixedIn::<f32>::new(1.2, 1.0, params, 512, 2).unwrap();
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("sse3") {
+            forced.force_isa(crate::CpuFeature::Sse3).unwrap();
+        }
+        forced.set_deterministic(true);
+
+        // The output is bit-identical to that of the scalar interpolator,
+        // whatever SIMD features are available.
+        for wave in waves.iter() {
+            let expected = reference.process(wave, None).unwrap();
+            assert_eq!(resampler.process(wave, None).unwrap(), expected);
+            assert_eq!(rebuilt.process(wave, None).unwrap(), expected);
+            assert_eq!(forced.process(wave, None).unwrap(), expected);
+        }
+        resampler.set_deterministic(false);
+        assert!(!resampler.is_deterministic());
+    }
 
+    #[test]
+    fn force_isa() {
+        use crate::CpuFeature;
+        if !(is_x86_feature_detected!("avx") && is_x86_feature_detected!("fma")) {
+            return;
+        }
         let mut rng = rand::thread_rng();
-        let mut waves = vec![vec![0.0f64; frames]; 2];
-        waves
-            .iter_mut()
-            .for_each(|ch| ch.iter_mut().for_each(|s| *s = rng.gen()));
-        let out1 = resampler.process(&waves, None).unwrap();
+        let waves = (0..4)
+            .map(|_| {
+                vec![
+                    (0..512)
+                        .map(|_| rng.gen::<f64>() - 0.5)
+                        .collect::<Vec<f64>>();
+                    2
+                ]
+            })
+            .collect::<Vec<_>>();
+        let mut avx = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 512, 2).unwrap();
+        let mut sse = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 512, 2).unwrap();
+        let mut sse_out = SincFixedOut::<f64>::new(1.2, 1.0, basic_params(), 512, 2).unwrap();
+        assert_eq!(avx.cpu_feature(), Some(CpuFeature::Avx));
+        assert_eq!(sse_out.cpu_feature(), Some(CpuFeature::Avx));
+        // Switch after the first chunk, which keeps the buffered input.
+        let expected = avx.process(&waves[0], None).unwrap();
+        assert_eq!(sse.process(&waves[0], None).unwrap(), expected);
+        sse.force_isa(CpuFeature::Sse3).unwrap();
+        sse_out.force_isa(CpuFeature::Sse3).unwrap();
+        assert_eq!(sse.cpu_feature(), Some(CpuFeature::Sse3));
+        assert_eq!(sse_out.cpu_feature(), Some(CpuFeature::Sse3));
+        for wave in waves[1..].iter() {
+            let expected = avx.process(wave, None).unwrap();
+            let output = sse.process(wave, None).unwrap();
+            assert_eq!(output[0].len(), expected[0].len());
+            for (out, exp) in output.iter().zip(expected.iter()) {
+                for (a, b) in out.iter().zip(exp.iter()) {
+                    assert_abs_diff_eq!(a, b, epsilon = 1.0e-12);
+                }
+            }
+        }
+        let frames = sse_out.input_frames_next();
+        let output = sse_out.process(&vec![vec![0.1; frames]; 2], None).unwrap();
+        assert_eq!(output[0].len(), 512);
+
+        // FMA selects the AVX interpolator.
+        sse.force_isa(CpuFeature::Fma).unwrap();
+        assert_eq!(sse.cpu_feature(), Some(CpuFeature::Avx));
+    }
+
+    #[test]
+    fn output_preview() {
+        let wave = (0..4000)
+            .map(|n| (n as f64 * 0.013).sin() * (n as f64 * 0.0007).cos())
+            .collect::<Vec<f64>>();
+        let mut resampler = SincFixedIn::<f64>::new(1.3, 1.0, basic_params(), 500, 1).unwrap();
+        assert!(resampler.output_stage().last_preview().is_empty());
+        resampler.output_stage_mut().set_preview_decimation(64);
+        let mut output = Vec::new();
+        let mut preview = Vec::new();
+        for chunk in wave.chunks(500) {
+            output.extend(resampler.process(&[chunk], None).unwrap().remove(0));
+            preview.extend_from_slice(&resampler.output_stage().last_preview()[0]);
+        }
+        assert_eq!(preview.len(), 2 * (output.len() / 64));
+        for (window, peaks) in output.chunks_exact(64).zip(preview.chunks(2)) {
+            let min = window.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = window.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            assert_eq!(peaks, &[min, max]);
+            assert!(window.iter().all(|v| *v >= peaks[0] && *v <= peaks[1]));
+        }
+        resampler.reset();
+        assert!(resampler.output_stage().last_preview()[0].is_empty());
+        resampler.output_stage_mut().set_preview_decimation(0);
+        assert!(resampler.output_stage().last_preview().is_empty());
+    }
+
+    #[test]
+    fn reset_state_only() {
+        let wave = (0..1024)
+            .map(|n| (n as f64 * 0.05).sin())
+            .collect::<Vec<f64>>();
+        let mut params = basic_params();
+        params.interpolation = SincInterpolationType::Adaptive { threshold: 0.001 };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 512, 1).unwrap();
+        resampler.output_stage_mut().set_output_checksum(true);
+        resampler.output_stage_mut().set_loudness_metering(true);
+        let mut first = Vec::new();
+        for chunk in wave.chunks(512) {
+            first.extend(resampler.process(&[chunk], None).unwrap().remove(0));
+        }
+        let counts = resampler.adaptive_counts();
+        let checksum = resampler.output_stage().output_checksum();
+        let loudness = resampler.output_stage().last_loudness();
+        assert!(counts.0 + counts.1 > 0);
+        assert!(loudness.frames > 0);
+
+        // The statistics are kept, while the state starts over.
+        resampler.reset_state_only();
+        assert_eq!(resampler.adaptive_counts(), counts);
+        assert_eq!(resampler.output_stage().output_checksum(), checksum);
+        assert_eq!(resampler.output_stage().last_loudness(), loudness);
+        let mut second = Vec::new();
+        for chunk in wave.chunks(512) {
+            second.extend(resampler.process(&[chunk], None).unwrap().remove(0));
+        }
+        assert_eq!(first, second);
+        assert_eq!(resampler.adaptive_counts(), (2 * counts.0, 2 * counts.1));
+        assert!(resampler.output_stage().output_checksum() != checksum);
+
+        // A full reset clears them.
+        resampler.reset();
+        assert_eq!(resampler.adaptive_counts(), (0, 0));
+        assert_eq!(
+            resampler.output_stage().last_loudness(),
+            LoudnessStats::default()
+        );
+        let mut fresh = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 512, 1).unwrap();
+        fresh.output_stage_mut().set_output_checksum(true);
+        assert_eq!(
+            resampler.output_stage().output_checksum(),
+            fresh.output_stage().output_checksum()
+        );
+    }
+
+    #[test]
+    fn intermediate_points() {
+        let wave = (0..2048)
+            .map(|n| (n as f64 * 0.3).sin() + 0.3 * (n as f64 * 1.7).cos())
+            .collect::<Vec<f64>>();
+        let mut resampler = SincFixedIn::<f64>::new(1.3, 1.1, basic_params(), 512, 2).unwrap();
+        assert!(resampler
+            .output_stage()
+            .last_intermediate_points()
+            .is_empty());
+        assert_eq!(resampler.output_stage().intermediate_points_per_frame(), 4);
+        resampler
+            .output_stage_mut()
+            .set_record_intermediate_points(true);
+        for (n, chunk) in wave.chunks(512).enumerate() {
+            if n == 2 {
+                resampler.set_resample_ratio(1.4, true).unwrap();
+            }
+            let mask = [true, false];
+            let out = resampler
+                .process(&[chunk.to_vec(), Vec::new()], Some(&mask))
+                .unwrap();
+            let points = resampler.output_stage().last_intermediate_points();
+            assert_eq!(points.len(), 2);
+            assert_eq!(points[0].len(), 4 * out[0].len());
+            assert!(points[1].is_empty());
+            let positions = resampler.output_stage().last_intermediate_positions();
+            assert_eq!(positions.len(), out[0].len());
+            // Combining the points with the cubic interpolation gives the normal output.
+            for ((value, frame), x) in out[0].iter().zip(points[0].chunks(4)).zip(positions) {
+                let combined = interp_cubic(*x, &[frame[0], frame[1], frame[2], frame[3]]);
+                assert!((combined - value).abs() < 1e-9);
+            }
+        }
         resampler.reset();
+        assert!(resampler.output_stage().last_intermediate_points()[0].is_empty());
+        assert!(resampler
+            .output_stage()
+            .last_intermediate_positions()
+            .is_empty());
+    }
+
+    #[test]
+    fn error_estimates() {
+        // A tone at 80% of the input Nyquist frequency.
+        let wave = (0..2048)
+            .map(|n| (n as f64 * 0.8 * PI).sin())
+            .collect::<Vec<f64>>();
+        let run = |interpolation: SincInterpolationType| {
+            let mut params = basic_params();
+            params.interpolation = interpolation;
+            let mut resampler = SincFixedIn::<f64>::new(1.3, 1.0, params, 512, 2).unwrap();
+            assert!(resampler.output_stage().last_error_estimates().is_empty());
+            resampler
+                .output_stage_mut()
+                .set_record_error_estimates(true);
+            let mut output = Vec::new();
+            let mut estimates = Vec::new();
+            for chunk in wave.chunks(512) {
+                let mask = [true, false];
+                let out = resampler
+                    .process(&[chunk.to_vec(), Vec::new()], Some(&mask))
+                    .unwrap();
+                let errors = resampler.output_stage().last_error_estimates();
+                assert_eq!(errors.len(), 2);
+                assert_eq!(errors[0].len(), out[0].len());
+                assert!(errors[1].is_empty());
+                output.extend_from_slice(&out[0]);
+                estimates.extend_from_slice(&errors[0]);
+            }
+            (output, estimates)
+        };
+        let rms = |values: &[f64]| {
+            (values.iter().map(|v| v * v).sum::<f64>() / values.len() as f64).sqrt()
+        };
+        let (linear, linear_errors) = run(SincInterpolationType::Linear);
+        let (cubic, cubic_errors) = run(SincInterpolationType::Cubic);
+        assert!(rms(&linear_errors[100..]) > 10.0 * rms(&cubic_errors[100..]));
+
+        // The reference is the same for both types,
+        // so the estimates differ by exactly the difference of the outputs.
+        let difference = linear
+            .iter()
+            .zip(cubic.iter())
+            .map(|(l, c)| l - c)
+            .collect::<Vec<f64>>();
+        let estimated = linear_errors
+            .iter()
+            .zip(cubic_errors.iter())
+            .map(|(l, c)| l - c)
+            .collect::<Vec<f64>>();
+        for (a, b) in difference.iter().zip(estimated.iter()) {
+            assert_abs_diff_eq!(a, b, epsilon = 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn loudness_metering() {
+        // A sine at a quarter of the sample rate, with the peaks halfway between the samples.
+        let wave = (0..2048)
+            .map(|n| (0.5 * PI * n as f64 + 0.25 * PI).sin())
+            .collect::<Vec<f64>>();
+        let mut resampler = SincFixedIn::<f64>::new(1.5, 1.0, basic_params(), 512, 1).unwrap();
         assert_eq!(
-            frames,
-            resampler.input_frames_next(),
-            "Resampler requires different number of frames when new and after a reset."
+            resampler.output_stage().last_loudness(),
+            LoudnessStats::default()
         );
-        let out2 = resampler.process(&waves, None).unwrap();
+        resampler.output_stage_mut().set_loudness_metering(true);
+        for chunk in wave.chunks(512) {
+            resampler.process(&[chunk], None).unwrap();
+        }
+        let stats = resampler.output_stage().last_loudness();
+        assert_eq!(stats.frames, 512);
+        assert_abs_diff_eq!(stats.sample_peak, 0.5_f64.sqrt(), epsilon = 1.0e-9);
+        assert_abs_diff_eq!(stats.rms, 0.5_f64.sqrt(), epsilon = 1.0e-9);
+        assert!(stats.true_peak > 0.98);
+        assert!(stats.true_peak > 1.3 * stats.sample_peak);
+
+        let mut resampler = SincFixedOut::<f64>::new(1.5, 1.0, basic_params(), 512, 1).unwrap();
+        resampler.output_stage_mut().set_loudness_metering(true);
+        let mut offset = 0;
+        for _ in 0..4 {
+            let frames = resampler.input_frames_next();
+            resampler
+                .process(&[&wave[offset..offset + frames]], None)
+                .unwrap();
+            offset += frames;
+            assert_eq!(resampler.output_stage().last_loudness().frames, frames);
+        }
+        let stats = resampler.output_stage().last_loudness();
+        assert!(stats.true_peak > 1.3 * stats.sample_peak);
+        resampler.output_stage_mut().set_loudness_metering(false);
         assert_eq!(
-            out1, out2,
-            "Resampler gives different output when new and after a reset."
+            resampler.output_stage().last_loudness(),
+            LoudnessStats::default()
         );
     }
 
     #[test]
-    fn make_resampler_fo_32() {
-        let params = basic_params();
-        let mut resampler = SincFixedOut::<f32>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        let frames = resampler.input_frames_next();
-        println!("{}", frames);
-        assert!(frames > 800 && frames < 900);
-        let waves = vec![vec![0.0f32; frames]; 2];
-        let out = resampler.process(&waves, None).unwrap();
-        assert_eq!(out.len(), 2);
-        assert_eq!(out[0].len(), 1024);
+    fn fractional_phase() {
+        // Each output frame advances the position by 0.625 input frames.
+        let mut resampler = SincFixedIn::<f64>::new(1.6, 1.0, basic_params(), 1000, 1).unwrap();
+        assert!((resampler.fractional_phase() - 0.625).abs() < 1.0e-12);
+        let waves = vec![vec![0.0; 1000]; 1];
+        let mut frames = 0;
+        for _ in 0..5 {
+            frames += resampler.process(&waves, None).unwrap()[0].len();
+            let expected = ((frames + 1) as f64 * 0.625).fract();
+            assert!((resampler.fractional_phase() - expected).abs() < 1.0e-9);
+        }
+        let mut resampler = SincFixedOut::<f64>::new(1.6, 1.0, basic_params(), 1000, 1).unwrap();
+        for n in 1..5 {
+            let waves = vec![vec![0.0; resampler.input_frames_next()]; 1];
+            resampler.process(&waves, None).unwrap();
+            let expected = ((n * 1000 + 1) as f64 * 0.625).fract();
+            assert!((resampler.fractional_phase() - expected).abs() < 1.0e-9);
+        }
     }
 
     #[test]
-    fn make_resampler_fo_skipped() {
-        let params = basic_params();
-        let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        let frames = resampler.input_frames_next();
-        println!("{}", frames);
-        assert!(frames > 800 && frames < 900);
-        let mut waves = vec![vec![0.0f64; frames], Vec::new()];
-        let mask = vec![true, false];
-        waves[0][100] = 3.0;
-        let out = resampler.process(&waves, Some(&mask)).unwrap();
-        assert_eq!(out.len(), 2);
-        assert_eq!(out[0].len(), 1024);
-        assert!(out[1].is_empty());
-        println!("{:?}", out[0]);
-        let summed = out[0].iter().sum::<f64>();
-        println!("sum: {}", summed);
-        assert!(summed < 4.0);
-        assert!(summed > 2.0);
+    fn dc_preserved() {
+        for interpolation in [
+            SincInterpolationType::Cubic,
+            SincInterpolationType::Linear,
+            SincInterpolationType::Quadratic,
+        ] {
+            let mut params = basic_params();
+            params.interpolation = interpolation;
+            for ratio in [0.5, 44100.0 / 48000.0, 1.0, 1.37, 3.0] {
+                let mut resampler =
+                    SincFixedIn::<f64>::new(ratio, 1.0, params.clone(), 512, 1).unwrap();
+                assert!((resampler.dc_gain() - 1.0).abs() < 1.0e-12);
+                let waves = vec![vec![0.3; 512]; 1];
+                resampler.process(&waves, None).unwrap();
+                for _ in 0..2 {
+                    let output = resampler.process(&waves, None).unwrap();
+                    for value in output[0].iter() {
+                        assert!((value - 0.3).abs() < 1.0e-12);
+                    }
+                }
+                let mut resampler =
+                    SincFixedOut::<f32>::new(ratio, 1.0, params.clone(), 512, 1).unwrap();
+                assert!((resampler.dc_gain() - 1.0).abs() < 1.0e-5);
+                for n in 0..3 {
+                    let waves = vec![vec![0.3f32; resampler.input_frames_next()]; 1];
+                    let output = resampler.process(&waves, None).unwrap();
+                    if n > 0 {
+                        for value in output[0].iter() {
+                            assert!((value - 0.3).abs() < 1.0e-5);
+                        }
+                    }
+                }
+            }
+        }
+    }
 
-        let frames = resampler.input_frames_next();
-        let mut waves = vec![Vec::new(), vec![0.0f64; frames]];
-        let mask = vec![false, true];
-        waves[1][10] = 3.0;
-        let out = resampler.process(&waves, Some(&mask)).unwrap();
-        assert_eq!(out.len(), 2);
-        assert_eq!(out[1].len(), 1024);
-        assert!(out[0].is_empty());
-        let summed = out[1].iter().sum::<f64>();
-        assert!(summed < 4.0);
-        assert!(summed > 2.0);
+    #[test]
+    fn interleaved_buffer() {
+        let mut rng = rand::thread_rng();
+        let channels = 5;
+        for interpolation in [
+            SincInterpolationType::Cubic,
+            SincInterpolationType::Quadratic,
+            SincInterpolationType::Linear,
+            SincInterpolationType::Nearest,
+        ] {
+            let mut params = basic_params();
+            params.interpolation = interpolation;
+            let ratio = if params.interpolation == SincInterpolationType::Nearest {
+                2.0
+            } else {
+                1.3
+            };
+            let mut reference =
+                SincFixedIn::<f64>::new(ratio, 1.1, params.clone(), 256, channels).unwrap();
+            let mut resampler =
+                SincFixedIn::<f64>::new(ratio, 1.1, params.clone(), 256, channels).unwrap();
+            resampler.set_interleaved_buffer(true);
+            let mut reference_out =
+                SincFixedOut::<f64>::new(ratio, 1.1, params.clone(), 256, channels).unwrap();
+            let mut resampler_out =
+                SincFixedOut::<f64>::new(ratio, 1.1, params.clone(), 256, channels).unwrap();
+            resampler_out.set_interleaved_buffer(true);
+            let mask = [true, false, true, true, false];
+            for n in 0..8 {
+                if n == 4 {
+                    reference.set_resample_ratio_relative(1.05, true).unwrap();
+                    resampler.set_resample_ratio_relative(1.05, true).unwrap();
+                    reference.set_quality(Quality::Preview);
+                    resampler.set_quality(Quality::Preview);
+                }
+                // Deactivate some channels for a while.
+                let mask = if n == 2 || n == 3 {
+                    Some(&mask[..])
+                } else {
+                    None
+                };
+                let waves = (0..channels)
+                    .map(|_| (0..256).map(|_| rng.gen::<f64>() - 0.5).collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+                let expected = reference.process(&waves, mask).unwrap();
+                let output = resampler.process(&waves, mask).unwrap();
+                assert_eq!(output[0].len(), expected[0].len());
+                for (out, exp) in output.iter().flatten().zip(expected.iter().flatten()) {
+                    assert!((out - exp).abs() < 1.0e-12);
+                }
+                let frames = reference_out.input_frames_next();
+                let waves = (0..channels)
+                    .map(|_| {
+                        (0..frames)
+                            .map(|_| rng.gen::<f64>() - 0.5)
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                let expected = reference_out.process(&waves, mask).unwrap();
+                let output = resampler_out.process(&waves, mask).unwrap();
+                for (out, exp) in output.iter().flatten().zip(expected.iter().flatten()) {
+                    assert!((out - exp).abs() < 1.0e-12);
+                }
+            }
+        }
     }
 
     #[test]
-    fn make_resampler_fo_downsample() {
-        let params = SincInterpolationParameters {
-            sinc_len: 256,
-            f_cutoff: 0.95,
-            interpolation: SincInterpolationType::Cubic,
-            oversampling_factor: 160,
-            window: WindowFunction::BlackmanHarris2,
-        };
-        let mut resampler = SincFixedOut::<f64>::new(0.125, 1.0, params, 1024, 2).unwrap();
-        let frames = resampler.input_frames_next();
-        println!("{}", frames);
-        assert!(
-            frames > 8192 && frames < 9000,
-            "expected {}..{} samples, got {}",
-            8192,
-            9000,
-            frames
-        );
-        let waves = vec![vec![0.0f64; frames]; 2];
-        let out = resampler.process(&waves, None).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+    fn memory_footprint() {
+        let params = basic_params();
+        let footprint = |channels| params.memory_footprint::<f64>(1024, channels);
+        // Sinc table, and one buffer per channel.
+        assert_eq!(footprint(0), 16 * 64 * 8);
+        assert_eq!(footprint(1), 16 * 64 * 8 + (1024 + 128) * 8 + 1);
+        // Doubling the number of channels doubles the per-channel part.
         assert_eq!(
-            out[0].len(),
-            1024,
-            "Expected {} frames, got {}",
-            1024,
-            out[0].len()
+            footprint(8) - footprint(0),
+            2 * (footprint(4) - footprint(0))
         );
-        let frames2 = resampler.input_frames_next();
-        assert!(
-            frames2 > 8189 && frames2 < 8195,
-            "expected {}..{} samples, got {}",
-            8189,
-            8195,
-            frames2
+        assert_eq!(
+            footprint(2) - footprint(0),
+            2 * (footprint(1) - footprint(0))
         );
-        let waves2 = vec![vec![0.0f64; frames2]; 2];
-        let out2 = resampler.process(&waves2, None).unwrap();
         assert_eq!(
-            out2[0].len(),
-            1024,
-            "Expected {} frames, got {}",
-            1024,
-            out2[0].len()
+            params.memory_footprint::<f32>(1024, 1),
+            16 * 64 * 4 + (1024 + 128) * 4 + 1
         );
     }
 
     #[test]
-    fn make_resampler_fo_upsample() {
-        let params = SincInterpolationParameters {
-            sinc_len: 256,
-            f_cutoff: 0.95,
-            interpolation: SincInterpolationType::Cubic,
-            oversampling_factor: 160,
-            window: WindowFunction::BlackmanHarris2,
-        };
-        let mut resampler = SincFixedOut::<f64>::new(8.0, 1.0, params, 1024, 2).unwrap();
-        let frames = resampler.input_frames_next();
-        println!("{}", frames);
-        assert!(
-            frames > 128 && frames < 300,
-            "expected {}..{} samples, got {}",
-            140,
-            200,
-            frames
-        );
-        let waves = vec![vec![0.0f64; frames]; 2];
-        let out = resampler.process(&waves, None).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+    fn delay_buffer_len() {
+        let mut params = basic_params();
+        params.sinc_len = 60;
+        let resampler = SincFixedIn::<f64>::new(1.5, 2.0, params, 1024, 2).unwrap();
+        assert_eq!(resampler.delay_buffer_len(), 128);
         assert_eq!(
-            out[0].len(),
-            1024,
-            "Expected {} frames, got {}",
-            1024,
-            out[0].len()
-        );
-        let frames2 = resampler.input_frames_next();
-        assert!(
-            frames2 > 125 && frames2 < 131,
-            "expected {}..{} samples, got {}",
-            125,
-            131,
-            frames2
+            resampler.output_delay(),
+            (resampler.delay_buffer_len() as f64 / 4.0 * 1.5) as usize
         );
-        let waves2 = vec![vec![0.0f64; frames2]; 2];
-        let out2 = resampler.process(&waves2, None).unwrap();
+        let resampler = SincFixedOut::<f64>::new(0.5, 2.0, basic_params(), 1024, 2).unwrap();
+        assert_eq!(resampler.delay_buffer_len(), 128);
         assert_eq!(
-            out2[0].len(),
-            1024,
-            "Expected {} frames, got {}",
-            1024,
-            out2[0].len()
+            resampler.output_delay(),
+            (resampler.delay_buffer_len() as f64 / 4.0 * 0.5) as usize
         );
     }
 
     #[test]
-    fn check_fo_output_up() {
-        let params = basic_params();
-        let mut resampler = SincFixedOut::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        check_output!(resampler);
+    fn process_arrays() {
+        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 1024, 2).unwrap();
+        let mut reference = SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 1024, 2).unwrap();
+        let mut wave_out = resampler.output_buffer_allocate(true);
+        let mut wave_out_ref = reference.output_buffer_allocate(true);
+        let mut rng = rand::thread_rng();
+        for _ in 0..3 {
+            let mut arrays = [[0.0f64; 1024]; 2];
+            for chan in arrays.iter_mut() {
+                chan.iter_mut().for_each(|v| *v = rng.gen::<f64>());
+            }
+            let vecs = arrays.iter().map(|a| a.to_vec()).collect::<Vec<_>>();
+            let result = resampler
+                .process_arrays_into_buffer(&arrays, &mut wave_out, None)
+                .unwrap();
+            let result_ref = reference
+                .process_into_buffer(&vecs, &mut wave_out_ref, None)
+                .unwrap();
+            assert_eq!(result, result_ref);
+            assert_eq!(wave_out, wave_out_ref);
+        }
+        let short = [[0.0f64; 512]; 2];
+        assert!(matches!(
+            resampler.process_arrays_into_buffer(&short, &mut wave_out, None),
+            Err(ResampleError::WrongNumberOfInputFrames {
+                expected: 1024,
+                actual: 512
+            })
+        ));
     }
 
     #[test]
-    fn check_fo_output_down() {
-        let params = basic_params();
-        let mut resampler = SincFixedOut::<f64>::new(0.8, 1.0, params, 1024, 2).unwrap();
-        check_output!(resampler);
+    fn reject_zero_channels() {
+        assert!(matches!(
+            SincFixedIn::<f64>::new(1.2, 1.0, basic_params(), 1024, 0),
+            Err(ResamplerConstructionError::InvalidChannelCount(0))
+        ));
+        assert!(matches!(
+            SincFixedOut::<f64>::new(1.2, 1.0, basic_params(), 1024, 0),
+            Err(ResamplerConstructionError::InvalidChannelCount(0))
+        ));
+        let interpolator = Box::new(ScalarInterpolator::<f64>::new(
+            64,
+            16,
+            0.95,
+            WindowFunction::BlackmanHarris2,
+        ));
+        assert!(matches!(
+            SincFixedIn::<f64>::new_with_interpolator(
+                1.2,
+                1.0,
+                SincInterpolationType::Linear,
+                interpolator,
+                1024,
+                0
+            ),
+            Err(ResamplerConstructionError::InvalidChannelCount(0))
+        ));
     }
 
     #[test]
-    fn check_fi_output_up() {
-        let params = basic_params();
-        let mut resampler = SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).unwrap();
-        check_output!(resampler);
+    fn nearest_exact() {
+        let mut params = basic_params();
+        params.interpolation = SincInterpolationType::Nearest;
+        params.oversampling_factor = 160;
+        assert!(validate_sinc_config(&params, 48000.0 / 44100.0, 1.0, 1024, 2).is_ok());
+        params.oversampling_factor = 2;
+        assert!(SincFixedIn::<f64>::new(2.0, 1.0, params, 1024, 2).is_ok());
     }
 
     #[test]
-    fn check_fi_output_down() {
-        let params = basic_params();
-        let mut resampler = SincFixedIn::<f64>::new(0.8, 1.0, params, 1024, 2).unwrap();
-        check_output!(resampler);
+    fn nearest_not_exact() {
+        let mut params = basic_params();
+        params.interpolation = SincInterpolationType::Nearest;
+        params.oversampling_factor = 16;
+        assert!(matches!(
+            validate_sinc_config(&params, 48000.0 / 44100.0, 1.0, 1024, 2),
+            Err(ResamplerConstructionError::NearestNotExact {
+                suggested_oversampling: 160
+            })
+        ));
+        assert!(matches!(
+            SincFixedOut::<f64>::new(1.5, 1.0, params, 1024, 2),
+            Err(ResamplerConstructionError::NearestNotExact {
+                suggested_oversampling: 48
+            })
+        ));
     }
 
     #[test]
-    fn resample_small_fo_up() {
-        let ratio = 96000.0 / 44100.0;
-        let params = basic_params();
-        let mut resampler = SincFixedOut::<f32>::new(ratio, 1.0, params, 1, 2).unwrap();
-        check_ratio!(resampler, ratio, 100000);
-    }
+    fn optimal_oversampling() {
+        let linear = SincInterpolationParameters::optimal_oversampling(
+            SincInterpolationType::Linear,
+            -120.0,
+        );
+        let cubic =
+            SincInterpolationParameters::optimal_oversampling(SincInterpolationType::Cubic, -120.0);
+        assert_eq!(linear, 1111);
+        assert_eq!(cubic, 39);
 
-    #[test]
-    fn resample_big_fo_up() {
-        let ratio = 96000.0 / 44100.0;
-        let params = basic_params();
-        let mut resampler = SincFixedOut::<f32>::new(ratio, 1.0, params, 1024, 2).unwrap();
-        check_ratio!(resampler, ratio, 100);
+        // Check the bound by interpolating a sine at the Nyquist frequency.
+        let peak_error = |factor: usize, cubic: bool| {
+            let step = PI / factor as f64;
+            (0..1000)
+                .map(|n| {
+                    let x = n as f64 / 1000.0;
+                    let points = [-step.sin(), 0.0, step.sin(), (2.0 * step).sin()];
+                    let value = if cubic {
+                        interp_cubic(x, &points)
+                    } else {
+                        interp_lin(x, &[points[1], points[2]])
+                    };
+                    (value - (x * step).sin()).abs()
+                })
+                .fold(0.0, f64::max)
+        };
+        for target in [-80.0, -100.0, -120.0] {
+            let max_error = 10.0f64.powf(target / 20.0);
+            let linear = SincInterpolationParameters::optimal_oversampling(
+                SincInterpolationType::Linear,
+                target,
+            );
+            let cubic = SincInterpolationParameters::optimal_oversampling(
+                SincInterpolationType::Cubic,
+                target,
+            );
+            assert!(linear > cubic);
+            assert!(peak_error(linear, false) <= max_error);
+            assert!(peak_error(cubic, true) <= max_error);
+            assert!(peak_error(cubic / 2, true) > max_error);
+        }
     }
 
     #[test]
-    fn resample_small_fo_down() {
-        let ratio = 44100.0 / 96000.0;
-        let params = basic_params();
-        let mut resampler = SincFixedOut::<f32>::new(ratio, 1.0, params, 1, 2).unwrap();
-        check_ratio!(resampler, ratio, 100000);
+    fn passband_ripple() {
+        let mut params = basic_params();
+        params.sinc_len = 32;
+        params.window = WindowFunction::Hann;
+        let ripple_short = params.passband_ripple_db();
+        params.sinc_len = 256;
+        let ripple_long = params.passband_ripple_db();
+        assert!(ripple_short > 0.0);
+        assert!(ripple_long < ripple_short);
     }
 
     #[test]
-    fn resample_big_fo_down() {
-        let ratio = 44100.0 / 96000.0;
+    fn validate_parameters() {
         let params = basic_params();
-        let mut resampler = SincFixedOut::<f32>::new(ratio, 1.0, params, 1024, 2).unwrap();
-        check_ratio!(resampler, ratio, 100);
-    }
-
-    #[test]
-    fn resample_small_fi_up() {
-        let ratio = 96000.0 / 44100.0;
+        assert!(validate_sinc_config(&params, 1.2, 1.0, 1024, 2).is_ok());
+        assert!(matches!(
+            validate_sinc_config(&params, 0.0, 1.0, 1024, 2),
+            Err(ResamplerConstructionError::InvalidRatio(_))
+        ));
+        assert!(matches!(
+            validate_sinc_config(&params, 1.2, 0.5, 1024, 2),
+            Err(ResamplerConstructionError::InvalidRelativeRatio(_))
+        ));
+        assert!(matches!(
+            validate_sinc_config(&params, 1.2, 1.0, 1024, 0),
+            Err(ResamplerConstructionError::InvalidChannelCount(0))
+        ));
+        let mut params = basic_params();
+        params.sinc_len = 0;
+        assert!(matches!(
+            validate_sinc_config(&params, 1.2, 1.0, 1024, 2),
+            Err(ResamplerConstructionError::InvalidSincLength(0))
+        ));
+        let mut params = basic_params();
+        params.oversampling_factor = 0;
+        assert!(matches!(
+            validate_sinc_config(&params, 1.2, 1.0, 1024, 2),
+            Err(ResamplerConstructionError::InvalidOversamplingFactor(0))
+        ));
+        let mut params = basic_params();
+        params.f_cutoff = 1.5;
+        assert!(matches!(
+            validate_sinc_config(&params, 1.2, 1.0, 1024, 2),
+            Err(ResamplerConstructionError::InvalidCutoff(_))
+        ));
         let params = basic_params();
-        let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, 1, 2).unwrap();
-        check_ratio!(resampler, ratio, 100000);
+        assert!(matches!(
+            validate_sinc_config(&params, 1.2, 1.0, 0, 2),
+            Err(ResamplerConstructionError::InvalidChunkSize(0))
+        ));
+        assert!(SincFixedOut::<f64>::new(1.2, 1.0, params.clone(), 0, 2).is_err());
+        let mut params = basic_params();
+        params.f_cutoff = 0.0;
+        assert!(SincFixedIn::<f64>::new(1.2, 1.0, params, 1024, 2).is_err());
     }
 
     #[test]
-    fn resample_big_fi_up() {
-        let ratio = 96000.0 / 44100.0;
-        let params = basic_params();
-        let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, 1024, 2).unwrap();
-        check_ratio!(resampler, ratio, 100);
+    fn validate_agrees_with_construction() {
+        let mut params = basic_params();
+        params.sinc_len = 8;
+        assert!(matches!(
+            validate_sinc_config(&params, 0.125, 2.0, 64, 1),
+            Err(ResamplerConstructionError::SincLenTooShort {
+                provided: 8,
+                minimum: 16
+            })
+        ));
+        assert!(matches!(
+            SincFixedIn::<f64>::new(0.125, 2.0, params.clone(), 64, 1),
+            Err(ResamplerConstructionError::SincLenTooShort {
+                provided: 8,
+                minimum: 16
+            })
+        ));
+        // SincFixedOut supports shorter sinc lengths.
+        assert!(SincFixedOut::<f64>::new(0.125, 2.0, params, 64, 1).is_ok());
+
+        for sinc_len in [1, 8, 16, 32, 64] {
+            for ratio in [0.1, 0.125, 0.5, 1.0, 2.0, 8.0] {
+                for max_relative in [1.0, 1.1, 2.0, 4.0] {
+                    let mut params = basic_params();
+                    params.sinc_len = sinc_len;
+                    let valid = validate_sinc_config(&params, ratio, max_relative, 64, 1);
+                    let constructed = SincFixedIn::<f64>::new(ratio, max_relative, params, 64, 1);
+                    assert_eq!(
+                        valid.is_ok(),
+                        constructed.is_ok(),
+                        "sinc_len {}, ratio {}, max_relative {}",
+                        sinc_len,
+                        ratio,
+                        max_relative
+                    );
+                }
+            }
+        }
     }
 
     #[test]
-    fn resample_small_fi_down() {
-        let ratio = 44100.0 / 96000.0;
-        let params = basic_params();
-        let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, 1, 2).unwrap();
-        check_ratio!(resampler, ratio, 100000);
+    fn peek_output_processing() {
+        let mut rng = rand::thread_rng();
+        let waves = vec![
+            (0..256)
+                .map(|_| rng.gen::<f64>() - 0.5)
+                .collect::<Vec<f64>>();
+            2
+        ];
+        for setup in 0..5 {
+            let mut params = basic_params();
+            if setup == 4 {
+                params.interpolation = SincInterpolationType::Adaptive { threshold: 0.1 };
+            }
+            let mut resampler = SincFixedIn::<f64>::new(1.7, 1.0, params, 256, 2).unwrap();
+            resampler.set_emphasis(EmphasisMode::PreemphasizeInput(EmphasisCurve::Cd), 44100.0);
+            resampler
+                .output_stage_mut()
+                .set_channel_gains(&[0.5, 2.0])
+                .unwrap();
+            match setup {
+                0 => resampler.set_output_rounding(OutputRounding::AlwaysFloor),
+                1 => resampler.set_interleaved_buffer(true),
+                2 => resampler.set_deterministic(true),
+                3 => resampler.set_match_rms(true),
+                _ => {}
+            }
+            resampler.set_lookahead(8).unwrap();
+            for _ in 0..3 {
+                resampler.process(&waves, None).unwrap();
+            }
+            let mut peeked = vec![vec![0.0f64; 64]; 2];
+            let nbr_peeked = resampler.peek_into_buffer(&mut peeked, 64).unwrap();
+            assert!(nbr_peeked >= 8);
+            let out = resampler.process(&waves, None).unwrap();
+            for chan in 0..2 {
+                if setup == 3 {
+                    // The RMS matching ramps the gain within the chunk.
+                    for (peek, value) in peeked[chan][..nbr_peeked].iter().zip(out[chan].iter()) {
+                        assert_abs_diff_eq!(peek, value, epsilon = 1.0e-2);
+                    }
+                } else {
+                    assert_eq!(peeked[chan][..nbr_peeked], out[chan][..nbr_peeked]);
+                }
+            }
+        }
     }
 
     #[test]
-    fn resample_big_fi_down() {
-        let ratio = 44100.0 / 96000.0;
+    fn peek_fi() {
         let params = basic_params();
-        let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, 1024, 2).unwrap();
-        check_ratio!(resampler, ratio, 100);
+        let mut resampler = SincFixedIn::<f64>::new(4.0, 1.0, params, 256, 2).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut waves = vec![vec![0.0f64; 256]; 2];
+        for _ in 0..2 {
+            waves
+                .iter_mut()
+                .for_each(|ch| ch.iter_mut().for_each(|s| *s = rng.gen()));
+            resampler.process(&waves, None).unwrap();
+        }
+        let mut peeked = vec![vec![0.0f64; 16]; 2];
+        let nbr_peeked = resampler.peek_into_buffer(&mut peeked, 16).unwrap();
+        assert_eq!(nbr_peeked, 0);
+        resampler.set_lookahead(8).unwrap();
+        assert!(resampler.set_lookahead(33).is_err());
+        resampler.process(&waves, None).unwrap();
+        let nbr_peeked = resampler.peek_into_buffer(&mut peeked, 16).unwrap();
+        assert!(nbr_peeked >= 16);
+        let out = resampler.process(&waves, None).unwrap();
+        for chan in 0..2 {
+            assert_eq!(peeked[chan][..nbr_peeked], out[chan][..nbr_peeked]);
+        }
     }
 }
@@ -0,0 +1,70 @@
+//! Simple timing helper for quick performance checks.
+//!
+//! This is not a substitute for a proper benchmarking framework such as `criterion`,
+//! but it gives a consistent way to compare resampler configurations
+//! and to put numbers on performance problems.
+
+use std::time::{Duration, Instant};
+
+use crate::{Resampler, SincFixedIn, SincInterpolationParameters};
+
+/// Number of chunks processed before timing starts.
+const WARMUP_CHUNKS: usize = 10;
+/// Number of chunks included in the timing.
+const TIMED_CHUNKS: usize = 100;
+
+/// Measure the steady-state processing time of a [SincFixedIn] resampler.
+///
+/// A resampler is created with the given parameters, for converting from `fs_in` to `fs_out`,
+/// with `channels` channels and a chunk size of `frames` input frames.
+/// It is warmed up by processing a few chunks, and then the time for a number of calls to
+/// [process_into_buffer](Resampler::process_into_buffer) is measured.
+/// Buffers are allocated before timing starts.
+///
+/// Returns the average processing time per input frame.
+///
+/// Panics if the resampler can't be created with the given parameters.
+pub fn run_bench(
+    params: SincInterpolationParameters,
+    fs_in: usize,
+    fs_out: usize,
+    channels: usize,
+    frames: usize,
+) -> Duration {
+    let ratio = fs_out as f64 / fs_in as f64;
+    let mut resampler = SincFixedIn::<f64>::new(ratio, 1.0, params, frames, channels)
+        .expect("Unable to create resampler");
+    let waves_in = vec![vec![0.0f64; frames]; channels];
+    let mut waves_out = resampler.output_buffer_allocate(true);
+    for _ in 0..WARMUP_CHUNKS {
+        resampler
+            .process_into_buffer(&waves_in, &mut waves_out, None)
+            .unwrap();
+    }
+    let start = Instant::now();
+    for _ in 0..TIMED_CHUNKS {
+        resampler
+            .process_into_buffer(&waves_in, &mut waves_out, None)
+            .unwrap();
+    }
+    start.elapsed() / (TIMED_CHUNKS * frames) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run_bench;
+    use crate::{SincInterpolationParameters, SincInterpolationType, WindowFunction};
+
+    #[test]
+    fn bench_nonzero() {
+        let params = SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let per_frame = run_bench(params, 44100, 48000, 2, 1024);
+        assert!(per_frame > std::time::Duration::ZERO);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{make_buffer, Resampler, Sample};
+
+/// A helper that owns a resampler, and limits the number of output frames produced per call.
+///
+/// This is intended for callbacks with a fixed deadline, where only a limited amount
+/// of work can be done in each cycle.
+/// Each call to [process_up_to](BudgetedResampler::process_up_to) takes any number of input frames.
+/// These are appended to an internal buffer, and the resampler then processes as many
+/// chunks as fit within the given budget of output frames.
+/// Input that is not consumed stays in the buffer until the next call.
+pub struct BudgetedResampler<T, R> {
+    resampler: R,
+    input: Vec<Vec<T>>,
+    output: Vec<Vec<T>>,
+}
+
+impl<T, R> BudgetedResampler<T, R>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    /// Create a new BudgetedResampler, wrapping the given resampler.
+    ///
+    /// The internal input buffer is allocated to hold `buffer_frames` frames per channel,
+    /// but at least twice the maximum input chunk size of the resampler.
+    /// It grows if more input is buffered than this, which means that the calls to
+    /// [process_up_to](BudgetedResampler::process_up_to) are only free of allocations
+    /// as long as the input is consumed at about the same rate as it is provided.
+    pub fn new(resampler: R, buffer_frames: usize) -> Self {
+        let channels = resampler.nbr_channels();
+        let capacity = buffer_frames.max(2 * resampler.input_frames_max());
+        let input = make_buffer(channels, capacity, false);
+        let output = resampler.output_buffer_allocate(true);
+        BudgetedResampler {
+            resampler,
+            input,
+            output,
+        }
+    }
+
+    /// Buffer the input, and resample as many chunks as possible
+    /// without producing more than `max_output_frames` frames.
+    ///
+    /// All channels of `wave_in` must have the same length, which may be zero.
+    /// The output channels in `wave_out` must hold at least `max_output_frames` frames.
+    /// The resampler processes whole chunks, and a chunk is only processed
+    /// if both its input is available and its output fits within the remaining budget.
+    /// Returns the number of frames written per channel.
+    ///
+    /// If the resampler returns an error, the error is returned and the contents
+    /// of `wave_out` are not valid, even if some chunks were processed before the error.
+    /// The frames produced by those chunks are lost, while their input has already
+    /// been removed from the buffer.
+    pub fn process_up_to<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        max_output_frames: usize,
+    ) -> ResampleResult<usize> {
+        let channels = self.resampler.nbr_channels();
+        if wave_in.len() != channels {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: channels,
+                actual: wave_in.len(),
+            });
+        }
+        if wave_out.len() != channels {
+            return Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: channels,
+                actual: wave_out.len(),
+            });
+        }
+        let frames_in = wave_in[0].as_ref().len();
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if wave.as_ref().len() != frames_in {
+                return Err(ResampleError::InsufficientInputBufferSize {
+                    channel: chan,
+                    expected: frames_in,
+                    actual: wave.as_ref().len(),
+                });
+            }
+        }
+        for (chan, wave) in wave_out.iter_mut().enumerate() {
+            if wave.as_mut().len() < max_output_frames {
+                return Err(ResampleError::InsufficientOutputBufferSize {
+                    channel: chan,
+                    expected: max_output_frames,
+                    actual: wave.as_mut().len(),
+                });
+            }
+        }
+        for (buffer, wave) in self.input.iter_mut().zip(wave_in.iter()) {
+            buffer.extend_from_slice(wave.as_ref());
+        }
+
+        let mut frames_out = 0;
+        while self.resampler.input_frames_next() <= self.buffered_input_frames()
+            && frames_out + self.resampler.output_frames_next() <= max_output_frames
+        {
+            let (consumed, produced) =
+                self.resampler
+                    .process_into_buffer(&self.input, &mut self.output, None)?;
+            for buffer in self.input.iter_mut() {
+                buffer.copy_within(consumed.., 0);
+                buffer.truncate(buffer.len() - consumed);
+            }
+            for (out, chunk) in wave_out.iter_mut().zip(self.output.iter()) {
+                out.as_mut()[frames_out..frames_out + produced].copy_from_slice(&chunk[..produced]);
+            }
+            frames_out += produced;
+        }
+        Ok(frames_out)
+    }
+
+    /// Get the number of input frames per channel that are buffered but not yet consumed.
+    pub fn buffered_input_frames(&self) -> usize {
+        self.input[0].len()
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn resampler(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler,
+    /// for example for changing the resampling ratio.
+    pub fn resampler_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Consume the BudgetedResampler and return the wrapped resampler.
+    /// Any buffered input is dropped.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BudgetedResampler;
+    use crate::{FastFixedIn, PolynomialDegree, Resampler, SincFixedOut};
+    use crate::{SincInterpolationParameters, SincInterpolationType, WindowFunction};
+    use rand::Rng;
+
+    fn run_capped<R: Resampler<f64>>(
+        resampler: R,
+        blocks: &[Vec<Vec<f64>>],
+        max_output_frames: usize,
+    ) -> Vec<Vec<f64>> {
+        let mut budgeted = BudgetedResampler::new(resampler, 0);
+        let mut wave_out = vec![vec![0.0; max_output_frames]; 2];
+        let mut collected = vec![Vec::new(); 2];
+        let empty = vec![Vec::<f64>::new(); 2];
+        // Feed all blocks with a capped output, then drain the buffered input.
+        for block in blocks.iter().map(Some).chain(std::iter::repeat(None)) {
+            let frames = budgeted
+                .process_up_to(block.unwrap_or(&empty), &mut wave_out, max_output_frames)
+                .unwrap();
+            assert!(frames <= max_output_frames);
+            for (coll, out) in collected.iter_mut().zip(wave_out.iter()) {
+                coll.extend_from_slice(&out[..frames]);
+            }
+            if block.is_none() && frames == 0 {
+                break;
+            }
+        }
+        collected
+    }
+
+    fn run_uncapped<R: Resampler<f64>>(mut resampler: R, input: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        let mut collected = vec![Vec::new(); 2];
+        let mut offset = 0;
+        while offset + resampler.input_frames_next() <= input[0].len() {
+            let frames = resampler.input_frames_next();
+            let chunk = input
+                .iter()
+                .map(|chan| &chan[offset..offset + frames])
+                .collect::<Vec<_>>();
+            let output = resampler.process(&chunk, None).unwrap();
+            for (coll, out) in collected.iter_mut().zip(output) {
+                coll.extend_from_slice(&out);
+            }
+            offset += frames;
+        }
+        collected
+    }
+
+    #[test]
+    fn capped_matches_uncapped() {
+        let mut rng = rand::thread_rng();
+        // Blocks of varying length, as from an audio callback.
+        let blocks = (0..40)
+            .map(|_| {
+                let frames = rng.gen_range(0..800);
+                (0..2)
+                    .map(|_| (0..frames).map(|_| rng.gen::<f64>() - 0.5).collect())
+                    .collect::<Vec<Vec<f64>>>()
+            })
+            .collect::<Vec<_>>();
+        let input = (0..2)
+            .map(|chan| {
+                blocks
+                    .iter()
+                    .flat_map(|block| block[chan].iter().copied())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let make_fast =
+            || FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let capped = run_capped(make_fast(), &blocks, 400);
+        assert_eq!(capped, run_uncapped(make_fast(), &input));
+
+        let params = SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let make_sinc = || SincFixedOut::<f64>::new(0.7, 1.0, params.clone(), 128, 2).unwrap();
+        let capped = run_capped(make_sinc(), &blocks, 200);
+        assert_eq!(capped, run_uncapped(make_sinc(), &input));
+    }
+
+    #[test]
+    fn budget_too_small() {
+        let resampler = FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mut budgeted = BudgetedResampler::new(resampler, 1024);
+        let mut wave_out = vec![vec![0.0; 100]; 2];
+        let frames = budgeted
+            .process_up_to(&vec![vec![0.0; 512]; 2], &mut wave_out, 100)
+            .unwrap();
+        assert_eq!(frames, 0);
+        assert_eq!(budgeted.buffered_input_frames(), 512);
+        assert!(budgeted
+            .process_up_to(&vec![vec![0.0; 512]; 2], &mut wave_out, 200)
+            .is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::error::{ResampleResult, ResamplerConstructionError};
+use crate::{Quality, ResamplerFactory, Sample, VecResampler};
+
+/// A resampler that converts from a fixed input sample rate to the rate of an output device,
+/// where the device rate may change while running.
+///
+/// The resampler is chosen like in a [ResamplerFactory], and takes `chunk_size`
+/// input frames per call.
+/// When the output rate changes with [set_output_rate](DeviceMatchResampler::set_output_rate),
+/// an asynchronous resampler just gets its ratio updated, as long as the new ratio is within
+/// the range it was created for. In all other cases, for example when an FFT resampler is used,
+/// a new resampler is created for the new rate.
+/// The new resampler starts from silence, and any input that the old one had buffered is dropped.
+pub struct DeviceMatchResampler<T> {
+    factory: ResamplerFactory<T>,
+    resampler: Box<dyn VecResampler<T>>,
+    input_sample_rate: f64,
+    output_sample_rate: f64,
+    nbr_channels: usize,
+    quality: Quality,
+}
+
+impl<T> DeviceMatchResampler<T>
+where
+    T: Sample,
+{
+    /// Create a new DeviceMatchResampler, converting from `input_sample_rate` to `output_sample_rate`,
+    /// for `nbr_channels` channels and the given quality level.
+    pub fn new(
+        input_sample_rate: f64,
+        output_sample_rate: f64,
+        chunk_size: usize,
+        nbr_channels: usize,
+        quality: Quality,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let factory = ResamplerFactory::new(chunk_size);
+        let resampler =
+            factory.create(input_sample_rate, output_sample_rate, nbr_channels, quality)?;
+        Ok(DeviceMatchResampler {
+            factory,
+            resampler,
+            input_sample_rate,
+            output_sample_rate,
+            nbr_channels,
+            quality,
+        })
+    }
+
+    /// Change the output sample rate, for example after the output device was switched.
+    ///
+    /// Returns true if a new resampler was created, and false if the ratio of
+    /// the current resampler was updated, or the rate is unchanged.
+    /// On error, the current resampler and output rate are kept.
+    /// Note that creating a new resampler allocates.
+    pub fn set_output_rate(
+        &mut self,
+        output_sample_rate: f64,
+    ) -> Result<bool, ResamplerConstructionError> {
+        if output_sample_rate == self.output_sample_rate {
+            return Ok(false);
+        }
+        let new_ratio = output_sample_rate / self.input_sample_rate;
+        let (min_ratio, max_ratio) = self.resampler.ratio_bounds();
+        let adjustable = !self
+            .factory
+            .uses_fft(self.input_sample_rate, output_sample_rate)
+            && new_ratio >= min_ratio
+            && new_ratio <= max_ratio
+            && self.resampler.set_resample_ratio(new_ratio, false).is_ok();
+        if !adjustable {
+            debug!(
+                "Creating a new resampler for {} to {} Hz",
+                self.input_sample_rate, output_sample_rate
+            );
+            self.resampler = self.factory.create(
+                self.input_sample_rate,
+                output_sample_rate,
+                self.nbr_channels,
+                self.quality,
+            )?;
+        }
+        self.output_sample_rate = output_sample_rate;
+        Ok(!adjustable)
+    }
+
+    /// Resample a chunk of input, see [VecResampler::process].
+    pub fn process(
+        &mut self,
+        wave_in: &[Vec<T>],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<Vec<Vec<T>>> {
+        self.resampler.process(wave_in, active_channels_mask)
+    }
+
+    /// Get the input sample rate.
+    pub fn input_rate(&self) -> f64 {
+        self.input_sample_rate
+    }
+
+    /// Get the current output sample rate.
+    pub fn output_rate(&self) -> f64 {
+        self.output_sample_rate
+    }
+
+    /// Get a reference to the current resampler.
+    /// Note that this is replaced when the output rate changes and a new resampler is needed.
+    pub fn resampler(&self) -> &dyn VecResampler<T> {
+        &*self.resampler
+    }
+
+    /// Get a mutable reference to the current resampler.
+    pub fn resampler_mut(&mut self) -> &mut dyn VecResampler<T> {
+        &mut *self.resampler
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeviceMatchResampler;
+    use crate::{Quality, ResamplerFactory};
+
+    fn sine(start: usize, frames: usize) -> Vec<Vec<f64>> {
+        vec![(start..start + frames)
+            .map(|n| (n as f64 * 0.05).sin())
+            .collect()]
+    }
+
+    #[test]
+    fn switch_output_rate() {
+        let mut resampler =
+            DeviceMatchResampler::<f64>::new(48000.0, 48000.0, 1024, 1, Quality::Full).unwrap();
+        for chunk in 0..4 {
+            let output = resampler.process(&sine(chunk * 1024, 1024), None).unwrap();
+            assert_eq!(output[0].len(), 1024);
+        }
+
+        // A new resampler is needed, and gives the same output as a fresh one.
+        assert!(resampler.set_output_rate(44100.0).unwrap());
+        assert_eq!(resampler.output_rate(), 44100.0);
+        assert_eq!(resampler.resampler().output_sample_rate(48000.0), 44100.0);
+        let mut reference = ResamplerFactory::<f64>::new(1024)
+            .create(48000.0, 44100.0, 1, Quality::Full)
+            .unwrap();
+        let mut position = 4 * 1024;
+        let mut total = 0;
+        for _ in 0..20 {
+            let frames = resampler.resampler().input_frames_next();
+            let input = sine(position, frames);
+            let output = resampler.process(&input, None).unwrap();
+            assert_eq!(output, reference.process(&input, None).unwrap());
+            position += frames;
+            total += output[0].len();
+        }
+        // The FFT resampler buffers up to one chunk of output.
+        let expected = (position - 4 * 1024) * 147 / 160;
+        assert!(total <= expected);
+        assert!(total + resampler.resampler().output_frames_max() >= expected);
+
+        // Setting the same rate again is a no-op.
+        assert!(!resampler.set_output_rate(44100.0).unwrap());
+        assert!(resampler.set_output_rate(0.0).is_err());
+        assert_eq!(resampler.output_rate(), 44100.0);
+    }
+
+    #[test]
+    fn update_async_ratio() {
+        let mut resampler =
+            DeviceMatchResampler::<f64>::new(47999.5, 48000.0, 1024, 1, Quality::Full).unwrap();
+        resampler.process(&sine(0, 1024), None).unwrap();
+        let delay = resampler.resampler().output_delay();
+
+        // The ratio is within the range of the sinc resampler, and is just updated.
+        assert!(!resampler.set_output_rate(44100.0).unwrap());
+        assert_eq!(resampler.output_rate(), 44100.0);
+        assert!((resampler.resampler().output_sample_rate(47999.5) - 44100.0).abs() < 1e-6);
+        let mut total = 0;
+        for chunk in 1..41 {
+            total += resampler.process(&sine(chunk * 1024, 1024), None).unwrap()[0].len();
+        }
+        let expected = 40.0 * 1024.0 * 44100.0 / 47999.5;
+        assert!((total as f64 - expected).abs() < 2.0);
+        assert!(resampler.resampler().output_delay() != delay);
+
+        // Outside the range, a new resampler is created.
+        assert!(resampler.set_output_rate(96000.0).unwrap());
+        let output = resampler.process(&sine(0, 1024), None).unwrap();
+        assert!(!output[0].is_empty());
+    }
+}
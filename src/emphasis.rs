@@ -0,0 +1,211 @@
+use crate::Sample;
+
+/// Time constants of the first-order emphasis curves.
+///
+/// The emphasis filter has the response `(1 + s * zero) / (1 + s * pole)`,
+/// which boosts high frequencies by a shelf of `zero / pole`.
+/// The de-emphasis filter is the inverse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmphasisCurve {
+    /// The 50/15 µs curve used on CD and DAT.
+    /// This boosts high frequencies by up to 10.5 dB.
+    Cd,
+    /// A curve with custom time constants, given in seconds.
+    /// The `pole` time constant must be smaller than the `zero` time constant.
+    Custom {
+        /// The time constant of the zero, that starts the boost.
+        zero: f64,
+        /// The time constant of the pole, that ends the boost.
+        pole: f64,
+    },
+}
+
+impl EmphasisCurve {
+    /// Get the time constants as a tuple `(zero, pole)`, in seconds.
+    pub fn time_constants(&self) -> (f64, f64) {
+        match *self {
+            EmphasisCurve::Cd => (50.0e-6, 15.0e-6),
+            EmphasisCurve::Custom { zero, pole } => (zero, pole),
+        }
+    }
+}
+
+/// Emphasis filtering, applied to the input before resampling and to the output after.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmphasisMode {
+    /// No emphasis filtering. This is the default.
+    Off,
+    /// The input carries emphasis. The emphasis is removed from the input,
+    /// and applied again to the output at the new sample rate.
+    DeemphasizeInput(EmphasisCurve),
+    /// Emphasis is applied to the input, and removed from the output at the new sample rate.
+    PreemphasizeInput(EmphasisCurve),
+}
+
+/// A first-order filter with the response `(1 + s * zero) / (1 + s * pole)`,
+/// discretized with the bilinear transform.
+#[derive(Debug)]
+struct FirstOrderFilter {
+    b0: f64,
+    b1: f64,
+    a1: f64,
+    /// The previous input and output value for each channel.
+    states: Vec<(f64, f64)>,
+}
+
+impl FirstOrderFilter {
+    fn new(zero: f64, pole: f64, sample_rate: f64, channels: usize) -> Self {
+        let k = 2.0 * sample_rate;
+        let a0 = 1.0 + k * pole;
+        FirstOrderFilter {
+            b0: (1.0 + k * zero) / a0,
+            b1: (1.0 - k * zero) / a0,
+            a1: (1.0 - k * pole) / a0,
+            states: vec![(0.0, 0.0); channels],
+        }
+    }
+
+    fn process<T: Sample>(&mut self, chan: usize, data: &mut [T]) {
+        self.states[chan] = self.filter(self.states[chan], data);
+    }
+
+    /// Filter `data` starting from `state`, and return the state after the last value.
+    fn filter<T: Sample>(&self, state: (f64, f64), data: &mut [T]) -> (f64, f64) {
+        let (mut x1, mut y1) = state;
+        for value in data.iter_mut() {
+            let x = value.to_f64();
+            let y = self.b0 * x + self.b1 * x1 - self.a1 * y1;
+            *value = T::coerce(y);
+            x1 = x;
+            y1 = y;
+        }
+        (x1, y1)
+    }
+
+    fn reset_channel(&mut self, chan: usize) {
+        self.states[chan] = (0.0, 0.0);
+    }
+}
+
+/// The emphasis filters of a resampler, with separate states for each channel.
+#[derive(Debug)]
+pub(crate) struct Emphasis {
+    filters: Option<(FirstOrderFilter, FirstOrderFilter)>,
+}
+
+impl Emphasis {
+    pub(crate) fn new() -> Self {
+        Emphasis { filters: None }
+    }
+
+    /// Set up the filters for the given mode and sample rates.
+    /// This clears the filter states.
+    pub(crate) fn set_mode(
+        &mut self,
+        mode: EmphasisMode,
+        input_sample_rate: f64,
+        output_sample_rate: f64,
+        channels: usize,
+    ) {
+        self.filters = match mode {
+            EmphasisMode::Off => None,
+            EmphasisMode::DeemphasizeInput(curve) => {
+                let (zero, pole) = curve.time_constants();
+                Some((
+                    FirstOrderFilter::new(pole, zero, input_sample_rate, channels),
+                    FirstOrderFilter::new(zero, pole, output_sample_rate, channels),
+                ))
+            }
+            EmphasisMode::PreemphasizeInput(curve) => {
+                let (zero, pole) = curve.time_constants();
+                Some((
+                    FirstOrderFilter::new(zero, pole, input_sample_rate, channels),
+                    FirstOrderFilter::new(pole, zero, output_sample_rate, channels),
+                ))
+            }
+        };
+    }
+
+    /// Filter a chunk of input for one channel.
+    pub(crate) fn apply_input<T: Sample>(&mut self, chan: usize, data: &mut [T]) {
+        if let Some((input, _)) = self.filters.as_mut() {
+            input.process(chan, data);
+        }
+    }
+
+    /// Filter a chunk of output for one channel.
+    pub(crate) fn apply_output<T: Sample>(&mut self, chan: usize, data: &mut [T]) {
+        if let Some((_, output)) = self.filters.as_mut() {
+            output.process(chan, data);
+        }
+    }
+
+    /// Filter output for one channel like [apply_output](Emphasis::apply_output),
+    /// but without updating the filter state.
+    pub(crate) fn preview_output<T: Sample>(&self, chan: usize, data: &mut [T]) {
+        if let Some((_, output)) = self.filters.as_ref() {
+            output.filter(output.states[chan], data);
+        }
+    }
+
+    /// Allocate filter states for up to `channels` channels.
+    pub(crate) fn set_max_channels(&mut self, channels: usize) {
+        if let Some((input, output)) = self.filters.as_mut() {
+            input
+                .states
+                .resize(channels.max(input.states.len()), (0.0, 0.0));
+            output
+                .states
+                .resize(channels.max(output.states.len()), (0.0, 0.0));
+        }
+    }
+
+    /// Clear the filter states of one channel.
+    pub(crate) fn reset_channel(&mut self, chan: usize) {
+        if let Some((input, output)) = self.filters.as_mut() {
+            input.reset_channel(chan);
+            output.reset_channel(chan);
+        }
+    }
+
+    /// Clear the filter states of all channels.
+    pub(crate) fn reset(&mut self) {
+        if let Some((input, output)) = self.filters.as_mut() {
+            for chan in 0..input.states.len() {
+                input.reset_channel(chan);
+                output.reset_channel(chan);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Emphasis, EmphasisCurve, EmphasisMode};
+
+    #[test]
+    fn inverse_filters() {
+        // With equal sample rates, the input and output filters cancel.
+        let mut emphasis = Emphasis::new();
+        emphasis.set_mode(
+            EmphasisMode::PreemphasizeInput(EmphasisCurve::Cd),
+            44100.0,
+            44100.0,
+            1,
+        );
+        let wave = (0..1000)
+            .map(|n| (n as f64 * 0.7).sin() + 0.3 * (n as f64 * 0.05).cos())
+            .collect::<Vec<_>>();
+        let mut data = wave.clone();
+        emphasis.apply_input(0, &mut data[..300]);
+        emphasis.apply_input(0, &mut data[300..]);
+        // The emphasis boosts the high frequencies.
+        let energy = |d: &[f64]| d.iter().map(|v| v * v).sum::<f64>();
+        assert!(energy(&data) > 1.5 * energy(&wave));
+        emphasis.apply_output(0, &mut data[..700]);
+        emphasis.apply_output(0, &mut data[700..]);
+        for (filtered, original) in data.iter().zip(wave.iter()) {
+            assert!((filtered - original).abs() < 1.0e-9);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use std::error;
 use std::fmt;
 
+use crate::pcm::SampleFormat;
+
 /// An identifier for a cpu feature.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CpuFeature {
@@ -42,6 +44,29 @@ impl CpuFeature {
     }
 }
 
+/// Get the CPU features that are detected on the current machine.
+///
+/// The list contains the features that the sinc interpolators can make use of,
+/// for example for logging which accelerated paths are available.
+/// The AVX interpolator needs both [CpuFeature::Avx] and [CpuFeature::Fma].
+/// On architectures without any accelerated paths the list is always empty.
+pub fn available_cpu_features() -> Vec<CpuFeature> {
+    let all: &[CpuFeature] = &[
+        #[cfg(target_arch = "x86_64")]
+        CpuFeature::Sse3,
+        #[cfg(target_arch = "x86_64")]
+        CpuFeature::Avx,
+        #[cfg(target_arch = "x86_64")]
+        CpuFeature::Fma,
+        #[cfg(target_arch = "aarch64")]
+        CpuFeature::Neon,
+    ];
+    all.iter()
+        .filter(|feature| feature.is_detected())
+        .copied()
+        .collect()
+}
+
 #[allow(unused_variables)]
 impl fmt::Display for CpuFeature {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -83,6 +108,16 @@ pub enum ResamplerConstructionError {
     InvalidSampleRate { input: usize, output: usize },
     InvalidRelativeRatio(f64),
     InvalidRatio(f64),
+    InvalidChannelCount(usize),
+    InvalidChunkSize(usize),
+    InvalidSincLength(usize),
+    SincLenTooShort { provided: usize, minimum: usize },
+    InvalidOversamplingFactor(usize),
+    InvalidCutoff(f32),
+    NearestNotExact { suggested_oversampling: usize },
+    InvalidFrequencyResponse { frequency: f64, value: f64 },
+    InvalidSegmentLength(usize),
+    InvalidLoopLength(usize),
 }
 
 impl fmt::Display for ResamplerConstructionError {
@@ -97,6 +132,36 @@ impl fmt::Display for ResamplerConstructionError {
             Self::InvalidRelativeRatio(provided) => write!(formatter,
                 "Invalid max_resample_ratio_relative provided: {}. max_resample_ratio_relative must be >= 1", provided
             ),
+            Self::InvalidChannelCount(provided) => write!(formatter,
+                "Invalid number of channels provided: {}. The number of channels must be > 0", provided
+            ),
+            Self::InvalidChunkSize(provided) => write!(formatter,
+                "Invalid chunk_size provided: {}. chunk_size must be > 0", provided
+            ),
+            Self::InvalidSincLength(provided) => write!(formatter,
+                "Invalid sinc_len provided: {}. sinc_len must be > 0", provided
+            ),
+            Self::SincLenTooShort{provided, minimum} => write!(formatter,
+                "sinc_len {} is too short for the given ratio range, the minimum is {}", provided, minimum
+            ),
+            Self::InvalidOversamplingFactor(provided) => write!(formatter,
+                "Invalid oversampling_factor provided: {}. oversampling_factor must be > 0", provided
+            ),
+            Self::InvalidCutoff(provided) => write!(formatter,
+                "Invalid f_cutoff provided: {}. f_cutoff must be > 0 and <= 1", provided
+            ),
+            Self::InvalidFrequencyResponse{frequency, value} => write!(formatter,
+                "Invalid frequency response value {} at frequency {}. The response must be finite and >= 0", value, frequency
+            ),
+            Self::NearestNotExact{suggested_oversampling} => write!(formatter,
+                "Nearest interpolation is not exact for this ratio. Use an oversampling_factor of {}", suggested_oversampling
+            ),
+            Self::InvalidSegmentLength(provided) => write!(formatter,
+                "Invalid segment length provided: {} chunks. The segment length must be > 0", provided
+            ),
+            Self::InvalidLoopLength(provided) => write!(formatter,
+                "Invalid loop length provided: {} frames. The loop length must be > 0", provided
+            ),
         }
     }
 }
@@ -128,6 +193,23 @@ pub enum ResampleError {
     WrongNumberOfOutputChannels { expected: usize, actual: usize },
     /// Error raised when the number of channels in the mask doesn't match the value expected.
     WrongNumberOfMaskChannels { expected: usize, actual: usize },
+    /// Error raised when the number of channel gains doesn't match the number of channels.
+    WrongNumberOfGains { expected: usize, actual: usize },
+    /// Error raised when a number of channels doesn't match the declared [ChannelLayout](crate::ChannelLayout).
+    WrongNumberOfLayoutChannels { expected: usize, actual: usize },
+    /// Error raised when the fixed number of frames of an input array
+    /// doesn't match the number of frames needed by the resampler.
+    WrongNumberOfInputFrames { expected: usize, actual: usize },
+    /// Error raised when trying to set a look-ahead longer than the maximum supported.
+    LookaheadOutOfBounds { provided: usize, max: usize },
+    /// Error raised when trying to set a reservoir shorter than the minimum needed.
+    ReservoirTooSmall { provided: usize, min: usize },
+    /// Error raised when trying to change the number of channels to zero,
+    /// or to more than the number of channels that buffers have been allocated for.
+    ChannelCountOutOfBounds { provided: usize, max: usize },
+    /// Error raised when the length of an interleaved buffer
+    /// is not a multiple of the number of channels.
+    InvalidInterleavedLength { length: usize, channels: usize },
     /// Error raised when the number of frames in an input channel is less
     /// than the minimum expected.
     InsufficientInputBufferSize {
@@ -142,6 +224,18 @@ pub enum ResampleError {
         expected: usize,
         actual: usize,
     },
+    /// Error raised when the output of an allocating method would exceed the limit
+    /// set by [Resampler::set_max_output_frames](crate::Resampler::set_max_output_frames).
+    OutputExceedsLimit { frames: usize, limit: usize },
+    /// Error raised when a [SampleFormat] has a combination of encoding
+    /// and number of bits that is not supported.
+    UnsupportedSampleFormat(SampleFormat),
+    /// Error raised when an input ring buffer runs empty after `actual` of the `expected` samples,
+    /// although it reported enough samples before.
+    RingBufferUnderflow { expected: usize, actual: usize },
+    /// Error raised when an output ring buffer runs full after `actual` of the `expected` samples,
+    /// although it reported enough space before.
+    RingBufferOverflow { expected: usize, actual: usize },
 }
 
 impl fmt::Display for ResampleError {
@@ -165,6 +259,13 @@ impl fmt::Display for ResampleError {
                     actual, expected
                 )
             }
+            Self::WrongNumberOfLayoutChannels { expected, actual } => {
+                write!(
+                    f,
+                    "Wrong number of channels {}, the channel layout has {}",
+                    actual, expected
+                )
+            }
             Self::WrongNumberOfOutputChannels { expected, actual } => {
                 write!(
                     f,
@@ -179,6 +280,48 @@ impl fmt::Display for ResampleError {
                     actual, expected
                 )
             }
+            Self::WrongNumberOfGains { expected, actual } => {
+                write!(
+                    f,
+                    "Wrong number of channel gains {}, expected {}",
+                    actual, expected
+                )
+            }
+            Self::WrongNumberOfInputFrames { expected, actual } => {
+                write!(
+                    f,
+                    "Wrong number of frames {} in input, expected {}",
+                    actual, expected
+                )
+            }
+            Self::LookaheadOutOfBounds { provided, max } => {
+                write!(
+                    f,
+                    "Look-ahead out of bounds. Provided {} frames, maximum is {}",
+                    provided, max
+                )
+            }
+            Self::ReservoirTooSmall { provided, min } => {
+                write!(
+                    f,
+                    "Reservoir too small. Provided {} frames, minimum is {}",
+                    provided, min
+                )
+            }
+            Self::ChannelCountOutOfBounds { provided, max } => {
+                write!(
+                    f,
+                    "Number of channels out of bounds. Provided {}, must be between 1 and {}",
+                    provided, max
+                )
+            }
+            Self::InvalidInterleavedLength { length, channels } => {
+                write!(
+                    f,
+                    "Interleaved buffer length {} is not a multiple of the number of channels {}",
+                    length, channels
+                )
+            }
             Self::InsufficientInputBufferSize {
                 channel,
                 expected,
@@ -201,6 +344,34 @@ impl fmt::Display for ResampleError {
                     actual, channel, expected
                 )
             }
+            Self::OutputExceedsLimit { frames, limit } => {
+                write!(
+                    f,
+                    "Output of {} frames exceeds the limit of {} frames",
+                    frames, limit
+                )
+            }
+            Self::UnsupportedSampleFormat(format) => {
+                write!(
+                    f,
+                    "Unsupported sample format, {:?} with {} bits",
+                    format.encoding, format.bits
+                )
+            }
+            Self::RingBufferUnderflow { expected, actual } => {
+                write!(
+                    f,
+                    "Input ring buffer ran empty after {} of {} samples",
+                    actual, expected
+                )
+            }
+            Self::RingBufferOverflow { expected, actual } => {
+                write!(
+                    f,
+                    "Output ring buffer ran full after {} of {} samples",
+                    actual, expected
+                )
+            }
         }
     }
 }
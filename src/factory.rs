@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use crate::error::ResamplerConstructionError;
+#[cfg(feature = "fft_resampler")]
+use crate::FftFixedIn;
+use crate::{
+    calculate_cutoff, Quality, Sample, SincFixedIn, SincInterpolationParameters,
+    SincInterpolationType, VecResampler, WindowFunction,
+};
+
+/// The maximum relative ratio of the sinc resamplers created by a [ResamplerFactory].
+const FACTORY_MAX_RELATIVE_RATIO: f64 = 1.1;
+
+/// The number of sub chunks of the FFT resamplers created by a [ResamplerFactory].
+#[cfg(feature = "fft_resampler")]
+const FACTORY_SUB_CHUNKS: usize = 2;
+
+/// The standard sample rates that [ResamplerFactory::to_nearest_standard] chooses from.
+const STANDARD_SAMPLE_RATES: [f64; 8] = [
+    8000.0, 16000.0, 22050.0, 44100.0, 48000.0, 88200.0, 96000.0, 192000.0,
+];
+
+/// The cache key, with the sample rates stored as bits since floats can't be hashed.
+type FactoryKey = (u64, u64, usize, Quality);
+
+/// A factory that creates resamplers for pairs of sample rates,
+/// and keeps them for reuse.
+///
+/// This takes care of choosing a suitable resampler for a conversion.
+/// All resamplers take `chunk_size` input frames per call, given to [new](ResamplerFactory::new),
+/// and return a varying number of output frames.
+///
+/// The selection is made like this, see also [uses_fft](ResamplerFactory::uses_fft):
+/// - When both sample rates are integers, and the input rate divided by the greatest common divisor
+///   of the rates is at most half the chunk size, the ratio is a simple fraction,
+///   and a [FftFixedIn] is used. This is the case for the common rates, like 44100 to 48000 Hz,
+///   where the fraction is 147/160.
+///   The FFT resampler is fast, and has the same quality for both quality levels.
+/// - Otherwise a [SincFixedIn] is used, with a sinc length of 256, an oversampling factor of 256,
+///   cubic interpolation and the [WindowFunction::BlackmanHarris2] window.
+///   The ratio can be adjusted by up to 10% in both directions, for example for drift correction.
+///   With [Quality::Preview], the resampler is set to that quality level.
+///
+/// Without the `fft_resampler` feature, a [SincFixedIn] is always used.
+///
+/// The resamplers are cached by the sample rates, number of channels and quality level.
+/// Asking again for the same combination returns the same instance, in its current state.
+/// Note that creating a resampler allocates, and the cache grows with each new combination.
+pub struct ResamplerFactory<T> {
+    chunk_size: usize,
+    cache: HashMap<FactoryKey, Box<dyn VecResampler<T>>>,
+}
+
+impl<T> ResamplerFactory<T>
+where
+    T: Sample,
+{
+    /// Create a new ResamplerFactory for resamplers that take `chunk_size` input frames per call.
+    pub fn new(chunk_size: usize) -> Self {
+        ResamplerFactory {
+            chunk_size,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Get a resampler from `input_sample_rate` to `output_sample_rate`,
+    /// for `nbr_channels` channels and the given quality level.
+    ///
+    /// A new resampler is created if there is none for this combination in the cache.
+    /// Errors from creating the resampler are returned, and nothing is cached.
+    pub fn get(
+        &mut self,
+        input_sample_rate: f64,
+        output_sample_rate: f64,
+        nbr_channels: usize,
+        quality: Quality,
+    ) -> Result<&mut Box<dyn VecResampler<T>>, ResamplerConstructionError> {
+        let key = (
+            input_sample_rate.to_bits(),
+            output_sample_rate.to_bits(),
+            nbr_channels,
+            quality,
+        );
+        if !self.cache.contains_key(&key) {
+            let resampler =
+                self.create(input_sample_rate, output_sample_rate, nbr_channels, quality)?;
+            self.cache.insert(key, resampler);
+        }
+        Ok(self.cache.get_mut(&key).unwrap())
+    }
+
+    /// Get a resampler from `input_sample_rate` to the nearest standard sample rate,
+    /// for `nbr_channels` channels and the given quality level.
+    ///
+    /// The standard rates are 8, 16, 22.05, 44.1, 48, 88.2, 96 and 192 kHz.
+    /// The nearest rate is the one with the smallest difference in Hz,
+    /// and the lower rate is chosen if two are equally near.
+    /// Returns the resampler, see [get](ResamplerFactory::get), together with the chosen output rate.
+    /// When the input rate is already a standard rate, the resampler converts to the same rate.
+    pub fn to_nearest_standard(
+        &mut self,
+        input_sample_rate: f64,
+        nbr_channels: usize,
+        quality: Quality,
+    ) -> Result<(&mut Box<dyn VecResampler<T>>, f64), ResamplerConstructionError> {
+        let output_sample_rate = nearest_standard_rate(input_sample_rate);
+        let resampler = self.get(input_sample_rate, output_sample_rate, nbr_channels, quality)?;
+        Ok((resampler, output_sample_rate))
+    }
+
+    /// Check if an FFT resampler is used for a conversion between two sample rates,
+    /// see [ResamplerFactory] for the rules.
+    pub fn uses_fft(&self, input_sample_rate: f64, output_sample_rate: f64) -> bool {
+        #[cfg(feature = "fft_resampler")]
+        {
+            integer_rates(input_sample_rate, output_sample_rate).map_or(false, |(input, output)| {
+                input / num_integer::gcd(input, output) <= self.chunk_size / FACTORY_SUB_CHUNKS
+            })
+        }
+        #[cfg(not(feature = "fft_resampler"))]
+        {
+            let _ = (input_sample_rate, output_sample_rate);
+            false
+        }
+    }
+
+    /// Get the number of cached resamplers.
+    pub fn cached(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Drop all cached resamplers.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    pub(crate) fn create(
+        &self,
+        input_sample_rate: f64,
+        output_sample_rate: f64,
+        nbr_channels: usize,
+        quality: Quality,
+    ) -> Result<Box<dyn VecResampler<T>>, ResamplerConstructionError> {
+        #[cfg(feature = "fft_resampler")]
+        if self.uses_fft(input_sample_rate, output_sample_rate) {
+            debug!(
+                "Factory creates FftFixedIn for {} to {} Hz",
+                input_sample_rate, output_sample_rate
+            );
+            return Ok(Box::new(FftFixedIn::<T>::new(
+                input_sample_rate as usize,
+                output_sample_rate as usize,
+                self.chunk_size,
+                FACTORY_SUB_CHUNKS,
+                nbr_channels,
+            )?));
+        }
+        debug!(
+            "Factory creates SincFixedIn for {} to {} Hz",
+            input_sample_rate, output_sample_rate
+        );
+        let window = WindowFunction::BlackmanHarris2;
+        let parameters = SincInterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: calculate_cutoff(256, window),
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 256,
+            window,
+        };
+        let resample_ratio = output_sample_rate / input_sample_rate;
+        if !resample_ratio.is_finite() {
+            return Err(ResamplerConstructionError::InvalidRatio(resample_ratio));
+        }
+        let mut resampler = SincFixedIn::<T>::new(
+            resample_ratio,
+            FACTORY_MAX_RELATIVE_RATIO,
+            parameters,
+            self.chunk_size,
+            nbr_channels,
+        )?;
+        resampler.set_quality(quality);
+        Ok(Box::new(resampler))
+    }
+}
+
+/// Find the standard sample rate nearest to `sample_rate`.
+fn nearest_standard_rate(sample_rate: f64) -> f64 {
+    let mut nearest = STANDARD_SAMPLE_RATES[0];
+    for rate in STANDARD_SAMPLE_RATES.iter() {
+        if (rate - sample_rate).abs() < (nearest - sample_rate).abs() {
+            nearest = *rate;
+        }
+    }
+    nearest
+}
+
+/// Get the sample rates as integers, if both are positive whole numbers.
+#[cfg(feature = "fft_resampler")]
+fn integer_rates(input: f64, output: f64) -> Option<(usize, usize)> {
+    let is_integer = |rate: f64| rate >= 1.0 && rate <= u32::MAX as f64 && rate.fract() == 0.0;
+    if is_integer(input) && is_integer(output) {
+        Some((input as usize, output as usize))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResamplerFactory;
+    use crate::{Quality, ResampleError};
+
+    #[test]
+    fn selects_resampler() {
+        let mut factory = ResamplerFactory::<f64>::new(1024);
+        assert!(factory.uses_fft(44100.0, 48000.0));
+        assert!(!factory.uses_fft(44100.0, 48000.5));
+        assert!(!factory.uses_fft(44100.0, 44101.0));
+
+        // The synchronous FFT resampler rejects ratio changes.
+        let resampler = factory.get(44100.0, 48000.0, 2, Quality::Full).unwrap();
+        assert!(matches!(
+            resampler.set_resample_ratio_relative(1.01, false),
+            Err(ResampleError::SyncNotAdjustable)
+        ));
+        let resampler = factory.get(44100.0, 48000.5, 2, Quality::Full).unwrap();
+        assert!(resampler.set_resample_ratio_relative(1.01, false).is_ok());
+        let output = resampler.process(&vec![vec![0.0; 1024]; 2], None).unwrap();
+        assert_eq!(output.len(), 2);
+    }
+
+    #[test]
+    fn nearest_standard() {
+        let mut factory = ResamplerFactory::<f64>::new(1024);
+        let (resampler, rate) = factory
+            .to_nearest_standard(45000.0, 2, Quality::Full)
+            .unwrap();
+        assert_eq!(rate, 44100.0);
+        assert_eq!(resampler.output_sample_rate(45000.0), 44100.0);
+        let output = resampler.process(&vec![vec![0.0; 1024]; 2], None).unwrap();
+        assert_eq!(output.len(), 2);
+        assert!(!output[0].is_empty());
+        assert_eq!(
+            factory
+                .to_nearest_standard(46050.0, 1, Quality::Full)
+                .unwrap()
+                .1,
+            44100.0
+        );
+        assert_eq!(
+            factory
+                .to_nearest_standard(1000.0, 1, Quality::Full)
+                .unwrap()
+                .1,
+            8000.0
+        );
+        assert_eq!(
+            factory
+                .to_nearest_standard(384000.0, 1, Quality::Full)
+                .unwrap()
+                .1,
+            192000.0
+        );
+        assert!(factory.to_nearest_standard(0.0, 1, Quality::Full).is_err());
+    }
+
+    #[test]
+    fn caches_resamplers() {
+        let mut factory = ResamplerFactory::<f32>::new(512);
+        let delay = factory
+            .get(44100.0, 44101.0, 1, Quality::Full)
+            .unwrap()
+            .output_delay();
+        factory
+            .get(44100.0, 44101.0, 1, Quality::Full)
+            .unwrap()
+            .set_resample_ratio_relative(1.05, false)
+            .unwrap();
+        assert_eq!(factory.cached(), 1);
+        // The same instance is returned, with the changed ratio.
+        let resampler = factory.get(44100.0, 44101.0, 1, Quality::Full).unwrap();
+        assert!(resampler.output_delay() != delay);
+        factory.get(44100.0, 44101.0, 2, Quality::Full).unwrap();
+        factory.get(44100.0, 44101.0, 2, Quality::Preview).unwrap();
+        assert_eq!(factory.cached(), 3);
+        assert!(factory.get(0.0, 44101.0, 2, Quality::Full).is_err());
+        assert_eq!(factory.cached(), 3);
+        factory.clear();
+        assert_eq!(factory.cached(), 0);
+    }
+}
@@ -0,0 +1,95 @@
+//! The FFT implementation used by the synchronous resamplers.
+//!
+//! The resamplers use [DefaultFft], based on RustFFT, unless another implementation
+//! of the [Fft] trait is given to the constructor.
+
+use crate::Sample;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::sync::Arc;
+
+pub use num_complex::Complex;
+
+/// A real-valued FFT, for the forward and inverse transforms of the synchronous resamplers.
+///
+/// A resampler calls [prepare](Fft::prepare) once when it is created, with the lengths
+/// of the forward and inverse transforms. This is where any planning and allocation should be done.
+/// After that, every forward transform takes `forward_len` real values and produces `forward_len / 2 + 1`
+/// complex values, and every inverse transform takes `inverse_len / 2 + 1` complex values
+/// and produces `inverse_len` real values.
+/// The transforms must not be normalized, which is the same convention as RustFFT uses.
+/// The contents of the input buffers may be overwritten.
+///
+/// The forward transform is also used once during construction, to transform the anti-aliasing filter.
+pub trait Fft<T>: Send {
+    /// Prepare the transforms for the given lengths.
+    fn prepare(&mut self, forward_len: usize, inverse_len: usize);
+
+    /// Run the forward transform, from `input` in the time domain to `output` in the frequency domain.
+    fn forward(&mut self, input: &mut [T], output: &mut [Complex<T>]);
+
+    /// Run the inverse transform, from `input` in the frequency domain to `output` in the time domain.
+    fn inverse(&mut self, input: &mut [Complex<T>], output: &mut [T]);
+}
+
+/// The default FFT implementation, using the RustFFT library via RealFFT.
+pub struct DefaultFft<T> {
+    fft: Option<Arc<dyn RealToComplex<T>>>,
+    ifft: Option<Arc<dyn ComplexToReal<T>>>,
+    scratch_fw: Vec<Complex<T>>,
+    scratch_inv: Vec<Complex<T>>,
+}
+
+impl<T> DefaultFft<T>
+where
+    T: Sample,
+{
+    /// Create a new DefaultFft. The transforms are planned by [prepare](Fft::prepare).
+    pub fn new() -> Self {
+        DefaultFft {
+            fft: None,
+            ifft: None,
+            scratch_fw: Vec::new(),
+            scratch_inv: Vec::new(),
+        }
+    }
+}
+
+impl<T> Default for DefaultFft<T>
+where
+    T: Sample,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Fft<T> for DefaultFft<T>
+where
+    T: Sample,
+{
+    fn prepare(&mut self, forward_len: usize, inverse_len: usize) {
+        let mut planner = RealFftPlanner::<T>::new();
+        let fft = planner.plan_fft_forward(forward_len);
+        let ifft = planner.plan_fft_inverse(inverse_len);
+        self.scratch_fw = fft.make_scratch_vec();
+        self.scratch_inv = ifft.make_scratch_vec();
+        self.fft = Some(fft);
+        self.ifft = Some(ifft);
+    }
+
+    fn forward(&mut self, input: &mut [T], output: &mut [Complex<T>]) {
+        self.fft
+            .as_ref()
+            .expect("The FFT must be prepared before use")
+            .process_with_scratch(input, output, &mut self.scratch_fw)
+            .unwrap();
+    }
+
+    fn inverse(&mut self, input: &mut [Complex<T>], output: &mut [T]) {
+        self.ifft
+            .as_ref()
+            .expect("The FFT must be prepared before use")
+            .process_with_scratch(input, output, &mut self.scratch_inv)
+            .unwrap();
+    }
+}
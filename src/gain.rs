@@ -0,0 +1,561 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::Sample;
+
+/// Smoothing factor for the gain, the fraction of the remaining difference
+/// to the target gain that is applied for each chunk.
+const GAIN_SMOOTHING: f64 = 0.2;
+
+/// Signals with an RMS level below this are considered silent and don't update the gain.
+const SILENCE_RMS: f64 = 1.0e-9;
+
+/// Keeps the RMS level of the output matched to that of the input,
+/// by applying a slowly varying gain to the output.
+#[derive(Debug)]
+pub(crate) struct RmsMatcher {
+    enabled: bool,
+    gain: f64,
+}
+
+impl RmsMatcher {
+    pub(crate) fn new() -> Self {
+        RmsMatcher {
+            enabled: false,
+            gain: 1.0,
+        }
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.gain = 1.0;
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.gain = 1.0;
+    }
+
+    /// Measure the input and output levels of a processed chunk,
+    /// and apply the gain to the first `frames_out` frames of the active output channels.
+    /// The gain is ramped linearly within the chunk, to avoid steps.
+    pub(crate) fn apply<T: Sample, Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        frames_in: usize,
+        wave_out: &mut [Vout],
+        frames_out: usize,
+        mask: &[bool],
+    ) {
+        if !self.enabled || frames_in == 0 || frames_out == 0 {
+            return;
+        }
+        let mut sum_in = 0.0;
+        let mut sum_out = 0.0;
+        for (chan, active) in mask.iter().enumerate() {
+            if *active {
+                sum_in += wave_in[chan].as_ref()[..frames_in]
+                    .iter()
+                    .map(|v| v.to_f64() * v.to_f64())
+                    .sum::<f64>();
+                sum_out += wave_out[chan].as_mut()[..frames_out]
+                    .iter()
+                    .map(|v| v.to_f64() * v.to_f64())
+                    .sum::<f64>();
+            }
+        }
+        let rms_in = (sum_in / frames_in as f64).sqrt();
+        let rms_out = (sum_out / frames_out as f64).sqrt();
+        let start_gain = self.gain;
+        if rms_in > SILENCE_RMS && rms_out > SILENCE_RMS {
+            let target = rms_in / rms_out;
+            self.gain += GAIN_SMOOTHING * (target - self.gain);
+        }
+        let step = (self.gain - start_gain) / frames_out as f64;
+        for (chan, active) in mask.iter().enumerate() {
+            if *active {
+                for (n, value) in wave_out[chan].as_mut()[..frames_out].iter_mut().enumerate() {
+                    let gain = start_gain + step * (n + 1) as f64;
+                    *value = T::coerce(value.to_f64() * gain);
+                }
+            }
+        }
+    }
+
+    /// Apply the current gain to the first `frames` frames of the active output channels,
+    /// without measuring the levels. This is used for output that has no matching input.
+    pub(crate) fn apply_gain<T: Sample, Vout: AsMut<[T]>>(
+        &self,
+        wave_out: &mut [Vout],
+        frames: usize,
+        mask: &[bool],
+    ) {
+        if !self.enabled || self.gain == 1.0 {
+            return;
+        }
+        for (chan, active) in mask.iter().enumerate() {
+            if *active {
+                for value in wave_out[chan].as_mut()[..frames].iter_mut() {
+                    *value = T::coerce(value.to_f64() * self.gain);
+                }
+            }
+        }
+    }
+}
+
+/// Fixed gains for each output channel.
+#[derive(Debug)]
+pub(crate) struct ChannelGains {
+    gains: Vec<f64>,
+    unity: bool,
+}
+
+impl ChannelGains {
+    pub(crate) fn new(channels: usize) -> Self {
+        ChannelGains {
+            gains: vec![1.0; channels],
+            unity: true,
+        }
+    }
+
+    /// Set the gains of the first `nbr_channels` channels.
+    pub(crate) fn set(&mut self, gains: &[f64], nbr_channels: usize) -> ResampleResult<()> {
+        if gains.len() != nbr_channels {
+            return Err(ResampleError::WrongNumberOfGains {
+                expected: nbr_channels,
+                actual: gains.len(),
+            });
+        }
+        self.gains[..nbr_channels].copy_from_slice(gains);
+        self.unity = self.gains.iter().all(|gain| *gain == 1.0);
+        Ok(())
+    }
+
+    /// Make room for up to `channels` channels, with unity gain for new channels.
+    pub(crate) fn set_max_channels(&mut self, channels: usize) {
+        if channels > self.gains.len() {
+            self.gains.resize(channels, 1.0);
+        }
+    }
+
+    /// Apply the gains to the first `frames` frames of the active output channels.
+    pub(crate) fn apply<T: Sample, Vout: AsMut<[T]>>(
+        &self,
+        wave_out: &mut [Vout],
+        frames: usize,
+        mask: &[bool],
+    ) {
+        if self.unity {
+            return;
+        }
+        for (chan, active) in mask.iter().enumerate() {
+            if *active && self.gains[chan] != 1.0 {
+                let gain = T::coerce(self.gains[chan]);
+                for value in wave_out[chan].as_mut()[..frames].iter_mut() {
+                    *value *= gain;
+                }
+            }
+        }
+    }
+}
+
+/// Records the positions of output samples with a magnitude above 1.0.
+#[derive(Debug)]
+pub(crate) struct ClipDetector {
+    max_clips: usize,
+    clips: Vec<(usize, usize)>,
+}
+
+impl ClipDetector {
+    pub(crate) fn new() -> Self {
+        ClipDetector {
+            max_clips: 0,
+            clips: Vec::new(),
+        }
+    }
+
+    /// Record at most `max_clips` clips per chunk, or disable the detection with 0.
+    pub(crate) fn set_max_clips(&mut self, max_clips: usize) {
+        self.max_clips = max_clips;
+        self.clips = Vec::with_capacity(max_clips);
+    }
+
+    pub(crate) fn clips(&self) -> &[(usize, usize)] {
+        &self.clips
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.clips.clear();
+    }
+
+    /// Find the clipped samples among the first `frames` frames of the active output channels.
+    pub(crate) fn detect<T: Sample, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+        frames: usize,
+        mask: &[bool],
+    ) {
+        if self.max_clips == 0 {
+            return;
+        }
+        self.clips.clear();
+        for (chan, active) in mask.iter().enumerate() {
+            if *active {
+                for (frame, value) in wave_out[chan].as_mut()[..frames].iter().enumerate() {
+                    if value.to_f64().abs() > 1.0 {
+                        if self.clips.len() == self.max_clips {
+                            return;
+                        }
+                        self.clips.push((chan, frame));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The offset basis of the 64-bit FNV-1a hash.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// The prime of the 64-bit FNV-1a hash.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Keeps a running 64-bit FNV-1a hash of the output samples.
+#[derive(Debug)]
+pub(crate) struct OutputChecksum {
+    enabled: bool,
+    hash: u64,
+}
+
+impl OutputChecksum {
+    pub(crate) fn new() -> Self {
+        OutputChecksum {
+            enabled: false,
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.reset();
+    }
+
+    pub(crate) fn value(&self) -> u64 {
+        self.hash
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.hash = FNV_OFFSET_BASIS;
+    }
+
+    /// Add the first `frames` frames of the active output channels to the hash,
+    /// frame by frame, with the samples as 64-bit floats in little-endian byte order.
+    pub(crate) fn update<T: Sample, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+        frames: usize,
+        mask: &[bool],
+    ) {
+        if !self.enabled {
+            return;
+        }
+        for frame in 0..frames {
+            for (chan, active) in mask.iter().enumerate() {
+                if *active {
+                    let value = wave_out[chan].as_mut()[frame].to_f64();
+                    for byte in value.to_bits().to_le_bytes() {
+                        self.hash ^= byte as u64;
+                        self.hash = self.hash.wrapping_mul(FNV_PRIME);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Pads the output with zeros to a multiple of a number of frames.
+#[derive(Debug)]
+pub(crate) struct OutputPadding {
+    multiple: usize,
+    valid_frames: usize,
+}
+
+impl OutputPadding {
+    pub(crate) fn new() -> Self {
+        OutputPadding {
+            multiple: 1,
+            valid_frames: 0,
+        }
+    }
+
+    /// Set the multiple, where 0 and 1 disable the padding.
+    pub(crate) fn set_multiple(&mut self, multiple: usize) {
+        self.multiple = multiple.max(1);
+    }
+
+    /// Get the largest number of frames that padding may add.
+    pub(crate) fn max_padding(&self) -> usize {
+        self.multiple - 1
+    }
+
+    pub(crate) fn valid_frames(&self) -> usize {
+        self.valid_frames
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.valid_frames = 0;
+    }
+
+    /// Zero the active output channels from `frames` up to the next multiple,
+    /// and return the padded number of frames.
+    pub(crate) fn apply<T: Sample, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+        frames: usize,
+        mask: &[bool],
+    ) -> usize {
+        self.valid_frames = frames;
+        let padded = (frames + self.multiple - 1) / self.multiple * self.multiple;
+        for (chan, active) in mask.iter().enumerate() {
+            if *active {
+                wave_out[chan].as_mut()[frames..padded]
+                    .iter_mut()
+                    .for_each(|s| *s = T::zero());
+            }
+        }
+        padded
+    }
+}
+
+/// Produces a decimated preview of the output, with the smallest and largest sample
+/// of each window of a fixed number of frames.
+#[derive(Debug)]
+pub(crate) struct OutputPreview<T> {
+    factor: usize,
+    window_frames: usize,
+    peaks: Vec<Option<[T; 2]>>,
+    preview: Vec<Vec<T>>,
+}
+
+impl<T: Sample> OutputPreview<T> {
+    pub(crate) fn new() -> Self {
+        OutputPreview {
+            factor: 0,
+            window_frames: 0,
+            peaks: Vec::new(),
+            preview: Vec::new(),
+        }
+    }
+
+    /// Set the number of output frames per preview frame, or disable the preview with 0.
+    /// The preview is allocated for `channels` channels and chunks of up to `max_frames` frames.
+    pub(crate) fn set_factor(&mut self, factor: usize, channels: usize, max_frames: usize) {
+        self.factor = factor;
+        match max_frames.checked_div(factor) {
+            Some(windows) => {
+                self.peaks = vec![None; channels];
+                self.preview = vec![Vec::with_capacity(2 * (windows + 1)); channels];
+            }
+            None => {
+                self.peaks = Vec::new();
+                self.preview = Vec::new();
+            }
+        }
+        self.reset();
+    }
+
+    /// Allocate for up to `channels` channels.
+    pub(crate) fn set_max_channels(&mut self, channels: usize) {
+        if self.factor > 0 && channels > self.preview.len() {
+            let capacity = self.preview[0].capacity();
+            self.peaks.resize(channels, None);
+            self.preview
+                .resize_with(channels, || Vec::with_capacity(capacity));
+        }
+    }
+
+    pub(crate) fn preview(&self) -> &[Vec<T>] {
+        &self.preview
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.window_frames = 0;
+        self.peaks.iter_mut().for_each(|peak| *peak = None);
+        self.preview.iter_mut().for_each(|chan| chan.clear());
+    }
+
+    /// Add the first `frames` frames of the active output channels to the preview.
+    /// The preview of the chunk holds the windows that were completed by these frames.
+    pub(crate) fn update<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+        frames: usize,
+        mask: &[bool],
+    ) {
+        if self.factor == 0 {
+            return;
+        }
+        let start = self.window_frames;
+        for (chan, active) in mask.iter().enumerate() {
+            let preview = &mut self.preview[chan];
+            preview.clear();
+            if !*active {
+                self.peaks[chan] = None;
+                continue;
+            }
+            let mut filled = start;
+            let mut peak = self.peaks[chan];
+            for value in wave_out[chan].as_mut()[..frames].iter() {
+                peak = Some(match peak {
+                    Some([min, max]) => [
+                        if value.to_f64() < min.to_f64() {
+                            *value
+                        } else {
+                            min
+                        },
+                        if value.to_f64() > max.to_f64() {
+                            *value
+                        } else {
+                            max
+                        },
+                    ],
+                    None => [*value, *value],
+                });
+                filled += 1;
+                if filled == self.factor {
+                    if let Some([min, max]) = peak {
+                        preview.push(min);
+                        preview.push(max);
+                    }
+                    peak = None;
+                    filled = 0;
+                }
+            }
+            self.peaks[chan] = peak;
+        }
+        self.window_frames = (start + frames) % self.factor;
+    }
+}
+
+/// Inputs with a peak level at or above this, about -193 dB, are processed without scaling.
+const AUTO_SCALE_MIN_PEAK: f64 = 2.3283064365386963e-10;
+
+/// The largest exponent of the scale factor, which keeps both the factor
+/// and its inverse well within the range of normal `f32` values.
+const AUTO_SCALE_MAX_EXPONENT: i32 = 100;
+
+/// Scales very quiet input by a power of two before filtering, and the output back.
+///
+/// The scale factor is `2^exponent`, chosen to bring the peak of the buffered input
+/// to between 0.5 and 1.0. Since multiplying by a power of two is exact,
+/// the output of normal signals, where the exponent stays at 0, is unchanged.
+#[derive(Debug)]
+pub(crate) struct AutoScale {
+    enabled: bool,
+    exponent: i32,
+}
+
+impl AutoScale {
+    pub(crate) fn new() -> Self {
+        AutoScale {
+            enabled: false,
+            exponent: 0,
+        }
+    }
+
+    /// Enable or disable the scaling.
+    /// Any scaling of the buffered input is undone by the next call to `scale_input`.
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.exponent = 0;
+    }
+
+    /// Get the factor that scales the buffered input back to the level of the input.
+    pub(crate) fn inverse_gain(&self) -> f64 {
+        2.0_f64.powi(-self.exponent)
+    }
+
+    /// Scale the new input in `start..end` of the buffers of the active channels,
+    /// after updating the scale factor from the peak of the new input and the history before it.
+    /// When the factor changes, the history is rescaled to match,
+    /// and the factor applied to the history is returned.
+    pub(crate) fn scale_input<T: Sample>(
+        &mut self,
+        buffers: &mut [Vec<T>],
+        mask: &[bool],
+        start: usize,
+        end: usize,
+    ) -> Option<T> {
+        if !self.enabled && self.exponent == 0 {
+            return None;
+        }
+        let exponent = if self.enabled {
+            let inverse_gain = 2.0_f64.powi(-self.exponent);
+            let mut peak = 0.0_f64;
+            for (buf, _) in buffers
+                .iter()
+                .zip(mask.iter())
+                .filter(|(_, active)| **active)
+            {
+                for value in buf[..start].iter() {
+                    peak = peak.max(value.to_f64().abs() * inverse_gain);
+                }
+                for value in buf[start..end].iter() {
+                    peak = peak.max(value.to_f64().abs());
+                }
+            }
+            if peak == 0.0 {
+                self.exponent
+            } else if peak >= AUTO_SCALE_MIN_PEAK {
+                0
+            } else {
+                (-1 - peak.log2().floor() as i32).min(AUTO_SCALE_MAX_EXPONENT)
+            }
+        } else {
+            0
+        };
+        let delta = exponent - self.exponent;
+        self.exponent = exponent;
+        let gain = T::coerce(2.0_f64.powi(exponent));
+        let history_gain = T::coerce(2.0_f64.powi(delta));
+        for (buf, _) in buffers
+            .iter_mut()
+            .zip(mask.iter())
+            .filter(|(_, active)| **active)
+        {
+            if delta != 0 {
+                buf[..start]
+                    .iter_mut()
+                    .for_each(|value| *value *= history_gain);
+            }
+            if exponent != 0 {
+                buf[start..end].iter_mut().for_each(|value| *value *= gain);
+            }
+        }
+        if delta != 0 {
+            Some(history_gain)
+        } else {
+            None
+        }
+    }
+
+    /// Scale the first `frames` frames of the active output channels back to the level of the input.
+    pub(crate) fn apply_output<T: Sample, Vout: AsMut<[T]>>(
+        &self,
+        wave_out: &mut [Vout],
+        frames: usize,
+        mask: &[bool],
+    ) {
+        if self.exponent == 0 {
+            return;
+        }
+        let gain = T::coerce(2.0_f64.powi(-self.exponent));
+        for (chan, active) in mask.iter().enumerate() {
+            if *active {
+                for value in wave_out[chan].as_mut()[..frames].iter_mut() {
+                    *value *= gain;
+                }
+            }
+        }
+    }
+}
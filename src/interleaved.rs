@@ -0,0 +1,167 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{Resampler, Sample};
+
+/// A helper that owns a resampler, and processes interleaved samples.
+///
+/// Many audio sources and sinks use interleaved samples, with the samples of all channels
+/// of a frame next to each other. The resamplers instead take one buffer per channel.
+/// This wrapper deinterleaves the input into internal buffers, resamples these with
+/// [process_into_buffer](Resampler::process_into_buffer), and interleaves the result.
+/// The internal buffers are allocated when the InterleavedResampler is created,
+/// and are reused for each call. Processing then doesn't allocate,
+/// as long as the output vector has enough capacity, see
+/// [process_interleaved](InterleavedResampler::process_interleaved).
+pub struct InterleavedResampler<T, R> {
+    resampler: R,
+    input: Vec<Vec<T>>,
+    output: Vec<Vec<T>>,
+}
+
+impl<T, R> InterleavedResampler<T, R>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    /// Create a new InterleavedResampler, wrapping the given resampler.
+    pub fn new(resampler: R) -> Self {
+        let input = resampler.input_buffer_allocate(true);
+        let output = resampler.output_buffer_allocate(true);
+        InterleavedResampler {
+            resampler,
+            input,
+            output,
+        }
+    }
+
+    /// Resample a chunk of interleaved samples.
+    ///
+    /// The input must hold exactly [input_frames_next](Resampler::input_frames_next) frames,
+    /// with [nbr_channels](Resampler::nbr_channels) samples each.
+    /// A length that is not a multiple of the number of channels gives a
+    /// [ResampleError::InvalidInterleavedLength] error, and a wrong number of frames a
+    /// [ResampleError::WrongNumberOfInputFrames] error.
+    /// The contents of `output` are replaced by the interleaved output frames.
+    /// The vector is only reallocated if its capacity is too small,
+    /// which can be avoided by reserving [output_frames_max](Resampler::output_frames_max)
+    /// frames beforehand.
+    /// Returns the number of output frames.
+    pub fn process_interleaved(
+        &mut self,
+        input: &[T],
+        output: &mut Vec<T>,
+    ) -> ResampleResult<usize> {
+        let channels = self.resampler.nbr_channels();
+        if input.len() % channels != 0 {
+            return Err(ResampleError::InvalidInterleavedLength {
+                length: input.len(),
+                channels,
+            });
+        }
+        let frames_in = input.len() / channels;
+        let needed = self.resampler.input_frames_next();
+        if frames_in != needed {
+            return Err(ResampleError::WrongNumberOfInputFrames {
+                expected: needed,
+                actual: frames_in,
+            });
+        }
+        // The number of channels may have been changed through resampler_mut.
+        self.input.resize_with(channels, Vec::new);
+        self.output.resize_with(channels, Vec::new);
+        for (chan, buffer) in self.input.iter_mut().enumerate() {
+            buffer.clear();
+            buffer.extend(input[chan..].iter().step_by(channels));
+        }
+        let frames_out = self.resampler.output_frames_next();
+        for buffer in self.output.iter_mut() {
+            buffer.resize(frames_out, T::zero());
+        }
+        let (_, frames_out) =
+            self.resampler
+                .process_into_buffer(&self.input, &mut self.output, None)?;
+        output.clear();
+        for frame in 0..frames_out {
+            output.extend(self.output.iter().map(|chan| chan[frame]));
+        }
+        Ok(frames_out)
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn resampler(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Get a mutable reference to the wrapped resampler,
+    /// for example for changing the resampling ratio.
+    pub fn resampler_mut(&mut self) -> &mut R {
+        &mut self.resampler
+    }
+
+    /// Consume the InterleavedResampler and return the wrapped resampler.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InterleavedResampler;
+    use crate::{
+        interleaved_to_planar, planar_to_interleaved, FastFixedIn, PolynomialDegree, ResampleError,
+        Resampler,
+    };
+
+    #[test]
+    fn matches_planar() {
+        let frames = 256 * 4;
+        let interleaved = (0..3 * frames)
+            .map(|n| ((n / 3) as f64 * 0.01 * (1 + n % 3) as f64).sin())
+            .collect::<Vec<f64>>();
+        let planar = interleaved_to_planar(&interleaved, 3).unwrap();
+
+        let mut reference =
+            FastFixedIn::<f64>::new(1.3, 1.1, PolynomialDegree::Cubic, 256, 3).unwrap();
+        let resampler = FastFixedIn::<f64>::new(1.3, 1.1, PolynomialDegree::Cubic, 256, 3).unwrap();
+        let mut wrapper = InterleavedResampler::new(resampler);
+        let mut output = Vec::with_capacity(3 * wrapper.resampler().output_frames_max());
+        let capacity = output.capacity();
+        for chunk in 0..4 {
+            let block = planar
+                .iter()
+                .map(|chan| &chan[chunk * 256..(chunk + 1) * 256])
+                .collect::<Vec<_>>();
+            let expected = reference.process(&block, None).unwrap();
+            let frames = wrapper
+                .process_interleaved(&interleaved[chunk * 768..(chunk + 1) * 768], &mut output)
+                .unwrap();
+            assert_eq!(frames, expected[0].len());
+            assert_eq!(output, planar_to_interleaved(&expected));
+            // The output vector is reused.
+            assert_eq!(output.capacity(), capacity);
+        }
+    }
+
+    #[test]
+    fn wrong_lengths() {
+        let resampler = FastFixedIn::<f32>::new(1.3, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mut wrapper = InterleavedResampler::new(resampler);
+        let mut output = Vec::new();
+        assert!(matches!(
+            wrapper.process_interleaved(&[0.0; 511], &mut output),
+            Err(ResampleError::InvalidInterleavedLength {
+                length: 511,
+                channels: 2
+            })
+        ));
+        assert!(matches!(
+            wrapper.process_interleaved(&[0.0; 510], &mut output),
+            Err(ResampleError::WrongNumberOfInputFrames {
+                expected: 256,
+                actual: 255
+            })
+        ));
+        assert!(wrapper
+            .process_interleaved(&[0.0; 512], &mut output)
+            .is_ok());
+    }
+}
@@ -51,6 +51,19 @@ pub fn get_nearest_times_4(t: f64, factor: isize, points: &mut [(isize, isize);
     }
 }
 
+/// Get the six nearest time points for time t in format (index, subindex).
+pub fn get_nearest_times_6(t: f64, factor: isize, points: &mut [(isize, isize); 6]) {
+    let start = t.floor() as isize;
+    let frac = ((t - t.floor()) * (factor as f64)).floor() as isize;
+    for (idx, sub) in (-2..4).enumerate() {
+        let subindex = frac + sub;
+        points[idx] = (
+            start + subindex.div_euclid(factor),
+            subindex.rem_euclid(factor),
+        );
+    }
+}
+
 /// Get the nearest time point for time t in format (index, subindex).
 pub fn get_nearest_time(t: f64, factor: isize) -> (isize, isize) {
     let mut index = t.floor() as isize;
@@ -68,6 +81,7 @@ mod tests {
     use crate::interpolation::get_nearest_times_2;
     use crate::interpolation::get_nearest_times_3;
     use crate::interpolation::get_nearest_times_4;
+    use crate::interpolation::get_nearest_times_6;
 
     #[test]
     fn get_nearest_2() {
@@ -121,6 +135,16 @@ mod tests {
         assert_eq!(times[3], (0, 1));
     }
 
+    #[test]
+    fn get_nearest_6() {
+        let t = 5.9f64;
+        let mut times = [(0isize, 0isize); 6];
+        get_nearest_times_6(t, 8, &mut times);
+        assert_eq!(times, [(5, 5), (5, 6), (5, 7), (6, 0), (6, 1), (6, 2)]);
+        get_nearest_times_6(t, 2, &mut times);
+        assert_eq!(times, [(4, 1), (5, 0), (5, 1), (6, 0), (6, 1), (7, 0)]);
+    }
+
     #[test]
     fn get_nearest_single() {
         let t = 5.5f64;
@@ -0,0 +1,227 @@
+use crate::error::ResampleError;
+use crate::{Resampler, Sample};
+
+/// An iterator that resamples the frames of another iterator,
+/// see [Resampler::resample_iter].
+///
+/// The source iterator gives one frame per item, with one sample for each channel.
+/// The frames are collected into chunks of the size needed by the resampler,
+/// and each output frame is returned as a vector with one sample for each channel.
+/// The chunks are processed with [process_into_buffer](Resampler::process_into_buffer),
+/// and the last incomplete chunk with
+/// [process_partial_into_buffer](Resampler::process_partial_into_buffer),
+/// which pads it with silence. The output is the same as when processing the chunks
+/// one by one, and is delayed by [output_delay](Resampler::output_delay) frames.
+///
+/// An iterator can't return errors. Instead the iteration ends at the first error,
+/// which can then be read with [error](ResampleIter::error).
+/// A source frame with the wrong number of samples gives a
+/// [ResampleError::WrongNumberOfInputChannels] error.
+/// Note that each returned frame is a newly allocated vector.
+pub struct ResampleIter<T, R, I> {
+    resampler: R,
+    input: I,
+    wave_in: Vec<Vec<T>>,
+    wave_out: Vec<Vec<T>>,
+    frames_out: usize,
+    position: usize,
+    finished: bool,
+    error: Option<ResampleError>,
+}
+
+impl<T, R, I> ResampleIter<T, R, I>
+where
+    T: Sample,
+    R: Resampler<T>,
+    I: Iterator,
+    I::Item: AsRef<[T]>,
+{
+    /// Create a new ResampleIter, resampling the frames of `input` with `resampler`.
+    pub fn new(resampler: R, input: I) -> Self {
+        let wave_in = resampler.input_buffer_allocate(false);
+        let wave_out = resampler.output_buffer_allocate(true);
+        ResampleIter {
+            resampler,
+            input,
+            wave_in,
+            wave_out,
+            frames_out: 0,
+            position: 0,
+            finished: false,
+            error: None,
+        }
+    }
+
+    /// Get the error that ended the iteration, if any.
+    pub fn error(&self) -> Option<&ResampleError> {
+        self.error.as_ref()
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn resampler(&self) -> &R {
+        &self.resampler
+    }
+
+    /// Consume the ResampleIter and return the wrapped resampler.
+    /// Any output frames that have not yet been returned are dropped.
+    pub fn into_inner(self) -> R {
+        self.resampler
+    }
+
+    /// Pull the next chunk from the source iterator and resample it.
+    /// Returns false when there is no more input to process.
+    fn process_next_chunk(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        let channels = self.resampler.nbr_channels();
+        let frames_needed = self.resampler.input_frames_next();
+        self.wave_in.iter_mut().for_each(|chan| chan.clear());
+        for frame in (&mut self.input).take(frames_needed) {
+            let frame = frame.as_ref();
+            if frame.len() != channels {
+                self.error = Some(ResampleError::WrongNumberOfInputChannels {
+                    expected: channels,
+                    actual: frame.len(),
+                });
+                self.finished = true;
+                return false;
+            }
+            for (chan, value) in self.wave_in.iter_mut().zip(frame.iter()) {
+                chan.push(*value);
+            }
+        }
+        let frames_in = self.wave_in[0].len();
+        let result = if frames_in == frames_needed {
+            self.resampler
+                .process_into_buffer(&self.wave_in, &mut self.wave_out, None)
+        } else {
+            self.finished = true;
+            if frames_in == 0 {
+                return false;
+            }
+            self.resampler.process_partial_into_buffer(
+                Some(&self.wave_in),
+                &mut self.wave_out,
+                None,
+            )
+        };
+        match result {
+            Ok((_, frames_out)) => {
+                self.frames_out = frames_out;
+                self.position = 0;
+                true
+            }
+            Err(err) => {
+                self.error = Some(err);
+                self.finished = true;
+                false
+            }
+        }
+    }
+}
+
+impl<T, R, I> Iterator for ResampleIter<T, R, I>
+where
+    T: Sample,
+    R: Resampler<T>,
+    I: Iterator,
+    I::Item: AsRef<[T]>,
+{
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        while self.position >= self.frames_out {
+            if !self.process_next_chunk() {
+                return None;
+            }
+        }
+        let frame = self
+            .wave_out
+            .iter()
+            .map(|chan| chan[self.position])
+            .collect();
+        self.position += 1;
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FastFixedIn, PolynomialDegree, ResampleError, Resampler, SincFixedOut};
+    use crate::{SincInterpolationParameters, SincInterpolationType, WindowFunction};
+    use rand::Rng;
+
+    #[test]
+    fn matches_blocks() {
+        let mut rng = rand::thread_rng();
+        let frames = 256 * 5 + 100;
+        let wave: Vec<Vec<f64>> = (0..2)
+            .map(|_| (0..frames).map(|_| rng.gen::<f64>() - 0.5).collect())
+            .collect();
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.3, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mut expected = vec![Vec::new(); 2];
+        for chunk in 0..5 {
+            let block: Vec<&[f64]> = wave
+                .iter()
+                .map(|chan| &chan[chunk * 256..(chunk + 1) * 256])
+                .collect();
+            let output = resampler.process(&block, None).unwrap();
+            for (exp, out) in expected.iter_mut().zip(output) {
+                exp.extend_from_slice(&out);
+            }
+        }
+        let block: Vec<&[f64]> = wave.iter().map(|chan| &chan[256 * 5..]).collect();
+        let output = resampler.process_partial(Some(&block), None).unwrap();
+        for (exp, out) in expected.iter_mut().zip(output) {
+            exp.extend_from_slice(&out);
+        }
+
+        let resampler = FastFixedIn::<f64>::new(1.3, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let input = (0..frames).map(|n| [wave[0][n], wave[1][n]]);
+        let mut iter = resampler.resample_iter(input);
+        let output: Vec<Vec<f64>> = iter.by_ref().collect();
+        assert!(iter.error().is_none());
+        assert_eq!(output.len(), expected[0].len());
+        for (n, frame) in output.iter().enumerate() {
+            assert_eq!(frame, &vec![expected[0][n], expected[1][n]]);
+        }
+    }
+
+    #[test]
+    fn varying_input_size() {
+        let params = SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let resampler = SincFixedOut::<f32>::new(0.7, 1.0, params, 128, 1).unwrap();
+        let input = (0..2000).map(|n| vec![(n as f32 * 0.01).sin()]);
+        let output: Vec<Vec<f32>> = resampler.resample_iter(input).collect();
+        // All chunks give 128 frames, including the padded last one.
+        assert_eq!(output.len() % 128, 0);
+        assert!(output.len() >= 1400);
+    }
+
+    #[test]
+    fn wrong_frame_length() {
+        let resampler = FastFixedIn::<f64>::new(1.3, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let input = (0..1000).map(|n| if n == 300 { vec![0.0] } else { vec![0.0; 2] });
+        let mut iter = resampler.resample_iter(input);
+        // Only the first chunk is complete before the error.
+        let mut reference =
+            FastFixedIn::<f64>::new(1.3, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let first_chunk = reference.process(&vec![vec![0.0; 256]; 2], None).unwrap();
+        assert_eq!(iter.by_ref().count(), first_chunk[0].len());
+        assert!(matches!(
+            iter.error(),
+            Some(ResampleError::WrongNumberOfInputChannels {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::error::{ResampleError, ResampleResult};
+
+/// Common channel layouts, giving the meaning and order of the channels.
+///
+/// A layout can be attached to the sinc resamplers with
+/// [SincFixedIn::set_channel_layout](crate::SincFixedIn::set_channel_layout).
+/// The resamplers process all channels in the same way, so the layout doesn't change
+/// the output. It is used to check that the number of channels, and the mixing matrices
+/// used together with the resampler, match the declared layout.
+/// The channel orders are those of WAVE files and of most audio interfaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelLayout {
+    /// A single channel.
+    Mono,
+    /// Left, right.
+    Stereo,
+    /// Front left, front right, back left, back right.
+    Quad,
+    /// Front left, front right, center, LFE, back left, back right.
+    Surround51,
+    /// Front left, front right, center, LFE, back left, back right, side left, side right.
+    Surround71,
+    /// A number of channels without any particular meaning or order.
+    Discrete(usize),
+}
+
+impl ChannelLayout {
+    /// Get the number of channels of the layout.
+    pub fn nbr_channels(&self) -> usize {
+        match *self {
+            ChannelLayout::Mono => 1,
+            ChannelLayout::Stereo => 2,
+            ChannelLayout::Quad => 4,
+            ChannelLayout::Surround51 => 6,
+            ChannelLayout::Surround71 => 8,
+            ChannelLayout::Discrete(channels) => channels,
+        }
+    }
+
+    /// Check that a mixing matrix takes input with this layout.
+    ///
+    /// The matrix has one row for each output channel,
+    /// with one coefficient for each channel of the layout, in the order of the layout.
+    /// A row of the wrong length gives a [ResampleError::WrongNumberOfLayoutChannels].
+    pub fn validate_mix_matrix(&self, matrix: &[Vec<f64>]) -> ResampleResult<()> {
+        let expected = self.nbr_channels();
+        match matrix.iter().find(|row| row.len() != expected) {
+            Some(row) => Err(ResampleError::WrongNumberOfLayoutChannels {
+                expected,
+                actual: row.len(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChannelLayout;
+    use crate::{ResampleError, SincFixedIn, SincInterpolationParameters};
+    use crate::{SincInterpolationType, WindowFunction};
+
+    #[test]
+    fn mismatched_layout() {
+        let params = SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.1, 1.0, params, 256, 6).unwrap();
+        assert!(matches!(
+            resampler.set_channel_layout(Some(ChannelLayout::Surround71)),
+            Err(ResampleError::WrongNumberOfLayoutChannels {
+                expected: 8,
+                actual: 6
+            })
+        ));
+        assert_eq!(resampler.channel_layout(), None);
+        resampler
+            .set_channel_layout(Some(ChannelLayout::Surround51))
+            .unwrap();
+
+        // A stereo downmix of 5.1, and one written for 7.1.
+        let downmix = vec![
+            vec![1.0, 0.0, 0.707, 0.0, 0.707, 0.0],
+            vec![0.0, 1.0, 0.707, 0.0, 0.0, 0.707],
+        ];
+        assert!(resampler.validate_mix_matrix(&downmix).is_ok());
+        let wrong = vec![vec![1.0, 0.0, 0.707, 0.0, 0.707, 0.0, 0.707, 0.0]; 2];
+        assert!(matches!(
+            resampler.validate_mix_matrix(&wrong),
+            Err(ResampleError::WrongNumberOfLayoutChannels {
+                expected: 6,
+                actual: 8
+            })
+        ));
+        assert!(ChannelLayout::Surround71
+            .validate_mix_matrix(&wrong)
+            .is_ok());
+
+        // The layout is removed when the number of channels changes.
+        resampler.set_nbr_channels(2).unwrap();
+        assert_eq!(resampler.channel_layout(), None);
+    }
+}
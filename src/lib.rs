@@ -23,6 +23,21 @@
 //! Since normal vectors implement the `AsRef` trait,
 //! `Vec<Vec<f32>>` and `Vec<Vec<f64>>` can be used for both input and output.
 //!
+//! Buffers that are shared between threads or processing nodes can be used as input
+//! without copying the samples. A shared slice, `Arc<[f32]>`, implements `AsRef<[f32]>`
+//! and can be passed directly, for example as `&[Arc<[f32]>]`.
+//! A shared vector, `Arc<Vec<f32>>`, only implements `AsRef<Vec<f32>>`,
+//! and the orphan rules prevent adding the missing implementation.
+//! Instead, pass slices that borrow the shared data:
+//! ```
+//! # use rubato::{FastFixedIn, PolynomialDegree, Resampler};
+//! # use std::sync::Arc;
+//! let mut resampler = FastFixedIn::<f32>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+//! let shared = vec![Arc::new(vec![0.0f32; 256]); 2];
+//! let slices = [shared[0].as_slice(), shared[1].as_slice()];
+//! let output = resampler.process(&slices, None).unwrap();
+//! ```
+//!
 //! # Asynchronous resampling
 //!
 //! The asynchronous resamplers are available with and without anti-aliasing filters.
@@ -64,12 +79,33 @@
 //! This feature is enabled by default. Disable it if the FFT resamplers are not needed,
 //! to save compile time and reduce the resulting binary size.
 //!
+//! ## `bench`: Enable the benchmarking helper
+//!
+//! This feature adds the `bench` module, with a `run_bench` function that measures
+//! the steady-state processing time per frame for a given sinc resampler configuration.
+//! This is intended for quick checks and comparisons, and is not a substitute for `criterion`.
+//!
+//! ## `ringbuf`: Enable resampling between ring buffers
+//!
+//! This feature adds the `process_from_ring` method to the resampler trait,
+//! that reads interleaved input from one ring buffer and writes the resampled output to another.
+//! The ring buffers are accessed via the small `RingConsumer` and `RingProducer` traits of the `ring` module,
+//! that are easily implemented for lock-free ring buffers such as `rtrb` or `ringbuf`.
+//!
+//! ## `rayon`: Enable parallel resampling of long signals
+//!
+//! This feature adds the `segmented` module, with a `SegmentedResampler` that splits
+//! a complete signal into segments and resamples them in parallel using `rayon`.
+//! The result is identical to resampling the whole signal with a single `SincFixedIn` resampler.
+//!
 //! ## `log`: Enable logging
 //!
 //! This feature enables logging via the `log` crate. This is intended for debugging purposes.
 //! Note that outputting logs allocates a [std::string::String] and most logging implementations involve various other system calls.
 //! These calls may take some (unpredictable) time to return, during which the application is blocked.
 //! This means that logging should be avoided if using this library in a realtime application.
+//! The resamplers only log when they are created or reconfigured, and at most once per processed chunk.
+//! There is no logging for individual frames or samples.
 //!
 //! # Example
 //!
@@ -190,30 +226,79 @@ macro_rules! error { ($($x:tt)*) => (
     }
 ) }
 
+mod accumulator;
 mod asynchro_fast;
 mod asynchro_sinc;
+#[cfg(feature = "bench")]
+pub mod bench;
+mod budget;
+mod device;
+mod emphasis;
 mod error;
+mod factory;
+#[cfg(feature = "fft_resampler")]
+pub mod fft;
+mod gain;
+mod interleaved;
 mod interpolation;
+mod iter;
+mod layout;
+mod looping;
+mod multichannel;
+mod output_stage;
+mod pcm;
+mod quantize;
+#[cfg(feature = "ringbuf")]
+pub mod ring;
 mod sample;
+#[cfg(feature = "rayon")]
+pub mod segmented;
 mod sinc;
 #[cfg(feature = "fft_resampler")]
+mod stream;
+#[cfg(feature = "fft_resampler")]
 mod synchro;
 mod windows;
 
 pub mod sinc_interpolator;
 
+pub use crate::accumulator::Accumulator;
 pub use crate::asynchro_fast::{FastFixedIn, FastFixedOut, PolynomialDegree};
 pub use crate::asynchro_sinc::{
+    design_antialias_fir, validate_sinc_config, EdgeMode, LoudnessStats, OutputRounding, Quality,
     SincFixedIn, SincFixedOut, SincInterpolationParameters, SincInterpolationType,
 };
+pub use crate::budget::BudgetedResampler;
+pub use crate::device::DeviceMatchResampler;
+pub use crate::emphasis::{EmphasisCurve, EmphasisMode};
 pub use crate::error::{
-    CpuFeature, MissingCpuFeature, ResampleError, ResampleResult, ResamplerConstructionError,
+    available_cpu_features, CpuFeature, MissingCpuFeature, ResampleError, ResampleResult,
+    ResamplerConstructionError,
 };
-pub use crate::sample::Sample;
+pub use crate::factory::ResamplerFactory;
+pub use crate::interleaved::InterleavedResampler;
+pub use crate::iter::ResampleIter;
+pub use crate::layout::ChannelLayout;
+pub use crate::looping::{loop_resample_ratio, resample_loop};
+pub use crate::output_stage::OutputStage;
+pub use crate::pcm::{Endianness, FloatBits, SampleEncoding, SampleFormat};
+pub use crate::quantize::{ClipMode, I16Quantizer, RoundingMode};
+#[cfg(feature = "ringbuf")]
+use crate::ring::{RingConsumer, RingProducer};
+pub use crate::sample::{Sample, SampleType};
+#[cfg(feature = "rayon")]
+pub use crate::segmented::SegmentedResampler;
+#[cfg(feature = "fft_resampler")]
+pub use crate::stream::FftStream;
 #[cfg(feature = "fft_resampler")]
-pub use crate::synchro::{FftFixedIn, FftFixedInOut, FftFixedOut};
+pub use crate::synchro::{
+    FftFixedIn, FftFixedInBuilder, FftFixedInOut, FftFixedInOutBuilder, FftFixedOut,
+    FftFixedOutBuilder,
+};
 pub use crate::windows::{calculate_cutoff, WindowFunction};
 
+use std::io::{self, Read, Write};
+
 /// A resampler that is used to resample a chunk of audio to a new sample rate.
 /// For asynchronous resamplers, the rate can be adjusted as required.
 ///
@@ -233,14 +318,16 @@ where
         active_channels_mask: Option<&[bool]>,
     ) -> ResampleResult<Vec<Vec<T>>> {
         let frames = self.output_frames_next();
+        check_output_limit(frames, self.max_output_frames())?;
         let channels = self.nbr_channels();
         let mut wave_out = Vec::with_capacity(channels);
         for chan in 0..channels {
-            let chan_out = if active_channels_mask.map(|mask| mask[chan]).unwrap_or(true) {
-                vec![T::zero(); frames]
-            } else {
-                vec![]
-            };
+            let chan_out =
+                if active_channels_mask.map_or(true, |mask| mask.get(chan) != Some(&false)) {
+                    vec![T::zero(); frames]
+                } else {
+                    vec![]
+                };
             wave_out.push(chan_out);
         }
         let (_, out_len) =
@@ -251,6 +338,44 @@ where
         Ok(wave_out)
     }
 
+    /// Resample a chunk of audio, and pass the output to a closure instead of returning it.
+    ///
+    /// This is intended for streaming the output to a sink, for example a file or a socket.
+    /// The output is written to `scratch`, and the closure `sink` is then called once per chunk
+    /// with the channels of `scratch`, each holding exactly the output frames.
+    /// Inactive channels are passed as empty vectors.
+    /// The input, the `active_channels_mask` and the returned tuple
+    /// are the same as for [process_into_buffer](Resampler::process_into_buffer).
+    /// The sink is not called if processing fails.
+    ///
+    /// The `scratch` buffer is resized to the number of channels and output frames as needed,
+    /// and should be reused for each call.
+    /// It then only allocates when it is too small,
+    /// which can be checked with [process_would_allocate](Resampler::process_would_allocate).
+    /// Like [process](Resampler::process), this respects the limit set by
+    /// [set_max_output_frames](Resampler::set_max_output_frames).
+    fn process_with_sink<V: AsRef<[T]>, F: FnMut(&[Vec<T>])>(
+        &mut self,
+        wave_in: &[V],
+        active_channels_mask: Option<&[bool]>,
+        scratch: &mut Vec<Vec<T>>,
+        mut sink: F,
+    ) -> ResampleResult<(usize, usize)> {
+        check_output_limit(self.output_frames_next(), self.max_output_frames())?;
+        resize_scratch(scratch, self.nbr_channels(), self.output_frames_next());
+        let (frames_in, frames_out) =
+            self.process_into_buffer(wave_in, scratch, active_channels_mask)?;
+        for (chan, chan_out) in scratch.iter_mut().enumerate() {
+            if active_channels_mask.and_then(|mask| mask.get(chan)) != Some(&false) {
+                chan_out.truncate(frames_out);
+            } else {
+                chan_out.clear();
+            }
+        }
+        sink(scratch);
+        Ok((frames_in, frames_out))
+    }
+
     /// Resample a buffer of audio to a pre-allocated output buffer.
     /// Use this in real-time applications where the unpredictable time required to allocate
     /// memory from the heap can cause glitches. If this is not a problem, you may use
@@ -272,13 +397,26 @@ where
     /// Any channel marked as inactive by a false value will be skipped during processing
     /// and the corresponding output will be left unchanged.
     /// If `None` is given, all channels will be considered active.
+    /// When a channel that was inactive in the previous call becomes active again,
+    /// its internal history is cleared before processing.
+    /// The channel then starts from silence, like after a [reset](Resampler::reset),
+    /// without any leftover signal from before it was deactivated.
     ///
     /// Before processing, it checks that the input and outputs are valid.
     /// If either has the wrong number of channels, or if the buffer for any channel is too short,
     /// a [ResampleError] is returned.
+    /// The checks are made before the resampler is modified in any way,
+    /// so after an error the state is the same as before the call.
+    /// The call can then be repeated with valid buffers, and the output is the same as
+    /// if the failed call had never been made.
     /// Both input and output are allowed to be longer than required.
     /// The number of input samples consumed and the number output samples written
     /// per channel is returned in a tuple, `(input_frames, output_frames)`.
+    ///
+    /// The input and output buffers must not overlap.
+    /// The borrow rules guarantee this as long as the buffers are created in safe code.
+    /// To use the same buffer for both input and output,
+    /// use [process_in_place_safe](Resampler::process_in_place_safe).
     fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
         &mut self,
         wave_in: &[Vin],
@@ -286,6 +424,449 @@ where
         active_channels_mask: Option<&[bool]>,
     ) -> ResampleResult<(usize, usize)>;
 
+    /// Resample a buffer of audio to a pre-allocated output buffer, without validating the buffers.
+    /// This is the same as [process_into_buffer](Resampler::process_into_buffer),
+    /// but skips the checks of the numbers of channels and frames.
+    /// It is intended for performance critical code that validates the buffers once,
+    /// and then processes a large number of chunks with the same buffers.
+    /// The default implementation forwards to [process_into_buffer](Resampler::process_into_buffer)
+    /// and keeps the checks. The [FastFixedIn] and [FastFixedOut] resamplers skip them,
+    /// and index the buffers without bounds checks.
+    /// In debug builds, the buffers are still validated, and invalid buffers cause a panic.
+    ///
+    /// # Safety
+    /// The caller must ensure that:
+    /// - `wave_in` and `wave_out` both have [nbr_channels](Resampler::nbr_channels) channels.
+    /// - `active_channels_mask`, if given, has [nbr_channels](Resampler::nbr_channels) values.
+    /// - each active input channel holds at least [input_frames_next](Resampler::input_frames_next) frames.
+    /// - each active output channel has room for at least
+    ///   [output_frames_next](Resampler::output_frames_next) frames.
+    ///
+    /// In other words, [process_into_buffer](Resampler::process_into_buffer) would not
+    /// return an error for the same arguments.
+    unsafe fn process_into_buffer_unchecked<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_into_buffer(wave_in, wave_out, active_channels_mask)
+    }
+
+    /// Resample a block of fixed-size arrays, one array of `N` frames per channel,
+    /// to a pre-allocated output buffer.
+    /// This is meant for pipelines with a fixed block size,
+    /// where `N` equals the number of frames the resampler needs for each call.
+    /// If `N` differs from [input_frames_next](Resampler::input_frames_next),
+    /// a [ResampleError::WrongNumberOfInputFrames] is returned.
+    /// Apart from this, it behaves like [process_into_buffer](Resampler::process_into_buffer).
+    fn process_arrays_into_buffer<const N: usize, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[[T; N]],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let expected = self.input_frames_next();
+        if N != expected {
+            return Err(ResampleError::WrongNumberOfInputFrames {
+                expected,
+                actual: N,
+            });
+        }
+        self.process_into_buffer(wave_in, wave_out, active_channels_mask)
+    }
+
+    /// Resample the next chunk of interleaved audio from one ring buffer to another.
+    /// The frames needed for the next chunk, given by [input_frames_next](Resampler::input_frames_next),
+    /// are read from `consumer`. The resampled frames are written to `producer`.
+    /// If `consumer` holds too few frames, or `producer` has space for too few frames,
+    /// nothing is done and `Ok(None)` is returned.
+    /// Otherwise the number of input and output frames is returned as for
+    /// [process_into_buffer](Resampler::process_into_buffer).
+    ///
+    /// The `channels` value is the number of interleaved channels in the ring buffers,
+    /// and must match the number of channels of the resampler.
+    /// The input and output frames are stored in `scratch_in` and `scratch_out`,
+    /// which are resized as needed. Reuse the same scratch buffers for each call,
+    /// then they only allocate when they need to grow.
+    ///
+    /// If the consumer runs empty or the producer runs full before a whole chunk has been transferred,
+    /// even though the available frames were checked first,
+    /// a [ResampleError::RingBufferUnderflow] or [ResampleError::RingBufferOverflow] is returned.
+    /// The samples transferred before that are lost.
+    #[cfg(feature = "ringbuf")]
+    fn process_from_ring<C: RingConsumer<T>, P: RingProducer<T>>(
+        &mut self,
+        consumer: &mut C,
+        producer: &mut P,
+        channels: usize,
+        scratch_in: &mut Vec<Vec<T>>,
+        scratch_out: &mut Vec<Vec<T>>,
+    ) -> ResampleResult<Option<(usize, usize)>> {
+        if channels != self.nbr_channels() {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: self.nbr_channels(),
+                actual: channels,
+            });
+        }
+        let frames_in = self.input_frames_next();
+        let frames_out = self.output_frames_next();
+        if consumer.occupied() < frames_in * channels || producer.vacant() < frames_out * channels {
+            return Ok(None);
+        }
+        resize_scratch(scratch_in, channels, frames_in);
+        for frame in 0..frames_in {
+            for (chan, wave) in scratch_in.iter_mut().enumerate() {
+                wave[frame] = consumer.pop().ok_or(ResampleError::RingBufferUnderflow {
+                    expected: frames_in * channels,
+                    actual: frame * channels + chan,
+                })?;
+            }
+        }
+        resize_scratch(scratch_out, channels, frames_out);
+        let (nbr_in, nbr_out) = self.process_into_buffer(&*scratch_in, scratch_out, None)?;
+        for frame in 0..nbr_out {
+            for (chan, wave) in scratch_out.iter().enumerate() {
+                if producer.push(wave[frame]).is_err() {
+                    return Err(ResampleError::RingBufferOverflow {
+                        expected: nbr_out * channels,
+                        actual: frame * channels + chan,
+                    });
+                }
+            }
+        }
+        Ok(Some((nbr_in, nbr_out)))
+    }
+
+    /// Resample a chunk of audio and write it as interleaved 16-bit integer samples,
+    /// the most common format for audio playback devices.
+    /// The input is the same as for [process_into_buffer](Resampler::process_into_buffer).
+    /// The output samples are scaled by 32768, rounded to the nearest integer and clipped
+    /// to the range of [i16], and written interleaved to the start of `out`.
+    ///
+    /// The `channels` value is the number of interleaved channels in `out`,
+    /// and must match the number of channels of the resampler.
+    /// If `out` is too short for all output frames, a [ResampleError::InsufficientOutputBufferSize]
+    /// is returned, with the total number of samples needed.
+    ///
+    /// The resampled frames are first written to `scratch`, which is resized as needed.
+    /// Reuse the same `scratch` for each call, then it only allocates when it needs to grow,
+    /// see [process_would_allocate](Resampler::process_would_allocate).
+    /// Returns the number of frames written.
+    fn process_into_interleaved_i16<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        out: &mut [i16],
+        channels: usize,
+        scratch: &mut Vec<Vec<T>>,
+    ) -> ResampleResult<usize> {
+        self.process_into_interleaved_i16_with(
+            wave_in,
+            out,
+            channels,
+            &mut I16Quantizer::new(),
+            scratch,
+        )
+    }
+
+    /// Resample a chunk of audio and write it as interleaved 16-bit integer samples,
+    /// using the given [I16Quantizer] for the conversion.
+    /// This allows choosing for example the rounding mode,
+    /// see [process_into_interleaved_i16](Resampler::process_into_interleaved_i16).
+    /// Pass the same quantizer for each chunk of a stream, so that the dither noise
+    /// continues from where the previous chunk ended.
+    fn process_into_interleaved_i16_with<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        out: &mut [i16],
+        channels: usize,
+        quantizer: &mut I16Quantizer,
+        scratch: &mut Vec<Vec<T>>,
+    ) -> ResampleResult<usize> {
+        if channels != self.nbr_channels() {
+            return Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: self.nbr_channels(),
+                actual: channels,
+            });
+        }
+        let expected = self.output_frames_next() * channels;
+        if out.len() < expected {
+            return Err(ResampleError::InsufficientOutputBufferSize {
+                channel: 0,
+                expected,
+                actual: out.len(),
+            });
+        }
+        resize_scratch(scratch, channels, self.output_frames_next());
+        let (_, frames) = self.process_into_buffer(wave_in, scratch, None)?;
+        for (frame, samples) in out.chunks_exact_mut(channels).take(frames).enumerate() {
+            for (chan_out, sample) in scratch.iter().zip(samples.iter_mut()) {
+                *sample = quantizer.quantize(chan_out[frame].to_f64());
+            }
+        }
+        Ok(frames)
+    }
+
+    /// Resample a chunk of audio given as raw interleaved bytes, for example read from a file.
+    /// The bytes are decoded according to `format`, see [SampleFormat],
+    /// and `channels` is the number of interleaved channels,
+    /// which must match the number of channels of the resampler.
+    ///
+    /// The input must hold at least [input_frames_next](Resampler::input_frames_next) frames,
+    /// which is `input_frames_next() * channels * format.bytes_per_sample()` bytes.
+    /// Otherwise a [ResampleError::InsufficientInputBufferSize] is returned, with the number of bytes needed.
+    /// Any further bytes are ignored.
+    /// The output is written to `wave_out` like for [process_into_buffer](Resampler::process_into_buffer),
+    /// and the number of input frames consumed and output frames written is returned
+    /// in a tuple, `(input_frames, output_frames)`.
+    /// Note that this method allocates a temporary buffer for the decoded input.
+    fn process_from_bytes<Vout: AsMut<[T]>>(
+        &mut self,
+        raw: &[u8],
+        format: SampleFormat,
+        channels: usize,
+        wave_out: &mut [Vout],
+    ) -> ResampleResult<(usize, usize)> {
+        format.validate()?;
+        if channels != self.nbr_channels() {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: self.nbr_channels(),
+                actual: channels,
+            });
+        }
+        let frames = self.input_frames_next();
+        let sample_bytes = format.bytes_per_sample();
+        let frame_bytes = channels * sample_bytes;
+        if raw.len() < frames * frame_bytes {
+            return Err(ResampleError::InsufficientInputBufferSize {
+                channel: 0,
+                expected: frames * frame_bytes,
+                actual: raw.len(),
+            });
+        }
+        let mut wave_in = make_buffer(channels, frames, false);
+        for frame in raw[..frames * frame_bytes].chunks_exact(frame_bytes) {
+            for (chan, bytes) in wave_in.iter_mut().zip(frame.chunks_exact(sample_bytes)) {
+                chan.push(T::coerce(format.decode(bytes)));
+            }
+        }
+        self.process_into_buffer(&wave_in, wave_out, None)
+    }
+
+    /// Resample a chunk of audio, and write the output as raw interleaved bytes,
+    /// for example for writing to a file or passing to an encoder.
+    /// This is the counterpart of [process_from_bytes](Resampler::process_from_bytes).
+    /// The samples are encoded according to `format`, see [SampleFormat],
+    /// and `channels` is the number of interleaved channels,
+    /// which must match the number of channels of the resampler.
+    /// Integer samples are rounded to the nearest value and clipped to the range of the format.
+    /// For other rounding modes or soft clipping of 16-bit samples, see
+    /// [process_into_interleaved_i16_with](Resampler::process_into_interleaved_i16_with).
+    ///
+    /// The input is given like for [process](Resampler::process).
+    /// If `raw` is too short for all output frames, a [ResampleError::InsufficientOutputBufferSize]
+    /// is returned, with the number of bytes needed,
+    /// which is `output_frames_next() * channels * format.bytes_per_sample()`.
+    ///
+    /// The resampled frames are first written to `scratch`, which is resized as needed.
+    /// Reuse the same `scratch` for each call, then it only allocates when it needs to grow,
+    /// see [process_would_allocate](Resampler::process_would_allocate).
+    /// Returns the number of frames written.
+    fn process_into_bytes<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        raw: &mut [u8],
+        format: SampleFormat,
+        channels: usize,
+        scratch: &mut Vec<Vec<T>>,
+    ) -> ResampleResult<usize> {
+        format.validate()?;
+        if channels != self.nbr_channels() {
+            return Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: self.nbr_channels(),
+                actual: channels,
+            });
+        }
+        let sample_bytes = format.bytes_per_sample();
+        let frame_bytes = channels * sample_bytes;
+        let expected = self.output_frames_next() * frame_bytes;
+        if raw.len() < expected {
+            return Err(ResampleError::InsufficientOutputBufferSize {
+                channel: 0,
+                expected,
+                actual: raw.len(),
+            });
+        }
+        resize_scratch(scratch, channels, self.output_frames_next());
+        let (_, frames) = self.process_into_buffer(wave_in, scratch, None)?;
+        for (frame, bytes) in raw.chunks_exact_mut(frame_bytes).take(frames).enumerate() {
+            for (chan_out, sample) in scratch.iter().zip(bytes.chunks_exact_mut(sample_bytes)) {
+                format.encode(chan_out[frame].to_f64(), sample);
+            }
+        }
+        Ok(frames)
+    }
+
+    /// Resample a chunk of audio, and append the output to `raw` as interleaved float samples,
+    /// for example for writing a raw float file.
+    ///
+    /// This works like [process_into_bytes](Resampler::process_into_bytes),
+    /// with a float format of the size given by `bits` and the byte order given by `endianness`.
+    /// Instead of requiring a buffer of the right size, `raw` is extended by the encoded frames.
+    /// If the capacity of `raw` is too small, it grows, which allocates.
+    /// On errors, `raw` is left unchanged.
+    /// The `scratch` buffer is used like for [process_into_bytes](Resampler::process_into_bytes),
+    /// and should be reused for each call.
+    /// Returns the number of frames appended.
+    fn process_into_float_bytes<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        raw: &mut Vec<u8>,
+        channels: usize,
+        bits: FloatBits,
+        endianness: Endianness,
+        scratch: &mut Vec<Vec<T>>,
+    ) -> ResampleResult<usize> {
+        let format = bits.format(endianness);
+        let frame_bytes = channels * format.bytes_per_sample();
+        let start = raw.len();
+        raw.resize(start + self.output_frames_next() * frame_bytes, 0);
+        match self.process_into_bytes(wave_in, &mut raw[start..], format, channels, scratch) {
+            Ok(frames) => {
+                raw.truncate(start + frames * frame_bytes);
+                Ok(frames)
+            }
+            Err(err) => {
+                raw.truncate(start);
+                Err(err)
+            }
+        }
+    }
+
+    /// Resample a complete stream of raw interleaved samples from `src`, and write the result to `dst`.
+    /// This is intended for offline conversion of files, and packages the loop
+    /// of reading, decoding, resampling, encoding and writing.
+    /// Both input and output use the given `format`, see [SampleFormat],
+    /// and `channels` is the number of interleaved channels,
+    /// which must match the number of channels of the resampler.
+    ///
+    /// The input is read in chunks of [input_frames_next](Resampler::input_frames_next) frames
+    /// until `src` reaches its end. The last incomplete chunk is processed with
+    /// [process_partial_into_buffer](Resampler::process_partial_into_buffer),
+    /// and the stream is then ended with [finish](Resampler::finish),
+    /// which also resets the resampler.
+    /// Any incomplete frame at the end of the input is ignored.
+    /// The output is the same as when processing the chunks one by one,
+    /// which means that it starts with the delay of the resampler,
+    /// see [output_delay](Resampler::output_delay).
+    /// Integer samples are rounded and clipped like for [process_into_bytes](Resampler::process_into_bytes).
+    ///
+    /// Returns the total number of output frames written.
+    /// Errors from reading and writing are returned as they are,
+    /// while a [ResampleError] is returned as an [io::Error]
+    /// of kind [io::ErrorKind::InvalidInput].
+    /// This includes a [ResampleError::OutputExceedsLimit] when a chunk would give more output frames
+    /// than allowed by [set_max_output_frames](Resampler::set_max_output_frames).
+    /// The output is not flushed, see [Write::flush].
+    /// Note that this method allocates buffers for reading and writing.
+    fn resample_stream<R: Read, W: Write>(
+        &mut self,
+        mut src: R,
+        mut dst: W,
+        format: SampleFormat,
+        channels: usize,
+    ) -> io::Result<u64> {
+        format.validate().map_err(to_io_error)?;
+        if channels != self.nbr_channels() {
+            return Err(to_io_error(ResampleError::WrongNumberOfInputChannels {
+                expected: self.nbr_channels(),
+                actual: channels,
+            }));
+        }
+        let sample_bytes = format.bytes_per_sample();
+        let frame_bytes = channels * sample_bytes;
+        let mut raw_in = vec![0u8; self.input_frames_max() * frame_bytes];
+        let mut raw_out = Vec::new();
+        let mut wave_in = self.input_buffer_allocate(false);
+        let mut wave_out = vec![Vec::new(); channels];
+        let mut total_frames = 0;
+        let mut write_frames = |wave_out: &[Vec<T>], frames: usize| -> io::Result<()> {
+            if raw_out.len() < frames * frame_bytes {
+                raw_out.resize(frames * frame_bytes, 0);
+            }
+            for (frame, bytes) in raw_out
+                .chunks_exact_mut(frame_bytes)
+                .take(frames)
+                .enumerate()
+            {
+                for (chan_out, sample) in wave_out.iter().zip(bytes.chunks_exact_mut(sample_bytes))
+                {
+                    format.encode(chan_out[frame].to_f64(), sample);
+                }
+            }
+            total_frames += frames as u64;
+            dst.write_all(&raw_out[..frames * frame_bytes])
+        };
+        loop {
+            let frames_out_next = self.output_frames_next();
+            check_output_limit(frames_out_next, self.max_output_frames()).map_err(to_io_error)?;
+            if buffer_length(&wave_out) < frames_out_next {
+                resize_buffer(&mut wave_out, frames_out_next);
+            }
+            let frames_needed = self.input_frames_next();
+            let bytes_read = read_to_fill(&mut src, &mut raw_in[..frames_needed * frame_bytes])?;
+            let frames_in = bytes_read / frame_bytes;
+            wave_in.iter_mut().for_each(|chan| chan.clear());
+            for frame in raw_in[..frames_in * frame_bytes].chunks_exact(frame_bytes) {
+                for (chan, bytes) in wave_in.iter_mut().zip(frame.chunks_exact(sample_bytes)) {
+                    chan.push(T::coerce(format.decode(bytes)));
+                }
+            }
+            if frames_in < frames_needed {
+                if frames_in > 0 {
+                    let (_, frames_out) = self
+                        .process_partial_into_buffer(Some(&wave_in), &mut wave_out, None)
+                        .map_err(to_io_error)?;
+                    write_frames(&wave_out, frames_out)?;
+                }
+                break;
+            }
+            let (_, frames_out) = self
+                .process_into_buffer(&wave_in, &mut wave_out, None)
+                .map_err(to_io_error)?;
+            write_frames(&wave_out, frames_out)?;
+        }
+        let tail = self.finish().map_err(to_io_error)?;
+        write_frames(&tail, tail[0].len())?;
+        Ok(total_frames)
+    }
+
+    /// Resample audio in place, using the same buffer for input and output.
+    /// The input frames are read from the start of each channel of `buffer`,
+    /// and are copied to a temporary buffer before processing starts.
+    /// The output frames are then written to the start of each channel,
+    /// overwriting the input.
+    /// Each channel must be long enough to hold both the input and the output frames.
+    /// Note that this method allocates space for the temporary input buffer.
+    ///
+    /// The `active_channels_mask` and the returned tuple
+    /// are the same as for [process_into_buffer](Resampler::process_into_buffer).
+    fn process_in_place_safe<V: AsMut<[T]>>(
+        &mut self,
+        buffer: &mut [V],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames = self.input_frames_next();
+        let wave_in = buffer
+            .iter_mut()
+            .map(|chan| {
+                let chan = chan.as_mut();
+                chan[..frames.min(chan.len())].to_vec()
+            })
+            .collect::<Vec<Vec<T>>>();
+        self.process_into_buffer(&wave_in, buffer, active_channels_mask)
+    }
+
     /// This is a convenience method for processing the last frames at the end of a stream.
     /// Use this when there are fewer frames remaining than what the resampler requires as input.
     /// Calling this function is equivalent to padding the input buffer with zeros
@@ -331,14 +912,16 @@ where
         active_channels_mask: Option<&[bool]>,
     ) -> ResampleResult<Vec<Vec<T>>> {
         let frames = self.output_frames_next();
+        check_output_limit(frames, self.max_output_frames())?;
         let channels = self.nbr_channels();
         let mut wave_out = Vec::with_capacity(channels);
         for chan in 0..channels {
-            let chan_out = if active_channels_mask.map(|mask| mask[chan]).unwrap_or(true) {
-                vec![T::zero(); frames]
-            } else {
-                vec![]
-            };
+            let chan_out =
+                if active_channels_mask.map_or(true, |mask| mask.get(chan) != Some(&false)) {
+                    vec![T::zero(); frames]
+                } else {
+                    vec![]
+                };
             wave_out.push(chan_out);
         }
         let (_, out_len) =
@@ -349,6 +932,21 @@ where
         Ok(wave_out)
     }
 
+    /// Convert the resampler into an iterator that resamples the frames of `input`.
+    ///
+    /// The source iterator gives one frame per item, as a slice or vector with
+    /// one sample for each channel, and the returned iterator gives the output frames in the same way.
+    /// The frames are buffered internally to the chunk size needed by the resampler.
+    /// See [ResampleIter] for details.
+    fn resample_iter<I>(self, input: I) -> ResampleIter<T, Self, I>
+    where
+        Self: Sized,
+        I: Iterator,
+        I::Item: AsRef<[T]>,
+    {
+        ResampleIter::new(self, input)
+    }
+
     /// Convenience method for allocating an input buffer suitable for use with
     /// [process_into_buffer](Resampler::process_into_buffer). The buffer's capacity
     /// is big enough to prevent allocating additional heap memory before any call to
@@ -397,6 +995,165 @@ where
     /// Get the delay for the resampler, reported as a number of output frames.
     fn output_delay(&self) -> usize;
 
+    /// Get the numeric type that the resampler uses for samples and calculations.
+    /// This is mainly useful for code that handles resamplers as trait objects,
+    /// where the type is otherwise hidden.
+    fn sample_type(&self) -> SampleType {
+        T::SAMPLE_TYPE
+    }
+
+    /// Limit the number of output frames that the allocating methods may allocate per call,
+    /// to put a bound on the memory used, for example in a sandbox with a hard memory limit.
+    ///
+    /// When the limit is set, [process](Resampler::process), [process_partial](Resampler::process_partial)
+    /// and [process_with_sink](Resampler::process_with_sink) return a [ResampleError::OutputExceedsLimit]
+    /// without allocating or processing anything,
+    /// if [output_frames_next](Resampler::output_frames_next) is larger than `max_frames`.
+    /// [resample_stream](Resampler::resample_stream) returns the same error for the first chunk
+    /// that exceeds the limit, and [measured_snr](Resampler::measured_snr) stops measuring there.
+    /// The methods writing to buffers provided by the caller, like
+    /// [process_into_buffer](Resampler::process_into_buffer), are not affected.
+    ///
+    /// For the resamplers with a fixed output size, the number of output frames is the chunk size,
+    /// and the limit only has an effect if it is set lower than that.
+    /// For the resamplers with a variable output size, [output_frames_next](Resampler::output_frames_next)
+    /// includes a small safety margin, and increases with the resampling ratio.
+    /// The limit should therefore be set with some headroom above the expected number of frames.
+    /// A ratio that is set too high, for example by a bug in the ratio calculation,
+    /// then gives an error instead of a large allocation.
+    /// By default there is no limit, which corresponds to `usize::MAX`.
+    fn set_max_output_frames(&mut self, max_frames: usize);
+
+    /// Get the limit for the number of output frames per call,
+    /// see [set_max_output_frames](Resampler::set_max_output_frames).
+    /// A value of `usize::MAX` means that there is no limit.
+    fn max_output_frames(&self) -> usize;
+
+    /// Check if the vectors of `wave_out` lack the capacity for the output of the next call to
+    /// [process_into_buffer](Resampler::process_into_buffer), in the current state and at the current ratio.
+    ///
+    /// The resampler itself never grows the output buffers,
+    /// and returns an error if they are too short.
+    /// Real-time code that resizes its output vectors to [output_frames_next](Resampler::output_frames_next)
+    /// before each call can use this to assert that the resizing will not allocate.
+    /// Returns true if any channel has a capacity below the predicted output length,
+    /// or if there are fewer channels than the resampler uses.
+    fn process_would_allocate(&self, wave_out: &[Vec<T>]) -> bool {
+        let frames = self.output_frames_next();
+        wave_out.len() < self.nbr_channels()
+            || wave_out
+                .iter()
+                .take(self.nbr_channels())
+                .any(|chan| chan.capacity() < frames)
+    }
+
+    /// Get the resampling ratio, as output rate / input rate, that the resampler is set to realize.
+    ///
+    /// For the asynchronous resamplers, this is the ratio given by the last call to
+    /// [set_resample_ratio](Resampler::set_resample_ratio), or to the constructor.
+    /// When a ratio change is being ramped, this is the ratio at the end of the ramp.
+    /// For the synchronous resamplers, this is the fixed ratio realized by the FFT sizes.
+    fn target_ratio(&self) -> f64;
+
+    /// Get the output sample rate that the resampler produces for the given input sample rate.
+    ///
+    /// This is the input rate multiplied by the [target_ratio](Resampler::target_ratio).
+    /// The synchronous resamplers calculate it from the FFT sizes directly,
+    /// which gives the exact output rate when the rates given to the constructor are integers.
+    fn output_sample_rate(&self, input_sample_rate: f64) -> f64 {
+        input_sample_rate * self.target_ratio()
+    }
+
+    /// Get the duration in seconds of the output for `input_frames` input frames,
+    /// when it is played at `output_sample_rate`.
+    ///
+    /// The duration is calculated from the ratio that the resampler realizes,
+    /// see [target_ratio](Resampler::target_ratio), as `input_frames * ratio`
+    /// output frames. When the realized ratio differs slightly from the ratio of the nominal rates,
+    /// for example because of the FFT sizes of a synchronous resampler, this shows up as
+    /// a small change in duration. The actual output is a whole number of frames,
+    /// and is delayed by [output_delay](Resampler::output_delay) frames, which is not included.
+    fn output_duration_secs(&self, input_frames: usize, output_sample_rate: f64) -> f64 {
+        input_frames as f64 * self.target_ratio() / output_sample_rate
+    }
+
+    /// Get the lowest and highest resampling ratios that the resampler can realize,
+    /// as `(min, max)`.
+    ///
+    /// For asynchronous resamplers, these are the bounds accepted by
+    /// [set_resample_ratio](Resampler::set_resample_ratio), given by the original ratio
+    /// and the maximum relative ratio that were provided to the constructor.
+    /// For synchronous resamplers, the ratio is fixed by the constructor,
+    /// and is returned as both bounds.
+    fn ratio_bounds(&self) -> (f64, f64);
+
+    /// Get the smallest number of input frames needed to produce at least one output frame,
+    /// in the current state and at the current ratio.
+    ///
+    /// For resamplers with a fixed input size, this is the same as
+    /// [input_frames_next](Resampler::input_frames_next).
+    /// For resamplers with a fixed output size, this is usually much smaller,
+    /// since [input_frames_next](Resampler::input_frames_next) is the number needed for a full output chunk.
+    /// This can be used to estimate the shortest possible latency in a pull loop,
+    /// where it equals the number of input frames requested by a resampler with an output chunk size of one frame.
+    /// The synchronous [FftFixedOut] returns zero when it has output frames
+    /// saved from a previous call.
+    fn min_input_for_output(&self) -> usize {
+        self.input_frames_next()
+    }
+
+    /// Get the number of output frames that remain to be produced for the input
+    /// that has been processed so far, assuming that there is no more input.
+    /// This counts the frames up to the end of the processed input, see [finish](Resampler::finish).
+    fn output_frames_remaining(&self) -> usize;
+
+    /// Finish a stream, by returning all the remaining output frames.
+    ///
+    /// This feeds the resampler with zeros until all input frames processed so far
+    /// have fully propagated through the filter.
+    /// The output is then trimmed so that it ends with the last frame that lies before the end of the input,
+    /// meaning that the tail that only results from the zero padding is dropped.
+    /// The number of returned frames is given by [output_frames_remaining](Resampler::output_frames_remaining).
+    /// For a stream of `N` input frames, the output from all calls
+    /// to [process](Resampler::process) plus the output of this method is then `ceil(N * ratio)` frames,
+    /// plus the frames of any initial delay of the resampler.
+    /// Finally the resampler is [reset](Resampler::reset), leaving no buffered input.
+    /// Note that this method allocates space for both input and output.
+    fn finish(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        let mut remaining = self.output_frames_remaining();
+        let mut wave_out = vec![Vec::with_capacity(remaining); self.nbr_channels()];
+        while remaining > 0 {
+            let chunk = self.process_partial(None::<&[Vec<T>]>, None)?;
+            let frames = chunk[0].len().min(remaining);
+            for (chan_out, chan_chunk) in wave_out.iter_mut().zip(chunk.iter()) {
+                chan_out.extend_from_slice(&chan_chunk[..frames]);
+            }
+            remaining -= frames;
+        }
+        self.reset();
+        Ok(wave_out)
+    }
+
+    /// Finish a stream like [finish](Resampler::finish), and fade out the end of the returned output.
+    ///
+    /// The last `fade_frames` frames of each channel are multiplied by a gain that falls
+    /// from one to zero along a raised cosine, and the last frame is exactly zero.
+    /// The gain starts with a zero slope, so that the fade doesn't introduce a step at its start.
+    /// If fewer frames than `fade_frames` remain, the fade is shortened to the available frames.
+    /// Note that this method allocates space for both input and output.
+    fn finish_with_fade(&mut self, fade_frames: usize) -> ResampleResult<Vec<Vec<T>>> {
+        let mut wave_out = self.finish()?;
+        for chan_out in wave_out.iter_mut() {
+            let fade_frames = fade_frames.min(chan_out.len());
+            let start = chan_out.len() - fade_frames;
+            for (n, value) in chan_out[start..].iter_mut().enumerate() {
+                let phase = std::f64::consts::PI * (n + 1) as f64 / fade_frames as f64;
+                *value = T::coerce(value.to_f64() * 0.5 * (1.0 + phase.cos()));
+            }
+        }
+        Ok(wave_out)
+    }
+
     /// Update the resample ratio.
     ///
     /// For asynchronous resamplers, the ratio must be within
@@ -409,6 +1166,10 @@ where
     /// If the argument `ramp` is set to true, the ratio will be ramped from the old to the new value
     /// during processing of the next chunk. This allows smooth transitions from one ratio to another.
     /// If `ramp` is false, the new ratio will be applied from the start of the next chunk.
+    ///
+    /// Updating the ratio of the asynchronous resamplers only updates a few scalar values.
+    /// It is a constant time operation that does not allocate memory, and is safe to call
+    /// before every chunk in realtime applications.
     fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> ResampleResult<()>;
 
     /// Update the resample ratio as a factor relative to the original one.
@@ -422,9 +1183,116 @@ where
     /// below 1.0 speed up the output and raise the pitch.
     ///
     /// For synchronous resamplers, this will always return [ResampleError::SyncNotAdjustable].
+    ///
+    /// Like [set_resample_ratio](Resampler::set_resample_ratio), this is a constant time
+    /// operation that does not allocate memory.
     fn set_resample_ratio_relative(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()>;
 
+    /// Process a throwaway chunk of silence, and then [reset](Resampler::reset) the resampler.
+    ///
+    /// The first call to [process_into_buffer](Resampler::process_into_buffer) is usually slower
+    /// than the following ones, since the filter tables and internal buffers are not yet in the
+    /// caches, and their memory pages may not even have been touched since they were allocated.
+    /// Calling this once after creating the resampler, before the real-time processing starts,
+    /// avoids this spike in the processing time of the first chunk.
+    /// The sinc resamplers also read through their whole table of sincs,
+    /// since a single chunk may not use all of them.
+    ///
+    /// Afterwards the resampler is in the same state as a newly created one,
+    /// with the settings kept as for [reset](Resampler::reset).
+    /// Since the reset also restores the original resample ratio, this should be called
+    /// before adjusting the ratio.
+    /// Note that this method allocates temporary buffers for the input and output.
+    fn warmup(&mut self) {
+        let wave_in = self.input_buffer_allocate(true);
+        let mut wave_out = self.output_buffer_allocate(true);
+        // The buffers are allocated to fit, so processing them can't fail.
+        let _ = self.process_into_buffer(&wave_in, &mut wave_out, None);
+        self.reset();
+    }
+
+    /// Measure the signal-to-noise ratio of the resampler, in dB, for a sine at `test_freq` Hz
+    /// sampled at `fs_in` Hz.
+    ///
+    /// The resampler is [reset](Resampler::reset), and a sine with amplitude 0.5 is fed to all channels.
+    /// The output of the first channel is compared with an ideal sine of the same frequency,
+    /// with amplitude and phase fitted to the output by least squares, skipping the transient at the start.
+    /// The SNR is the power of the fitted sine relative to the power of everything else,
+    /// which includes aliasing, imaging, interpolation errors and the ripple of the filter.
+    /// Frequencies near or above the Nyquist frequency of the output are attenuated by the
+    /// anti-aliasing filter, and give a low SNR.
+    /// This is intended for comparing configurations, for example in a test suite.
+    /// If processing fails, or a chunk would give more output frames than allowed by
+    /// [set_max_output_frames](Resampler::set_max_output_frames),
+    /// the measurement uses the output produced before that, and may return NaN.
+    /// Afterwards the resampler is reset again, which also restores the original resample ratio.
+    /// Note that this method allocates buffers for the test signal and the output.
+    fn measured_snr(&mut self, test_freq: f64, fs_in: f64) -> f64 {
+        const ANALYSIS_FRAMES: usize = 8192;
+        self.reset();
+        let ratio = self.target_ratio();
+        let skip = 2 * self.output_delay() + 64;
+        let step_in = 2.0 * std::f64::consts::PI * test_freq / fs_in;
+        let mut output = Vec::with_capacity(skip + ANALYSIS_FRAMES);
+        let mut wave_out = vec![Vec::new(); self.nbr_channels()];
+        let mut position = 0;
+        while output.len() < skip + ANALYSIS_FRAMES {
+            let frames_out_next = self.output_frames_next();
+            if check_output_limit(frames_out_next, self.max_output_frames()).is_err() {
+                break;
+            }
+            if buffer_length(&wave_out) < frames_out_next {
+                resize_buffer(&mut wave_out, frames_out_next);
+            }
+            let frames = self.input_frames_next();
+            let chunk = (position..position + frames)
+                .map(|n| T::coerce(0.5 * (step_in * n as f64).sin()))
+                .collect::<Vec<T>>();
+            let wave_in = vec![chunk; self.nbr_channels()];
+            match self.process_into_buffer(&wave_in, &mut wave_out, None) {
+                Ok((_, frames_out)) => {
+                    output.extend(wave_out[0][..frames_out].iter().map(|value| value.to_f64()))
+                }
+                Err(_) => break,
+            }
+            position += frames;
+        }
+        self.reset();
+        let output = &output[skip.min(output.len())..];
+
+        // Fit `a * sin(wt) + b * cos(wt)` by solving the normal equations.
+        let step_out = step_in / ratio;
+        let (mut ss, mut sc, mut cc, mut ys, mut yc) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (n, value) in output.iter().enumerate() {
+            let (sin, cos) = (step_out * n as f64).sin_cos();
+            ss += sin * sin;
+            sc += sin * cos;
+            cc += cos * cos;
+            ys += value * sin;
+            yc += value * cos;
+        }
+        let det = ss * cc - sc * sc;
+        let a = (ys * cc - yc * sc) / det;
+        let b = (yc * ss - ys * sc) / det;
+        let mut signal = 0.0;
+        let mut noise = 0.0;
+        for (n, value) in output.iter().enumerate() {
+            let (sin, cos) = (step_out * n as f64).sin_cos();
+            let fitted = a * sin + b * cos;
+            signal += fitted * fitted;
+            noise += (value - fitted) * (value - fitted);
+        }
+        10.0 * (signal / noise).log10()
+    }
+
     /// Reset the resampler state and clear all internal buffers.
+    ///
+    /// This clears the buffered input and output, restores the resampling ratio
+    /// given to the constructor, and resets the position of the interpolation,
+    /// so that the resampler can be reused for an independent stream.
+    /// The sinc tables and FFT plans are kept, which makes this much cheaper than
+    /// creating a new resampler. The output after a reset is identical
+    /// to that of a newly created resampler with the same settings.
     fn reset(&mut self);
 }
 
@@ -465,6 +1333,43 @@ macro_rules! implement_resampler {
                 active_channels_mask: Option<&[bool]>,
             ) -> rubato::ResampleResult<(usize, usize)>;
 
+            /// Refer to [Resampler::process_from_bytes].
+            fn process_from_bytes(
+                &mut self,
+                raw: &[u8],
+                format: rubato::SampleFormat,
+                channels: usize,
+                wave_out: $out_type,
+            ) -> rubato::ResampleResult<(usize, usize)>;
+
+            /// Refer to [Resampler::process_into_bytes].
+            fn process_into_bytes(
+                &mut self,
+                wave_in: $in_type,
+                raw: &mut [u8],
+                format: rubato::SampleFormat,
+                channels: usize,
+                scratch: &mut Vec<Vec<T>>,
+            ) -> rubato::ResampleResult<usize>;
+
+            /// Refer to [Resampler::process_into_float_bytes].
+            fn process_into_float_bytes(
+                &mut self,
+                wave_in: $in_type,
+                raw: &mut Vec<u8>,
+                channels: usize,
+                bits: rubato::FloatBits,
+                endianness: rubato::Endianness,
+                scratch: &mut Vec<Vec<T>>,
+            ) -> rubato::ResampleResult<usize>;
+
+            /// Refer to [Resampler::process_in_place_safe].
+            fn process_in_place_safe(
+                &mut self,
+                buffer: $out_type,
+                active_channels_mask: Option<&[bool]>,
+            ) -> rubato::ResampleResult<(usize, usize)>;
+
             /// Refer to [Resampler::process_partial_into_buffer].
             fn process_partial_into_buffer(
                 &mut self,
@@ -504,6 +1409,51 @@ macro_rules! implement_resampler {
             /// Refer to [Resampler::output_delay].
             fn output_delay(&self) -> usize;
 
+            /// Refer to [Resampler::sample_type].
+            fn sample_type(&self) -> rubato::SampleType;
+
+            /// Refer to [Resampler::set_max_output_frames].
+            fn set_max_output_frames(&mut self, max_frames: usize);
+
+            /// Refer to [Resampler::max_output_frames].
+            fn max_output_frames(&self) -> usize;
+
+            /// Refer to [Resampler::process_would_allocate].
+            fn process_would_allocate(&self, wave_out: &[Vec<T>]) -> bool;
+
+            /// Refer to [Resampler::target_ratio].
+            fn target_ratio(&self) -> f64;
+
+            /// Refer to [Resampler::output_sample_rate].
+            fn output_sample_rate(&self, input_sample_rate: f64) -> f64;
+
+            /// Refer to [Resampler::output_duration_secs].
+            fn output_duration_secs(&self, input_frames: usize, output_sample_rate: f64) -> f64;
+
+            /// Refer to [Resampler::ratio_bounds].
+            fn ratio_bounds(&self) -> (f64, f64);
+
+            /// Refer to [Resampler::output_frames_remaining].
+            fn output_frames_remaining(&self) -> usize;
+
+            /// Refer to [Resampler::min_input_for_output].
+            fn min_input_for_output(&self) -> usize;
+
+            /// Refer to [Resampler::finish].
+            fn finish(&mut self) -> rubato::ResampleResult<Vec<Vec<T>>>;
+
+            /// Refer to [Resampler::finish_with_fade].
+            fn finish_with_fade(&mut self, fade_frames: usize) -> rubato::ResampleResult<Vec<Vec<T>>>;
+
+            /// Refer to [Resampler::warmup].
+            fn warmup(&mut self);
+
+            /// Refer to [Resampler::reset].
+            fn reset(&mut self);
+
+            /// Refer to [Resampler::measured_snr].
+            fn measured_snr(&mut self, test_freq: f64, fs_in: f64) -> f64;
+
             /// Refer to [Resampler::set_resample_ratio].
             fn set_resample_ratio(&mut self, new_ratio: f64, ramp: bool) -> rubato::ResampleResult<()>;
 
@@ -533,6 +1483,49 @@ macro_rules! implement_resampler {
                 rubato::Resampler::process_into_buffer(self, wave_in, wave_out, active_channels_mask)
             }
 
+            fn process_from_bytes(
+                &mut self,
+                raw: &[u8],
+                format: rubato::SampleFormat,
+                channels: usize,
+                wave_out: $out_type,
+            ) -> rubato::ResampleResult<(usize, usize)> {
+                rubato::Resampler::process_from_bytes(self, raw, format, channels, wave_out)
+            }
+
+            fn process_into_bytes(
+                &mut self,
+                wave_in: $in_type,
+                raw: &mut [u8],
+                format: rubato::SampleFormat,
+                channels: usize,
+                scratch: &mut Vec<Vec<T>>,
+            ) -> rubato::ResampleResult<usize> {
+                rubato::Resampler::process_into_bytes(self, wave_in, raw, format, channels, scratch)
+            }
+
+            fn process_into_float_bytes(
+                &mut self,
+                wave_in: $in_type,
+                raw: &mut Vec<u8>,
+                channels: usize,
+                bits: rubato::FloatBits,
+                endianness: rubato::Endianness,
+                scratch: &mut Vec<Vec<T>>,
+            ) -> rubato::ResampleResult<usize> {
+                rubato::Resampler::process_into_float_bytes(
+                    self, wave_in, raw, channels, bits, endianness, scratch,
+                )
+            }
+
+            fn process_in_place_safe(
+                &mut self,
+                buffer: $out_type,
+                active_channels_mask: Option<&[bool]>,
+            ) -> rubato::ResampleResult<(usize, usize)> {
+                rubato::Resampler::process_in_place_safe(self, buffer, active_channels_mask)
+            }
+
             fn process_partial_into_buffer(
                 &mut self,
                 wave_in: Option<$in_type>,
@@ -575,6 +1568,66 @@ macro_rules! implement_resampler {
                 rubato::Resampler::output_delay(self)
             }
 
+            fn sample_type(&self) -> rubato::SampleType {
+                rubato::Resampler::sample_type(self)
+            }
+
+            fn set_max_output_frames(&mut self, max_frames: usize) {
+                rubato::Resampler::set_max_output_frames(self, max_frames)
+            }
+
+            fn max_output_frames(&self) -> usize {
+                rubato::Resampler::max_output_frames(self)
+            }
+
+            fn process_would_allocate(&self, wave_out: &[Vec<T>]) -> bool {
+                rubato::Resampler::process_would_allocate(self, wave_out)
+            }
+
+            fn target_ratio(&self) -> f64 {
+                rubato::Resampler::target_ratio(self)
+            }
+
+            fn output_sample_rate(&self, input_sample_rate: f64) -> f64 {
+                rubato::Resampler::output_sample_rate(self, input_sample_rate)
+            }
+
+            fn output_duration_secs(&self, input_frames: usize, output_sample_rate: f64) -> f64 {
+                rubato::Resampler::output_duration_secs(self, input_frames, output_sample_rate)
+            }
+
+            fn ratio_bounds(&self) -> (f64, f64) {
+                rubato::Resampler::ratio_bounds(self)
+            }
+
+            fn output_frames_remaining(&self) -> usize {
+                rubato::Resampler::output_frames_remaining(self)
+            }
+
+            fn min_input_for_output(&self) -> usize {
+                rubato::Resampler::min_input_for_output(self)
+            }
+
+            fn finish(&mut self) -> rubato::ResampleResult<Vec<Vec<T>>> {
+                rubato::Resampler::finish(self)
+            }
+
+            fn finish_with_fade(&mut self, fade_frames: usize) -> rubato::ResampleResult<Vec<Vec<T>>> {
+                rubato::Resampler::finish_with_fade(self, fade_frames)
+            }
+
+            fn warmup(&mut self) {
+                rubato::Resampler::warmup(self)
+            }
+
+            fn reset(&mut self) {
+                rubato::Resampler::reset(self)
+            }
+
+            fn measured_snr(&mut self, test_freq: f64, fs_in: f64) -> f64 {
+                rubato::Resampler::measured_snr(self, test_freq, fs_in)
+            }
+
             fn nbr_channels(&self) -> usize {
                 rubato::Resampler::nbr_channels(self)
             }
@@ -605,10 +1658,135 @@ fn update_mask_from_buffers(mask: &mut [bool]) {
     mask.iter_mut().for_each(|v| *v = true);
 }
 
+/// Helper to find the channels that are inactive in the current `mask`,
+/// and become active with `new_mask`. The history of these channels should be cleared.
+fn reactivated_channels<'a>(
+    mask: &'a [bool],
+    new_mask: Option<&'a [bool]>,
+) -> impl Iterator<Item = usize> + 'a {
+    mask.iter()
+        .enumerate()
+        .filter(move |(chan, active)| {
+            !**active && new_mask.map_or(true, |m| m.get(*chan).copied().unwrap_or(false))
+        })
+        .map(|(chan, _)| chan)
+}
+
+/// Helper for the asynchronous resamplers, to allocate the per-channel buffers
+/// and the mask for up to `max_channels` channels.
+/// The buffers are never shrunk, and the history of the existing channels is kept.
+fn reserve_channels<T: Sample>(
+    buffer: &mut Vec<Vec<T>>,
+    mask: &mut Vec<bool>,
+    max_channels: usize,
+) {
+    if max_channels > buffer.len() {
+        let frames = buffer[0].len();
+        buffer.resize(max_channels, vec![T::zero(); frames]);
+    }
+    mask.reserve(buffer.len() - mask.len());
+}
+
+/// Helper for the asynchronous resamplers, to change the number of channels
+/// within the buffers allocated by [reserve_channels], without allocating.
+/// The history of any added channels is cleared.
+fn change_nbr_channels<T: Sample>(
+    buffer: &mut [Vec<T>],
+    mask: &mut Vec<bool>,
+    nbr_channels: usize,
+) -> ResampleResult<()> {
+    if nbr_channels == 0 || nbr_channels > buffer.len() {
+        return Err(ResampleError::ChannelCountOutOfBounds {
+            provided: nbr_channels,
+            max: buffer.len(),
+        });
+    }
+    let first_added = mask.len().min(nbr_channels);
+    for chan_buffer in buffer[first_added..nbr_channels].iter_mut() {
+        chan_buffer.iter_mut().for_each(|s| *s = T::zero());
+    }
+    mask.resize(nbr_channels, true);
+    Ok(())
+}
+
+/// Helper to check that a resampler is created with at least one channel.
+pub(crate) fn validate_channels(nbr_channels: usize) -> Result<(), ResamplerConstructionError> {
+    if nbr_channels == 0 {
+        return Err(ResamplerConstructionError::InvalidChannelCount(
+            nbr_channels,
+        ));
+    }
+    Ok(())
+}
+
+/// Helper for [Resampler::resample_stream], to read from `src` until `buf` is full
+/// or the end of the stream is reached. Returns the number of bytes read.
+fn read_to_fill<R: Read>(src: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match src.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(bytes) => filled += bytes,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+/// Helper to return a [ResampleError] as an [io::Error].
+fn to_io_error(err: ResampleError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err)
+}
+
+/// Resize a scratch buffer to `channels` channels of `frames` frames each.
+/// Shrinking keeps the capacity, so a reused buffer only allocates when it needs to grow.
+pub(crate) fn resize_scratch<T: Sample>(scratch: &mut Vec<Vec<T>>, channels: usize, frames: usize) {
+    scratch.resize_with(channels, Vec::new);
+    resize_buffer(scratch, frames);
+}
+
+/// Check that the number of output frames for the next call
+/// is within the limit set by [Resampler::set_max_output_frames].
+pub(crate) fn check_output_limit(frames: usize, limit: usize) -> ResampleResult<()> {
+    if frames > limit {
+        return Err(ResampleError::OutputExceedsLimit { frames, limit });
+    }
+    Ok(())
+}
+
+/// Helper for the asynchronous resamplers, to get the number of output frames that
+/// are positioned before the end of the input processed so far.
+/// The `last_index` is the position of the last output frame, relative to the end of the input.
+pub(crate) fn frames_before_end(last_index: f64, resample_ratio: f64) -> usize {
+    let frames = (-last_index * resample_ratio).ceil() - 1.0;
+    if frames > 0.0 {
+        frames as usize
+    } else {
+        0
+    }
+}
+
+/// Helper for the asynchronous resamplers, to get the fractional part of the
+/// input position of the next output frame.
+/// The `last_index` is the position of the last output frame, relative to the end of the input.
+pub(crate) fn next_frame_phase(last_index: f64, resample_ratio: f64) -> f64 {
+    let phase = (last_index + 1.0 / resample_ratio).rem_euclid(1.0);
+    // The remainder can round up to exactly 1.0 for tiny negative positions.
+    if phase < 1.0 {
+        phase
+    } else {
+        0.0
+    }
+}
+
+/// Helper to check the buffers before processing, with `mask` being the new mask of active channels,
+/// or `None` for all channels. This must be called before any state is modified,
+/// to make sure that a failed call leaves the resampler unchanged.
 pub(crate) fn validate_buffers<T, Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
     wave_in: &[Vin],
     wave_out: &mut [Vout],
-    mask: &[bool],
+    mask: Option<&[bool]>,
     channels: usize,
     min_input_len: usize,
     min_output_len: usize,
@@ -619,13 +1797,16 @@ pub(crate) fn validate_buffers<T, Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
             actual: wave_in.len(),
         });
     }
-    if mask.len() != channels {
-        return Err(ResampleError::WrongNumberOfMaskChannels {
-            expected: channels,
-            actual: wave_in.len(),
-        });
+    if let Some(mask) = mask {
+        if mask.len() != channels {
+            return Err(ResampleError::WrongNumberOfMaskChannels {
+                expected: channels,
+                actual: mask.len(),
+            });
+        }
     }
-    for (chan, wave_in) in wave_in.iter().enumerate().filter(|(chan, _)| mask[*chan]) {
+    let active = |chan: usize| mask.map_or(true, |mask| mask[chan]);
+    for (chan, wave_in) in wave_in.iter().enumerate().filter(|(chan, _)| active(*chan)) {
         let actual_len = wave_in.as_ref().len();
         if actual_len < min_input_len {
             return Err(ResampleError::InsufficientInputBufferSize {
@@ -644,7 +1825,7 @@ pub(crate) fn validate_buffers<T, Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
     for (chan, wave_out) in wave_out
         .iter_mut()
         .enumerate()
-        .filter(|(chan, _)| mask[*chan])
+        .filter(|(chan, _)| active(*chan))
     {
         let actual_len = wave_out.as_mut().len();
         if actual_len < min_output_len {
@@ -684,38 +1865,72 @@ pub fn resize_buffer<T: Sample>(buffer: &mut [Vec<T>], frames: usize) {
 /// Convenience method for getting the current length of a buffer in frames.
 /// Checks the [length](Vec::len) of the vector for each channel and returns the smallest.
 pub fn buffer_length<T: Sample>(buffer: &[Vec<T>]) -> usize {
-    return buffer.iter().map(|v| v.len()).min().unwrap_or_default();
+    buffer.iter().map(|v| v.len()).min().unwrap_or_default()
 }
 
 /// Convenience method for getting the current allocated capacity of a buffer in frames.
 /// Checks the [capacity](Vec::capacity) of the vector for each channel and returns the smallest.
 pub fn buffer_capacity<T: Sample>(buffer: &[Vec<T>]) -> usize {
-    return buffer
+    buffer
         .iter()
         .map(|v| v.capacity())
         .min()
-        .unwrap_or_default();
+        .unwrap_or_default()
+}
+
+/// Convenience method for converting a planar buffer, with one vector per channel,
+/// to a single vector of interleaved samples.
+/// If the channels differ in length, only the frames available in all channels are included.
+pub fn planar_to_interleaved<T: Sample>(planar: &[Vec<T>]) -> Vec<T> {
+    let frames = buffer_length(planar);
+    let mut interleaved = Vec::with_capacity(frames * planar.len());
+    for frame in 0..frames {
+        interleaved.extend(planar.iter().map(|chan| chan[frame]));
+    }
+    interleaved
+}
+
+/// Convenience method for converting a vector of interleaved samples
+/// to a planar buffer, with one vector per channel.
+/// The length of the interleaved buffer must be a multiple of the number of channels,
+/// otherwise a [ResampleError::InvalidInterleavedLength] is returned.
+pub fn interleaved_to_planar<T: Sample>(
+    interleaved: &[T],
+    channels: usize,
+) -> ResampleResult<Vec<Vec<T>>> {
+    if channels == 0 || interleaved.len() % channels != 0 {
+        return Err(ResampleError::InvalidInterleavedLength {
+            length: interleaved.len(),
+            channels,
+        });
+    }
+    let mut planar = make_buffer(channels, interleaved.len() / channels, false);
+    for frame in interleaved.chunks_exact(channels) {
+        for (chan, value) in planar.iter_mut().zip(frame) {
+            chan.push(*value);
+        }
+    }
+    Ok(planar)
 }
 
 #[cfg(test)]
 pub mod tests {
+    use crate::{available_cpu_features, CpuFeature};
     use crate::{buffer_capacity, buffer_length, make_buffer, resize_buffer, VecResampler};
-    use crate::{FastFixedIn, PolynomialDegree, SincFixedIn, SincFixedOut};
+    use crate::{interleaved_to_planar, planar_to_interleaved, ResampleError};
+    use crate::{Endianness, FloatBits, SampleEncoding, SampleFormat, SampleType};
+    use crate::{FastFixedIn, FastFixedOut, PolynomialDegree, SincFixedIn, SincFixedOut};
     #[cfg(feature = "fft_resampler")]
     use crate::{FftFixedIn, FftFixedInOut, FftFixedOut};
+    use crate::{SincInterpolationParameters, SincInterpolationType, WindowFunction};
+    use std::sync::Arc;
 
     // This tests that a VecResampler can be boxed.
     #[test]
     fn boxed_resampler() {
         let mut boxed: Box<dyn VecResampler<f64>> = Box::new(
-            FastFixedIn::<f64>::new(
-                88200 as f64 / 44100 as f64,
-                1.1,
-                PolynomialDegree::Cubic,
-                1024,
-                2,
-            )
-            .unwrap(),
+            FastFixedIn::<f64>::new(88200_f64 / 44100_f64, 1.1, PolynomialDegree::Cubic, 1024, 2)
+                .unwrap(),
         );
         let _ = process_with_boxed(&mut boxed);
         let result = process_with_boxed(&mut boxed);
@@ -730,6 +1945,354 @@ pub mod tests {
         resampler.process(&waves, None).unwrap()
     }
 
+    #[cfg(feature = "log")]
+    mod log_counter {
+        use std::cell::Cell;
+
+        thread_local! {
+            pub static RECORDS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        pub struct CountingLogger;
+
+        impl log::Log for CountingLogger {
+            fn enabled(&self, _metadata: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, _record: &log::Record) {
+                RECORDS.with(|r| r.set(r.get() + 1));
+            }
+
+            fn flush(&self) {}
+        }
+
+        pub static LOGGER: CountingLogger = CountingLogger;
+    }
+
+    // Check that processing logs at most once per chunk, and not for every frame.
+    // The counter is per thread, so logs from tests running in parallel are not included.
+    #[cfg(feature = "log")]
+    #[test]
+    fn log_at_most_once_per_chunk() {
+        let _ = log::set_logger(&log_counter::LOGGER);
+        log::set_max_level(log::LevelFilter::Trace);
+        let params = SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resamplers: Vec<Box<dyn VecResampler<f64>>> = vec![
+            Box::new(SincFixedIn::<f64>::new(1.5, 1.1, params.clone(), 1024, 2).unwrap()),
+            Box::new(SincFixedOut::<f64>::new(1.5, 1.1, params, 1024, 2).unwrap()),
+            Box::new(FastFixedIn::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 1024, 2).unwrap()),
+            Box::new(FastFixedOut::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 1024, 2).unwrap()),
+        ];
+        #[cfg(feature = "fft_resampler")]
+        {
+            resamplers.push(Box::new(
+                FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap(),
+            ));
+            resamplers.push(Box::new(
+                FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 2).unwrap(),
+            ));
+            resamplers.push(Box::new(
+                FftFixedInOut::<f64>::new(44100, 48000, 1024, 2).unwrap(),
+            ));
+        }
+        let chunks = 10;
+        for resampler in resamplers.iter_mut() {
+            log_counter::RECORDS.with(|r| r.set(0));
+            for _ in 0..chunks {
+                let _ = process_with_boxed(resampler);
+            }
+            let records = log_counter::RECORDS.with(|r| r.get());
+            assert!(
+                records <= chunks,
+                "{} log records for {} chunks",
+                records,
+                chunks
+            );
+        }
+        log::set_max_level(log::LevelFilter::Off);
+    }
+
+    /// Process a constant signal, then mute the second channel for a few chunks,
+    /// and check that there is no leftover signal after unmuting with silent input.
+    fn assert_no_stale_data(mut resampler: Box<dyn VecResampler<f64>>) {
+        let mask = [true, false];
+        for active_channels_mask in [None, Some(&mask[..])] {
+            for _ in 0..3 {
+                let frames = resampler.input_frames_next();
+                let waves = vec![vec![1.0f64; frames]; 2];
+                resampler.process(&waves, active_channels_mask).unwrap();
+            }
+        }
+        for _ in 0..3 {
+            let frames = resampler.input_frames_next();
+            let waves = vec![vec![1.0f64; frames], vec![0.0f64; frames]];
+            let output = resampler.process(&waves, None).unwrap();
+            assert!(output[0].iter().any(|v| *v != 0.0));
+            assert!(output[1].iter().all(|v| *v == 0.0));
+        }
+    }
+
+    #[test]
+    fn unmute_channel() {
+        assert_no_stale_data(Box::new(
+            FastFixedIn::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap(),
+        ));
+        assert_no_stale_data(Box::new(
+            FastFixedOut::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap(),
+        ));
+        let params = SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        assert_no_stale_data(Box::new(
+            SincFixedIn::<f64>::new(1.5, 1.1, params.clone(), 256, 2).unwrap(),
+        ));
+        assert_no_stale_data(Box::new(
+            SincFixedOut::<f64>::new(1.5, 1.1, params, 256, 2).unwrap(),
+        ));
+        #[cfg(feature = "fft_resampler")]
+        {
+            assert_no_stale_data(Box::new(
+                FftFixedIn::<f64>::new(44100, 48000, 256, 2, 2).unwrap(),
+            ));
+            assert_no_stale_data(Box::new(
+                FftFixedOut::<f64>::new(44100, 48000, 256, 2, 2).unwrap(),
+            ));
+            assert_no_stale_data(Box::new(
+                FftFixedInOut::<f64>::new(44100, 48000, 256, 2).unwrap(),
+            ));
+        }
+    }
+
+    /// Warm up one resampler, and check that its state and output then match a fresh one.
+    fn assert_warmup_like_new(
+        mut fresh: Box<dyn VecResampler<f64>>,
+        mut warmed: Box<dyn VecResampler<f64>>,
+    ) {
+        warmed.warmup();
+        for chunk in 0..4 {
+            assert_eq!(warmed.input_frames_next(), fresh.input_frames_next());
+            assert_eq!(warmed.output_frames_next(), fresh.output_frames_next());
+            assert_eq!(
+                warmed.output_frames_remaining(),
+                fresh.output_frames_remaining()
+            );
+            assert_eq!(warmed.output_delay(), fresh.output_delay());
+            let frames = fresh.input_frames_next();
+            let waves = vec![
+                (0..frames)
+                    .map(|n| ((chunk * frames + n) as f64 * 0.1).sin())
+                    .collect::<Vec<f64>>();
+                2
+            ];
+            assert_eq!(
+                warmed.process(&waves, None).unwrap(),
+                fresh.process(&waves, None).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn warmup() {
+        assert_warmup_like_new(
+            Box::new(FastFixedOut::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap()),
+            Box::new(FastFixedOut::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap()),
+        );
+        let params = SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        assert_warmup_like_new(
+            Box::new(SincFixedIn::<f64>::new(1.5, 1.1, params.clone(), 256, 2).unwrap()),
+            Box::new(SincFixedIn::<f64>::new(1.5, 1.1, params.clone(), 256, 2).unwrap()),
+        );
+        assert_warmup_like_new(
+            Box::new(SincFixedOut::<f64>::new(1.5, 1.1, params.clone(), 256, 2).unwrap()),
+            Box::new(SincFixedOut::<f64>::new(1.5, 1.1, params, 256, 2).unwrap()),
+        );
+        #[cfg(feature = "fft_resampler")]
+        assert_warmup_like_new(
+            Box::new(FftFixedOut::<f64>::new(44100, 48000, 256, 2, 2).unwrap()),
+            Box::new(FftFixedOut::<f64>::new(44100, 48000, 256, 2, 2).unwrap()),
+        );
+    }
+
+    /// Process one stream with a resampler, including a ratio change where supported,
+    /// reset it, and check that a second stream then gives the same output as with a fresh one.
+    fn assert_reset_like_new(
+        mut fresh: Box<dyn VecResampler<f64>>,
+        mut used: Box<dyn VecResampler<f64>>,
+    ) {
+        for chunk in 0..3 {
+            if chunk == 1 {
+                // The synchronous resamplers don't support this, and ignore it.
+                let _ = used.set_resample_ratio_relative(1.05, true);
+            }
+            let frames = used.input_frames_next();
+            let waves = vec![
+                (0..frames)
+                    .map(|n| ((chunk * frames + n) as f64 * 0.37).cos())
+                    .collect::<Vec<f64>>();
+                2
+            ];
+            used.process(&waves, None).unwrap();
+        }
+        used.reset();
+        for chunk in 0..4 {
+            let frames = fresh.input_frames_next();
+            assert_eq!(used.input_frames_next(), frames);
+            let waves = vec![
+                (0..frames)
+                    .map(|n| ((chunk * frames + n) as f64 * 0.1).sin())
+                    .collect::<Vec<f64>>();
+                2
+            ];
+            assert_eq!(
+                used.process(&waves, None).unwrap(),
+                fresh.process(&waves, None).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn reset_between_streams() {
+        assert_reset_like_new(
+            Box::new(FastFixedIn::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap()),
+            Box::new(FastFixedIn::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap()),
+        );
+        assert_reset_like_new(
+            Box::new(FastFixedOut::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap()),
+            Box::new(FastFixedOut::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap()),
+        );
+        let params = SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        assert_reset_like_new(
+            Box::new(SincFixedIn::<f64>::new(1.5, 1.1, params.clone(), 256, 2).unwrap()),
+            Box::new(SincFixedIn::<f64>::new(1.5, 1.1, params.clone(), 256, 2).unwrap()),
+        );
+        assert_reset_like_new(
+            Box::new(SincFixedOut::<f64>::new(1.5, 1.1, params.clone(), 256, 2).unwrap()),
+            Box::new(SincFixedOut::<f64>::new(1.5, 1.1, params, 256, 2).unwrap()),
+        );
+        #[cfg(feature = "fft_resampler")]
+        {
+            assert_reset_like_new(
+                Box::new(FftFixedIn::<f64>::new(44100, 48000, 256, 2, 2).unwrap()),
+                Box::new(FftFixedIn::<f64>::new(44100, 48000, 256, 2, 2).unwrap()),
+            );
+            assert_reset_like_new(
+                Box::new(FftFixedOut::<f64>::new(44100, 48000, 256, 2, 2).unwrap()),
+                Box::new(FftFixedOut::<f64>::new(44100, 48000, 256, 2, 2).unwrap()),
+            );
+            assert_reset_like_new(
+                Box::new(FftFixedInOut::<f64>::new(44100, 48000, 256, 2).unwrap()),
+                Box::new(FftFixedInOut::<f64>::new(44100, 48000, 256, 2).unwrap()),
+            );
+        }
+    }
+
+    /// Process a sine with two resamplers, where the second one also gets
+    /// invalid buffers before each chunk, and check that the outputs are identical.
+    fn assert_transactional(
+        mut resampler: Box<dyn VecResampler<f64>>,
+        mut failing: Box<dyn VecResampler<f64>>,
+    ) {
+        let mask = [true, false];
+        for chunk in 0..4 {
+            let frames = resampler.input_frames_next();
+            let waves: Vec<Vec<f64>> = (0..2)
+                .map(|_| {
+                    (0..frames)
+                        .map(|n| ((chunk * frames + n) as f64 * 0.05).sin())
+                        .collect()
+                })
+                .collect();
+            let active_channels_mask = if chunk == 1 { Some(&mask[..]) } else { None };
+
+            let short = vec![vec![0.0; frames - 1]; 2];
+            assert!(matches!(
+                failing.process(&short, None),
+                Err(ResampleError::InsufficientInputBufferSize { .. })
+            ));
+            assert!(matches!(
+                failing.process(&waves, Some(&[true])),
+                Err(ResampleError::WrongNumberOfMaskChannels {
+                    expected: 2,
+                    actual: 1
+                })
+            ));
+            let mut wave_out = vec![vec![0.0; failing.output_frames_next() - 1]; 2];
+            assert!(matches!(
+                failing.process_into_buffer(&waves, &mut wave_out, None),
+                Err(ResampleError::InsufficientOutputBufferSize { .. })
+            ));
+
+            let expected = resampler.process(&waves, active_channels_mask).unwrap();
+            let output = failing.process(&waves, active_channels_mask).unwrap();
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn errors_leave_state_unchanged() {
+        assert_transactional(
+            Box::new(FastFixedIn::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap()),
+            Box::new(FastFixedIn::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap()),
+        );
+        assert_transactional(
+            Box::new(FastFixedOut::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap()),
+            Box::new(FastFixedOut::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap()),
+        );
+        let params = SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        assert_transactional(
+            Box::new(SincFixedIn::<f64>::new(1.5, 1.1, params.clone(), 256, 2).unwrap()),
+            Box::new(SincFixedIn::<f64>::new(1.5, 1.1, params.clone(), 256, 2).unwrap()),
+        );
+        assert_transactional(
+            Box::new(SincFixedOut::<f64>::new(1.5, 1.1, params.clone(), 256, 2).unwrap()),
+            Box::new(SincFixedOut::<f64>::new(1.5, 1.1, params, 256, 2).unwrap()),
+        );
+        #[cfg(feature = "fft_resampler")]
+        {
+            assert_transactional(
+                Box::new(FftFixedIn::<f64>::new(44100, 48000, 256, 2, 2).unwrap()),
+                Box::new(FftFixedIn::<f64>::new(44100, 48000, 256, 2, 2).unwrap()),
+            );
+            assert_transactional(
+                Box::new(FftFixedOut::<f64>::new(44100, 48000, 256, 2, 2).unwrap()),
+                Box::new(FftFixedOut::<f64>::new(44100, 48000, 256, 2, 2).unwrap()),
+            );
+            assert_transactional(
+                Box::new(FftFixedInOut::<f64>::new(44100, 48000, 256, 2).unwrap()),
+                Box::new(FftFixedInOut::<f64>::new(44100, 48000, 256, 2).unwrap()),
+            );
+        }
+    }
+
     fn impl_send<T: Send>() {
         fn is_send<T: Send>() {}
         is_send::<SincFixedOut<T>>();
@@ -779,7 +2342,7 @@ pub mod tests {
                     for ch in 0..2 {
                         waves[ch][m] = val;
                     }
-                    val = val + 0.1;
+                    val += 0.1;
                 }
                 let out = $resampler.process(&waves, None).unwrap();
                 let frames_out = out[0].len();
@@ -839,6 +2402,492 @@ pub mod tests {
         };
     }
 
+    #[test]
+    fn test_process_with_sink() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mut resampler_sink =
+            FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mask = [true, false];
+        let mut scratch = Vec::new();
+        for n in 0..4 {
+            let waves = vec![vec![n as f64 + 0.5; 256], vec![0.25; 256]];
+            let mask = if n == 2 { Some(&mask[..]) } else { None };
+            let expected = resampler.process(&waves, mask).unwrap();
+            let mut calls = 0;
+            let (frames_in, frames_out) = crate::Resampler::process_with_sink(
+                &mut resampler_sink,
+                &waves,
+                mask,
+                &mut scratch,
+                |chunk: &[Vec<f64>]| {
+                    calls += 1;
+                    assert_eq!(chunk.len(), 2);
+                    for (chan, exp) in chunk.iter().zip(expected.iter()) {
+                        assert_eq!(chan, exp);
+                    }
+                },
+            )
+            .unwrap();
+            assert_eq!(calls, 1);
+            assert_eq!(frames_in, 256);
+            assert_eq!(frames_out, expected[0].len());
+            // After the first call, the scratch buffer is reused without allocating.
+            assert!(!crate::Resampler::process_would_allocate(
+                &resampler_sink,
+                &scratch
+            ));
+        }
+    }
+
+    #[test]
+    fn test_process_from_bytes() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mut resampler_bytes =
+            FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let format = SampleFormat::new(16, SampleEncoding::SignedInt, Endianness::Little);
+        let mut wave_out = resampler_bytes.output_buffer_allocate(true);
+        for n in 0..3 {
+            let samples = (0..512)
+                .map(|i: i32| ((i * 97 + n * 1000) % 65536 - 32768) as i16)
+                .collect::<Vec<i16>>();
+            let raw = samples
+                .iter()
+                .flat_map(|s| s.to_le_bytes())
+                .collect::<Vec<u8>>();
+            let planar = (0..2)
+                .map(|chan| {
+                    samples[chan..]
+                        .iter()
+                        .step_by(2)
+                        .map(|s| *s as f64 / 32768.0)
+                        .collect::<Vec<f64>>()
+                })
+                .collect::<Vec<_>>();
+            let expected = resampler.process(&planar, None).unwrap();
+            let (frames_in, frames_out) = resampler_bytes
+                .process_from_bytes(&raw, format, 2, &mut wave_out)
+                .unwrap();
+            assert_eq!(frames_in, 256);
+            assert_eq!(frames_out, expected[0].len());
+            for (chan_out, exp) in wave_out.iter().zip(expected.iter()) {
+                assert_eq!(&chan_out[..frames_out], &exp[..]);
+            }
+        }
+        assert!(matches!(
+            resampler_bytes.process_from_bytes(&[0; 1000], format, 2, &mut wave_out),
+            Err(ResampleError::InsufficientInputBufferSize {
+                expected: 1024,
+                actual: 1000,
+                ..
+            })
+        ));
+        let format = SampleFormat::new(16, SampleEncoding::Float, Endianness::Little);
+        assert!(matches!(
+            resampler_bytes.process_from_bytes(&[0; 1024], format, 2, &mut wave_out),
+            Err(ResampleError::UnsupportedSampleFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_process_into_bytes() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mut resampler_bytes =
+            FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let format = SampleFormat::new(32, SampleEncoding::Float, Endianness::Little);
+        let mut raw = vec![0u8; resampler_bytes.output_frames_max() * 2 * 4];
+        let mut scratch = Vec::new();
+        for n in 0..3 {
+            let planar = (0..2)
+                .map(|chan| {
+                    (0..256)
+                        .map(|i| ((i + 100 * n + chan) as f64 * 0.1).sin())
+                        .collect::<Vec<f64>>()
+                })
+                .collect::<Vec<_>>();
+            let expected = resampler.process(&planar, None).unwrap();
+            let frames = resampler_bytes
+                .process_into_bytes(&planar, &mut raw, format, 2, &mut scratch)
+                .unwrap();
+            assert_eq!(frames, expected[0].len());
+            for (frame, bytes) in raw.chunks_exact(8).take(frames).enumerate() {
+                for (chan, exp) in expected.iter().enumerate() {
+                    let value =
+                        f32::from_le_bytes(bytes[4 * chan..4 * chan + 4].try_into().unwrap());
+                    assert_eq!(value, exp[frame] as f32);
+                }
+            }
+        }
+        let planar = vec![vec![0.0; 256]; 2];
+        assert!(matches!(
+            resampler_bytes.process_into_bytes(&planar, &mut raw[..100], format, 2, &mut scratch),
+            Err(ResampleError::InsufficientOutputBufferSize { actual: 100, .. })
+        ));
+        assert!(matches!(
+            resampler_bytes.process_into_bytes(&planar, &mut raw, format, 1, &mut scratch),
+            Err(ResampleError::WrongNumberOfOutputChannels {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_process_into_float_bytes() {
+        for (bits, endianness) in [
+            (FloatBits::F64, Endianness::Little),
+            (FloatBits::F32, Endianness::Big),
+        ] {
+            let mut resampler =
+                FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+            let mut resampler_bytes =
+                FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+            let mut expected = Vec::new();
+            let mut raw = Vec::new();
+            let mut scratch = Vec::new();
+            for n in 0..3 {
+                let planar = (0..2)
+                    .map(|chan| {
+                        (0..256)
+                            .map(|i| ((i + 100 * n + chan) as f64 * 0.1).sin())
+                            .collect::<Vec<f64>>()
+                    })
+                    .collect::<Vec<_>>();
+                let output = resampler.process(&planar, None).unwrap();
+                for value in planar_to_interleaved(&output) {
+                    match bits {
+                        FloatBits::F64 => expected.extend_from_slice(&value.to_le_bytes()),
+                        FloatBits::F32 => expected.extend_from_slice(&(value as f32).to_be_bytes()),
+                    }
+                }
+                let frames = resampler_bytes
+                    .process_into_float_bytes(&planar, &mut raw, 2, bits, endianness, &mut scratch)
+                    .unwrap();
+                assert_eq!(frames, output[0].len());
+            }
+            assert_eq!(raw, expected);
+            let planar = vec![vec![0.0; 256]; 2];
+            assert!(matches!(
+                resampler_bytes.process_into_float_bytes(
+                    &planar,
+                    &mut raw,
+                    1,
+                    bits,
+                    endianness,
+                    &mut scratch
+                ),
+                Err(ResampleError::WrongNumberOfOutputChannels {
+                    expected: 2,
+                    actual: 1
+                })
+            ));
+            assert_eq!(raw, expected);
+        }
+    }
+
+    #[test]
+    fn test_resample_stream() {
+        let frames = 1000;
+        let wave: Vec<Vec<f64>> = (0..2)
+            .map(|chan| {
+                (0..frames)
+                    .map(|n| ((n + 100 * chan) as f64 * 0.02).sin())
+                    .collect()
+            })
+            .collect();
+        let mut raw_in = Vec::new();
+        for n in 0..frames {
+            for chan in wave.iter() {
+                raw_in.extend_from_slice(&chan[n].to_le_bytes());
+            }
+        }
+        // An incomplete frame at the end is ignored.
+        raw_in.extend_from_slice(&[0; 3]);
+        let format = SampleFormat::new(64, SampleEncoding::Float, Endianness::Little);
+
+        // Process the same frames chunk by chunk.
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.5, 1.1, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mut expected = vec![Vec::new(); 2];
+        let mut start = 0;
+        while start < frames {
+            let frames_next = crate::Resampler::input_frames_next(&resampler);
+            let end = (start + frames_next).min(frames);
+            let chunk: Vec<&[f64]> = wave.iter().map(|chan| &chan[start..end]).collect();
+            let output = if end - start == frames_next {
+                crate::Resampler::process(&mut resampler, &chunk, None).unwrap()
+            } else {
+                crate::Resampler::process_partial(&mut resampler, Some(&chunk), None).unwrap()
+            };
+            for (exp, out) in expected.iter_mut().zip(output.iter()) {
+                exp.extend_from_slice(out);
+            }
+            start = end;
+        }
+        for (exp, out) in expected
+            .iter_mut()
+            .zip(crate::Resampler::finish(&mut resampler).unwrap())
+        {
+            exp.extend_from_slice(&out);
+        }
+
+        let mut raw_out = Vec::new();
+        let written = crate::Resampler::resample_stream(
+            &mut resampler,
+            std::io::Cursor::new(&raw_in),
+            &mut raw_out,
+            format,
+            2,
+        )
+        .unwrap();
+        assert_eq!(written, expected[0].len() as u64);
+        assert_eq!(raw_out.len(), expected[0].len() * 16);
+        for (n, frame) in raw_out.chunks_exact(16).enumerate() {
+            let left = f64::from_le_bytes(frame[..8].try_into().unwrap());
+            let right = f64::from_le_bytes(frame[8..].try_into().unwrap());
+            assert_eq!(left, expected[0][n]);
+            assert_eq!(right, expected[1][n]);
+        }
+
+        let err = crate::Resampler::resample_stream(
+            &mut resampler,
+            std::io::Cursor::new(&raw_in),
+            &mut raw_out,
+            format,
+            1,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_sample_type() {
+        let boxed_32: Box<dyn VecResampler<f32>> =
+            Box::new(FastFixedIn::<f32>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap());
+        let boxed_64: Box<dyn VecResampler<f64>> =
+            Box::new(FastFixedOut::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap());
+        assert_eq!(boxed_32.sample_type(), SampleType::F32);
+        assert_eq!(boxed_64.sample_type(), SampleType::F64);
+    }
+
+    #[test]
+    fn test_output_duration_secs() {
+        let resampler: Box<dyn VecResampler<f64>> = Box::new(
+            FastFixedIn::<f64>::new(48000.0 / 44100.0, 1.1, PolynomialDegree::Cubic, 256, 2)
+                .unwrap(),
+        );
+        assert!((resampler.output_duration_secs(44100, 48000.0) - 1.0).abs() < 1.0e-12);
+        #[cfg(feature = "fft_resampler")]
+        {
+            let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+            let duration = crate::Resampler::output_duration_secs(&resampler, 44100, 48000.0);
+            assert!((duration - 1.0).abs() < 1.0e-12);
+            // Played at a slightly different rate, the duration changes accordingly.
+            let duration = crate::Resampler::output_duration_secs(&resampler, 44100, 48048.0);
+            assert!((duration - 48000.0 / 48048.0).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_measured_snr() {
+        let params = |sinc_len, interpolation, oversampling_factor| SincInterpolationParameters {
+            sinc_len,
+            f_cutoff: 0.95,
+            interpolation,
+            oversampling_factor,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let ratio = 48000.0 / 44100.0;
+        let mut high: Box<dyn VecResampler<f64>> = Box::new(
+            SincFixedIn::<f64>::new(
+                ratio,
+                1.0,
+                params(256, SincInterpolationType::Cubic, 256),
+                1024,
+                2,
+            )
+            .unwrap(),
+        );
+        let mut low: Box<dyn VecResampler<f64>> = Box::new(
+            SincFixedIn::<f64>::new(
+                ratio,
+                1.0,
+                params(32, SincInterpolationType::Linear, 16),
+                1024,
+                2,
+            )
+            .unwrap(),
+        );
+        let snr_high = high.measured_snr(10000.0, 44100.0);
+        let snr_low = low.measured_snr(10000.0, 44100.0);
+        assert!(snr_high > 150.0);
+        assert!(snr_low < 100.0);
+        // The measurement doesn't depend on the previous state.
+        let frames = high.input_frames_next();
+        high.process(&vec![vec![1.0; frames]; 2], None).unwrap();
+        assert_eq!(high.measured_snr(10000.0, 44100.0), snr_high);
+    }
+
+    #[test]
+    fn test_shared_buffers() {
+        let mut resampler =
+            FastFixedIn::<f32>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let mut resampler_shared =
+            FastFixedIn::<f32>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        for n in 0..3 {
+            let waves = (0..2)
+                .map(|chan| {
+                    (0..256)
+                        .map(|i| ((i + 256 * n + chan) as f32 * 0.1).sin())
+                        .collect::<Vec<f32>>()
+                })
+                .collect::<Vec<_>>();
+            let expected = crate::Resampler::process(&mut resampler, &waves, None).unwrap();
+            let shared = waves
+                .iter()
+                .map(|wave| Arc::new(wave.clone()))
+                .collect::<Vec<Arc<Vec<f32>>>>();
+            let slices = shared
+                .iter()
+                .map(|wave| wave.as_slice())
+                .collect::<Vec<_>>();
+            // Alternate between shared vectors and shared slices.
+            let output = if n % 2 == 0 {
+                crate::Resampler::process(&mut resampler_shared, &slices, None).unwrap()
+            } else {
+                let shared_slices = slices
+                    .iter()
+                    .map(|slice| Arc::from(*slice))
+                    .collect::<Vec<Arc<[f32]>>>();
+                crate::Resampler::process(&mut resampler_shared, &shared_slices, None).unwrap()
+            };
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn test_max_output_frames() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.0, 4.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        assert_eq!(resampler.max_output_frames(), usize::MAX);
+        resampler.set_max_output_frames(300);
+        let waves = vec![vec![0.0; 256]; 2];
+        assert!(resampler.process(&waves, None).is_ok());
+        // A much too high ratio must give an error instead of a large allocation.
+        resampler.set_resample_ratio(3.5, false).unwrap();
+        assert!(matches!(
+            resampler.process(&waves, None),
+            Err(ResampleError::OutputExceedsLimit {
+                frames: 906,
+                limit: 300
+            })
+        ));
+        assert!(matches!(
+            resampler.process_partial(Some(&waves), None),
+            Err(ResampleError::OutputExceedsLimit { .. })
+        ));
+        resampler.set_max_output_frames(usize::MAX);
+        assert!(resampler.process(&waves, None).unwrap()[0].len() > 300);
+
+        let mut resampler =
+            FastFixedOut::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        resampler.set_max_output_frames(255);
+        let waves = vec![vec![0.0; resampler.input_frames_next()]; 2];
+        assert!(resampler.process(&waves, None).is_err());
+        resampler.set_max_output_frames(256);
+        assert!(resampler.process(&waves, None).is_ok());
+
+        // The stream helpers also respect the limit.
+        let format = SampleFormat::new(16, SampleEncoding::SignedInt, Endianness::Little);
+        let raw_in = vec![0u8; 4 * 1000];
+        resampler.set_max_output_frames(255);
+        let err = crate::Resampler::resample_stream(
+            &mut resampler,
+            std::io::Cursor::new(&raw_in),
+            Vec::new(),
+            format,
+            2,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(resampler.measured_snr(1000.0, 44100.0).is_nan());
+        resampler.set_max_output_frames(256);
+        assert!(crate::Resampler::resample_stream(
+            &mut resampler,
+            std::io::Cursor::new(&raw_in),
+            Vec::new(),
+            format,
+            2,
+        )
+        .is_ok());
+        assert!(resampler.measured_snr(1000.0, 44100.0) > 40.0);
+    }
+
+    #[test]
+    fn test_process_would_allocate() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.5, 2.0, PolynomialDegree::Cubic, 256, 2).unwrap();
+        let frames = resampler.output_frames_next();
+        let enough = make_buffer::<f64>(2, frames, false);
+        let too_small = vec![Vec::with_capacity(frames), Vec::with_capacity(frames - 1)];
+        assert!(!resampler.process_would_allocate(&enough));
+        assert!(resampler.process_would_allocate(&too_small));
+        assert!(resampler.process_would_allocate(&enough[..1]));
+        // A higher ratio gives more output frames.
+        resampler.set_resample_ratio(2.0, false).unwrap();
+        assert!(resampler.process_would_allocate(&enough));
+        let allocated = resampler.output_buffer_allocate(false);
+        assert!(!resampler.process_would_allocate(&allocated));
+    }
+
+    #[test]
+    fn test_available_cpu_features() {
+        let features = available_cpu_features();
+        assert!(features.iter().all(|feature| feature.is_detected()));
+        #[cfg(target_arch = "x86_64")]
+        {
+            // Only the very first generations of x86_64 cpus lack sse3.
+            assert_eq!(features.is_empty(), !is_x86_feature_detected!("sse3"));
+            assert_eq!(
+                features.contains(&CpuFeature::Sse3),
+                is_x86_feature_detected!("sse3")
+            );
+            assert_eq!(
+                features.contains(&CpuFeature::Avx),
+                is_x86_feature_detected!("avx")
+            );
+            assert_eq!(
+                features.contains(&CpuFeature::Fma),
+                is_x86_feature_detected!("fma")
+            );
+        }
+    }
+
+    #[test]
+    fn test_interleaving_helpers() {
+        let planar = vec![vec![1.0f64, 3.0, 5.0], vec![2.0, 4.0, 6.0]];
+        let interleaved = planar_to_interleaved(&planar);
+        assert_eq!(interleaved, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(interleaved_to_planar(&interleaved, 2).unwrap(), planar);
+        assert_eq!(
+            interleaved_to_planar(&interleaved, 3).unwrap(),
+            vec![vec![1.0, 4.0], vec![2.0, 5.0], vec![3.0, 6.0]]
+        );
+        assert!(matches!(
+            interleaved_to_planar(&interleaved, 4),
+            Err(ResampleError::InvalidInterleavedLength {
+                length: 6,
+                channels: 4
+            })
+        ));
+        assert!(interleaved_to_planar(&interleaved, 0).is_err());
+        // Extra frames in a longer channel are not included.
+        let uneven = vec![vec![1.0f32, 3.0, 5.0], vec![2.0, 4.0]];
+        assert_eq!(planar_to_interleaved(&uneven), vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
     #[test]
     fn test_buffer_helpers() {
         let buf1 = vec![vec![0.0f64; 7], vec![0.0f64; 5], vec![0.0f64; 10]];
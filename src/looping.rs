@@ -0,0 +1,164 @@
+//! Resampling of looped audio, where the loop must stay seamless after conversion.
+//!
+//! A loop of `loop_frames` input frames becomes `loop_frames * resample_ratio` output frames,
+//! which is usually not an integer. Rounding the length would move the loop point
+//! by a fraction of a frame on each repetition, giving a click at the join.
+//! Instead, the ratio is adjusted slightly so that the loop maps to an integer number of frames,
+//! see [loop_resample_ratio].
+//! The adjustment changes the pitch and duration by at most half an output frame per loop,
+//! which for a loop of 1000 output frames is a pitch change below 1 cent.
+//!
+//! The loop is then resampled as a periodic signal, see [resample_loop].
+//! The frames before the loop start are taken from the end of the loop and vice versa,
+//! so that the anti-aliasing filter sees the same signal as when the loop is played repeatedly.
+
+use num_integer as integer;
+
+use crate::error::ResamplerConstructionError;
+use crate::{buffer_length, Resampler, Sample, SincFixedIn, SincInterpolationParameters};
+
+/// Calculate the adjusted ratio for resampling a loop of `loop_frames` input frames.
+///
+/// The number of output frames is `loop_frames * resample_ratio` rounded to the nearest integer,
+/// but at least one. The returned tuple is `(adjusted_ratio, output_frames)`,
+/// where `adjusted_ratio` is `output_frames / loop_frames`.
+pub fn loop_resample_ratio(loop_frames: usize, resample_ratio: f64) -> (f64, usize) {
+    let output_frames = ((loop_frames as f64 * resample_ratio).round() as usize).max(1);
+    (output_frames as f64 / loop_frames as f64, output_frames)
+}
+
+/// Resample a loop, so that it stays seamless when played repeatedly.
+///
+/// The input `wave_in` holds the frames of the loop region for each channel.
+/// If the channels differ in length, the loop is as long as the shortest one.
+/// The ratio is adjusted with [loop_resample_ratio], and the returned loop starts at the
+/// same position as the input loop.
+/// The resampling uses a [SincFixedIn] with the given parameters.
+///
+/// An empty loop gives a [ResamplerConstructionError::InvalidLoopLength],
+/// and invalid parameters give the same errors as [SincFixedIn::new].
+pub fn resample_loop<T, V>(
+    wave_in: &[V],
+    resample_ratio: f64,
+    parameters: &SincInterpolationParameters,
+) -> Result<Vec<Vec<T>>, ResamplerConstructionError>
+where
+    T: Sample,
+    V: AsRef<[T]>,
+{
+    let loop_frames = wave_in
+        .iter()
+        .map(|chan| chan.as_ref().len())
+        .min()
+        .unwrap_or(0);
+    if loop_frames == 0 {
+        return Err(ResamplerConstructionError::InvalidLoopLength(loop_frames));
+    }
+    let (ratio, output_frames) = loop_resample_ratio(loop_frames, resample_ratio);
+    let chunk_size = 1024;
+    let mut resampler =
+        SincFixedIn::<T>::new(ratio, 1.0, parameters.clone(), chunk_size, wave_in.len())?;
+    let sinc_len = resampler.polyphase_info().1;
+
+    // Output frame `m` of a new SincFixedIn is placed at the input position `(m + 1) / ratio - 1`.
+    // The loop is preceded by `pre` frames, chosen such that `pre + 1` is a multiple of
+    // `loop_frames / gcd`. The position `pre`, where the loop starts, is then hit exactly
+    // by output frame `(pre + 1) * ratio - 1`. It also gives the filter enough history.
+    let gcd = integer::gcd(loop_frames, output_frames);
+    let step = loop_frames / gcd;
+    let repeats = (sinc_len + 1 + step - 1) / step;
+    let pre = repeats * step - 1;
+    let first_frame = repeats * (output_frames / gcd) - 1;
+    let offset = loop_frames - pre % loop_frames;
+
+    let mut chunk_in = vec![vec![T::zero(); chunk_size]; wave_in.len()];
+    let mut chunk_out = resampler.output_buffer_allocate(true);
+    let mut wave_out = vec![Vec::with_capacity(first_frame + output_frames); wave_in.len()];
+    let mut position = 0;
+    while buffer_length(&wave_out) < first_frame + output_frames {
+        for (chunk, wave) in chunk_in.iter_mut().zip(wave_in.iter()) {
+            let wave = wave.as_ref();
+            for (n, value) in chunk.iter_mut().enumerate() {
+                *value = wave[(position + n + offset) % loop_frames];
+            }
+        }
+        position += chunk_size;
+        let (_, frames) = resampler
+            .process_into_buffer(&chunk_in, &mut chunk_out, None)
+            .expect("Buffers are allocated to match the resampler");
+        for (out, chunk) in wave_out.iter_mut().zip(chunk_out.iter()) {
+            out.extend_from_slice(&chunk[..frames]);
+        }
+    }
+    for out in wave_out.iter_mut() {
+        out.drain(..first_frame);
+        out.truncate(output_frames);
+    }
+    Ok(wave_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{loop_resample_ratio, resample_loop};
+    use crate::{
+        ResamplerConstructionError, SincInterpolationParameters, SincInterpolationType,
+        WindowFunction,
+    };
+
+    fn parameters() -> SincInterpolationParameters {
+        SincInterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+        }
+    }
+
+    #[test]
+    fn adjusted_ratio() {
+        let (ratio, frames) = loop_resample_ratio(1000, 48000.0 / 44100.0);
+        assert_eq!(frames, 1088);
+        assert_eq!(ratio, 1.088);
+        assert_eq!(loop_resample_ratio(3, 0.1), (1.0 / 3.0, 1));
+    }
+
+    #[test]
+    fn seamless_loop() {
+        // A loop holding exactly 7 periods of a sine.
+        for (loop_frames, ratio) in [(1000, 48000.0 / 44100.0), (2205, 0.5), (997, 1.7)] {
+            let periods = 7.0;
+            let omega = 2.0 * std::f64::consts::PI * periods;
+            let wave = (0..loop_frames)
+                .map(|n| (omega * n as f64 / loop_frames as f64).sin())
+                .collect::<Vec<_>>();
+            let output = resample_loop(&[&wave], ratio, &parameters()).unwrap();
+            let (_, frames) = loop_resample_ratio(loop_frames, ratio);
+            assert_eq!(output[0].len(), frames);
+            // The output must be the same sine, starting at the loop start.
+            for (n, value) in output[0].iter().enumerate() {
+                let expected = (omega * n as f64 / frames as f64).sin();
+                assert!(
+                    (value - expected).abs() < 1.0e-3,
+                    "{} {} {}",
+                    n,
+                    value,
+                    expected
+                );
+            }
+            // The step across the join is like any other step.
+            let join = output[0][0] - output[0][frames - 1];
+            let max_step = 2.0 * (omega / frames as f64 / 2.0).sin();
+            assert!(join.abs() <= max_step + 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn empty_loop() {
+        let empty: Vec<Vec<f64>> = vec![Vec::new(); 2];
+        assert!(matches!(
+            resample_loop(&empty, 1.5, &parameters()),
+            Err(ResamplerConstructionError::InvalidLoopLength(0))
+        ));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::asynchro_sinc::{interp_cubic, interp_lin, interp_quad};
+use crate::interpolation::*;
+use crate::sinc_interpolator::SincInterpolator;
+use crate::{Sample, SincInterpolationType};
+
+/// The number of channels that are processed together in the scalar products.
+const BLOCK: usize = 16;
+
+/// The delay lines of all channels of a sinc resampler, stored in a single buffer
+/// with the samples of each frame next to each other,
+/// see [SincFixedIn::set_interleaved_buffer](crate::SincFixedIn::set_interleaved_buffer).
+///
+/// The scalar products are calculated for all channels at once, with the channels in the inner loop.
+/// This gives a contiguous memory access pattern that vectorizes well for many channels.
+pub(crate) struct InterleavedBuffer<T> {
+    /// The samples, stored frame by frame with `channels` samples per frame.
+    data: Vec<T>,
+    channels: usize,
+    /// The active taps of each sinc.
+    sincs: Vec<Vec<T>>,
+    /// The number of taps skipped at the start of each sinc.
+    skip: usize,
+    /// The intermediate points of all channels, stored point by point.
+    points: Vec<T>,
+    /// A unit impulse, for reading the sincs out of the interpolator.
+    impulse: Vec<T>,
+    /// The last calculated output frame.
+    output: Vec<T>,
+}
+
+impl<T> InterleavedBuffer<T>
+where
+    T: Sample,
+{
+    /// Create a new buffer holding a copy of the per-channel buffers.
+    /// The sincs are read out of the interpolator.
+    pub(crate) fn new(interpolator: &dyn SincInterpolator<T>, buffers: &[Vec<T>]) -> Self {
+        let channels = buffers.len();
+        let mut interleaved = InterleavedBuffer {
+            data: vec![T::zero(); channels * buffers[0].len()],
+            channels,
+            sincs: vec![Vec::with_capacity(interpolator.len()); interpolator.nbr_sincs()],
+            skip: 0,
+            points: vec![T::zero(); 4 * channels],
+            impulse: vec![T::zero(); 2 * interpolator.len() + 1],
+            output: vec![T::zero(); channels],
+        };
+        interleaved.impulse[interpolator.len()] = T::one();
+        interleaved.update_sincs(interpolator);
+        for (chan, buffer) in buffers.iter().enumerate() {
+            interleaved.store(chan, 0, buffer);
+        }
+        interleaved
+    }
+
+    /// Read the active taps of the sincs out of the interpolator.
+    ///
+    /// Each tap is obtained as the scalar product of a sinc with a unit impulse.
+    /// This works for any interpolator, and doesn't allocate.
+    pub(crate) fn update_sincs(&mut self, interpolator: &dyn SincInterpolator<T>) {
+        let sinc_len = interpolator.len();
+        let active_len = interpolator.active_len();
+        self.skip = (sinc_len - active_len) / 2;
+        // Interpolating at index `sinc_len - n` picks out tap `n`.
+        for (subindex, sinc) in self.sincs.iter_mut().enumerate() {
+            sinc.clear();
+            sinc.extend((self.skip..self.skip + active_len).map(|n| {
+                interpolator.get_sinc_interpolated(&self.impulse, sinc_len - n, subindex)
+            }));
+        }
+    }
+
+    /// Copy the values of one channel into the buffer, starting at frame `start`.
+    pub(crate) fn store(&mut self, chan: usize, start: usize, values: &[T]) {
+        for (frame, value) in self.data[start * self.channels..]
+            .chunks_exact_mut(self.channels)
+            .zip(values.iter())
+        {
+            frame[chan] = *value;
+        }
+    }
+
+    /// Copy the first `frames` frames of the active channels into the buffer.
+    /// This is done once per chunk, after the per-channel buffers have been updated.
+    pub(crate) fn load(&mut self, buffers: &[Vec<T>], channel_mask: &[bool], frames: usize) {
+        for (chan, (buffer, active)) in buffers.iter().zip(channel_mask.iter()).enumerate() {
+            if *active {
+                self.store(chan, 0, &buffer[..frames]);
+            }
+        }
+    }
+
+    /// Clear the buffer.
+    pub(crate) fn clear(&mut self) {
+        self.data.iter_mut().for_each(|v| *v = T::zero());
+    }
+
+    /// Calculate the scalar products of one sinc and all channels, and store them as intermediate point `point`.
+    ///
+    /// The channels are processed in blocks of [BLOCK] channels, with the sums of a block
+    /// kept in a local array while stepping through the taps, so that they can stay in registers.
+    fn scalar_products(&mut self, point: usize, index: usize, subindex: usize) {
+        let channels = self.channels;
+        let sinc = &self.sincs[subindex];
+        let start = (index + self.skip) * channels;
+        let data = &self.data[start..start + sinc.len() * channels];
+        let sums = &mut self.points[point * channels..(point + 1) * channels];
+        let mut first = 0;
+        while first < channels {
+            let width = BLOCK.min(channels - first);
+            let mut block = [T::zero(); BLOCK];
+            if width == BLOCK {
+                for (frame, coeff) in data.chunks_exact(channels).zip(sinc.iter()) {
+                    let values = &frame[first..first + BLOCK];
+                    for (sum, value) in block.iter_mut().zip(values.iter()) {
+                        *sum += *coeff * *value;
+                    }
+                }
+            } else {
+                for (frame, coeff) in data.chunks_exact(channels).zip(sinc.iter()) {
+                    let values = &frame[first..first + width];
+                    for (sum, value) in block.iter_mut().zip(values.iter()) {
+                        *sum += *coeff * *value;
+                    }
+                }
+            }
+            sums[first..first + width].copy_from_slice(&block[..width]);
+            first += width;
+        }
+    }
+
+    /// Calculate one output frame for all channels at the time `idx`,
+    /// relative to the start of the new data, and return it.
+    /// The steps are the same as in the processing loops of the resamplers.
+    pub(crate) fn interpolate(
+        &mut self,
+        interpolation: &SincInterpolationType,
+        sinc_len: usize,
+        idx: f64,
+    ) -> &[T] {
+        let oversampling_factor = self.sincs.len();
+        let frac = idx * oversampling_factor as f64 - (idx * oversampling_factor as f64).floor();
+        let frac_offset = T::coerce(frac);
+        let offset = 2 * sinc_len as isize;
+        let channels = self.channels;
+        match interpolation {
+            SincInterpolationType::Cubic => {
+                let mut nearest = [(0isize, 0isize); 4];
+                get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
+                for (point, n) in nearest.iter().enumerate() {
+                    self.scalar_products(point, (n.0 + offset) as usize, n.1 as usize);
+                }
+                for (chan, out) in self.output.iter_mut().enumerate() {
+                    let points = [
+                        self.points[chan],
+                        self.points[channels + chan],
+                        self.points[2 * channels + chan],
+                        self.points[3 * channels + chan],
+                    ];
+                    *out = interp_cubic(frac_offset, &points);
+                }
+            }
+            SincInterpolationType::Quadratic => {
+                let mut nearest = [(0isize, 0isize); 3];
+                get_nearest_times_3(idx, oversampling_factor as isize, &mut nearest);
+                for (point, n) in nearest.iter().enumerate() {
+                    self.scalar_products(point, (n.0 + offset) as usize, n.1 as usize);
+                }
+                for (chan, out) in self.output.iter_mut().enumerate() {
+                    let points = [
+                        self.points[chan],
+                        self.points[channels + chan],
+                        self.points[2 * channels + chan],
+                    ];
+                    *out = interp_quad(frac_offset, &points);
+                }
+            }
+            SincInterpolationType::Linear => {
+                let mut nearest = [(0isize, 0isize); 2];
+                get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
+                for (point, n) in nearest.iter().enumerate() {
+                    self.scalar_products(point, (n.0 + offset) as usize, n.1 as usize);
+                }
+                for (chan, out) in self.output.iter_mut().enumerate() {
+                    let points = [self.points[chan], self.points[channels + chan]];
+                    *out = interp_lin(frac_offset, &points);
+                }
+            }
+            SincInterpolationType::Nearest => {
+                let nearest = get_nearest_time(idx, oversampling_factor as isize);
+                self.scalar_products(0, (nearest.0 + offset) as usize, nearest.1 as usize);
+                self.output.copy_from_slice(&self.points[..channels]);
+            }
+            SincInterpolationType::Adaptive { .. } => {
+                unreachable!("The adaptive interpolation processes the channels separately")
+            }
+        }
+        &self.output
+    }
+}
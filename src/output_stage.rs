@@ -0,0 +1,435 @@
+use crate::error::ResampleResult;
+use crate::gain::{ChannelGains, ClipDetector, OutputChecksum, OutputPadding, OutputPreview};
+use crate::{LoudnessStats, Sample};
+
+/// The processing that the sinc resamplers apply to their output after the interpolation.
+///
+/// Both [SincFixedIn](crate::SincFixedIn) and [SincFixedOut](crate::SincFixedOut) own an output stage,
+/// that is reached with their `output_stage` and `output_stage_mut` methods.
+/// The settings are kept by [reset](crate::Resampler::reset).
+#[derive(Debug)]
+pub struct OutputStage<T> {
+    nbr_channels: usize,
+    max_frames: usize,
+    max_channels: usize,
+    channel_gains: ChannelGains,
+    clip_detector: ClipDetector,
+    checksum: OutputChecksum,
+    preview: OutputPreview<T>,
+    padding: OutputPadding,
+    input_indices: Option<Vec<f64>>,
+    error_estimates: Option<Vec<Vec<T>>>,
+    points_per_frame: usize,
+    intermediate_points: Option<Vec<Vec<T>>>,
+    intermediate_positions: Vec<f64>,
+    loudness: Option<LoudnessStats>,
+}
+
+impl<T: Sample> OutputStage<T> {
+    /// Create a new output stage, for chunks of at most `max_frames` frames before padding,
+    /// and an interpolation that combines `points_per_frame` intermediate points.
+    pub(crate) fn new(nbr_channels: usize, max_frames: usize, points_per_frame: usize) -> Self {
+        OutputStage {
+            nbr_channels,
+            max_frames,
+            max_channels: nbr_channels,
+            channel_gains: ChannelGains::new(nbr_channels),
+            clip_detector: ClipDetector::new(),
+            checksum: OutputChecksum::new(),
+            preview: OutputPreview::new(),
+            padding: OutputPadding::new(),
+            input_indices: None,
+            error_estimates: None,
+            points_per_frame,
+            intermediate_points: None,
+            intermediate_positions: Vec::new(),
+            loudness: None,
+        }
+    }
+
+    /// Set a gain for each channel, applied to the output.
+    ///
+    /// This is intended for simple mixing, and saves a separate pass over the output.
+    /// The gains are linear factors, and there must be one gain for each of the current
+    /// number of channels, otherwise a [ResampleError::WrongNumberOfGains](crate::ResampleError::WrongNumberOfGains)
+    /// is returned.
+    /// The gains are applied after the RMS matching, see [SincFixedIn::set_match_rms](crate::SincFixedIn::set_match_rms),
+    /// so that the matching doesn't undo them.
+    /// Channels that are added with [SincFixedIn::set_nbr_channels](crate::SincFixedIn::set_nbr_channels)
+    /// keep any previously set gain, or unity gain.
+    /// The gains are 1.0 by default, and are not changed by [reset](crate::Resampler::reset).
+    pub fn set_channel_gains(&mut self, gains: &[f64]) -> ResampleResult<()> {
+        self.channel_gains.set(gains, self.nbr_channels)
+    }
+
+    /// Enable or disable the detection of clipping in the output.
+    ///
+    /// When enabled, each processed chunk is scanned for output samples
+    /// with a magnitude above 1.0, after the [channel gains](OutputStage::set_channel_gains) have been applied.
+    /// This is intended for quality control, for example to find inter-sample peaks
+    /// that appear when upsampling a signal that is close to full scale.
+    /// The positions are available from [last_clips](OutputStage::last_clips) until the next chunk.
+    /// At most `max_clips` positions are recorded per chunk, and further clips are not reported.
+    /// A `max_clips` of zero disables the detection, which is the default.
+    /// Enabling allocates space for the positions, and should not be done from a realtime thread.
+    pub fn set_clip_detection(&mut self, max_clips: usize) {
+        self.clip_detector.set_max_clips(max_clips);
+    }
+
+    /// Get the positions of the clipped samples in the output of the last processed chunk,
+    /// as `(channel, frame)` tuples, see [set_clip_detection](OutputStage::set_clip_detection).
+    ///
+    /// The positions are ordered by channel, and then by frame.
+    /// The frame is the index in the output of the chunk.
+    /// The list is empty when no samples clipped, or when the detection is disabled.
+    pub fn last_clips(&self) -> &[(usize, usize)] {
+        self.clip_detector.clips()
+    }
+
+    /// Enable or disable a running checksum of the output.
+    ///
+    /// When enabled, each processed chunk updates a 64-bit FNV-1a hash of the output,
+    /// after the [channel gains](OutputStage::set_channel_gains) have been applied.
+    /// The samples of the active channels are hashed frame by frame, as 64-bit floats,
+    /// so that the checksum doesn't depend on how the output is divided into chunks.
+    /// This is a cheap way to check that a resampled stream was transferred correctly,
+    /// by comparing with a checksum calculated in the same way for the received samples.
+    /// It does not protect against deliberate tampering.
+    /// Enabling or disabling resets the checksum. The checksum is disabled by default.
+    pub fn set_output_checksum(&mut self, enabled: bool) {
+        self.checksum.set_enabled(enabled);
+    }
+
+    /// Get the checksum of the output produced since the checksum was last reset,
+    /// see [set_output_checksum](OutputStage::set_output_checksum).
+    pub fn output_checksum(&self) -> u64 {
+        self.checksum.value()
+    }
+
+    /// Reset the output checksum, for example at the start of a new transfer.
+    /// The checksum is also reset by [reset](crate::Resampler::reset).
+    pub fn reset_output_checksum(&mut self) {
+        self.checksum.reset();
+    }
+
+    /// Produce a decimated preview of the output, for example for drawing a waveform,
+    /// with one preview frame for every `factor` output frames.
+    /// A factor of 0 disables the preview, which is the default.
+    ///
+    /// The preview is made from the output as it is returned, after the rest of the output stage,
+    /// and is available from [last_preview](OutputStage::last_preview).
+    /// Note that enabling allocates space for the preview of
+    /// [output_frames_max](crate::Resampler::output_frames_max) frames for each channel.
+    pub fn set_preview_decimation(&mut self, factor: usize) {
+        self.preview
+            .set_factor(factor, self.max_channels, self.max_frames);
+    }
+
+    /// Get the preview frames completed by the last processed chunk,
+    /// see [set_preview_decimation](OutputStage::set_preview_decimation).
+    ///
+    /// The preview is given as one vector per channel, with a pair of values per preview frame,
+    /// the smallest and the largest sample of the window of `factor` output frames.
+    /// The windows continue across chunks, and a window is reported by the chunk that completes it.
+    /// This means that a chunk can give more or fewer preview frames than its number of output frames
+    /// divided by the factor, and that the last incomplete window is not reported until it is filled.
+    /// Inactive channels get empty vectors, and their current window is dropped.
+    /// Returns an empty slice when the preview is disabled.
+    pub fn last_preview(&self) -> &[Vec<T>] {
+        self.preview.preview()
+    }
+
+    /// Pad the output of each chunk with zeros, up to the next multiple of `multiple` frames.
+    ///
+    /// This is intended for processing the output with kernels that work on blocks of
+    /// a fixed size, such as a SIMD width, without a separate loop for the remainder.
+    /// The returned number of output frames, and the length of the vectors returned by
+    /// [process](crate::Resampler::process), then include the padding,
+    /// while the number of frames of actual output is given by
+    /// [valid_output_frames](OutputStage::valid_output_frames).
+    /// The padding is added after the rest of the output stage, and is not included by the checksum
+    /// or the clip detection. Since the zeros are not part of the signal, the padded chunks
+    /// can't be joined to a continuous stream without first removing them.
+    /// [output_frames_next](crate::Resampler::output_frames_next) and
+    /// [output_frames_max](crate::Resampler::output_frames_max) are increased to make room for the padding.
+    /// A multiple of 0 or 1 disables the padding, which is the default.
+    pub fn pad_output_to(&mut self, multiple: usize) {
+        self.padding.set_multiple(multiple);
+    }
+
+    /// Get the number of frames of actual output, before padding, from the last processed chunk,
+    /// see [pad_output_to](OutputStage::pad_output_to).
+    pub fn valid_output_frames(&self) -> usize {
+        self.padding.valid_frames()
+    }
+
+    /// Enable or disable recording of the input position of each output frame,
+    /// see [last_input_indices](OutputStage::last_input_indices).
+    /// This is intended for visualizations and teaching tools.
+    /// Recording is disabled by default.
+    /// Note that enabling allocates space for the positions of [output_frames_max](crate::Resampler::output_frames_max) frames.
+    pub fn set_record_input_indices(&mut self, enable: bool) {
+        self.input_indices = if enable {
+            Some(Vec::with_capacity(self.max_frames))
+        } else {
+            None
+        };
+    }
+
+    /// Get the input position of each output frame of the last processed chunk,
+    /// when enabled with [set_record_input_indices](OutputStage::set_record_input_indices).
+    ///
+    /// The positions are fractional input frames, counted from the first frame
+    /// of the input chunk of the last call. This is the position where the center of the
+    /// sinc filter was placed when calculating the output frame.
+    /// Positions before the start of the chunk, in the input of earlier calls, are negative.
+    /// The positions advance by `1 / resample_ratio` for each output frame,
+    /// and the step changes gradually while the ratio is ramped.
+    /// Returns an empty slice when recording is disabled, and before the first chunk.
+    pub fn last_input_indices(&self) -> &[f64] {
+        self.input_indices.as_deref().unwrap_or(&[])
+    }
+
+    /// Enable or disable recording of an estimate of the interpolation error of each output sample,
+    /// see [last_error_estimates](OutputStage::last_error_estimates).
+    /// This is intended for research into the quality of the interpolation,
+    /// and roughly doubles the processing time. Recording is disabled by default.
+    /// Note that enabling allocates space for the estimates of
+    /// [output_frames_max](crate::Resampler::output_frames_max) frames for each channel.
+    pub fn set_record_error_estimates(&mut self, enable: bool) {
+        self.error_estimates = if enable {
+            Some(vec![Vec::with_capacity(self.max_frames); self.max_channels])
+        } else {
+            None
+        };
+    }
+
+    /// Get an estimate of the interpolation error of each output sample of the last processed chunk,
+    /// when enabled with [set_record_error_estimates](OutputStage::set_record_error_estimates).
+    ///
+    /// The estimates are given as one vector per channel, parallel to the output,
+    /// with one value per output frame. Inactive channels get empty vectors.
+    /// Each output sample is interpolated between the nearest points of the oversampled signal,
+    /// the scalar products with the sincs, using the chosen [SincInterpolationType](crate::SincInterpolationType).
+    /// The estimate is the output sample minus a reference value obtained by fifth order
+    /// Lagrange interpolation between the six nearest points. Since the reference is much more
+    /// accurate than the interpolation types in use, the difference is a good estimate
+    /// of the error of the chosen interpolation.
+    /// It does not include the errors of the sinc filter itself, from its limited length and window,
+    /// which are common to the output and the reference.
+    /// The estimates are taken before the output processing, and don't include output emphasis,
+    /// RMS matching or channel gains. The configured interpolation type is used,
+    /// also for chunks that were degraded to meet a [time budget](crate::SincFixedIn::set_time_budget).
+    /// Returns an empty slice when recording is disabled.
+    pub fn last_error_estimates(&self) -> &[Vec<T>] {
+        self.error_estimates.as_deref().unwrap_or(&[])
+    }
+
+    /// Enable or disable recording of the intermediate points that are combined
+    /// into each output sample, see [last_intermediate_points](OutputStage::last_intermediate_points).
+    /// This is intended for experiments with other ways of combining the points,
+    /// for example a learned post-filter. Recording is disabled by default.
+    /// Note that enabling allocates space for the points of
+    /// [output_frames_max](crate::Resampler::output_frames_max) frames for each channel.
+    pub fn set_record_intermediate_points(&mut self, enable: bool) {
+        self.intermediate_positions = Vec::new();
+        self.intermediate_points = if enable {
+            self.intermediate_positions = Vec::with_capacity(self.max_frames);
+            Some(vec![
+                Vec::with_capacity(
+                    self.points_per_frame * self.max_frames
+                );
+                self.max_channels
+            ])
+        } else {
+            None
+        };
+    }
+
+    /// Get the intermediate points that were combined into each output sample of the last processed chunk,
+    /// when enabled with [set_record_intermediate_points](OutputStage::set_record_intermediate_points).
+    ///
+    /// The intermediate points are the values of the oversampled signal, the scalar products
+    /// of the input with the sincs, nearest to the position of the output sample.
+    /// The points are given as one vector per channel, with
+    /// [intermediate_points_per_frame](OutputStage::intermediate_points_per_frame) values per output frame,
+    /// stored one frame after the other. Inactive channels get empty vectors.
+    /// Within a frame, the points are ordered by time and spaced by `1 / oversampling_factor`
+    /// input frames. With a fractional position `x` between 0 and 1, the output sample is:
+    /// - [SincInterpolationType::Cubic](crate::SincInterpolationType::Cubic): the cubic through the points at -1, 0, 1 and 2, evaluated at `x`.
+    /// - [SincInterpolationType::Quadratic](crate::SincInterpolationType::Quadratic): the quadratic through the points at 0, 1 and 2, evaluated at `x`.
+    /// - [SincInterpolationType::Linear](crate::SincInterpolationType::Linear): the points at 0 and 1, weighted by `1 - x` and `x`.
+    /// - [SincInterpolationType::Nearest](crate::SincInterpolationType::Nearest): the single nearest point.
+    /// - [SincInterpolationType::Adaptive](crate::SincInterpolationType::Adaptive): the same four points as for cubic, where linear interpolation
+    ///   uses the middle two.
+    ///
+    /// The fractional positions are given by
+    /// [last_intermediate_positions](OutputStage::last_intermediate_positions).
+    /// The points are taken before the output processing, and don't include output emphasis,
+    /// RMS matching, channel gains or automatic scaling.
+    /// Returns an empty slice when recording is disabled.
+    pub fn last_intermediate_points(&self) -> &[Vec<T>] {
+        self.intermediate_points.as_deref().unwrap_or(&[])
+    }
+
+    /// Get the fractional position of each output frame of the last processed chunk,
+    /// relative to its intermediate points, see [last_intermediate_points](OutputStage::last_intermediate_points).
+    ///
+    /// This is the fractional part of the input position, counted in steps of `1 / oversampling_factor`
+    /// input frames, and is between 0 and 1. The value is calculated in the same way as when processing.
+    /// Computing it from [last_input_indices](OutputStage::last_input_indices) instead may round differently
+    /// when an output frame falls very close to an intermediate point.
+    /// Returns an empty slice when recording is disabled.
+    pub fn last_intermediate_positions(&self) -> &[f64] {
+        &self.intermediate_positions
+    }
+
+    /// Get the number of intermediate points per output frame,
+    /// see [last_intermediate_points](OutputStage::last_intermediate_points).
+    pub fn intermediate_points_per_frame(&self) -> usize {
+        self.points_per_frame
+    }
+
+    /// Enable or disable measuring the levels of the input of each processed chunk,
+    /// see [last_loudness](OutputStage::last_loudness).
+    /// This is disabled by default.
+    pub fn set_loudness_metering(&mut self, enable: bool) {
+        self.loudness = if enable {
+            Some(LoudnessStats::default())
+        } else {
+            None
+        };
+    }
+
+    /// Get the levels of the input block of the last processed chunk,
+    /// when enabled with [set_loudness_metering](OutputStage::set_loudness_metering).
+    ///
+    /// The block has the same length as the input chunk,
+    /// but ends `sinc_len / 2` frames before the end of it, since the oversampled signal
+    /// can only be calculated where the whole sinc is covered by the buffered input.
+    /// For [SincFixedOut](crate::SincFixedOut), the length of the block is the number
+    /// of input frames used by the chunk.
+    /// The blocks of consecutive chunks follow each other without gaps or overlaps.
+    /// The RMS level and the sample peak are measured on the input samples.
+    /// The true peak is the largest magnitude of the input oversampled by a factor four,
+    /// calculated with the sincs of the resampler.
+    /// When downsampling, the sincs also remove the frequencies above the output Nyquist frequency,
+    /// so the true peak is that of the signal within the output bandwidth.
+    /// The input is measured after any [input emphasis](crate::SincFixedIn::set_emphasis).
+    /// The oversampling calculates three extra scalar products per input frame and channel,
+    /// which is similar to the cost of resampling with a ratio of three.
+    /// Returns zero levels for zero frames when metering is disabled, and before the first chunk.
+    pub fn last_loudness(&self) -> LoudnessStats {
+        self.loudness.unwrap_or_default()
+    }
+
+    /// Get the largest number of output frames of a chunk, before padding.
+    pub(crate) fn max_frames(&self) -> usize {
+        self.max_frames
+    }
+
+    /// Get the largest number of frames that the padding may add to a chunk.
+    pub(crate) fn max_padding(&self) -> usize {
+        self.padding.max_padding()
+    }
+
+    /// Get the input positions for recording, when enabled.
+    pub(crate) fn input_indices_mut(&mut self) -> Option<&mut Vec<f64>> {
+        self.input_indices.as_mut()
+    }
+
+    /// Get the error estimates for recording, when enabled.
+    pub(crate) fn error_estimates_mut(&mut self) -> Option<&mut Vec<Vec<T>>> {
+        self.error_estimates.as_mut()
+    }
+
+    /// Get the intermediate points and their positions for recording, when enabled.
+    pub(crate) fn intermediate_points_mut(&mut self) -> Option<(&mut Vec<Vec<T>>, &mut Vec<f64>)> {
+        self.intermediate_points
+            .as_mut()
+            .map(|points| (points, &mut self.intermediate_positions))
+    }
+
+    /// Get the levels for measuring, when enabled.
+    pub(crate) fn loudness_mut(&mut self) -> Option<&mut LoudnessStats> {
+        self.loudness.as_mut()
+    }
+
+    /// Update the number of channels, after it was changed for the resampler.
+    pub(crate) fn set_nbr_channels(&mut self, nbr_channels: usize) {
+        self.nbr_channels = nbr_channels;
+    }
+
+    /// Update the number of intermediate points per output frame,
+    /// after the interpolation type was changed.
+    pub(crate) fn set_points_per_frame(&mut self, points_per_frame: usize) {
+        self.points_per_frame = points_per_frame;
+    }
+
+    /// Make room for up to `max_channels` channels.
+    pub(crate) fn set_max_channels(&mut self, max_channels: usize) {
+        self.max_channels = self.max_channels.max(max_channels);
+        self.channel_gains.set_max_channels(max_channels);
+        self.preview.set_max_channels(self.max_channels);
+        if let Some(estimates) = self.error_estimates.as_mut() {
+            let capacity = estimates[0].capacity();
+            estimates.resize_with(self.max_channels, || Vec::with_capacity(capacity));
+        }
+        if let Some(points) = self.intermediate_points.as_mut() {
+            let capacity = points[0].capacity();
+            points.resize_with(self.max_channels, || Vec::with_capacity(capacity));
+        }
+    }
+
+    /// Apply the channel gains to the first `frames` frames of the active output channels.
+    pub(crate) fn apply_gains<Vout: AsMut<[T]>>(
+        &self,
+        wave_out: &mut [Vout],
+        frames: usize,
+        mask: &[bool],
+    ) {
+        self.channel_gains.apply(wave_out, frames, mask);
+    }
+
+    /// Process the first `frames` frames of the active output channels of a chunk,
+    /// update the results, and return the number of frames including the padding.
+    pub(crate) fn process<Vout: AsMut<[T]>>(
+        &mut self,
+        wave_out: &mut [Vout],
+        frames: usize,
+        mask: &[bool],
+    ) -> usize {
+        self.channel_gains.apply(wave_out, frames, mask);
+        self.clip_detector.detect(wave_out, frames, mask);
+        self.checksum.update(wave_out, frames, mask);
+        self.preview.update(wave_out, frames, mask);
+        self.padding.apply(wave_out, frames, mask)
+    }
+
+    /// Clear the recordings of the current stream, for `reset_state_only`.
+    pub(crate) fn reset_state(&mut self) {
+        self.preview.reset();
+        self.padding.reset();
+        if let Some(indices) = self.input_indices.as_mut() {
+            indices.clear();
+        }
+        if let Some(estimates) = self.error_estimates.as_mut() {
+            estimates.iter_mut().for_each(|estimate| estimate.clear());
+        }
+        if let Some(points) = self.intermediate_points.as_mut() {
+            points
+                .iter_mut()
+                .for_each(|chan_points| chan_points.clear());
+        }
+        self.intermediate_positions.clear();
+    }
+
+    /// Clear the results, for [reset](crate::Resampler::reset).
+    pub(crate) fn reset(&mut self) {
+        self.clip_detector.reset();
+        self.checksum.reset();
+        if let Some(loudness) = self.loudness.as_mut() {
+            *loudness = LoudnessStats::default();
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::error::{ResampleError, ResampleResult};
+
+/// The byte order of raw samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    /// Least significant byte first, as in WAV files.
+    Little,
+    /// Most significant byte first, as in AIFF files.
+    Big,
+}
+
+/// The encoding of raw samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleEncoding {
+    /// Signed integers in two's complement.
+    SignedInt,
+    /// Unsigned integers, where silence is at the middle of the range.
+    UnsignedInt,
+    /// IEEE 754 floating point values.
+    Float,
+}
+
+/// The size of raw float samples, see [Resampler::process_into_float_bytes](crate::Resampler::process_into_float_bytes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatBits {
+    /// 32-bit floats.
+    F32,
+    /// 64-bit floats.
+    F64,
+}
+
+impl FloatBits {
+    /// Get the [SampleFormat] for floats of this size with the given byte order.
+    pub fn format(&self, endianness: Endianness) -> SampleFormat {
+        let bits = match self {
+            FloatBits::F32 => 32,
+            FloatBits::F64 => 64,
+        };
+        SampleFormat::new(bits, SampleEncoding::Float, endianness)
+    }
+}
+
+/// The format of raw interleaved samples, see [Resampler::process_from_bytes](crate::Resampler::process_from_bytes)
+/// and [Resampler::process_into_bytes](crate::Resampler::process_into_bytes).
+///
+/// The supported formats are integers of 8, 16, 24 or 32 bits, and floats of 32 or 64 bits.
+/// Integers are scaled so that the full range maps to -1.0 to +1.0,
+/// meaning that 16-bit samples are divided by 32768.
+/// Samples of 24 bits are packed into three bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleFormat {
+    /// The number of bits per sample.
+    pub bits: usize,
+    /// The encoding of the samples.
+    pub encoding: SampleEncoding,
+    /// The byte order of the samples.
+    pub endianness: Endianness,
+}
+
+impl SampleFormat {
+    /// Create a new SampleFormat.
+    pub fn new(bits: usize, encoding: SampleEncoding, endianness: Endianness) -> Self {
+        SampleFormat {
+            bits,
+            encoding,
+            endianness,
+        }
+    }
+
+    /// Get the number of bytes used by each sample.
+    pub fn bytes_per_sample(&self) -> usize {
+        self.bits / 8
+    }
+
+    /// Check that the combination of encoding and number of bits is supported.
+    pub(crate) fn validate(&self) -> ResampleResult<()> {
+        let supported = match self.encoding {
+            SampleEncoding::SignedInt | SampleEncoding::UnsignedInt => {
+                matches!(self.bits, 8 | 16 | 24 | 32)
+            }
+            SampleEncoding::Float => matches!(self.bits, 32 | 64),
+        };
+        if supported {
+            Ok(())
+        } else {
+            Err(ResampleError::UnsupportedSampleFormat(*self))
+        }
+    }
+
+    /// Decode one sample from its bytes.
+    /// The format must have been checked with [validate](SampleFormat::validate).
+    pub(crate) fn decode(&self, bytes: &[u8]) -> f64 {
+        let mut raw: u64 = 0;
+        match self.endianness {
+            Endianness::Little => {
+                for byte in bytes.iter().rev() {
+                    raw = (raw << 8) | *byte as u64;
+                }
+            }
+            Endianness::Big => {
+                for byte in bytes.iter() {
+                    raw = (raw << 8) | *byte as u64;
+                }
+            }
+        }
+        let scale = (1u64 << (self.bits - 1)) as f64;
+        match self.encoding {
+            SampleEncoding::SignedInt => {
+                let shift = 64 - self.bits;
+                (((raw << shift) as i64) >> shift) as f64 / scale
+            }
+            SampleEncoding::UnsignedInt => (raw as f64 - scale) / scale,
+            SampleEncoding::Float => {
+                if self.bits == 32 {
+                    f32::from_bits(raw as u32) as f64
+                } else {
+                    f64::from_bits(raw)
+                }
+            }
+        }
+    }
+
+    /// Encode one sample into `bytes`, which must hold [bytes_per_sample](SampleFormat::bytes_per_sample) bytes.
+    /// Integer samples are rounded to the nearest value, and clipped to the range of the format.
+    /// The format must have been checked with [validate](SampleFormat::validate).
+    pub(crate) fn encode(&self, value: f64, bytes: &mut [u8]) {
+        let scale = (1u64 << (self.bits - 1)) as f64;
+        let clipped = || (value * scale).round().max(-scale).min(scale - 1.0) as i64;
+        let raw = match self.encoding {
+            SampleEncoding::SignedInt => clipped() as u64,
+            SampleEncoding::UnsignedInt => (clipped() + scale as i64) as u64,
+            SampleEncoding::Float => {
+                if self.bits == 32 {
+                    (value as f32).to_bits() as u64
+                } else {
+                    value.to_bits()
+                }
+            }
+        };
+        match self.endianness {
+            Endianness::Little => {
+                for (n, byte) in bytes.iter_mut().enumerate() {
+                    *byte = (raw >> (8 * n)) as u8;
+                }
+            }
+            Endianness::Big => {
+                for (n, byte) in bytes.iter_mut().rev().enumerate() {
+                    *byte = (raw >> (8 * n)) as u8;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Endianness, SampleEncoding, SampleFormat};
+
+    #[test]
+    fn decode_formats() {
+        let format = SampleFormat::new(16, SampleEncoding::SignedInt, Endianness::Little);
+        assert_eq!(format.decode(&(-16384i16).to_le_bytes()), -0.5);
+        assert_eq!(format.decode(&i16::MIN.to_le_bytes()), -1.0);
+        let format = SampleFormat::new(24, SampleEncoding::SignedInt, Endianness::Big);
+        assert_eq!(format.decode(&[0x40, 0x00, 0x00]), 0.5);
+        assert_eq!(format.decode(&[0xc0, 0x00, 0x00]), -0.5);
+        let format = SampleFormat::new(8, SampleEncoding::UnsignedInt, Endianness::Little);
+        assert_eq!(format.decode(&[128]), 0.0);
+        assert_eq!(format.decode(&[0]), -1.0);
+        let format = SampleFormat::new(32, SampleEncoding::Float, Endianness::Big);
+        assert_eq!(format.decode(&0.25f32.to_be_bytes()), 0.25);
+        let format = SampleFormat::new(64, SampleEncoding::Float, Endianness::Little);
+        assert_eq!(format.decode(&(-0.75f64).to_le_bytes()), -0.75);
+    }
+
+    #[test]
+    fn encode_formats() {
+        let mut bytes = [0u8; 8];
+        let format = SampleFormat::new(16, SampleEncoding::SignedInt, Endianness::Little);
+        format.encode(-0.5, &mut bytes[..2]);
+        assert_eq!(bytes[..2], (-16384i16).to_le_bytes());
+        format.encode(1.5, &mut bytes[..2]);
+        assert_eq!(bytes[..2], i16::MAX.to_le_bytes());
+        let format = SampleFormat::new(24, SampleEncoding::SignedInt, Endianness::Big);
+        format.encode(-0.5, &mut bytes[..3]);
+        assert_eq!(bytes[..3], [0xc0, 0x00, 0x00]);
+        let format = SampleFormat::new(8, SampleEncoding::UnsignedInt, Endianness::Little);
+        format.encode(-1.0, &mut bytes[..1]);
+        assert_eq!(bytes[0], 0);
+        format.encode(0.0, &mut bytes[..1]);
+        assert_eq!(bytes[0], 128);
+        let format = SampleFormat::new(32, SampleEncoding::Float, Endianness::Big);
+        format.encode(0.25, &mut bytes[..4]);
+        assert_eq!(bytes[..4], 0.25f32.to_be_bytes());
+
+        // Values that fit the format survive a round trip.
+        for bits in [8, 16, 24, 32] {
+            for encoding in [SampleEncoding::SignedInt, SampleEncoding::UnsignedInt] {
+                for endianness in [Endianness::Little, Endianness::Big] {
+                    let format = SampleFormat::new(bits, encoding, endianness);
+                    let bytes = &mut bytes[..format.bytes_per_sample()];
+                    for value in [-1.0, -0.25, 0.0, 0.5] {
+                        format.encode(value, bytes);
+                        assert_eq!(format.decode(bytes), value);
+                    }
+                }
+            }
+        }
+        let format = SampleFormat::new(64, SampleEncoding::Float, Endianness::Little);
+        format.encode(-0.123, &mut bytes);
+        assert_eq!(format.decode(&bytes), -0.123);
+    }
+
+    #[test]
+    fn unsupported_formats() {
+        assert!(
+            SampleFormat::new(24, SampleEncoding::Float, Endianness::Little)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            SampleFormat::new(12, SampleEncoding::SignedInt, Endianness::Little)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            SampleFormat::new(24, SampleEncoding::UnsignedInt, Endianness::Big)
+                .validate()
+                .is_ok()
+        );
+    }
+}
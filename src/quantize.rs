@@ -0,0 +1,255 @@
+/// Rounding modes for converting samples to integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Round to the nearest integer, with halfway cases rounded away from zero.
+    Nearest,
+    /// Round toward zero, by truncating the fractional part.
+    TowardZero,
+    /// Round toward negative infinity.
+    Floor,
+    /// Round toward positive infinity.
+    Ceil,
+    /// Round to the nearest integer, with halfway cases rounded to the nearest even integer.
+    HalfEven,
+}
+
+impl Default for RoundingMode {
+    fn default() -> Self {
+        RoundingMode::Nearest
+    }
+}
+
+impl RoundingMode {
+    fn round(&self, value: f64) -> f64 {
+        match self {
+            RoundingMode::Nearest => value.round(),
+            RoundingMode::TowardZero => value.trunc(),
+            RoundingMode::Floor => value.floor(),
+            RoundingMode::Ceil => value.ceil(),
+            RoundingMode::HalfEven => {
+                let rounded = value.round();
+                if (value - value.trunc()).abs() == 0.5 && rounded % 2.0 != 0.0 {
+                    rounded - value.signum()
+                } else {
+                    rounded
+                }
+            }
+        }
+    }
+}
+
+/// Clipping modes for samples that exceed the range of the integer output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipMode {
+    /// Clip samples hard at full scale.
+    Hard,
+    /// Pass samples below the knee unchanged, and compress larger samples smoothly
+    /// towards full scale using a tanh curve.
+    ///
+    /// The knee is given as a fraction of full scale, in the range 0.0 to 1.0.
+    /// The curve has the same slope as the linear part at the knee,
+    /// so that there is no discontinuity in level or slope.
+    /// A knee of 1.0 or larger gives the same result as hard clipping.
+    Soft { knee: f64 },
+}
+
+impl Default for ClipMode {
+    fn default() -> Self {
+        ClipMode::Hard
+    }
+}
+
+impl ClipMode {
+    fn apply(&self, value: f64) -> f64 {
+        match self {
+            ClipMode::Hard => value,
+            ClipMode::Soft { knee } => {
+                let knee = knee.max(0.0);
+                let magnitude = value.abs();
+                if knee >= 1.0 || magnitude <= knee {
+                    return value;
+                }
+                let range = 1.0 - knee;
+                value.signum() * (knee + range * ((magnitude - knee) / range).tanh())
+            }
+        }
+    }
+}
+
+/// Get the next value of a SplitMix64 generator, as a float in the range 0.0 to 1.0.
+fn next_random(state: &mut u64) -> f64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Converts samples in the range -1.0 to 1.0 to 16-bit integers,
+/// used by [process_into_interleaved_i16_with](crate::Resampler::process_into_interleaved_i16_with).
+///
+/// The samples are optionally soft-clipped, then scaled by 32768,
+/// optionally dithered, rounded, and clipped to the range of [i16].
+#[derive(Debug, Clone, Default)]
+pub struct I16Quantizer {
+    rounding: RoundingMode,
+    clipping: ClipMode,
+    dither: bool,
+    dither_state: u64,
+}
+
+impl I16Quantizer {
+    /// Create a new I16Quantizer, that rounds to the nearest integer and clips hard.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the rounding mode.
+    pub fn set_rounding(&mut self, rounding: RoundingMode) {
+        self.rounding = rounding;
+    }
+
+    /// Set the clipping mode.
+    pub fn set_clip_mode(&mut self, clipping: ClipMode) {
+        self.clipping = clipping;
+    }
+
+    /// Enable or disable dither, which is disabled by default.
+    ///
+    /// The dither is noise with a triangular probability density function,
+    /// with a peak amplitude of one least significant bit, that is added before rounding.
+    /// The noise comes from a fast pseudo-random generator, that is seeded with 0 by default.
+    /// This means that the dithered output is deterministic, and the same for each run.
+    /// Use [set_dither_seed](I16Quantizer::set_dither_seed) to choose a different sequence.
+    pub fn set_dither(&mut self, enable: bool) {
+        self.dither = enable;
+    }
+
+    /// Seed the generator of the dither noise, see [set_dither](I16Quantizer::set_dither).
+    ///
+    /// Quantizers with the same seed produce the same output for the same samples.
+    /// The generator advances for each quantized sample, and is only restarted by setting the seed.
+    pub fn set_dither_seed(&mut self, seed: u64) {
+        self.dither_state = seed;
+    }
+
+    /// Convert a single sample.
+    /// This advances the generator of the dither noise when dither is enabled.
+    pub fn quantize(&mut self, value: f64) -> i16 {
+        let mut scaled = self.clipping.apply(value) * 32768.0;
+        if self.dither {
+            scaled += next_random(&mut self.dither_state) - next_random(&mut self.dither_state);
+        }
+        self.rounding
+            .round(scaled)
+            .max(i16::MIN as f64)
+            .min(i16::MAX as f64) as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClipMode, I16Quantizer, RoundingMode};
+    use crate::{FastFixedIn, PolynomialDegree, Resampler};
+
+    fn quantize(mode: RoundingMode, scaled: f64) -> i16 {
+        let mut quantizer = I16Quantizer::new();
+        quantizer.set_rounding(mode);
+        quantizer.quantize(scaled / 32768.0)
+    }
+
+    #[test]
+    fn rounding_modes() {
+        let values = [2.5, 3.5, -2.5, -3.5, 2.7, -2.7, 2.2, -2.2];
+        let expected = [
+            (RoundingMode::Nearest, [3, 4, -3, -4, 3, -3, 2, -2]),
+            (RoundingMode::TowardZero, [2, 3, -2, -3, 2, -2, 2, -2]),
+            (RoundingMode::Floor, [2, 3, -3, -4, 2, -3, 2, -3]),
+            (RoundingMode::Ceil, [3, 4, -2, -3, 3, -2, 3, -2]),
+            (RoundingMode::HalfEven, [2, 4, -2, -4, 3, -3, 2, -2]),
+        ];
+        for (mode, results) in expected {
+            for (value, result) in values.iter().zip(results) {
+                assert_eq!(quantize(mode, *value), result, "{:?} of {}", mode, value);
+            }
+        }
+    }
+
+    #[test]
+    fn clipping() {
+        let mut quantizer = I16Quantizer::new();
+        assert_eq!(quantizer.quantize(1.0), i16::MAX);
+        assert_eq!(quantizer.quantize(-1.0), i16::MIN);
+        assert_eq!(quantizer.quantize(-2.0), i16::MIN);
+    }
+
+    #[test]
+    fn soft_clipping() {
+        let mut hard = I16Quantizer::new();
+        let mut soft = I16Quantizer::new();
+        soft.set_clip_mode(ClipMode::Soft { knee: 0.5 });
+        let mut prev = soft.quantize(-2.0);
+        for n in 0..=4000 {
+            let value = -2.0 + n as f64 * 0.001;
+            let result = soft.quantize(value);
+            if value.abs() <= 0.5 {
+                assert_eq!(result, hard.quantize(value));
+            } else {
+                assert!((result as i32).abs() <= (hard.quantize(value) as i32).abs());
+            }
+            // Monotonic, and never steeper than the linear part.
+            let step = result as i32 - prev as i32;
+            assert!((0..=34).contains(&step), "step {} at {}", step, value);
+            prev = result;
+        }
+        // Samples that would clip hard stay below full scale.
+        assert!(soft.quantize(1.5) < i16::MAX);
+        assert!(soft.quantize(-1.5) > i16::MIN);
+    }
+
+    #[test]
+    fn dither_seed() {
+        let values = (0..1000)
+            .map(|n| 0.3 * (n as f64 * 0.01).sin())
+            .collect::<Vec<f64>>();
+        let run = |seed: Option<u64>| {
+            let mut quantizer = I16Quantizer::new();
+            if let Some(seed) = seed {
+                quantizer.set_dither(true);
+                quantizer.set_dither_seed(seed);
+            }
+            let mut resampler =
+                FastFixedIn::<f64>::new(1.5, 1.0, PolynomialDegree::Cubic, 1000, 1).unwrap();
+            let mut out = vec![0; resampler.output_frames_max()];
+            let frames = resampler
+                .process_into_interleaved_i16_with(
+                    &[&values],
+                    &mut out,
+                    1,
+                    &mut quantizer,
+                    &mut Vec::new(),
+                )
+                .unwrap();
+            out.truncate(frames);
+            out
+        };
+        let plain = run(None);
+        let dithered = run(Some(1));
+        assert_eq!(dithered, run(Some(1)));
+        assert!(dithered != run(Some(2)));
+        assert!(dithered != plain);
+        // The dither changes the samples by at most one step.
+        for (d, p) in dithered.iter().zip(plain.iter()) {
+            assert!((*d as i32 - *p as i32).abs() <= 1);
+        }
+
+        // The default seed is deterministic.
+        let mut first = I16Quantizer::new();
+        first.set_dither(true);
+        let mut second = first.clone();
+        for value in values.iter() {
+            assert_eq!(first.quantize(*value), second.quantize(*value));
+        }
+    }
+}
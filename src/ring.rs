@@ -0,0 +1,192 @@
+//! Minimal ring buffer interfaces used by
+//! [process_from_ring](crate::Resampler::process_from_ring).
+//!
+//! The traits only need the operations used by the resampler,
+//! so adapting a lock-free ring buffer such as `rtrb` or `ringbuf` takes a few lines.
+//! For example, for an `rtrb::Consumer<f32>`, `occupied` maps to `slots()` and `pop` to `pop().ok()`.
+//! Implementations for [VecDeque] are included.
+
+use std::collections::VecDeque;
+
+/// The reading end of a ring buffer holding interleaved samples.
+pub trait RingConsumer<T> {
+    /// Get the number of samples available for reading.
+    fn occupied(&self) -> usize;
+
+    /// Read one sample, or return `None` if the buffer is empty.
+    fn pop(&mut self) -> Option<T>;
+}
+
+/// The writing end of a ring buffer holding interleaved samples.
+pub trait RingProducer<T> {
+    /// Get the number of samples that can be written before the buffer is full.
+    fn vacant(&self) -> usize;
+
+    /// Write one sample. The sample is returned as the error if the buffer is full.
+    fn push(&mut self, value: T) -> Result<(), T>;
+}
+
+impl<T> RingConsumer<T> for VecDeque<T> {
+    fn occupied(&self) -> usize {
+        self.len()
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+}
+
+impl<T> RingProducer<T> for VecDeque<T> {
+    fn vacant(&self) -> usize {
+        usize::MAX - self.len()
+    }
+
+    fn push(&mut self, value: T) -> Result<(), T> {
+        self.push_back(value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RingConsumer, RingProducer};
+    use crate::{FastFixedIn, PolynomialDegree, ResampleError, Resampler};
+    use std::collections::VecDeque;
+
+    #[test]
+    fn sine_through_rings() {
+        let ratio = 48000.0 / 44100.0;
+        let chunk_size = 256;
+        let mut resampler =
+            FastFixedIn::<f64>::new(ratio, 1.0, PolynomialDegree::Cubic, chunk_size, 2).unwrap();
+        let mut reference =
+            FastFixedIn::<f64>::new(ratio, 1.0, PolynomialDegree::Cubic, chunk_size, 2).unwrap();
+        let sine = (0..10 * chunk_size)
+            .map(|n| (n as f64 * 0.05).sin())
+            .collect::<Vec<f64>>();
+        let mut input = VecDeque::new();
+        let mut output = VecDeque::new();
+        let mut scratch_in = Vec::new();
+        let mut scratch_out = Vec::new();
+
+        // Not enough input for a chunk, nothing should happen.
+        input.extend(sine[..chunk_size - 1].iter().flat_map(|s| [*s, -*s]));
+        let result = resampler
+            .process_from_ring(
+                &mut input,
+                &mut output,
+                2,
+                &mut scratch_in,
+                &mut scratch_out,
+            )
+            .unwrap();
+        assert!(result.is_none());
+        assert_eq!(input.len(), 2 * (chunk_size - 1));
+
+        input.extend(sine[chunk_size - 1..].iter().flat_map(|s| [*s, -*s]));
+        let mut expected = Vec::new();
+        for chunk in sine.chunks(chunk_size) {
+            let (_, nbr_out) = resampler
+                .process_from_ring(
+                    &mut input,
+                    &mut output,
+                    2,
+                    &mut scratch_in,
+                    &mut scratch_out,
+                )
+                .unwrap()
+                .unwrap();
+            let waves = vec![chunk.to_vec(), chunk.iter().map(|s| -s).collect()];
+            let out = reference.process(&waves, None).unwrap();
+            assert_eq!(out[0].len(), nbr_out);
+            expected.extend(out[0].iter().zip(out[1].iter()).flat_map(|(l, r)| [*l, *r]));
+        }
+        assert!(input.is_empty());
+        assert_eq!(output.into_iter().collect::<Vec<f64>>(), expected);
+    }
+
+    #[test]
+    fn wrong_channels() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.0, 1.0, PolynomialDegree::Linear, 64, 2).unwrap();
+        let mut input = VecDeque::from(vec![0.0; 256]);
+        let mut output = VecDeque::new();
+        assert!(resampler
+            .process_from_ring(&mut input, &mut output, 1, &mut Vec::new(), &mut Vec::new())
+            .is_err());
+    }
+
+    /// A ring buffer that claims to hold more samples, and to have more space, than it has.
+    struct Misreporting {
+        samples: VecDeque<f64>,
+        space: usize,
+    }
+
+    impl RingConsumer<f64> for Misreporting {
+        fn occupied(&self) -> usize {
+            usize::MAX
+        }
+
+        fn pop(&mut self) -> Option<f64> {
+            self.samples.pop_front()
+        }
+    }
+
+    impl RingProducer<f64> for Misreporting {
+        fn vacant(&self) -> usize {
+            usize::MAX
+        }
+
+        fn push(&mut self, value: f64) -> Result<(), f64> {
+            if self.samples.len() == self.space {
+                return Err(value);
+            }
+            self.samples.push_back(value);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn underflow_and_overflow() {
+        let mut resampler =
+            FastFixedIn::<f64>::new(1.0, 1.0, PolynomialDegree::Linear, 64, 2).unwrap();
+        let mut scratch_in = Vec::new();
+        let mut scratch_out = Vec::new();
+        let mut input = Misreporting {
+            samples: VecDeque::from(vec![0.0; 100]),
+            space: 0,
+        };
+        let mut output = VecDeque::new();
+        let result = resampler.process_from_ring(
+            &mut input,
+            &mut output,
+            2,
+            &mut scratch_in,
+            &mut scratch_out,
+        );
+        assert!(matches!(
+            result,
+            Err(ResampleError::RingBufferUnderflow {
+                expected: 128,
+                actual: 100
+            })
+        ));
+
+        let mut input = VecDeque::from(vec![0.0; 128]);
+        let mut output = Misreporting {
+            samples: VecDeque::new(),
+            space: 11,
+        };
+        let result = resampler.process_from_ring(
+            &mut input,
+            &mut output,
+            2,
+            &mut scratch_in,
+            &mut scratch_out,
+        );
+        assert!(matches!(
+            result,
+            Err(ResampleError::RingBufferOverflow { actual: 11, .. })
+        ));
+    }
+}
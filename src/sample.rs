@@ -14,6 +14,19 @@ pub trait FftNum: Copy + FromPrimitive + Signed + Sync + Send + Debug + 'static
 #[cfg(not(feature = "fft_resampler"))]
 impl<T> FftNum for T where T: Copy + FromPrimitive + Signed + Sync + Send + Debug + 'static {}
 
+/// The numeric type used by a resampler for samples and calculations,
+/// see [Resampler::sample_type](crate::Resampler::sample_type).
+///
+/// More variants may be added in the future.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SampleType {
+    /// 32-bit floating point, [f32].
+    F32,
+    /// 64-bit floating point, [f64].
+    F64,
+}
+
 /// The trait governing a single sample.
 ///
 /// There are two types which implements this trait so far:
@@ -42,12 +55,18 @@ where
 {
     const PI: Self;
 
+    /// The [SampleType] identifying this type.
+    const SAMPLE_TYPE: SampleType;
+
     /// Calculate the sine of `self`.
     fn sin(self) -> Self;
 
     /// Calculate the cosine of `self`.
     fn cos(self) -> Self;
 
+    /// Convert `self` to a 64-bit float.
+    fn to_f64(self) -> f64;
+
     /// Coerce `value` into the current type.
     ///
     /// Coercions are governed through the private `CoerceFrom` trait.
@@ -61,6 +80,7 @@ where
 
 impl Sample for f32 {
     const PI: Self = std::f32::consts::PI;
+    const SAMPLE_TYPE: SampleType = SampleType::F32;
 
     fn sin(self) -> Self {
         f32::sin(self)
@@ -69,10 +89,15 @@ impl Sample for f32 {
     fn cos(self) -> Self {
         f32::cos(self)
     }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
 }
 
 impl Sample for f64 {
     const PI: Self = std::f64::consts::PI;
+    const SAMPLE_TYPE: SampleType = SampleType::F64;
 
     fn sin(self) -> Self {
         f64::sin(self)
@@ -81,6 +106,10 @@ impl Sample for f64 {
     fn cos(self) -> Self {
         f64::cos(self)
     }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
 }
 
 /// The trait used to coerce a value infallibly from one type to another.
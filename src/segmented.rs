@@ -0,0 +1,269 @@
+//! Parallel resampling of long signals that are available in full.
+//!
+//! The [SegmentedResampler] splits the input into segments that are resampled
+//! on the `rayon` thread pool, and then joins the results.
+//! The output is bit-identical to resampling the whole signal chunk by chunk
+//! with a single [SincFixedIn] resampler.
+//!
+//! ## Overlap between segments
+//!
+//! An output frame at input position `t` is calculated from the input frames
+//! in the range `t - sinc_len/2 .. t + sinc_len/2`.
+//! The first output frames of a segment therefore depend on up to `sinc_len/2`
+//! input frames before the segment boundary, which belong to the previous segment.
+//! A fresh resampler also has no knowledge of the fractional position
+//! of the first output frame, which depends on all chunks processed before.
+//!
+//! Both are handled when starting a segment:
+//! - The position is advanced over all preceding chunks, using the same arithmetic
+//!   as when processing them, but without calculating any output.
+//! - The chunks just before the boundary are processed and their output discarded.
+//!   The resampler keeps the last `2 * sinc_len` input frames in its buffer,
+//!   so `ceil(2 * sinc_len / chunk_size)` chunks are replayed.
+//!   This is more than the `sinc_len/2` frames the filter needs,
+//!   but ensures that the buffer content is identical to that of the serial resampler.
+//!
+//! The overhead is thus one or a few chunks per segment,
+//! which is small as long as the segments are much longer than the sinc filter.
+//! The sinc tables are calculated once per call to [process](SegmentedResampler::process),
+//! and are shared by the resamplers of all segments.
+
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use crate::asynchro_sinc::{interpolator_settings, make_interpolator};
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::sinc_interpolator::SincInterpolator;
+use crate::{validate_sinc_config, Resampler, Sample, SincFixedIn, SincInterpolationParameters};
+
+/// A helper for resampling a long signal in parallel, using [SincFixedIn] resamplers.
+///
+/// The ratio is fixed, and the whole input must be available when calling
+/// [process](SegmentedResampler::process).
+/// The input is processed in chunks of `chunk_size` frames, where the last chunk is padded with zeros.
+/// Each segment consists of `segment_chunks` chunks.
+/// See the [module documentation](crate::segmented) for how the segments are joined.
+pub struct SegmentedResampler {
+    resample_ratio: f64,
+    parameters: SincInterpolationParameters,
+    chunk_size: usize,
+    segment_chunks: usize,
+    nbr_channels: usize,
+    sinc_len: usize,
+}
+
+impl SegmentedResampler {
+    /// Create a new SegmentedResampler.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `parameters`: Parameters for interpolation, see `SincInterpolationParameters`.
+    /// - `chunk_size`: Size of the chunks passed to each resampler.
+    /// - `segment_chunks`: Number of chunks in each segment, must be at least 1.
+    /// - `nbr_channels`: Number of channels in input/output.
+    pub fn new(
+        resample_ratio: f64,
+        parameters: SincInterpolationParameters,
+        chunk_size: usize,
+        segment_chunks: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        if segment_chunks == 0 {
+            return Err(ResamplerConstructionError::InvalidSegmentLength(
+                segment_chunks,
+            ));
+        }
+        validate_sinc_config(&parameters, resample_ratio, 1.0, chunk_size, nbr_channels)?;
+        let (sinc_len, _) =
+            interpolator_settings(parameters.sinc_len, resample_ratio, parameters.f_cutoff);
+        Ok(SegmentedResampler {
+            resample_ratio,
+            parameters,
+            chunk_size,
+            segment_chunks,
+            nbr_channels,
+            sinc_len,
+        })
+    }
+
+    /// Resample a complete signal, given as one vector per channel.
+    ///
+    /// The segments are processed in parallel, and the result is returned as one vector per channel.
+    /// All input channels must have the same length.
+    pub fn process<T>(&self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>>
+    where
+        T: Sample + Sync,
+    {
+        if wave_in.len() != self.nbr_channels {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: self.nbr_channels,
+                actual: wave_in.len(),
+            });
+        }
+        let frames = wave_in.first().map(|chan| chan.len()).unwrap_or(0);
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if wave.len() != frames {
+                return Err(ResampleError::InsufficientInputBufferSize {
+                    channel: chan,
+                    expected: frames,
+                    actual: wave.len(),
+                });
+            }
+        }
+        let nbr_chunks = (frames as f64 / self.chunk_size as f64).ceil() as usize;
+        let (interpolator, _) = make_interpolator::<T>(
+            self.parameters.sinc_len,
+            self.resample_ratio,
+            self.parameters.f_cutoff,
+            self.parameters.oversampling_factor,
+            self.parameters.window,
+        );
+        let interpolator = Arc::from(interpolator);
+        let segments = (0..nbr_chunks)
+            .step_by(self.segment_chunks)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|first| {
+                let end = (first + self.segment_chunks).min(nbr_chunks);
+                self.process_segment(wave_in, &interpolator, first, end)
+            })
+            .collect::<ResampleResult<Vec<_>>>()?;
+
+        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+        for segment in segments {
+            for (out, seg) in wave_out.iter_mut().zip(segment) {
+                out.extend_from_slice(&seg);
+            }
+        }
+        Ok(wave_out)
+    }
+
+    /// Resample the chunks from `first` up to, but not including, `end`,
+    /// using the shared interpolator.
+    fn process_segment<T: Sample>(
+        &self,
+        wave_in: &[Vec<T>],
+        interpolator: &Arc<dyn SincInterpolator<T> + Sync>,
+        first: usize,
+        end: usize,
+    ) -> ResampleResult<Vec<Vec<T>>> {
+        // The parameters were validated when creating the SegmentedResampler.
+        let mut resampler = SincFixedIn::<T>::from_validated_parts(
+            self.resample_ratio,
+            1.0,
+            self.parameters.interpolation.clone(),
+            Box::new(Arc::clone(interpolator)),
+            self.chunk_size,
+            self.nbr_channels,
+        );
+        let replay_chunks = (2.0 * self.sinc_len as f64 / self.chunk_size as f64).ceil() as usize;
+        let start = first.saturating_sub(replay_chunks);
+        for _ in 0..start {
+            resampler.skip_chunk();
+        }
+
+        let mut chunk_in = vec![vec![T::zero(); self.chunk_size]; self.nbr_channels];
+        let mut chunk_out = resampler.output_buffer_allocate(true);
+        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+        for chunk in start..end {
+            let offset = chunk * self.chunk_size;
+            for (buf, wave) in chunk_in.iter_mut().zip(wave_in.iter()) {
+                let available = wave.len().saturating_sub(offset).min(self.chunk_size);
+                buf[..available].copy_from_slice(&wave[offset..offset + available]);
+                for sample in buf[available..].iter_mut() {
+                    *sample = T::zero();
+                }
+            }
+            let (_, nbr_out) = resampler.process_into_buffer(&chunk_in, &mut chunk_out, None)?;
+            if chunk >= first {
+                for (out, buf) in wave_out.iter_mut().zip(chunk_out.iter()) {
+                    out.extend_from_slice(&buf[..nbr_out]);
+                }
+            }
+        }
+        Ok(wave_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SegmentedResampler;
+    use crate::{
+        Resampler, ResamplerConstructionError, SincFixedIn, SincInterpolationParameters,
+        SincInterpolationType, WindowFunction,
+    };
+    use rand::Rng;
+
+    fn parameters() -> SincInterpolationParameters {
+        SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        }
+    }
+
+    fn serial(ratio: f64, chunk_size: usize, wave_in: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        let mut resampler =
+            SincFixedIn::<f64>::new(ratio, 1.0, parameters(), chunk_size, wave_in.len()).unwrap();
+        let mut wave_out = vec![Vec::new(); wave_in.len()];
+        let mut chunk_out = resampler.output_buffer_allocate(true);
+        let mut offset = 0;
+        while offset < wave_in[0].len() {
+            let end = (offset + chunk_size).min(wave_in[0].len());
+            let chunk_in = wave_in
+                .iter()
+                .map(|chan| &chan[offset..end])
+                .collect::<Vec<_>>();
+            let (_, nbr_out) = resampler
+                .process_partial_into_buffer(Some(&chunk_in), &mut chunk_out, None)
+                .unwrap();
+            for (out, buf) in wave_out.iter_mut().zip(chunk_out.iter()) {
+                out.extend_from_slice(&buf[..nbr_out]);
+            }
+            offset = end;
+        }
+        wave_out
+    }
+
+    #[test]
+    fn matches_serial() {
+        let mut rng = rand::thread_rng();
+        let wave_in = (0..2)
+            .map(|_| {
+                (0..20000)
+                    .map(|_| rng.gen::<f64>() - 0.5)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        for (ratio, chunk_size, segment_chunks) in [(44100.0 / 48000.0, 256, 8), (1.7, 100, 3)] {
+            let segmented =
+                SegmentedResampler::new(ratio, parameters(), chunk_size, segment_chunks, 2)
+                    .unwrap();
+            let parallel = segmented.process(&wave_in).unwrap();
+            let expected = serial(ratio, chunk_size, &wave_in);
+            assert_eq!(parallel, expected);
+        }
+    }
+
+    #[test]
+    fn wrong_channels() {
+        let segmented = SegmentedResampler::new(0.5, parameters(), 256, 4, 2).unwrap();
+        assert!(segmented.process(&[vec![0.0f64; 1000]]).is_err());
+        assert!(SegmentedResampler::new(0.5, parameters(), 256, 0, 2).is_err());
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        assert!(matches!(
+            SegmentedResampler::new(0.0, parameters(), 256, 4, 2),
+            Err(ResamplerConstructionError::InvalidRatio(_))
+        ));
+        assert!(matches!(
+            SegmentedResampler::new(0.5, parameters(), 0, 4, 2),
+            Err(ResamplerConstructionError::InvalidChunkSize(0))
+        ));
+    }
+}
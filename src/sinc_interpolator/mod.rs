@@ -1,6 +1,8 @@
-use crate::sinc::make_sincs;
+use crate::error::ResamplerConstructionError;
+use crate::sinc::{make_sincs, make_sincs_from_response};
 use crate::windows::WindowFunction;
 use crate::Sample;
+use std::sync::Arc;
 
 /// Helper macro to define a dummy implementation of the sample trait if a
 /// feature is not supported.
@@ -63,6 +65,90 @@ pub trait SincInterpolator<T>: Send {
 
     /// Get number of sincs used for oversampling.
     fn nbr_sincs(&self) -> usize;
+
+    /// Limit the scalar products to the central `taps` taps of each sinc,
+    /// for a faster but less accurate interpolation.
+    ///
+    /// The stored sincs are not changed, and the full length can be restored at any time.
+    /// The number of taps is rounded up so that an equal multiple of 8 taps is skipped at each end,
+    /// and is limited to the range from 8 to [len](SincInterpolator::len).
+    /// Returns the number of taps in use, see [active_len](SincInterpolator::active_len).
+    /// The default implementation doesn't support this, and always uses all taps.
+    fn set_active_len(&mut self, _taps: usize) -> usize {
+        self.len()
+    }
+
+    /// Get the number of taps of each sinc that are used for the scalar products.
+    fn active_len(&self) -> usize {
+        self.len()
+    }
+
+    /// Make the scalar products between the sinc of `subindex` and both channels of a
+    /// stereo waveform starting at `index`.
+    ///
+    /// The results must be identical to calling
+    /// [get_sinc_interpolated](SincInterpolator::get_sinc_interpolated) for each channel separately,
+    /// which is what the default implementation does.
+    /// Implementations may override this to process both channels in a single pass over the sinc.
+    fn get_sinc_interpolated_pair(
+        &self,
+        waves: [&[T]; 2],
+        index: usize,
+        subindex: usize,
+    ) -> [T; 2] {
+        [
+            self.get_sinc_interpolated(waves[0], index, subindex),
+            self.get_sinc_interpolated(waves[1], index, subindex),
+        ]
+    }
+}
+
+/// An interpolator shared between several resamplers, for example running on different threads.
+///
+/// The number of active taps can only be changed while the interpolator is not shared,
+/// otherwise [set_active_len](SincInterpolator::set_active_len) keeps the current number.
+impl<T, I> SincInterpolator<T> for Arc<I>
+where
+    I: SincInterpolator<T> + Sync + ?Sized,
+{
+    fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
+        (**self).get_sinc_interpolated(wave, index, subindex)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        (**self).nbr_sincs()
+    }
+
+    fn set_active_len(&mut self, taps: usize) -> usize {
+        match Arc::get_mut(self) {
+            Some(interpolator) => interpolator.set_active_len(taps),
+            None => self.active_len(),
+        }
+    }
+
+    fn active_len(&self) -> usize {
+        (**self).active_len()
+    }
+
+    fn get_sinc_interpolated_pair(
+        &self,
+        waves: [&[T]; 2],
+        index: usize,
+        subindex: usize,
+    ) -> [T; 2] {
+        (**self).get_sinc_interpolated_pair(waves, index, subindex)
+    }
+}
+
+/// Get the number of taps to skip at each end of a sinc of length `length`,
+/// to use at least `taps` taps. The result is a multiple of 8.
+pub(crate) fn skip_for_active_len(length: usize, taps: usize) -> usize {
+    let taps = taps.max(8).min(length);
+    8 * ((length - taps) / 16)
 }
 
 /// A plain scalar interpolator.
@@ -70,6 +156,7 @@ pub struct ScalarInterpolator<T> {
     sincs: Vec<Vec<T>>,
     length: usize,
     nbr_sincs: usize,
+    skip: usize,
 }
 
 impl<T> SincInterpolator<T> for ScalarInterpolator<T>
@@ -90,8 +177,8 @@ where
             subindex,
             self.nbr_sincs - 1
         );
-        let wave_cut = &wave[index..(index + self.sincs[subindex].len())];
-        let sinc = &self.sincs[subindex];
+        let wave_cut = &wave[index + self.skip..index + self.length - self.skip];
+        let sinc = &self.sincs[subindex][self.skip..self.length - self.skip];
         unsafe {
             let mut acc0 = T::zero();
             let mut acc1 = T::zero();
@@ -124,6 +211,15 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn set_active_len(&mut self, taps: usize) -> usize {
+        self.skip = skip_for_active_len(self.length, taps);
+        self.active_len()
+    }
+
+    fn active_len(&self) -> usize {
+        self.length - 2 * self.skip
+    }
 }
 
 impl<T> ScalarInterpolator<T>
@@ -149,8 +245,50 @@ where
             sincs,
             length: sinc_len,
             nbr_sincs: oversampling_factor,
+            skip: 0,
         }
     }
+
+    /// Create a new ScalarInterpolator, with filters designed from a frequency response
+    /// instead of a sinc at a fixed cutoff.
+    ///
+    /// The `response` closure gives the desired magnitude for a frequency from 0 to 1,
+    /// relative to the Nyquist frequency of the input.
+    /// It is sampled, transformed to an impulse response and windowed.
+    /// The values are real magnitudes that are mirrored to the negative frequencies,
+    /// which means that the impulse response is always real and even.
+    /// A [ResamplerConstructionError::InvalidFrequencyResponse] is returned
+    /// if the closure gives a negative or non-finite value.
+    ///
+    /// Parameters are:
+    /// - `sinc_len`: Length of the filters.
+    /// - `oversampling_factor`: Number of intermediate filters (oversampling factor).
+    /// - `response`: Closure giving the magnitude at a relative frequency.
+    /// - `window`: Window function to use.
+    pub fn new_with_response<F>(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        response: F,
+        window: WindowFunction,
+    ) -> Result<Self, ResamplerConstructionError>
+    where
+        F: Fn(f64) -> f64,
+    {
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8");
+        let sincs = make_sincs_from_response(sinc_len, oversampling_factor, response, window)
+            .map_err(
+                |(frequency, value)| ResamplerConstructionError::InvalidFrequencyResponse {
+                    frequency,
+                    value,
+                },
+            )?;
+        Ok(Self {
+            sincs,
+            length: sinc_len,
+            nbr_sincs: oversampling_factor,
+            skip: 0,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -177,7 +315,7 @@ mod tests {
             wave.push(rng.gen::<f64>());
         }
         let sinc_len = 256;
-        let f_cutoff = 0.9473371669037001;
+        let f_cutoff = 0.947_337_15;
         let oversampling_factor = 256;
         let window = WindowFunction::BlackmanHarris2;
 
@@ -188,6 +326,41 @@ mod tests {
         assert!((value - check).abs() < 1.0e-9);
     }
 
+    #[test]
+    fn test_scalar_interpolator_active_len() {
+        let mut rng = rand::thread_rng();
+        let wave = (0..2048).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>();
+        let window = WindowFunction::BlackmanHarris2;
+        let mut interpolator = ScalarInterpolator::<f64>::new(256, 256, 0.95, window);
+        assert_eq!(interpolator.set_active_len(64), 64);
+        assert_eq!(interpolator.active_len(), 64);
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
+        let check = get_sinc_interpolated(&wave, 333 + 96, &interpolator.sincs[123][96..160]);
+        assert!((value - check).abs() < 1.0e-9);
+        assert_eq!(interpolator.set_active_len(1000), 256);
+    }
+
+    #[test]
+    fn test_scalar_interpolator_from_response() {
+        let mut rng = rand::thread_rng();
+        let wave = (0..2048).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>();
+        let window = WindowFunction::BlackmanHarris2;
+        let reference = ScalarInterpolator::<f64>::new(64, 16, 0.9, window);
+        let interpolator = ScalarInterpolator::<f64>::new_with_response(
+            64,
+            16,
+            |freq| if freq < 0.9 { 1.0 } else { 0.0 },
+            window,
+        )
+        .unwrap();
+        for subindex in [0, 5, 15] {
+            let value = interpolator.get_sinc_interpolated(&wave, 333, subindex);
+            let check = reference.get_sinc_interpolated(&wave, 333, subindex);
+            assert!((value - check).abs() < 0.02);
+        }
+        assert!(ScalarInterpolator::<f64>::new_with_response(64, 16, |_| -1.0, window).is_err());
+    }
+
     #[test]
     fn test_scalar_interpolator_32() {
         let mut rng = rand::thread_rng();
@@ -196,7 +369,7 @@ mod tests {
             wave.push(rng.gen::<f32>());
         }
         let sinc_len = 256;
-        let f_cutoff = 0.9473371669037001;
+        let f_cutoff = 0.947_337_15;
         let oversampling_factor = 256;
         let window = WindowFunction::BlackmanHarris2;
 
@@ -1,6 +1,6 @@
 use crate::error::{CpuFeature, MissingCpuFeature};
 use crate::sinc::make_sincs;
-use crate::sinc_interpolator::SincInterpolator;
+use crate::sinc_interpolator::{skip_for_active_len, SincInterpolator};
 use crate::windows::WindowFunction;
 use crate::Sample;
 use core::arch::x86_64::{
@@ -20,7 +20,7 @@ static FEATURES: &[CpuFeature] = &[CpuFeature::Avx, CpuFeature::Fma];
 
 /// Trait governing what can be done with an AvxSample.
 pub trait AvxSample: Sized + Send {
-    type Sinc: Send;
+    type Sinc: Send + Sync;
 
     /// Pack sincs into a vector.
     ///
@@ -34,15 +34,27 @@ pub trait AvxSample: Sized + Send {
     ///
     /// # Safety
     ///
-    /// The caller must ensure that the various indexes are not out of bounds
-    /// in the collection of sincs.
+    /// The caller must ensure that the wave and the sinc
+    /// both hold at least `length` values, starting at `index` and at the start of the sinc.
     unsafe fn get_sinc_interpolated_unsafe(
         wave: &[Self],
         index: usize,
-        subindex: usize,
-        sincs: &[Vec<Self::Sinc>],
+        sinc: &[Self::Sinc],
         length: usize,
     ) -> Self;
+
+    /// Interpolate a sinc sample for both channels of a stereo wave.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that both waves and the sinc
+    /// hold at least `length` values, starting at `index` and at the start of the sinc.
+    unsafe fn get_sinc_interpolated_pair_unsafe(
+        waves: [&[Self]; 2],
+        index: usize,
+        sinc: &[Self::Sinc],
+        length: usize,
+    ) -> [Self; 2];
 }
 
 impl AvxSample for f32 {
@@ -66,11 +78,9 @@ impl AvxSample for f32 {
     unsafe fn get_sinc_interpolated_unsafe(
         wave: &[f32],
         index: usize,
-        subindex: usize,
-        sincs: &[Vec<Self::Sinc>],
+        sinc: &[Self::Sinc],
         length: usize,
     ) -> f32 {
-        let sinc = sincs.get_unchecked(subindex);
         let wave_cut = &wave[index..(index + length)];
         let mut acc = _mm256_setzero_ps();
         let mut w_idx = 0;
@@ -87,6 +97,40 @@ impl AvxSample for f32 {
         _mm_store_ss(&mut result, temp1);
         result
     }
+
+    #[target_feature(enable = "avx", enable = "fma")]
+    unsafe fn get_sinc_interpolated_pair_unsafe(
+        waves: [&[f32]; 2],
+        index: usize,
+        sinc: &[Self::Sinc],
+        length: usize,
+    ) -> [f32; 2] {
+        // The same steps as in `get_sinc_interpolated_unsafe` for each channel,
+        // with each packed sinc value loaded once for both channels.
+        let wave_cuts = [
+            &waves[0][index..(index + length)],
+            &waves[1][index..(index + length)],
+        ];
+        let mut acc = [_mm256_setzero_ps(); 2];
+        let mut w_idx = 0;
+        for s_idx in 0..length / 8 {
+            let s = *sinc.get_unchecked(s_idx);
+            for (a, wave_cut) in acc.iter_mut().zip(wave_cuts.iter()) {
+                let w = _mm256_loadu_ps(wave_cut.get_unchecked(w_idx));
+                *a = _mm256_fmadd_ps(w, s, *a);
+            }
+            w_idx += 8;
+        }
+        let mut result = [0.0; 2];
+        for (res, a) in result.iter_mut().zip(acc.iter()) {
+            let acc_high = _mm256_extractf128_ps(*a, 1);
+            let acc_low = _mm_add_ps(acc_high, _mm256_castps256_ps128(*a));
+            let temp2 = _mm_hadd_ps(acc_low, acc_low);
+            let temp1 = _mm_hadd_ps(temp2, temp2);
+            _mm_store_ss(res, temp1);
+        }
+        result
+    }
 }
 
 impl AvxSample for f64 {
@@ -110,11 +154,9 @@ impl AvxSample for f64 {
     unsafe fn get_sinc_interpolated_unsafe(
         wave: &[f64],
         index: usize,
-        subindex: usize,
-        sincs: &[Vec<Self::Sinc>],
+        sinc: &[Self::Sinc],
         length: usize,
     ) -> f64 {
-        let sinc = sincs.get_unchecked(subindex);
         let wave_cut = &wave[index..(index + length)];
         let mut acc0 = _mm256_setzero_pd();
         let mut acc1 = _mm256_setzero_pd();
@@ -136,6 +178,45 @@ impl AvxSample for f64 {
         _mm_store_sd(&mut result, temp1);
         result
     }
+
+    #[target_feature(enable = "avx", enable = "fma")]
+    unsafe fn get_sinc_interpolated_pair_unsafe(
+        waves: [&[f64]; 2],
+        index: usize,
+        sinc: &[Self::Sinc],
+        length: usize,
+    ) -> [f64; 2] {
+        // The same steps as in `get_sinc_interpolated_unsafe` for each channel,
+        // with each packed sinc value loaded once for both channels.
+        let wave_cuts = [
+            &waves[0][index..(index + length)],
+            &waves[1][index..(index + length)],
+        ];
+        let mut acc = [[_mm256_setzero_pd(); 2]; 2];
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..length / 8 {
+            let s0 = *sinc.get_unchecked(s_idx);
+            let s1 = *sinc.get_unchecked(s_idx + 1);
+            for (a, wave_cut) in acc.iter_mut().zip(wave_cuts.iter()) {
+                let w0 = _mm256_loadu_pd(wave_cut.get_unchecked(w_idx));
+                let w1 = _mm256_loadu_pd(wave_cut.get_unchecked(w_idx + 4));
+                a[0] = _mm256_fmadd_pd(w0, s0, a[0]);
+                a[1] = _mm256_fmadd_pd(w1, s1, a[1]);
+            }
+            w_idx += 8;
+            s_idx += 2;
+        }
+        let mut result = [0.0; 2];
+        for (res, a) in result.iter_mut().zip(acc.iter()) {
+            let acc_all = _mm256_add_pd(a[0], a[1]);
+            let acc_high = _mm256_extractf128_pd(acc_all, 1);
+            let temp2 = _mm_add_pd(acc_high, _mm256_castpd256_pd128(acc_all));
+            let temp1 = _mm_hadd_pd(temp2, temp2);
+            _mm_store_sd(res, temp1);
+        }
+        result
+    }
 }
 
 /// An AVX accelerated interpolator.
@@ -146,6 +227,22 @@ where
     sincs: Vec<Vec<T::Sinc>>,
     length: usize,
     nbr_sincs: usize,
+    skip: usize,
+}
+
+impl<T> AvxInterpolator<T>
+where
+    T: AvxSample,
+{
+    /// Get the packed values of the sinc of `subindex`, without the skipped taps.
+    ///
+    /// # Safety
+    ///
+    /// The `subindex` must be less than the number of sincs.
+    unsafe fn active_sinc(&self, subindex: usize) -> &[T::Sinc] {
+        let values_per_pack = std::mem::size_of::<T::Sinc>() / std::mem::size_of::<T>();
+        &self.sincs.get_unchecked(subindex)[self.skip / values_per_pack..]
+    }
 }
 
 impl<T> SincInterpolator<T> for AvxInterpolator<T>
@@ -166,7 +263,14 @@ where
             subindex,
             self.nbr_sincs - 1
         );
-        unsafe { T::get_sinc_interpolated_unsafe(wave, index, subindex, &self.sincs, self.length) }
+        unsafe {
+            T::get_sinc_interpolated_unsafe(
+                wave,
+                index + self.skip,
+                self.active_sinc(subindex),
+                self.length - 2 * self.skip,
+            )
+        }
     }
 
     fn len(&self) -> usize {
@@ -176,6 +280,40 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn set_active_len(&mut self, taps: usize) -> usize {
+        self.skip = skip_for_active_len(self.length, taps);
+        self.active_len()
+    }
+
+    fn active_len(&self) -> usize {
+        self.length - 2 * self.skip
+    }
+
+    /// Calculate the scalar products of both channels of a stereo wave and the selected sinc filter.
+    fn get_sinc_interpolated_pair(&self, waves: [&[T]; 2], index: usize, subindex: usize) -> [T; 2] {
+        let wave_len = waves[0].len().min(waves[1].len());
+        assert!(
+            (index + self.length) < wave_len,
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave_len - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        unsafe {
+            T::get_sinc_interpolated_pair_unsafe(
+                waves,
+                index + self.skip,
+                self.active_sinc(subindex),
+                self.length - 2 * self.skip,
+            )
+        }
+    }
 }
 
 impl<T> AvxInterpolator<T>
@@ -207,6 +345,7 @@ where
             sincs,
             length: sinc_len,
             nbr_sincs: oversampling_factor,
+            skip: 0,
         })
     }
 }
@@ -236,7 +375,7 @@ mod tests {
             wave.push(rng.gen::<f64>());
         }
         let sinc_len = 256;
-        let f_cutoff = 0.9473371669037001;
+        let f_cutoff = 0.947_337_15;
         let oversampling_factor = 256;
         let window = WindowFunction::BlackmanHarris2;
         let sincs = make_sincs::<f64>(sinc_len, oversampling_factor, f_cutoff, window);
@@ -263,7 +402,7 @@ mod tests {
             wave.push(rng.gen::<f32>());
         }
         let sinc_len = 256;
-        let f_cutoff = 0.9473371669037001;
+        let f_cutoff = 0.947_337_15;
         let oversampling_factor = 256;
         let window = WindowFunction::BlackmanHarris2;
         let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window);
@@ -1,6 +1,6 @@
 use crate::error::{CpuFeature, MissingCpuFeature};
 use crate::sinc::make_sincs;
-use crate::sinc_interpolator::SincInterpolator;
+use crate::sinc_interpolator::{skip_for_active_len, SincInterpolator};
 use crate::windows::WindowFunction;
 use crate::Sample;
 use core::arch::aarch64::{float32x4_t, float64x2_t};
@@ -14,7 +14,7 @@ static FEATURES: &[CpuFeature] = &[CpuFeature::Neon];
 
 /// Trait governing what can be done with an NeonSample.
 pub trait NeonSample: Sized + Send {
-    type Sinc: Send;
+    type Sinc: Send + Sync;
 
     /// Pack sincs into a vector.
     ///
@@ -28,13 +28,12 @@ pub trait NeonSample: Sized + Send {
     ///
     /// # Safety
     ///
-    /// The caller must ensure that the various indexes are not out of bounds
-    /// in the collection of sincs.
+    /// The caller must ensure that the wave and the sinc
+    /// both hold at least `length` values, starting at `index` and at the start of the sinc.
     unsafe fn get_sinc_interpolated_unsafe(
         wave: &[Self],
         index: usize,
-        subindex: usize,
-        sincs: &[Vec<Self::Sinc>],
+        sinc: &[Self::Sinc],
         length: usize,
     ) -> Self;
 }
@@ -60,11 +59,9 @@ impl NeonSample for f32 {
     unsafe fn get_sinc_interpolated_unsafe(
         wave: &[f32],
         index: usize,
-        subindex: usize,
-        sincs: &[Vec<Self::Sinc>],
+        sinc: &[Self::Sinc],
         length: usize,
     ) -> f32 {
-        let sinc = sincs.get_unchecked(subindex);
         let wave_cut = &wave[index..(index + length)];
         let mut acc0 = vmovq_n_f32(0.0);
         let mut acc1 = vmovq_n_f32(0.0);
@@ -109,11 +106,9 @@ impl NeonSample for f64 {
     unsafe fn get_sinc_interpolated_unsafe(
         wave: &[f64],
         index: usize,
-        subindex: usize,
-        sincs: &[Vec<Self::Sinc>],
+        sinc: &[Self::Sinc],
         length: usize,
     ) -> f64 {
-        let sinc = sincs.get_unchecked(subindex);
         let wave_cut = &wave[index..(index + length)];
         let mut acc0 = vmovq_n_f64(0.0);
         let mut acc1 = vmovq_n_f64(0.0);
@@ -150,6 +145,22 @@ where
     sincs: Vec<Vec<T::Sinc>>,
     length: usize,
     nbr_sincs: usize,
+    skip: usize,
+}
+
+impl<T> NeonInterpolator<T>
+where
+    T: Sample,
+{
+    /// Get the packed values of the sinc of `subindex`, without the skipped taps.
+    ///
+    /// # Safety
+    ///
+    /// The `subindex` must be less than the number of sincs.
+    unsafe fn active_sinc(&self, subindex: usize) -> &[T::Sinc] {
+        let values_per_pack = std::mem::size_of::<T::Sinc>() / std::mem::size_of::<T>();
+        &self.sincs.get_unchecked(subindex)[self.skip / values_per_pack..]
+    }
 }
 
 impl<T> SincInterpolator<T> for NeonInterpolator<T>
@@ -170,7 +181,14 @@ where
             subindex,
             self.nbr_sincs - 1
         );
-        unsafe { T::get_sinc_interpolated_unsafe(wave, index, subindex, &self.sincs, self.length) }
+        unsafe {
+            T::get_sinc_interpolated_unsafe(
+                wave,
+                index + self.skip,
+                self.active_sinc(subindex),
+                self.length - 2 * self.skip,
+            )
+        }
     }
 
     fn len(&self) -> usize {
@@ -180,6 +198,15 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn set_active_len(&mut self, taps: usize) -> usize {
+        self.skip = skip_for_active_len(self.length, taps);
+        self.active_len()
+    }
+
+    fn active_len(&self) -> usize {
+        self.length - 2 * self.skip
+    }
 }
 
 impl<T> NeonInterpolator<T>
@@ -211,6 +238,7 @@ where
             sincs,
             length: sinc_len,
             nbr_sincs: oversampling_factor,
+            skip: 0,
         })
     }
 }
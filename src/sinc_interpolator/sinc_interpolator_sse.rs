@@ -1,6 +1,6 @@
 use crate::error::{CpuFeature, MissingCpuFeature};
 use crate::sinc::make_sincs;
-use crate::sinc_interpolator::SincInterpolator;
+use crate::sinc_interpolator::{skip_for_active_len, SincInterpolator};
 use crate::windows::WindowFunction;
 use crate::Sample;
 use core::arch::x86_64::{__m128, __m128d};
@@ -16,7 +16,7 @@ static FEATURES: &[CpuFeature] = &[CpuFeature::Sse3];
 
 /// Trait governing what can be done with an SseSample.
 pub trait SseSample: Sized + Send {
-    type Sinc: Send;
+    type Sinc: Send + Sync;
 
     /// Pack sincs into a vector.
     ///
@@ -30,15 +30,27 @@ pub trait SseSample: Sized + Send {
     ///
     /// # Safety
     ///
-    /// The caller must ensure that the various indexes are not out of bounds
-    /// in the collection of sincs.
+    /// The caller must ensure that the wave and the sinc
+    /// both hold at least `length` values, starting at `index` and at the start of the sinc.
     unsafe fn get_sinc_interpolated_unsafe(
         wave: &[Self],
         index: usize,
-        subindex: usize,
-        sincs: &[Vec<Self::Sinc>],
+        sinc: &[Self::Sinc],
         length: usize,
     ) -> Self;
+
+    /// Interpolate a sinc sample for both channels of a stereo wave.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that both waves and the sinc
+    /// hold at least `length` values, starting at `index` and at the start of the sinc.
+    unsafe fn get_sinc_interpolated_pair_unsafe(
+        waves: [&[Self]; 2],
+        index: usize,
+        sinc: &[Self::Sinc],
+        length: usize,
+    ) -> [Self; 2];
 }
 
 impl SseSample for f32 {
@@ -62,11 +74,9 @@ impl SseSample for f32 {
     unsafe fn get_sinc_interpolated_unsafe(
         wave: &[f32],
         index: usize,
-        subindex: usize,
-        sincs: &[Vec<Self::Sinc>],
+        sinc: &[Self::Sinc],
         length: usize,
     ) -> f32 {
-        let sinc = sincs.get_unchecked(subindex);
         let wave_cut = &wave[index..(index + length)];
         let mut acc0 = _mm_setzero_ps();
         let mut acc1 = _mm_setzero_ps();
@@ -89,6 +99,44 @@ impl SseSample for f32 {
         _mm_store_ss(&mut result, temp1);
         result
     }
+
+    #[target_feature(enable = "sse3")]
+    unsafe fn get_sinc_interpolated_pair_unsafe(
+        waves: [&[f32]; 2],
+        index: usize,
+        sinc: &[Self::Sinc],
+        length: usize,
+    ) -> [f32; 2] {
+        // The same steps as in `get_sinc_interpolated_unsafe` for each channel,
+        // with each packed sinc value loaded once for both channels.
+        let wave_cuts = [
+            &waves[0][index..(index + length)],
+            &waves[1][index..(index + length)],
+        ];
+        let mut acc = [[_mm_setzero_ps(); 2]; 2];
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..length / 8 {
+            let s0 = *sinc.get_unchecked(s_idx);
+            let s1 = *sinc.get_unchecked(s_idx + 1);
+            for (a, wave_cut) in acc.iter_mut().zip(wave_cuts.iter()) {
+                let w0 = _mm_loadu_ps(wave_cut.get_unchecked(w_idx));
+                let w1 = _mm_loadu_ps(wave_cut.get_unchecked(w_idx + 4));
+                a[0] = _mm_add_ps(a[0], _mm_mul_ps(w0, s0));
+                a[1] = _mm_add_ps(a[1], _mm_mul_ps(w1, s1));
+            }
+            w_idx += 8;
+            s_idx += 2;
+        }
+        let mut result = [0.0; 2];
+        for (res, a) in result.iter_mut().zip(acc.iter()) {
+            let temp4 = _mm_add_ps(a[0], a[1]);
+            let temp2 = _mm_hadd_ps(temp4, temp4);
+            let temp1 = _mm_hadd_ps(temp2, temp2);
+            _mm_store_ss(res, temp1);
+        }
+        result
+    }
 }
 
 impl SseSample for f64 {
@@ -112,11 +160,9 @@ impl SseSample for f64 {
     unsafe fn get_sinc_interpolated_unsafe(
         wave: &[f64],
         index: usize,
-        subindex: usize,
-        sincs: &[Vec<Self::Sinc>],
+        sinc: &[Self::Sinc],
         length: usize,
     ) -> f64 {
-        let sinc = sincs.get_unchecked(subindex);
         let wave_cut = &wave[index..(index + length)];
         let mut acc0 = _mm_setzero_pd();
         let mut acc1 = _mm_setzero_pd();
@@ -148,6 +194,49 @@ impl SseSample for f64 {
         _mm_store_sd(&mut result, temp1);
         result
     }
+
+    #[target_feature(enable = "sse3")]
+    unsafe fn get_sinc_interpolated_pair_unsafe(
+        waves: [&[f64]; 2],
+        index: usize,
+        sinc: &[Self::Sinc],
+        length: usize,
+    ) -> [f64; 2] {
+        // The same steps as in `get_sinc_interpolated_unsafe` for each channel,
+        // with each packed sinc value loaded once for both channels.
+        let wave_cuts = [
+            &waves[0][index..(index + length)],
+            &waves[1][index..(index + length)],
+        ];
+        let mut acc = [[_mm_setzero_pd(); 4]; 2];
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..length / 8 {
+            let s = [
+                *sinc.get_unchecked(s_idx),
+                *sinc.get_unchecked(s_idx + 1),
+                *sinc.get_unchecked(s_idx + 2),
+                *sinc.get_unchecked(s_idx + 3),
+            ];
+            for (a, wave_cut) in acc.iter_mut().zip(wave_cuts.iter()) {
+                for (n, (acc_n, s_n)) in a.iter_mut().zip(s.iter()).enumerate() {
+                    let w = _mm_loadu_pd(wave_cut.get_unchecked(w_idx + 2 * n));
+                    *acc_n = _mm_add_pd(*acc_n, _mm_mul_pd(w, *s_n));
+                }
+            }
+            w_idx += 8;
+            s_idx += 4;
+        }
+        let mut result = [0.0; 2];
+        for (res, a) in result.iter_mut().zip(acc.iter()) {
+            let temp2_0 = _mm_add_pd(a[0], a[1]);
+            let temp2_1 = _mm_add_pd(a[2], a[3]);
+            let temp2 = _mm_hadd_pd(temp2_0, temp2_1);
+            let temp1 = _mm_hadd_pd(temp2, temp2);
+            _mm_store_sd(res, temp1);
+        }
+        result
+    }
 }
 
 /// A SSE accelerated interpolator.
@@ -158,6 +247,22 @@ where
     sincs: Vec<Vec<T::Sinc>>,
     length: usize,
     nbr_sincs: usize,
+    skip: usize,
+}
+
+impl<T> SseInterpolator<T>
+where
+    T: SseSample,
+{
+    /// Get the packed values of the sinc of `subindex`, without the skipped taps.
+    ///
+    /// # Safety
+    ///
+    /// The `subindex` must be less than the number of sincs.
+    unsafe fn active_sinc(&self, subindex: usize) -> &[T::Sinc] {
+        let values_per_pack = std::mem::size_of::<T::Sinc>() / std::mem::size_of::<T>();
+        &self.sincs.get_unchecked(subindex)[self.skip / values_per_pack..]
+    }
 }
 
 impl<T> SincInterpolator<T> for SseInterpolator<T>
@@ -178,7 +283,14 @@ where
             subindex,
             self.nbr_sincs - 1
         );
-        unsafe { T::get_sinc_interpolated_unsafe(wave, index, subindex, &self.sincs, self.length) }
+        unsafe {
+            T::get_sinc_interpolated_unsafe(
+                wave,
+                index + self.skip,
+                self.active_sinc(subindex),
+                self.length - 2 * self.skip,
+            )
+        }
     }
 
     fn len(&self) -> usize {
@@ -188,6 +300,40 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn set_active_len(&mut self, taps: usize) -> usize {
+        self.skip = skip_for_active_len(self.length, taps);
+        self.active_len()
+    }
+
+    fn active_len(&self) -> usize {
+        self.length - 2 * self.skip
+    }
+
+    /// Calculate the scalar products of both channels of a stereo wave and the selected sinc filter.
+    fn get_sinc_interpolated_pair(&self, waves: [&[T]; 2], index: usize, subindex: usize) -> [T; 2] {
+        let wave_len = waves[0].len().min(waves[1].len());
+        assert!(
+            (index + self.length) < wave_len,
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave_len - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        unsafe {
+            T::get_sinc_interpolated_pair_unsafe(
+                waves,
+                index + self.skip,
+                self.active_sinc(subindex),
+                self.length - 2 * self.skip,
+            )
+        }
+    }
 }
 
 impl<T> SseInterpolator<T>
@@ -219,6 +365,7 @@ where
             sincs,
             length: sinc_len,
             nbr_sincs: oversampling_factor,
+            skip: 0,
         })
     }
 }
@@ -248,7 +395,7 @@ mod tests {
             wave.push(rng.gen::<f64>());
         }
         let sinc_len = 256;
-        let f_cutoff = 0.9473371669037001;
+        let f_cutoff = 0.947_337_15;
         let oversampling_factor = 256;
         let window = WindowFunction::BlackmanHarris2;
         let sincs = make_sincs::<f64>(sinc_len, oversampling_factor, f_cutoff, window);
@@ -267,7 +414,7 @@ mod tests {
             wave.push(rng.gen::<f32>());
         }
         let sinc_len = 256;
-        let f_cutoff = 0.9473371669037001;
+        let f_cutoff = 0.947_337_15;
         let oversampling_factor = 256;
         let window = WindowFunction::BlackmanHarris2;
         let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window);
@@ -0,0 +1,230 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{make_buffer, FftFixedIn, Resampler, Sample};
+
+/// A helper that owns an [FftFixedIn], and accepts input of any length.
+///
+/// The FFT resamplers need the input in chunks of a fixed size.
+/// This helper buffers the input pushed with [push](FftStream::push),
+/// and processes a chunk each time enough input has been collected.
+/// Input that doesn't fill a complete chunk stays in the buffer until the next push.
+/// The output is identical to that of the wrapped resampler when given the same input
+/// in chunks of the exact size.
+///
+/// At the end of the stream, [flush](FftStream::flush) processes the remaining input
+/// and returns the rest of the output.
+pub struct FftStream<T> {
+    resampler: FftFixedIn<T>,
+    input: Vec<Vec<T>>,
+    chunk_in: Vec<Vec<T>>,
+    chunk_out: Vec<Vec<T>>,
+    frames_in: usize,
+    frames_out: usize,
+}
+
+impl<T> FftStream<T>
+where
+    T: Sample,
+{
+    /// Create a new FftStream, wrapping the given resampler.
+    pub fn new(resampler: FftFixedIn<T>) -> Self {
+        let input = make_buffer(
+            resampler.nbr_channels(),
+            2 * resampler.input_frames_max(),
+            false,
+        );
+        let chunk_in = make_buffer(resampler.nbr_channels(), resampler.input_frames_max(), true);
+        let chunk_out = resampler.output_buffer_allocate(true);
+        FftStream {
+            resampler,
+            input,
+            chunk_in,
+            chunk_out,
+            frames_in: 0,
+            frames_out: 0,
+        }
+    }
+
+    /// Push any number of input frames, and return the output of all chunks that could be completed.
+    ///
+    /// All channels of `wave_in` must have the same length, which may be zero.
+    /// The returned vector holds one channel for each input channel,
+    /// and may be empty if the buffered input is still shorter than a chunk.
+    /// Note that this method allocates the returned vector,
+    /// and grows the internal buffer if more than one chunk of input is pushed at once.
+    pub fn push<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
+        let channels = self.resampler.nbr_channels();
+        if wave_in.len() != channels {
+            return Err(ResampleError::WrongNumberOfInputChannels {
+                expected: channels,
+                actual: wave_in.len(),
+            });
+        }
+        let frames = wave_in[0].as_ref().len();
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if wave.as_ref().len() != frames {
+                return Err(ResampleError::InsufficientInputBufferSize {
+                    channel: chan,
+                    expected: frames,
+                    actual: wave.as_ref().len(),
+                });
+            }
+        }
+        for (buffer, wave) in self.input.iter_mut().zip(wave_in.iter()) {
+            buffer.extend_from_slice(wave.as_ref());
+        }
+        self.frames_in += frames;
+
+        let chunk_size = self.resampler.input_frames_next();
+        let chunks = self.buffered_frames() / chunk_size;
+        let mut wave_out =
+            vec![Vec::with_capacity(chunks * self.resampler.output_frames_max()); channels];
+        for chunk in 0..chunks {
+            for (buf, buffer) in self.chunk_in.iter_mut().zip(self.input.iter()) {
+                buf[..chunk_size]
+                    .copy_from_slice(&buffer[chunk * chunk_size..(chunk + 1) * chunk_size]);
+            }
+            let (_, produced) =
+                self.resampler
+                    .process_into_buffer(&self.chunk_in, &mut self.chunk_out, None)?;
+            for (out, chan_out) in wave_out.iter_mut().zip(self.chunk_out.iter()) {
+                out.extend_from_slice(&chan_out[..produced]);
+            }
+            self.frames_out += produced;
+        }
+        for buffer in self.input.iter_mut() {
+            buffer.drain(..chunks * chunk_size);
+        }
+        Ok(wave_out)
+    }
+
+    /// Finish the stream, by processing the remaining buffered input
+    /// and returning all the remaining output frames.
+    ///
+    /// The output follows the same rules as [finish](Resampler::finish) of the wrapped resampler.
+    /// For a stream of `N` pushed input frames, the output from all calls to [push](FftStream::push)
+    /// plus the output of this method is `ceil(N * ratio)` frames plus the
+    /// [output_delay](Resampler::output_delay) of the resampler.
+    /// The stream is then reset, and can be used for a new stream.
+    pub fn flush(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        let (fft_size_in, fft_size_out) = self.resampler.fft_sizes();
+        let total_out = (self.frames_in * fft_size_out + fft_size_in - 1) / fft_size_in
+            + self.resampler.output_delay();
+        let channels = self.resampler.nbr_channels();
+        let mut wave_out = vec![Vec::new(); channels];
+        if self.buffered_frames() > 0 {
+            // The last partial chunk is padded with zeros.
+            let chunk = self.resampler.process_partial(Some(&self.input), None)?;
+            for (out, chan_out) in wave_out.iter_mut().zip(chunk) {
+                out.extend_from_slice(&chan_out);
+            }
+        }
+        let tail = self.resampler.finish()?;
+        for (out, chan_out) in wave_out.iter_mut().zip(tail) {
+            out.extend_from_slice(&chan_out);
+            // Drop the frames that only result from the zero padding.
+            out.truncate(total_out.saturating_sub(self.frames_out));
+        }
+        for buffer in self.input.iter_mut() {
+            buffer.clear();
+        }
+        self.frames_in = 0;
+        self.frames_out = 0;
+        Ok(wave_out)
+    }
+
+    /// Get the number of input frames per channel that are buffered but not yet processed.
+    pub fn buffered_frames(&self) -> usize {
+        self.input[0].len()
+    }
+
+    /// Get a reference to the wrapped resampler.
+    pub fn resampler(&self) -> &FftFixedIn<T> {
+        &self.resampler
+    }
+
+    /// Consume the FftStream and return the wrapped resampler.
+    /// Any buffered input is dropped.
+    pub fn into_inner(self) -> FftFixedIn<T> {
+        self.resampler
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FftStream;
+    use crate::{FftFixedIn, Resampler};
+    use rand::Rng;
+
+    fn random_waves(frames: usize) -> Vec<Vec<f64>> {
+        let mut rng = rand::thread_rng();
+        (0..2)
+            .map(|_| (0..frames).map(|_| rng.gen::<f64>() - 0.5).collect())
+            .collect()
+    }
+
+    fn push_random_lengths(stream: &mut FftStream<f64>, input: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        let mut rng = rand::thread_rng();
+        let mut collected = vec![Vec::new(); 2];
+        let mut offset = 0;
+        while offset < input[0].len() {
+            let frames = rng.gen_range(0..700).min(input[0].len() - offset);
+            let block = input
+                .iter()
+                .map(|chan| &chan[offset..offset + frames])
+                .collect::<Vec<_>>();
+            let output = stream.push(&block).unwrap();
+            for (coll, out) in collected.iter_mut().zip(output) {
+                coll.extend_from_slice(&out);
+            }
+            offset += frames;
+        }
+        collected
+    }
+
+    #[test]
+    fn matches_exact_chunks() {
+        let input = random_waves(10 * 512);
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2).unwrap();
+        let mut expected = vec![Vec::new(); 2];
+        for chunk in 0..10 {
+            let block = input
+                .iter()
+                .map(|chan| &chan[chunk * 512..(chunk + 1) * 512])
+                .collect::<Vec<_>>();
+            let output = resampler.process(&block, None).unwrap();
+            for (exp, out) in expected.iter_mut().zip(output) {
+                exp.extend_from_slice(&out);
+            }
+        }
+        let mut stream = FftStream::new(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2).unwrap());
+        let collected = push_random_lengths(&mut stream, &input);
+        assert_eq!(stream.buffered_frames(), 0);
+        assert_eq!(collected, expected);
+        assert_eq!(stream.flush().unwrap(), resampler.finish().unwrap());
+    }
+
+    #[test]
+    fn flush_partial_chunk() {
+        let frames_in = 10 * 512 + 300;
+        let input = random_waves(frames_in);
+        let mut stream = FftStream::new(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2).unwrap());
+        let mut collected = push_random_lengths(&mut stream, &input);
+        assert_eq!(stream.buffered_frames(), 300);
+        let delay = stream.resampler().output_delay();
+        for (coll, out) in collected.iter_mut().zip(stream.flush().unwrap()) {
+            coll.extend_from_slice(&out);
+        }
+        let expected_len = (frames_in as f64 * 48000.0 / 44100.0).ceil() as usize + delay;
+        assert_eq!(collected[0].len(), expected_len);
+        assert_eq!(collected[1].len(), expected_len);
+        assert_eq!(stream.buffered_frames(), 0);
+    }
+
+    #[test]
+    fn wrong_channels() {
+        let mut stream = FftStream::new(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2).unwrap());
+        assert!(stream.push(&vec![vec![0.0; 100]; 1]).is_err());
+        assert!(stream.push(&[vec![0.0; 100], vec![0.0; 99]]).is_err());
+        assert_eq!(stream.buffered_frames(), 0);
+    }
+}
@@ -1,24 +1,23 @@
 use crate::error::ResamplerConstructionError;
+use crate::fft::{DefaultFft, Fft};
 use crate::sinc::make_sincs;
 use crate::windows::WindowFunction;
 use num_complex::Complex;
 use num_integer as integer;
 use num_traits::Zero;
-use std::sync::Arc;
 
 use crate::error::{ResampleError, ResampleResult};
-use crate::{calculate_cutoff, update_mask_from_buffers, validate_buffers, Resampler, Sample};
-use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use crate::{
+    calculate_cutoff, reactivated_channels, update_mask_from_buffers, validate_buffers,
+    validate_channels, Resampler, Sample,
+};
 
 /// A helper for resampling a single chunk of data.
 struct FftResampler<T> {
     fft_size_in: usize,
     fft_size_out: usize,
     filter_f: Vec<Complex<T>>,
-    fft: Arc<dyn RealToComplex<T>>,
-    ifft: Arc<dyn ComplexToReal<T>>,
-    scratch_fw: Vec<Complex<T>>,
-    scratch_inv: Vec<Complex<T>>,
+    fft: Box<dyn Fft<T>>,
     input_buf: Vec<T>,
     input_f: Vec<Complex<T>>,
     output_f: Vec<Complex<T>>,
@@ -41,6 +40,7 @@ pub struct FftFixedIn<T> {
     channel_mask: Vec<bool>,
     saved_frames: usize,
     resampler: FftResampler<T>,
+    max_output_frames: usize,
 }
 
 /// A synchronous resampler that needs a varying number of audio frames for input
@@ -59,7 +59,9 @@ pub struct FftFixedOut<T> {
     channel_mask: Vec<bool>,
     saved_frames: usize,
     frames_needed: usize,
+    reservoir_len: usize,
     resampler: FftResampler<T>,
+    max_output_frames: usize,
 }
 
 /// A synchronous resampler that accepts a fixed number of audio frames for input
@@ -76,6 +78,30 @@ pub struct FftFixedInOut<T> {
     channel_mask: Vec<bool>,
     overlaps: Vec<Vec<T>>,
     resampler: FftResampler<T>,
+    double_precision: Option<DoublePrecisionFft<T>>,
+    max_output_frames: usize,
+}
+
+/// Resampler and buffers for running the transforms in double precision,
+/// for resamplers that store their samples in single precision.
+struct DoublePrecisionFft<T> {
+    resampler: FftResampler<f64>,
+    overlaps: Vec<Vec<f64>>,
+    wave_in: Vec<f64>,
+    wave_out: Vec<f64>,
+    /// The spectrum in single precision, for editing by [FftFixedInOut::process_with_spectrum].
+    spectrum: Vec<Complex<T>>,
+}
+
+/// An editing function for the spectrum of each block, see [FftFixedIn::process_with_spectrum].
+type SpectrumEdit<'a, T> = Option<&'a mut dyn FnMut(&mut [Complex<T>])>;
+
+/// Reborrow an editing function, for passing it on once per block.
+fn reborrow<'a, T>(edit: &'a mut SpectrumEdit<'_, T>) -> SpectrumEdit<'a, T> {
+    match edit {
+        Some(edit) => Some(&mut **edit),
+        None => None,
+    }
 }
 
 fn validate_sample_rates(input: usize, output: usize) -> Result<(), ResamplerConstructionError> {
@@ -85,59 +111,91 @@ fn validate_sample_rates(input: usize, output: usize) -> Result<(), ResamplerCon
     Ok(())
 }
 
+/// The largest sample rate, after dividing by the greatest common divisor of the rates,
+/// that the constructors accept without warning about the FFT size.
+const MAX_REDUCED_RATE: usize = 8192;
+
+/// Log a warning if the sample rates, divided by their greatest common divisor,
+/// are so large that the FFTs become impractically large. Returns true if it warned.
+fn warn_large_fft(input: usize, output: usize) -> bool {
+    let gcd = integer::gcd(input, output);
+    let reduced = (input / gcd).max(output / gcd);
+    if reduced > MAX_REDUCED_RATE {
+        warn!(
+            "The sample rates {} and {} reduce to {}/{}, which gives FFTs of at least {} points. Consider using an asynchronous resampler instead",
+            input,
+            output,
+            input / gcd,
+            output / gcd,
+            2 * reduced
+        );
+        return true;
+    }
+    false
+}
+
 impl<T> FftResampler<T>
 where
     T: Sample,
 {
     //
     pub fn new(fft_size_in: usize, fft_size_out: usize) -> Self {
-        // calculate antialiasing cutoff
-        let cutoff = if fft_size_in > fft_size_out {
-            calculate_cutoff::<f32>(fft_size_out, WindowFunction::BlackmanHarris2)
-                * fft_size_out as f32
-                / fft_size_in as f32
-        } else {
-            calculate_cutoff::<f32>(fft_size_in, WindowFunction::BlackmanHarris2)
-        };
-        debug!(
-            "Create new FftResampler, fft_size_in: {}, fft_size_out: {}, cutoff: {}",
-            fft_size_in, fft_size_out, cutoff
-        );
-        let sinc = make_sincs::<T>(fft_size_in, 1, cutoff, WindowFunction::BlackmanHarris2);
-        let mut filter_t: Vec<T> = vec![T::zero(); 2 * fft_size_in];
-        let mut filter_f: Vec<Complex<T>> = vec![Complex::zero(); fft_size_in + 1];
-        for (n, f) in filter_t.iter_mut().enumerate().take(fft_size_in) {
-            *f = sinc[0][n] / T::coerce(2 * fft_size_in);
-        }
+        Self::new_with_fft(fft_size_in, fft_size_out, Box::new(DefaultFft::new()))
+    }
 
+    /// Create a new FftResampler using the given FFT implementation.
+    pub fn new_with_fft(fft_size_in: usize, fft_size_out: usize, mut fft: Box<dyn Fft<T>>) -> Self {
         let input_f: Vec<Complex<T>> = vec![Complex::zero(); fft_size_in + 1];
         let input_buf: Vec<T> = vec![T::zero(); 2 * fft_size_in];
         let output_f: Vec<Complex<T>> = vec![Complex::zero(); fft_size_out + 1];
         let output_buf: Vec<T> = vec![T::zero(); 2 * fft_size_out];
-        let mut planner = RealFftPlanner::<T>::new();
-        let fft = planner.plan_fft_forward(2 * fft_size_in);
-        let ifft = planner.plan_fft_inverse(2 * fft_size_out);
-        fft.process(&mut filter_t, &mut filter_f).unwrap();
-        let scratch_fw = fft.make_scratch_vec();
-        let scratch_inv = ifft.make_scratch_vec();
-
-        FftResampler {
+        fft.prepare(2 * fft_size_in, 2 * fft_size_out);
+
+        let mut resampler = FftResampler {
             fft_size_in,
             fft_size_out,
-            filter_f,
+            filter_f: vec![Complex::zero(); fft_size_in + 1],
             fft,
-            ifft,
-            scratch_fw,
-            scratch_inv,
             input_buf,
             input_f,
             output_f,
             output_buf,
+        };
+        resampler.set_window(WindowFunction::BlackmanHarris2);
+        resampler
+    }
+
+    /// Calculate the antialiasing filter, using the given window function.
+    fn set_window(&mut self, window: WindowFunction) {
+        let fft_size_in = self.fft_size_in;
+        let fft_size_out = self.fft_size_out;
+        // calculate antialiasing cutoff
+        let cutoff = if fft_size_in > fft_size_out {
+            calculate_cutoff::<f32>(fft_size_out, window) * fft_size_out as f32 / fft_size_in as f32
+        } else {
+            calculate_cutoff::<f32>(fft_size_in, window)
+        };
+        debug!(
+            "Create new FftResampler, fft_size_in: {}, fft_size_out: {}, cutoff: {}, window: {:?}",
+            fft_size_in, fft_size_out, cutoff, window
+        );
+        let sinc = make_sincs::<T>(fft_size_in, 1, cutoff, window);
+        let mut filter_t: Vec<T> = vec![T::zero(); 2 * fft_size_in];
+        for (n, f) in filter_t.iter_mut().enumerate().take(fft_size_in) {
+            *f = sinc[0][n] / T::coerce(2 * fft_size_in);
         }
+        self.fft.forward(&mut filter_t, &mut self.filter_f);
     }
 
     /// Resample a small chunk.
-    fn resample_unit(&mut self, wave_in: &[T], wave_out: &mut [T], overlap: &mut [T]) {
+    /// The spectrum is passed to `edit` right after the forward transform.
+    fn resample_unit(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        overlap: &mut [T],
+        edit: SpectrumEdit<T>,
+    ) {
         // Copy to input buffer and clear padding area.
         self.input_buf[0..self.fft_size_in].copy_from_slice(wave_in);
         for item in self
@@ -150,9 +208,10 @@ where
         }
 
         // FFT and store result in history, update index.
-        self.fft
-            .process_with_scratch(&mut self.input_buf, &mut self.input_f, &mut self.scratch_fw)
-            .unwrap();
+        self.fft.forward(&mut self.input_buf, &mut self.input_f);
+        if let Some(edit) = edit {
+            edit(&mut self.input_f);
+        }
 
         let new_len = if self.fft_size_in < self.fft_size_out {
             self.fft_size_in + 1
@@ -173,18 +232,55 @@ where
             *val = Complex::zero();
         }
         // IFFT result, store result and overlap.
-        self.ifft
-            .process_with_scratch(
-                &mut self.output_f,
-                &mut self.output_buf,
-                &mut self.scratch_inv,
-            )
-            .unwrap();
+        self.fft.inverse(&mut self.output_f, &mut self.output_buf);
         for (n, item) in wave_out.iter_mut().enumerate().take(self.fft_size_out) {
             *item = self.output_buf[n] + overlap[n];
         }
         overlap.copy_from_slice(&self.output_buf[self.fft_size_out..]);
     }
+
+    /// Get the number of bytes used by the buffers of a FftResampler with the given sizes.
+    /// The memory used by the FFT implementation is not included.
+    fn memory_footprint(fft_size_in: usize, fft_size_out: usize) -> usize {
+        let sample = std::mem::size_of::<T>();
+        let complex = std::mem::size_of::<Complex<T>>();
+        (2 * (fft_size_in + 1) + fft_size_out + 1) * complex
+            + 2 * (fft_size_in + fft_size_out) * sample
+    }
+}
+
+/// Calculate the FFT sizes `(fft_size_in, fft_size_out)` for a resampler
+/// with a fixed input chunk size, split into `sub_chunks` sub chunks.
+fn fft_sizes_fixed_in(
+    sample_rate_input: usize,
+    sample_rate_output: usize,
+    chunk_size_in: usize,
+    sub_chunks: usize,
+) -> (usize, usize) {
+    let gcd = integer::gcd(sample_rate_input, sample_rate_output);
+    let min_chunk_in = sample_rate_input / gcd;
+    let wanted_subsize = chunk_size_in / sub_chunks;
+    let fft_chunks = (wanted_subsize as f32 / min_chunk_in as f32).ceil() as usize;
+    let fft_size_out = fft_chunks * sample_rate_output / gcd;
+    let fft_size_in = fft_chunks * sample_rate_input / gcd;
+    (fft_size_in, fft_size_out)
+}
+
+/// Calculate the FFT sizes `(fft_size_in, fft_size_out)` for a resampler
+/// with a fixed output chunk size, split into `sub_chunks` sub chunks.
+fn fft_sizes_fixed_out(
+    sample_rate_input: usize,
+    sample_rate_output: usize,
+    chunk_size_out: usize,
+    sub_chunks: usize,
+) -> (usize, usize) {
+    let gcd = integer::gcd(sample_rate_input, sample_rate_output);
+    let min_chunk_out = sample_rate_output / gcd;
+    let wanted_subsize = chunk_size_out / sub_chunks;
+    let fft_chunks = (wanted_subsize as f32 / min_chunk_out as f32).ceil() as usize;
+    let fft_size_out = fft_chunks * sample_rate_output / gcd;
+    let fft_size_in = fft_chunks * sample_rate_input / gcd;
+    (fft_size_in, fft_size_out)
 }
 
 impl<T> FftFixedInOut<T>
@@ -197,32 +293,112 @@ where
     /// - `sample_rate_input`: Input sample rate, must be > 0.
     /// - `sample_rate_output`: Output sample rate, must be > 0.
     /// - `chunk_size_in`: desired length of input data in frames, actual value may be different.
-    /// - `nbr_channels`: number of channels in input/output.
+    /// - `nbr_channels`: number of channels in input/output, must be > 0.
     pub fn new(
         sample_rate_input: usize,
         sample_rate_output: usize,
         chunk_size_in: usize,
         nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::new_with_fft_precision(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            nbr_channels,
+            false,
+        )
+    }
+
+    /// Create a new FftFixedInOut, optionally running the transforms in double precision.
+    ///
+    /// When `double_precision_fft` is true, each block is converted to f64 before
+    /// the forward transform, and the result is converted back after the inverse transform.
+    /// This reduces the accumulated rounding errors of f32 transforms,
+    /// which is useful for long cascades of processing.
+    /// The f64 transforms take roughly twice the time of the f32 ones, and the conversions add some more.
+    /// For a resampler of f64 samples the flag has no effect.
+    ///
+    /// Parameters are:
+    /// - `sample_rate_input`: Input sample rate, must be > 0.
+    /// - `sample_rate_output`: Output sample rate, must be > 0.
+    /// - `chunk_size_in`: desired length of input data in frames, actual value may be different.
+    /// - `nbr_channels`: number of channels in input/output, must be > 0.
+    /// - `double_precision_fft`: run the transforms in f64.
+    pub fn new_with_fft_precision(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+        double_precision_fft: bool,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::new_with_options(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            nbr_channels,
+            double_precision_fft,
+            Box::new(DefaultFft::new()),
+        )
+    }
+
+    /// Create a new FftFixedInOut using a custom FFT implementation, see [Fft].
+    ///
+    /// The other parameters are the same as for [FftFixedInOut::new].
+    pub fn new_with_fft(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+        fft: Box<dyn Fft<T>>,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::new_with_options(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            nbr_channels,
+            false,
+            fft,
+        )
+    }
+
+    fn new_with_options(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+        double_precision_fft: bool,
+        fft: Box<dyn Fft<T>>,
     ) -> Result<Self, ResamplerConstructionError> {
         validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        validate_channels(nbr_channels)?;
+        warn_large_fft(sample_rate_input, sample_rate_output);
 
         debug!(
-            "Create new FftFixedInOut, sample_rate_input: {}, sample_rate_output: {} chunk_size_in: {}, channels: {}",
-            sample_rate_input, sample_rate_output, chunk_size_in, nbr_channels
+            "Create new FftFixedInOut, sample_rate_input: {}, sample_rate_output: {} chunk_size_in: {}, channels: {}, double_precision_fft: {}",
+            sample_rate_input, sample_rate_output, chunk_size_in, nbr_channels, double_precision_fft
         );
 
-        let gcd = integer::gcd(sample_rate_input, sample_rate_output);
-        let min_chunk_in = sample_rate_input / gcd;
-        let fft_chunks = (chunk_size_in as f32 / min_chunk_in as f32).ceil() as usize;
-        let fft_size_out = fft_chunks * sample_rate_output / gcd;
-        let fft_size_in = fft_chunks * sample_rate_input / gcd;
+        let (fft_size_in, fft_size_out) =
+            fft_sizes_fixed_in(sample_rate_input, sample_rate_output, chunk_size_in, 1);
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
+        let resampler = FftResampler::<T>::new_with_fft(fft_size_in, fft_size_out, fft);
 
         let overlaps: Vec<Vec<T>> = vec![vec![T::zero(); fft_size_out]; nbr_channels];
 
         let channel_mask = vec![true; nbr_channels];
 
+        let double_precision = if double_precision_fft && std::mem::size_of::<T>() < 8 {
+            Some(DoublePrecisionFft {
+                resampler: FftResampler::<f64>::new(fft_size_in, fft_size_out),
+                overlaps: vec![vec![0.0; fft_size_out]; nbr_channels],
+                wave_in: vec![0.0; fft_size_in],
+                wave_out: vec![0.0; fft_size_out],
+                spectrum: vec![Complex::zero(); fft_size_in + 1],
+            })
+        } else {
+            None
+        };
+
         Ok(FftFixedInOut {
             nbr_channels,
             chunk_size_in: fft_size_in,
@@ -231,46 +407,175 @@ where
             overlaps,
             resampler,
             channel_mask,
+            double_precision,
+            max_output_frames: usize::MAX,
         })
     }
+
+    /// Estimate the number of bytes used by a FftFixedInOut with the given parameters.
+    ///
+    /// The parameters are the same as for [FftFixedInOut::new].
+    /// The estimate includes the same components as [FftFixedIn::memory_footprint],
+    /// except that there is no input buffer.
+    /// The additional buffers needed for running the transforms in double precision,
+    /// see [FftFixedInOut::new_with_fft_precision], are not included.
+    pub fn memory_footprint(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+    ) -> Result<usize, ResamplerConstructionError> {
+        validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        let (fft_size_in, fft_size_out) =
+            fft_sizes_fixed_in(sample_rate_input, sample_rate_output, chunk_size_in, 1);
+        let per_channel = fft_size_out * std::mem::size_of::<T>() + std::mem::size_of::<bool>();
+        Ok(
+            FftResampler::<T>::memory_footprint(fft_size_in, fft_size_out)
+                + nbr_channels * per_channel,
+        )
+    }
+
+    /// Get the latency caused by the FFT block processing, in output frames.
+    ///
+    /// A whole block of `fft_size_in` input frames is needed before it can be transformed,
+    /// which gives a latency of one block of output frames in addition to the
+    /// [output_delay](Resampler::output_delay) of the filter.
+    /// The block size is determined by the sample rates divided by their greatest common divisor,
+    /// and becomes large for rates that have few common factors, like 44101 and 48000 Hz.
+    pub fn fft_latency_frames(&self) -> usize {
+        self.chunk_size_out + self.output_delay()
+    }
+
+    /// Set the window function used for the antialiasing filter,
+    /// replacing the default [WindowFunction::BlackmanHarris2].
+    pub(crate) fn set_window(&mut self, window: WindowFunction) {
+        self.resampler.set_window(window);
+        if let Some(dp) = self.double_precision.as_mut() {
+            dp.resampler.set_window(window);
+        }
+    }
+
+    /// Process a chunk, and let `edit` modify the spectrum of each block in between the transforms.
+    /// See [FftFixedIn::process_with_spectrum].
+    ///
+    /// When the transforms run in double precision, see [FftFixedInOut::new_with_fft_precision],
+    /// the spectrum is rounded to the sample type before it is passed to `edit`.
+    pub fn process_with_spectrum<Vin, Vout, F>(
+        &mut self,
+        wave_in: &[Vin],
+        mut edit: F,
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        Vin: AsRef<[T]>,
+        Vout: AsMut<[T]>,
+        F: FnMut(&mut [Complex<T>]),
+    {
+        self.process_buffers(wave_in, wave_out, active_channels_mask, Some(&mut edit))
+    }
 }
 
-impl<T> Resampler<T> for FftFixedInOut<T>
+impl<T> FftFixedInOut<T>
 where
     T: Sample,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    /// Process a chunk of input into the output buffers.
+    fn process_buffers<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
         &mut self,
         wave_in: &[Vin],
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
+        mut edit: SpectrumEdit<T>,
     ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
-        } else {
-            update_mask_from_buffers(&mut self.channel_mask);
-        };
-
         validate_buffers(
             wave_in,
             wave_out,
-            &self.channel_mask,
+            active_channels_mask,
             self.nbr_channels,
             self.chunk_size_in,
             self.chunk_size_out,
         )?;
 
+        for chan in reactivated_channels(&self.channel_mask, active_channels_mask) {
+            self.overlaps[chan].iter_mut().for_each(|s| *s = T::zero());
+            if let Some(double_precision) = self.double_precision.as_mut() {
+                double_precision.overlaps[chan]
+                    .iter_mut()
+                    .for_each(|s| *s = 0.0);
+            }
+        }
+        if let Some(mask) = active_channels_mask {
+            self.channel_mask.copy_from_slice(mask);
+        } else {
+            update_mask_from_buffers(&mut self.channel_mask);
+        };
+
         for (channel, active) in self.channel_mask.iter().enumerate() {
-            if *active {
+            if !*active {
+                continue;
+            }
+            let chunk_in = &wave_in[channel].as_ref()[..self.chunk_size_in];
+            let chunk_out = &mut wave_out[channel].as_mut()[..self.chunk_size_out];
+            if let Some(double) = &mut self.double_precision {
+                for (wide, sample) in double.wave_in.iter_mut().zip(chunk_in) {
+                    *wide = sample.to_f64();
+                }
+                match reborrow(&mut edit) {
+                    Some(edit) => {
+                        // The spectrum is edited in the sample type of the resampler.
+                        let spectrum = &mut double.spectrum;
+                        let mut narrowed = |wide: &mut [Complex<f64>]| {
+                            for (narrow, wide) in spectrum.iter_mut().zip(wide.iter()) {
+                                *narrow = Complex::new(T::coerce(wide.re), T::coerce(wide.im));
+                            }
+                            edit(spectrum);
+                            for (wide, narrow) in wide.iter_mut().zip(spectrum.iter()) {
+                                *wide = Complex::new(narrow.re.to_f64(), narrow.im.to_f64());
+                            }
+                        };
+                        double.resampler.resample_unit(
+                            &double.wave_in,
+                            &mut double.wave_out,
+                            &mut double.overlaps[channel],
+                            Some(&mut narrowed),
+                        );
+                    }
+                    None => double.resampler.resample_unit(
+                        &double.wave_in,
+                        &mut double.wave_out,
+                        &mut double.overlaps[channel],
+                        None,
+                    ),
+                }
+                for (sample, wide) in chunk_out.iter_mut().zip(&double.wave_out) {
+                    *sample = T::coerce(*wide);
+                }
+            } else {
                 self.resampler.resample_unit(
-                    &wave_in[channel].as_ref()[..self.chunk_size_in],
-                    &mut wave_out[channel].as_mut()[..self.chunk_size_out],
+                    chunk_in,
+                    chunk_out,
                     &mut self.overlaps[channel],
-                )
+                    reborrow(&mut edit),
+                );
             }
         }
         Ok((self.chunk_size_in, self.chunk_size_out))
     }
+}
+
+impl<T> Resampler<T> for FftFixedInOut<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_buffers(wave_in, wave_out, active_channels_mask, None)
+    }
 
     fn input_frames_max(&self) -> usize {
         self.fft_size_in
@@ -292,10 +597,35 @@ where
         self.output_frames_max()
     }
 
+    fn output_frames_remaining(&self) -> usize {
+        self.output_delay()
+    }
+
     fn output_delay(&self) -> usize {
         self.chunk_size_out / 2
     }
 
+    fn set_max_output_frames(&mut self, max_frames: usize) {
+        self.max_output_frames = max_frames;
+    }
+
+    fn max_output_frames(&self) -> usize {
+        self.max_output_frames
+    }
+
+    fn target_ratio(&self) -> f64 {
+        self.chunk_size_out as f64 / self.chunk_size_in as f64
+    }
+
+    fn output_sample_rate(&self, input_sample_rate: f64) -> f64 {
+        input_sample_rate * self.chunk_size_out as f64 / self.chunk_size_in as f64
+    }
+
+    fn ratio_bounds(&self) -> (f64, f64) {
+        let ratio = self.chunk_size_out as f64 / self.chunk_size_in as f64;
+        (ratio, ratio)
+    }
+
     /// Update the resample ratio. This is not supported by this resampler and
     /// always returns an [ResampleError::SyncNotAdjustable].
     fn set_resample_ratio(&mut self, _new_ratio: f64, _ramp: bool) -> ResampleResult<()> {
@@ -312,6 +642,12 @@ where
         self.overlaps
             .iter_mut()
             .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
+        if let Some(double) = &mut self.double_precision {
+            double
+                .overlaps
+                .iter_mut()
+                .for_each(|ch| ch.iter_mut().for_each(|s| *s = 0.0));
+        }
         self.channel_mask.iter_mut().for_each(|val| *val = true);
     }
 }
@@ -327,24 +663,47 @@ where
     /// - `sample_rate_output`: Output sample rate, must be > 0.
     /// - `chunk_size_out`: length of output data in frames.
     /// - `sub_chunks`: desired number of subchunks for processing, actual number may be different.
-    /// - `nbr_channels`: number of channels in input/output.
+    /// - `nbr_channels`: number of channels in input/output, must be > 0.
     pub fn new(
         sample_rate_input: usize,
         sample_rate_output: usize,
         chunk_size_out: usize,
         sub_chunks: usize,
         nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::new_with_fft(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_out,
+            sub_chunks,
+            nbr_channels,
+            Box::new(DefaultFft::new()),
+        )
+    }
+
+    /// Create a new FftFixedOut using a custom FFT implementation, see [Fft].
+    ///
+    /// The other parameters are the same as for [FftFixedOut::new].
+    pub fn new_with_fft(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_out: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        fft: Box<dyn Fft<T>>,
     ) -> Result<Self, ResamplerConstructionError> {
         validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        validate_channels(nbr_channels)?;
+        warn_large_fft(sample_rate_input, sample_rate_output);
 
-        let gcd = integer::gcd(sample_rate_input, sample_rate_output);
-        let min_chunk_out = sample_rate_output / gcd;
-        let wanted_subsize = chunk_size_out / sub_chunks;
-        let fft_chunks = (wanted_subsize as f32 / min_chunk_out as f32).ceil() as usize;
-        let fft_size_out = fft_chunks * sample_rate_output / gcd;
-        let fft_size_in = fft_chunks * sample_rate_input / gcd;
+        let (fft_size_in, fft_size_out) = fft_sizes_fixed_out(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_out,
+            sub_chunks,
+        );
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
+        let resampler = FftResampler::<T>::new_with_fft(fft_size_in, fft_size_out, fft);
 
         debug!(
             "Create new FftFixedOut, sample_rate_input: {}, sample_rate_output: {} chunk_size_in: {}, channels: {}, fft_size_in: {}, fft_size_out: {}",
@@ -370,37 +729,145 @@ where
             output_buffers,
             saved_frames,
             frames_needed,
+            reservoir_len: chunk_size_out + fft_size_out,
             resampler,
             channel_mask,
+            max_output_frames: usize::MAX,
         })
     }
+
+    /// Estimate the number of bytes used by a FftFixedOut with the given parameters.
+    ///
+    /// The parameters are the same as for [FftFixedOut::new].
+    /// The estimate includes the same components as [FftFixedIn::memory_footprint],
+    /// with an output buffer per channel instead of the input buffer.
+    pub fn memory_footprint(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_out: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+    ) -> Result<usize, ResamplerConstructionError> {
+        validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        let (fft_size_in, fft_size_out) = fft_sizes_fixed_out(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_out,
+            sub_chunks,
+        );
+        let per_channel = (fft_size_out + chunk_size_out + fft_size_out) * std::mem::size_of::<T>()
+            + std::mem::size_of::<bool>();
+        Ok(
+            FftResampler::<T>::memory_footprint(fft_size_in, fft_size_out)
+                + nbr_channels * per_channel,
+        )
+    }
+
+    /// Get the latency caused by the FFT block processing, in output frames.
+    /// See [FftFixedInOut::fft_latency_frames].
+    pub fn fft_latency_frames(&self) -> usize {
+        self.fft_size_out + self.output_delay()
+    }
+
+    /// Set the window function used for the antialiasing filter,
+    /// replacing the default [WindowFunction::BlackmanHarris2].
+    pub(crate) fn set_window(&mut self, window: WindowFunction) {
+        self.resampler.set_window(window);
+    }
+
+    /// Get the length of the output reservoir in frames, see [set_reservoir_len](FftFixedOut::set_reservoir_len).
+    pub fn reservoir_len(&self) -> usize {
+        self.reservoir_len
+    }
+
+    /// Set the length of the output reservoir in frames.
+    ///
+    /// The resampler produces output in blocks of a fixed length, and keeps them in a reservoir
+    /// from which the output chunks are taken. The frames left over after each chunk are saved
+    /// for the next call, and the number of input frames needed varies from call to call
+    /// depending on how many blocks are needed to complete the chunk.
+    /// The shortest reservoir, and the default, is `chunk_size_out` plus the length of one block.
+    ///
+    /// A longer reservoir starts out with the extra frames filled with silence.
+    /// Each extra frame adds one frame of latency, which is included in
+    /// [output_delay](Resampler::output_delay), and lets the resampler request its input later.
+    /// With at least one block of extra frames, a reservoir length of at least
+    /// `2 * default - chunk_size_out`, the input needed for the first `k` output chunks
+    /// never exceeds `k * chunk_size_out / ratio` frames.
+    /// A source that delivers input at the nominal rate is then never asked for more input than
+    /// it has delivered, which avoids underflow when the input and output run on separate clocks
+    /// with a ratio that doesn't give a whole number of input frames per output chunk.
+    ///
+    /// A length shorter than the default gives a [ResampleError::ReservoirTooSmall].
+    /// This resets the resampler, and should be called before processing starts.
+    /// Note that this method allocates the longer reservoir.
+    pub fn set_reservoir_len(&mut self, frames: usize) -> ResampleResult<()> {
+        let min = self.chunk_size_out + self.fft_size_out;
+        if frames < min {
+            return Err(ResampleError::ReservoirTooSmall {
+                provided: frames,
+                min,
+            });
+        }
+        self.reservoir_len = frames;
+        for buffer in self.output_buffers.iter_mut() {
+            buffer.resize(frames, T::zero());
+        }
+        self.reset();
+        Ok(())
+    }
+
+    /// Process a chunk, and let `edit` modify the spectrum of each block in between the transforms.
+    /// See [FftFixedIn::process_with_spectrum].
+    pub fn process_with_spectrum<Vin, Vout, F>(
+        &mut self,
+        wave_in: &[Vin],
+        mut edit: F,
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        Vin: AsRef<[T]>,
+        Vout: AsMut<[T]>,
+        F: FnMut(&mut [Complex<T>]),
+    {
+        self.process_buffers(wave_in, wave_out, active_channels_mask, Some(&mut edit))
+    }
 }
 
-impl<T> Resampler<T> for FftFixedOut<T>
+impl<T> FftFixedOut<T>
 where
     T: Sample,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    /// Process a chunk of input into the output buffers.
+    fn process_buffers<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
         &mut self,
         wave_in: &[Vin],
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
+        mut edit: SpectrumEdit<T>,
     ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
-        } else {
-            update_mask_from_buffers(&mut self.channel_mask);
-        };
-
         validate_buffers(
             wave_in,
             wave_out,
-            &self.channel_mask,
+            active_channels_mask,
             self.nbr_channels,
             self.frames_needed,
             self.chunk_size_out,
         )?;
 
+        for chan in reactivated_channels(&self.channel_mask, active_channels_mask) {
+            self.overlaps[chan].iter_mut().for_each(|s| *s = T::zero());
+            self.output_buffers[chan]
+                .iter_mut()
+                .for_each(|s| *s = T::zero());
+        }
+        if let Some(mask) = active_channels_mask {
+            self.channel_mask.copy_from_slice(mask);
+        } else {
+            update_mask_from_buffers(&mut self.channel_mask);
+        };
+
         for (chan, active) in self.channel_mask.iter().enumerate() {
             if *active {
                 debug_assert!(self.chunk_size_out <= wave_out[chan].as_mut().len());
@@ -411,8 +878,12 @@ where
                             .chunks_mut(self.fft_size_out),
                     )
                 {
-                    self.resampler
-                        .resample_unit(in_chunk, out_chunk, &mut self.overlaps[chan]);
+                    self.resampler.resample_unit(
+                        in_chunk,
+                        out_chunk,
+                        &mut self.overlaps[chan],
+                        reborrow(&mut edit),
+                    );
                 }
             }
         }
@@ -436,16 +907,26 @@ where
             self.saved_frames = processed_frames;
         }
         // Calculate number of needed frames from next round.
-        let frames_needed_out = if self.chunk_size_out > self.saved_frames {
-            self.chunk_size_out - self.saved_frames
-        } else {
-            0
-        };
+        let frames_needed_out = self.chunk_size_out.saturating_sub(self.saved_frames);
         let input_frames_used = self.frames_needed;
         let chunks_needed = (frames_needed_out as f32 / self.fft_size_out as f32).ceil() as usize;
         self.frames_needed = chunks_needed * self.fft_size_in;
         Ok((input_frames_used, self.chunk_size_out))
     }
+}
+
+impl<T> Resampler<T> for FftFixedOut<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_buffers(wave_in, wave_out, active_channels_mask, None)
+    }
 
     fn input_frames_max(&self) -> usize {
         (self.chunk_size_out as f32 / self.fft_size_out as f32).ceil() as usize * self.fft_size_in
@@ -467,8 +948,41 @@ where
         self.output_frames_max()
     }
 
+    fn output_frames_remaining(&self) -> usize {
+        self.saved_frames + self.fft_size_out / 2
+    }
+
+    fn min_input_for_output(&self) -> usize {
+        if self.saved_frames > 0 {
+            0
+        } else {
+            self.fft_size_in
+        }
+    }
+
     fn output_delay(&self) -> usize {
-        self.fft_size_out / 2
+        self.fft_size_out / 2 + self.reservoir_len - self.chunk_size_out - self.fft_size_out
+    }
+
+    fn set_max_output_frames(&mut self, max_frames: usize) {
+        self.max_output_frames = max_frames;
+    }
+
+    fn max_output_frames(&self) -> usize {
+        self.max_output_frames
+    }
+
+    fn target_ratio(&self) -> f64 {
+        self.fft_size_out as f64 / self.fft_size_in as f64
+    }
+
+    fn output_sample_rate(&self, input_sample_rate: f64) -> f64 {
+        input_sample_rate * self.fft_size_out as f64 / self.fft_size_in as f64
+    }
+
+    fn ratio_bounds(&self) -> (f64, f64) {
+        let ratio = self.fft_size_out as f64 / self.fft_size_in as f64;
+        (ratio, ratio)
     }
 
     /// Update the resample ratio. This is not supported by this resampler and
@@ -491,8 +1005,10 @@ where
             .iter_mut()
             .for_each(|ch| ch.iter_mut().for_each(|s| *s = T::zero()));
         self.channel_mask.iter_mut().for_each(|val| *val = true);
-        self.saved_frames = 0;
-        let chunks_needed = (self.chunk_size_out as f32 / self.fft_size_out as f32).ceil() as usize;
+        // The extra frames of a longer reservoir start out as saved silence.
+        self.saved_frames = self.reservoir_len - self.chunk_size_out - self.fft_size_out;
+        let frames_needed_out = self.chunk_size_out.saturating_sub(self.saved_frames);
+        let chunks_needed = (frames_needed_out as f32 / self.fft_size_out as f32).ceil() as usize;
         self.frames_needed = chunks_needed * self.fft_size_in;
     }
 }
@@ -508,24 +1024,47 @@ where
     /// - `sample_rate_output`: Output sample rate, must be > 0.
     /// - `chunk_size_in`: length of input data in frames.
     /// - `sub_chunks`: desired number of subchunks for processing, actual number used may be different.
-    /// - `nbr_channels`: number of channels in input/output.
+    /// - `nbr_channels`: number of channels in input/output, must be > 0.
     pub fn new(
         sample_rate_input: usize,
         sample_rate_output: usize,
         chunk_size_in: usize,
         sub_chunks: usize,
         nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        Self::new_with_fft(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            sub_chunks,
+            nbr_channels,
+            Box::new(DefaultFft::new()),
+        )
+    }
+
+    /// Create a new FftFixedIn using a custom FFT implementation, see [Fft].
+    ///
+    /// The other parameters are the same as for [FftFixedIn::new].
+    pub fn new_with_fft(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        fft: Box<dyn Fft<T>>,
     ) -> Result<Self, ResamplerConstructionError> {
         validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        validate_channels(nbr_channels)?;
+        warn_large_fft(sample_rate_input, sample_rate_output);
 
-        let gcd = integer::gcd(sample_rate_input, sample_rate_output);
-        let min_chunk_in = sample_rate_input / gcd;
-        let wanted_subsize = chunk_size_in / sub_chunks;
-        let fft_chunks = (wanted_subsize as f32 / min_chunk_in as f32).ceil() as usize;
-        let fft_size_out = fft_chunks * sample_rate_output / gcd;
-        let fft_size_in = fft_chunks * sample_rate_input / gcd;
+        let (fft_size_in, fft_size_out) = fft_sizes_fixed_in(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            sub_chunks,
+        );
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
+        let resampler = FftResampler::<T>::new_with_fft(fft_size_in, fft_size_out, fft);
         debug!(
             "Create new FftFixedOut, sample_rate_input: {}, sample_rate_output: {} chunk_size_in: {}, channels: {}, fft_size_in: {}, fft_size_out: {}",
             sample_rate_input, sample_rate_output, chunk_size_in, nbr_channels, fft_size_in, fft_size_out
@@ -549,26 +1088,105 @@ where
             saved_frames,
             resampler,
             channel_mask,
+            max_output_frames: usize::MAX,
         })
     }
+
+    /// Estimate the number of bytes used by a FftFixedIn with the given parameters.
+    ///
+    /// The parameters are the same as for [FftFixedIn::new].
+    /// The estimate includes:
+    /// - The buffers for the spectra and the transforms, and the spectrum of the anti-aliasing filter.
+    ///   These depend on the FFT sizes, which are determined by the sample rates
+    ///   and the length of the sub chunks.
+    /// - One input buffer and one overlap buffer per channel, as well as the channel mask.
+    ///
+    /// The memory used by the FFT implementation for plans and scratch space is not included,
+    /// nor the small fixed size of the resampler struct or any buffers allocated by the caller.
+    pub fn memory_footprint(
+        sample_rate_input: usize,
+        sample_rate_output: usize,
+        chunk_size_in: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+    ) -> Result<usize, ResamplerConstructionError> {
+        validate_sample_rates(sample_rate_input, sample_rate_output)?;
+        let (fft_size_in, fft_size_out) = fft_sizes_fixed_in(
+            sample_rate_input,
+            sample_rate_output,
+            chunk_size_in,
+            sub_chunks,
+        );
+        let per_channel = (fft_size_out + chunk_size_in + fft_size_in) * std::mem::size_of::<T>()
+            + std::mem::size_of::<bool>();
+        Ok(
+            FftResampler::<T>::memory_footprint(fft_size_in, fft_size_out)
+                + nbr_channels * per_channel,
+        )
+    }
+
+    /// Get the latency caused by the FFT block processing, in output frames.
+    /// See [FftFixedInOut::fft_latency_frames].
+    pub fn fft_latency_frames(&self) -> usize {
+        self.fft_size_out + self.output_delay()
+    }
+
+    /// Set the window function used for the antialiasing filter,
+    /// replacing the default [WindowFunction::BlackmanHarris2].
+    pub(crate) fn set_window(&mut self, window: WindowFunction) {
+        self.resampler.set_window(window);
+    }
+
+    /// Process a chunk like [process_into_buffer](Resampler::process_into_buffer),
+    /// and let `edit` modify the spectrum of each block in between the transforms.
+    ///
+    /// This allows spectral processing, like notch filtering or equalization,
+    /// to be done together with the resampling, without transforming the signal twice.
+    /// The input is resampled in blocks of `fft_size_in` frames, each zero padded
+    /// to `2 * fft_size_in` frames before the forward transform.
+    /// The function `edit` is called for every block of every active channel,
+    /// with the `fft_size_in + 1` bins of the spectrum, right after the forward transform.
+    /// Bin `k` is at the frequency `k * input_sample_rate / (2 * fft_size_in)`,
+    /// and the block size is given by the length of the spectrum.
+    /// The spectrum is not normalized, and the anti-aliasing filter has not been applied yet.
+    /// After the edit, the spectrum is filtered, truncated or extended to the output size,
+    /// and transformed back.
+    /// The zero padding leaves room for the impulse response of the edit,
+    /// which should be shorter than `fft_size_in` frames to avoid time domain aliasing.
+    /// An `edit` that doesn't modify the spectrum gives the same output as `process_into_buffer`.
+    pub fn process_with_spectrum<Vin, Vout, F>(
+        &mut self,
+        wave_in: &[Vin],
+        mut edit: F,
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        Vin: AsRef<[T]>,
+        Vout: AsMut<[T]>,
+        F: FnMut(&mut [Complex<T>]),
+    {
+        self.process_buffers(wave_in, wave_out, active_channels_mask, Some(&mut edit))
+    }
 }
 
-impl<T> Resampler<T> for FftFixedIn<T>
+impl<T> FftFixedIn<T>
 where
     T: Sample,
 {
-    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+    /// Get the lengths of the forward and inverse transforms, as `(fft_size_in, fft_size_out)`.
+    pub(crate) fn fft_sizes(&self) -> (usize, usize) {
+        (self.fft_size_in, self.fft_size_out)
+    }
+
+    /// Process a chunk of input into the output buffers.
+    fn process_buffers<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
         &mut self,
         wave_in: &[Vin],
         wave_out: &mut [Vout],
         active_channels_mask: Option<&[bool]>,
+        mut edit: SpectrumEdit<T>,
     ) -> ResampleResult<(usize, usize)> {
-        if let Some(mask) = active_channels_mask {
-            self.channel_mask.copy_from_slice(mask);
-        } else {
-            update_mask_from_buffers(&mut self.channel_mask);
-        };
-
         let next_saved_frames = self.saved_frames + self.chunk_size_in;
         let nbr_chunks_ready =
             (next_saved_frames as f32 / self.fft_size_in as f32).floor() as usize;
@@ -577,12 +1195,24 @@ where
         validate_buffers(
             wave_in,
             wave_out,
-            &self.channel_mask,
+            active_channels_mask,
             self.nbr_channels,
             self.chunk_size_in,
             needed_len,
         )?;
 
+        for chan in reactivated_channels(&self.channel_mask, active_channels_mask) {
+            self.overlaps[chan].iter_mut().for_each(|s| *s = T::zero());
+            self.input_buffers[chan]
+                .iter_mut()
+                .for_each(|s| *s = T::zero());
+        }
+        if let Some(mask) = active_channels_mask {
+            self.channel_mask.copy_from_slice(mask);
+        } else {
+            update_mask_from_buffers(&mut self.channel_mask);
+        };
+
         // Copy new samples to input buffer.
         for (chan, active) in self.channel_mask.iter().enumerate() {
             if *active {
@@ -607,8 +1237,12 @@ where
                     .take(nbr_chunks_ready)
                     .zip(wave_out[chan].as_mut().chunks_mut(self.fft_size_out))
                 {
-                    self.resampler
-                        .resample_unit(in_chunk, out_chunk, &mut self.overlaps[chan]);
+                    self.resampler.resample_unit(
+                        in_chunk,
+                        out_chunk,
+                        &mut self.overlaps[chan],
+                        reborrow(&mut edit),
+                    );
                 }
             }
         }
@@ -627,6 +1261,20 @@ where
         self.saved_frames = extra;
         Ok((self.chunk_size_in, needed_len))
     }
+}
+
+impl<T> Resampler<T> for FftFixedIn<T>
+where
+    T: Sample,
+{
+    fn process_into_buffer<Vin: AsRef<[T]>, Vout: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[Vin],
+        wave_out: &mut [Vout],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_buffers(wave_in, wave_out, active_channels_mask, None)
+    }
 
     fn input_frames_max(&self) -> usize {
         self.chunk_size_in
@@ -653,10 +1301,36 @@ where
             * self.fft_size_out
     }
 
+    fn output_frames_remaining(&self) -> usize {
+        let pending = self.saved_frames * self.fft_size_out;
+        (pending + self.fft_size_in - 1) / self.fft_size_in + self.output_delay()
+    }
+
     fn output_delay(&self) -> usize {
         self.fft_size_out / 2
     }
 
+    fn set_max_output_frames(&mut self, max_frames: usize) {
+        self.max_output_frames = max_frames;
+    }
+
+    fn max_output_frames(&self) -> usize {
+        self.max_output_frames
+    }
+
+    fn target_ratio(&self) -> f64 {
+        self.fft_size_out as f64 / self.fft_size_in as f64
+    }
+
+    fn output_sample_rate(&self, input_sample_rate: f64) -> f64 {
+        input_sample_rate * self.fft_size_out as f64 / self.fft_size_in as f64
+    }
+
+    fn ratio_bounds(&self) -> (f64, f64) {
+        let ratio = self.fft_size_out as f64 / self.fft_size_in as f64;
+        (ratio, ratio)
+    }
+
     /// Update the resample ratio. This is not supported by this resampler and
     /// always returns [ResampleError::SyncNotAdjustable].
     fn set_resample_ratio(&mut self, _new_ratio: f64, _ramp: bool) -> ResampleResult<()> {
@@ -681,12 +1355,286 @@ where
     }
 }
 
+/// A builder for [FftFixedInOut], as an alternative to the positional arguments of [FftFixedInOut::new].
+///
+/// The sample rates have no useful defaults and must always be set.
+/// The defaults for the other values are:
+/// - `chunk_size_in`: 1024
+/// - `nbr_channels`: 1
+/// - `double_precision_fft`: false
+/// - `window`: [WindowFunction::BlackmanHarris2]
+#[derive(Debug, Clone)]
+pub struct FftFixedInOutBuilder {
+    sample_rate_input: usize,
+    sample_rate_output: usize,
+    chunk_size_in: usize,
+    nbr_channels: usize,
+    double_precision_fft: bool,
+    window: WindowFunction,
+}
+
+impl Default for FftFixedInOutBuilder {
+    fn default() -> Self {
+        FftFixedInOutBuilder {
+            sample_rate_input: 0,
+            sample_rate_output: 0,
+            chunk_size_in: 1024,
+            nbr_channels: 1,
+            double_precision_fft: false,
+            window: WindowFunction::BlackmanHarris2,
+        }
+    }
+}
+
+impl FftFixedInOutBuilder {
+    /// Create a new builder with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the input sample rate, must be > 0.
+    pub fn sample_rate_input(mut self, sample_rate: usize) -> Self {
+        self.sample_rate_input = sample_rate;
+        self
+    }
+
+    /// Set the output sample rate, must be > 0.
+    pub fn sample_rate_output(mut self, sample_rate: usize) -> Self {
+        self.sample_rate_output = sample_rate;
+        self
+    }
+
+    /// Set the desired length of input data in frames, actual value may be different.
+    pub fn chunk_size_in(mut self, chunk_size: usize) -> Self {
+        self.chunk_size_in = chunk_size;
+        self
+    }
+
+    /// Set the number of channels in input/output, must be > 0.
+    pub fn nbr_channels(mut self, nbr_channels: usize) -> Self {
+        self.nbr_channels = nbr_channels;
+        self
+    }
+
+    /// Run the transforms in f64, see [FftFixedInOut::new_with_fft_precision].
+    pub fn double_precision_fft(mut self, enable: bool) -> Self {
+        self.double_precision_fft = enable;
+        self
+    }
+
+    /// Set the window function used for the antialiasing filter.
+    pub fn window(mut self, window: WindowFunction) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Validate the settings and create the resampler.
+    pub fn build<T: Sample>(&self) -> Result<FftFixedInOut<T>, ResamplerConstructionError> {
+        let mut resampler = FftFixedInOut::new_with_fft_precision(
+            self.sample_rate_input,
+            self.sample_rate_output,
+            self.chunk_size_in,
+            self.nbr_channels,
+            self.double_precision_fft,
+        )?;
+        if self.window != WindowFunction::BlackmanHarris2 {
+            resampler.set_window(self.window);
+        }
+        Ok(resampler)
+    }
+}
+
+/// A builder for [FftFixedOut], as an alternative to the positional arguments of [FftFixedOut::new].
+///
+/// The sample rates have no useful defaults and must always be set.
+/// The defaults for the other values are:
+/// - `chunk_size_out`: 1024
+/// - `sub_chunks`: 1
+/// - `nbr_channels`: 1
+/// - `window`: [WindowFunction::BlackmanHarris2]
+#[derive(Debug, Clone)]
+pub struct FftFixedOutBuilder {
+    sample_rate_input: usize,
+    sample_rate_output: usize,
+    chunk_size_out: usize,
+    sub_chunks: usize,
+    nbr_channels: usize,
+    window: WindowFunction,
+}
+
+impl Default for FftFixedOutBuilder {
+    fn default() -> Self {
+        FftFixedOutBuilder {
+            sample_rate_input: 0,
+            sample_rate_output: 0,
+            chunk_size_out: 1024,
+            sub_chunks: 1,
+            nbr_channels: 1,
+            window: WindowFunction::BlackmanHarris2,
+        }
+    }
+}
+
+impl FftFixedOutBuilder {
+    /// Create a new builder with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the input sample rate, must be > 0.
+    pub fn sample_rate_input(mut self, sample_rate: usize) -> Self {
+        self.sample_rate_input = sample_rate;
+        self
+    }
+
+    /// Set the output sample rate, must be > 0.
+    pub fn sample_rate_output(mut self, sample_rate: usize) -> Self {
+        self.sample_rate_output = sample_rate;
+        self
+    }
+
+    /// Set the length of output data in frames.
+    pub fn chunk_size_out(mut self, chunk_size: usize) -> Self {
+        self.chunk_size_out = chunk_size;
+        self
+    }
+
+    /// Set the desired number of subchunks for processing, actual number may be different.
+    pub fn sub_chunks(mut self, sub_chunks: usize) -> Self {
+        self.sub_chunks = sub_chunks;
+        self
+    }
+
+    /// Set the number of channels in input/output, must be > 0.
+    pub fn nbr_channels(mut self, nbr_channels: usize) -> Self {
+        self.nbr_channels = nbr_channels;
+        self
+    }
+
+    /// Set the window function used for the antialiasing filter.
+    pub fn window(mut self, window: WindowFunction) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Validate the settings and create the resampler.
+    pub fn build<T: Sample>(&self) -> Result<FftFixedOut<T>, ResamplerConstructionError> {
+        let mut resampler = FftFixedOut::new(
+            self.sample_rate_input,
+            self.sample_rate_output,
+            self.chunk_size_out,
+            self.sub_chunks,
+            self.nbr_channels,
+        )?;
+        if self.window != WindowFunction::BlackmanHarris2 {
+            resampler.set_window(self.window);
+        }
+        Ok(resampler)
+    }
+}
+
+/// A builder for [FftFixedIn], as an alternative to the positional arguments of [FftFixedIn::new].
+///
+/// The sample rates have no useful defaults and must always be set.
+/// The defaults for the other values are:
+/// - `chunk_size_in`: 1024
+/// - `sub_chunks`: 1
+/// - `nbr_channels`: 1
+/// - `window`: [WindowFunction::BlackmanHarris2]
+#[derive(Debug, Clone)]
+pub struct FftFixedInBuilder {
+    sample_rate_input: usize,
+    sample_rate_output: usize,
+    chunk_size_in: usize,
+    sub_chunks: usize,
+    nbr_channels: usize,
+    window: WindowFunction,
+}
+
+impl Default for FftFixedInBuilder {
+    fn default() -> Self {
+        FftFixedInBuilder {
+            sample_rate_input: 0,
+            sample_rate_output: 0,
+            chunk_size_in: 1024,
+            sub_chunks: 1,
+            nbr_channels: 1,
+            window: WindowFunction::BlackmanHarris2,
+        }
+    }
+}
+
+impl FftFixedInBuilder {
+    /// Create a new builder with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the input sample rate, must be > 0.
+    pub fn sample_rate_input(mut self, sample_rate: usize) -> Self {
+        self.sample_rate_input = sample_rate;
+        self
+    }
+
+    /// Set the output sample rate, must be > 0.
+    pub fn sample_rate_output(mut self, sample_rate: usize) -> Self {
+        self.sample_rate_output = sample_rate;
+        self
+    }
+
+    /// Set the length of input data in frames.
+    pub fn chunk_size_in(mut self, chunk_size: usize) -> Self {
+        self.chunk_size_in = chunk_size;
+        self
+    }
+
+    /// Set the desired number of subchunks for processing, actual number may be different.
+    pub fn sub_chunks(mut self, sub_chunks: usize) -> Self {
+        self.sub_chunks = sub_chunks;
+        self
+    }
+
+    /// Set the number of channels in input/output, must be > 0.
+    pub fn nbr_channels(mut self, nbr_channels: usize) -> Self {
+        self.nbr_channels = nbr_channels;
+        self
+    }
+
+    /// Set the window function used for the antialiasing filter.
+    pub fn window(mut self, window: WindowFunction) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Validate the settings and create the resampler.
+    pub fn build<T: Sample>(&self) -> Result<FftFixedIn<T>, ResamplerConstructionError> {
+        let mut resampler = FftFixedIn::new(
+            self.sample_rate_input,
+            self.sample_rate_output,
+            self.chunk_size_in,
+            self.sub_chunks,
+            self.nbr_channels,
+        )?;
+        if self.window != WindowFunction::BlackmanHarris2 {
+            resampler.set_window(self.window);
+        }
+        Ok(resampler)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::check_output;
-    use crate::synchro::{FftFixedIn, FftFixedInOut, FftFixedOut, FftResampler};
-    use crate::Resampler;
+    use crate::fft::{Complex, DefaultFft, Fft};
+    use crate::synchro::warn_large_fft;
+    use crate::synchro::{
+        FftFixedIn, FftFixedInBuilder, FftFixedInOut, FftFixedInOutBuilder, FftFixedOut,
+        FftFixedOutBuilder, FftResampler,
+    };
+    use crate::{ResampleError, Resampler, ResamplerConstructionError, WindowFunction};
     use rand::Rng;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     #[test]
     fn resample_unit() {
@@ -702,13 +1650,327 @@ mod tests {
 
         let mut wave_out = vec![0.0; 1000];
         let mut overlap = vec![0.0; 1000];
-        resampler.resample_unit(&wave_in, &mut wave_out, &mut overlap);
+        resampler.resample_unit(&wave_in, &mut wave_out, &mut overlap, None);
         let vecsum = wave_out.iter().sum::<f64>();
-        let maxval = wave_out.iter().cloned().fold(0. / 0., f64::max);
+        let maxval = wave_out.iter().cloned().fold(f64::NAN, f64::max);
         assert!((vecsum - 4.0 * 1000.0 / 147.0).abs() < 1.0e-6);
         assert!((maxval - 1.0).abs() < 0.1);
     }
 
+    #[test]
+    fn process_with_spectrum() {
+        let mut rng = rand::thread_rng();
+        let waves: Vec<Vec<Vec<f64>>> = (0..3)
+            .map(|_| vec![(0..1029).map(|_| rng.gen::<f64>() - 0.5).collect(); 2])
+            .collect();
+        let mut out_plain = vec![vec![0.0; 2048]; 2];
+        let mut out_edited = vec![vec![0.0; 2048]; 2];
+
+        let mut plain = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let mut edited = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let mut calls = 0;
+        for wave in waves.iter() {
+            let frames_plain = plain
+                .process_into_buffer(wave, &mut out_plain, None)
+                .unwrap();
+            let frames_edited = edited
+                .process_with_spectrum(wave, |_| calls += 1, &mut out_edited, None)
+                .unwrap();
+            assert_eq!(frames_plain, frames_edited);
+            assert_eq!(out_plain, out_edited);
+        }
+        assert!(calls > 0);
+
+        let mut plain = FftFixedOut::<f64>::new(44100, 48000, 512, 2, 2).unwrap();
+        let mut edited = FftFixedOut::<f64>::new(44100, 48000, 512, 2, 2).unwrap();
+        for wave in waves.iter() {
+            let frames = plain.input_frames_next();
+            let chunk: Vec<&[f64]> = wave.iter().map(|ch| &ch[..frames]).collect();
+            let frames_plain = plain
+                .process_into_buffer(&chunk, &mut out_plain, None)
+                .unwrap();
+            let frames_edited = edited
+                .process_with_spectrum(&chunk, |_| {}, &mut out_edited, None)
+                .unwrap();
+            assert_eq!(frames_plain, frames_edited);
+            assert_eq!(out_plain, out_edited);
+        }
+
+        let mut plain = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2).unwrap();
+        let mut edited = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2).unwrap();
+        for wave in waves.iter() {
+            plain
+                .process_into_buffer(wave, &mut out_plain, None)
+                .unwrap();
+            edited
+                .process_with_spectrum(wave, |_| {}, &mut out_edited, None)
+                .unwrap();
+            assert_eq!(out_plain, out_edited);
+        }
+
+        // Removing everything gives silence, also when running the transforms in f64.
+        let mut edited =
+            FftFixedInOut::<f32>::new_with_fft_precision(44100, 48000, 1024, 1, true).unwrap();
+        let wave = vec![vec![0.5f32; 1029]];
+        let mut out = vec![vec![1.0f32; 1120]];
+        edited
+            .process_with_spectrum(
+                &wave,
+                |spectrum| {
+                    spectrum
+                        .iter_mut()
+                        .for_each(|bin| *bin = Complex::new(0.0, 0.0))
+                },
+                &mut out,
+                None,
+            )
+            .unwrap();
+        assert!(out[0].iter().all(|v| *v == 0.0));
+    }
+
+    #[test]
+    fn reject_zero_channels() {
+        assert!(matches!(
+            FftFixedInOut::<f64>::new(44100, 48000, 1024, 0),
+            Err(ResamplerConstructionError::InvalidChannelCount(0))
+        ));
+        assert!(matches!(
+            FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 0),
+            Err(ResamplerConstructionError::InvalidChannelCount(0))
+        ));
+        assert!(matches!(
+            FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 0),
+            Err(ResamplerConstructionError::InvalidChannelCount(0))
+        ));
+    }
+
+    #[test]
+    fn fio_double_precision_fft() {
+        // A long DC-plus-sine signal, resampled with f64 as reference.
+        let frames = 1029 * 200;
+        let signal = (0..frames)
+            .map(|n| 0.5 + 0.25 * (n as f64 * 0.01).sin())
+            .collect::<Vec<f64>>();
+        let mut reference = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1).unwrap();
+        let mut single = FftFixedInOut::<f32>::new(44100, 48000, 1024, 1).unwrap();
+        let mut double =
+            FftFixedInOut::<f32>::new_with_fft_precision(44100, 48000, 1024, 1, true).unwrap();
+        let mut error_single = 0.0;
+        let mut error_double = 0.0;
+        for block in signal.chunks(1029) {
+            let block_32 = vec![block.iter().map(|v| *v as f32).collect::<Vec<f32>>()];
+            let out_ref = reference.process(&[block], None).unwrap();
+            let out_single = single.process(&block_32, None).unwrap();
+            let out_double = double.process(&block_32, None).unwrap();
+            for n in 0..out_ref[0].len() {
+                error_single += (out_single[0][n] as f64 - out_ref[0][n]).powi(2);
+                error_double += (out_double[0][n] as f64 - out_ref[0][n]).powi(2);
+            }
+        }
+        assert!(
+            error_double < error_single,
+            "f64 fft error {} not smaller than f32 fft error {}",
+            error_double,
+            error_single
+        );
+    }
+
+    #[test]
+    fn min_input_for_output() {
+        let mut resampler = FftFixedOut::<f64>::new(44100, 48000, 1000, 2, 2).unwrap();
+        let fft_size_in = resampler.min_input_for_output();
+        assert!(fft_size_in > 0);
+        assert!(fft_size_in < resampler.input_frames_next());
+        let frames = resampler.input_frames_next();
+        resampler
+            .process(&vec![vec![0.0f64; frames]; 2], None)
+            .unwrap();
+        // Output frames are saved from the last sub chunk.
+        assert_eq!(resampler.min_input_for_output(), 0);
+
+        let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        assert_eq!(resampler.min_input_for_output(), 1024);
+    }
+
+    /// An FFT that counts the calls, and forwards them to the default implementation.
+    struct CountingFft {
+        inner: DefaultFft<f64>,
+        forward: Arc<AtomicUsize>,
+        inverse: Arc<AtomicUsize>,
+    }
+
+    impl Fft<f64> for CountingFft {
+        fn prepare(&mut self, forward_len: usize, inverse_len: usize) {
+            self.inner.prepare(forward_len, inverse_len);
+        }
+
+        fn forward(&mut self, input: &mut [f64], output: &mut [Complex<f64>]) {
+            self.forward.fetch_add(1, Ordering::Relaxed);
+            self.inner.forward(input, output);
+        }
+
+        fn inverse(&mut self, input: &mut [Complex<f64>], output: &mut [f64]) {
+            self.inverse.fetch_add(1, Ordering::Relaxed);
+            self.inner.inverse(input, output);
+        }
+    }
+
+    #[test]
+    fn custom_fft() {
+        let forward = Arc::new(AtomicUsize::new(0));
+        let inverse = Arc::new(AtomicUsize::new(0));
+        let fft = CountingFft {
+            inner: DefaultFft::new(),
+            forward: forward.clone(),
+            inverse: inverse.clone(),
+        };
+        // A chunk size of 1176 frames gives two sub chunks of 588 frames.
+        let mut resampler =
+            FftFixedIn::<f64>::new_with_fft(44100, 48000, 1176, 2, 2, Box::new(fft)).unwrap();
+        let mut reference = FftFixedIn::<f64>::new(44100, 48000, 1176, 2, 2).unwrap();
+        // The filter is transformed once during construction.
+        assert_eq!(forward.load(Ordering::Relaxed), 1);
+        assert_eq!(inverse.load(Ordering::Relaxed), 0);
+
+        let waves = vec![vec![0.5f64; 1176]; 2];
+        for block in 1..4 {
+            let out = resampler.process(&waves, None).unwrap();
+            let out_ref = reference.process(&waves, None).unwrap();
+            assert_eq!(out, out_ref);
+            // One transform each way per sub chunk and channel.
+            assert_eq!(forward.load(Ordering::Relaxed), 1 + block * 2 * 2);
+            assert_eq!(inverse.load(Ordering::Relaxed), block * 2 * 2);
+        }
+    }
+
+    #[test]
+    fn output_sample_rate() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1).unwrap();
+        assert_eq!(resampler.output_sample_rate(44100.0), 48000.0);
+        let waves = vec![vec![0.0f64; 1024]];
+        let mut frames_out = 0;
+        for _ in 0..100 {
+            frames_out += resampler.process(&waves, None).unwrap()[0].len();
+        }
+        let measured = 44100.0 * frames_out as f64 / (100 * 1024) as f64;
+        assert!((measured - 48000.0).abs() / 48000.0 < 1.0e-3);
+
+        let resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1).unwrap();
+        assert_eq!(resampler.output_sample_rate(88200.0), 96000.0);
+    }
+
+    #[test]
+    fn ratio_bounds() {
+        let ratio = 48000.0 / 44100.0;
+        let resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1).unwrap();
+        assert_eq!(resampler.ratio_bounds(), (ratio, ratio));
+        let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1).unwrap();
+        assert_eq!(resampler.ratio_bounds(), (ratio, ratio));
+        let resampler = FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 1).unwrap();
+        assert_eq!(resampler.ratio_bounds(), (ratio, ratio));
+    }
+
+    #[test]
+    fn builders() {
+        let mut built = FftFixedInOutBuilder::new()
+            .sample_rate_input(44100)
+            .sample_rate_output(48000)
+            .chunk_size_in(1024)
+            .nbr_channels(2)
+            .build::<f64>()
+            .unwrap();
+        let mut positional = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2).unwrap();
+        assert_eq!(built.input_frames_next(), positional.input_frames_next());
+        assert_eq!(built.output_frames_next(), positional.output_frames_next());
+        let frames = positional.input_frames_next();
+        let waves = vec![
+            (0..frames)
+                .map(|n| n as f64 / frames as f64)
+                .collect::<Vec<_>>();
+            2
+        ];
+        assert_eq!(
+            built.process(&waves, None).unwrap(),
+            positional.process(&waves, None).unwrap()
+        );
+
+        let built = FftFixedOutBuilder::new()
+            .sample_rate_input(44100)
+            .sample_rate_output(48000)
+            .chunk_size_out(1024)
+            .sub_chunks(2)
+            .nbr_channels(2)
+            .build::<f64>()
+            .unwrap();
+        let positional = FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        assert_eq!(built.input_frames_max(), positional.input_frames_max());
+        assert_eq!(built.output_frames_max(), positional.output_frames_max());
+
+        let built = FftFixedInBuilder::new()
+            .sample_rate_input(44100)
+            .sample_rate_output(48000)
+            .chunk_size_in(1024)
+            .sub_chunks(2)
+            .nbr_channels(2)
+            .build::<f64>()
+            .unwrap();
+        let positional = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        assert_eq!(built.input_frames_max(), positional.input_frames_max());
+        assert_eq!(built.output_frames_max(), positional.output_frames_max());
+
+        assert!(matches!(
+            FftFixedInBuilder::new().build::<f64>(),
+            Err(ResamplerConstructionError::InvalidSampleRate { .. })
+        ));
+    }
+
+    #[test]
+    fn kaiser_bessel_derived_window() {
+        // A tone well inside the passband should be unaffected by the choice of window.
+        let builder = FftFixedInOutBuilder::new()
+            .sample_rate_input(44100)
+            .sample_rate_output(48000)
+            .chunk_size_in(1024);
+        let mut reference = builder.clone().build::<f64>().unwrap();
+        let mut resampler = builder
+            .window(WindowFunction::KaiserBesselDerived { beta: 12.0 })
+            .build::<f64>()
+            .unwrap();
+        let frames_in = resampler.input_frames_next();
+        let omega = 2.0 * std::f64::consts::PI * 1000.0 / 44100.0;
+        for chunk in 0..10 {
+            let wave = (0..frames_in)
+                .map(|n| (omega * (chunk * frames_in + n) as f64).sin())
+                .collect::<Vec<_>>();
+            let expected = reference.process(&[&wave], None).unwrap();
+            let output = resampler.process(&[&wave], None).unwrap();
+            assert_ne!(output, expected);
+            for (value, exp) in output[0].iter().zip(expected[0].iter()) {
+                assert!((value - exp).abs() < 1.0e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn finish_fi() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let waves = vec![vec![0.5f64; 1024]; 2];
+        let mut frames_out = 0;
+        for _ in 0..5 {
+            frames_out += resampler.process(&waves, None).unwrap()[0].len();
+        }
+        let delay = resampler.output_delay();
+        let tail = resampler.finish().unwrap();
+        let total = frames_out + tail[0].len();
+        let expected = (5.0 * 1024.0 * 48000.0 / 44100.0_f64).ceil() as usize + delay;
+        assert!(
+            (total as isize - expected as isize).abs() <= 1,
+            "got {} frames, expected {}",
+            total,
+            expected
+        );
+    }
+
     #[test]
     fn make_resampler_fio() {
         // asking for 1024 give the nearest which is 1029 -> 1120
@@ -916,6 +2178,64 @@ mod tests {
         assert_eq!(out[0].len(), 4411);
     }
 
+    #[test]
+    fn reservoir_len() {
+        let chunk_size = 1000;
+        let mut resampler = FftFixedOut::<f64>::new(44100, 48000, chunk_size, 2, 1).unwrap();
+        let default_len = resampler.reservoir_len();
+        let block = default_len - chunk_size;
+        let default_delay = resampler.output_delay();
+        assert!(matches!(
+            resampler.set_reservoir_len(default_len - 1),
+            Err(ResampleError::ReservoirTooSmall { provided, min }) if provided == default_len - 1 && min == default_len
+        ));
+
+        // Count the chunks where more input is needed than a source at the nominal rate has delivered.
+        let underflows = |resampler: &mut FftFixedOut<f64>| {
+            let mut needed = 0;
+            let mut underflows = 0;
+            for k in 1..=100 {
+                needed += resampler.input_frames_next();
+                if needed > k * chunk_size * 44100 / 48000 {
+                    underflows += 1;
+                }
+                let waves = vec![vec![0.0; resampler.input_frames_next()]];
+                resampler.process(&waves, None).unwrap();
+            }
+            underflows
+        };
+        assert!(underflows(&mut resampler) > 0);
+
+        let mut long = FftFixedOut::<f64>::new(44100, 48000, chunk_size, 2, 1).unwrap();
+        long.set_reservoir_len(default_len + block).unwrap();
+        assert_eq!(long.reservoir_len(), default_len + block);
+        assert_eq!(long.output_delay(), default_delay + block);
+        assert_eq!(underflows(&mut long), 0);
+
+        // The output is the same, delayed by the extra frames.
+        resampler.reset();
+        long.reset();
+        let mut rng = rand::thread_rng();
+        let mut short_out = Vec::new();
+        let mut long_out = Vec::new();
+        let wave = (0..20000).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>();
+        for (resampler, output) in [(&mut resampler, &mut short_out), (&mut long, &mut long_out)] {
+            let mut offset = 0;
+            while offset + resampler.input_frames_next() <= wave.len() {
+                let frames = resampler.input_frames_next();
+                let out = resampler
+                    .process(&[&wave[offset..offset + frames]], None)
+                    .unwrap();
+                output.extend_from_slice(&out[0]);
+                offset += frames;
+            }
+        }
+        assert!(long_out[..block].iter().all(|v| *v == 0.0));
+        for (a, b) in short_out.iter().zip(long_out[block..].iter()) {
+            assert!((a - b).abs() < 1.0e-12);
+        }
+    }
+
     #[test]
     fn make_resampler_fo_unusualratio() {
         let mut resampler = FftFixedOut::<f64>::new(44100, 44110, 1024, 2, 2).unwrap();
@@ -927,6 +2247,38 @@ mod tests {
         assert_eq!(out[0].len(), 1024);
     }
 
+    #[test]
+    fn memory_footprint() {
+        let fp_fi = |ch| FftFixedIn::<f64>::memory_footprint(44100, 48000, 1024, 2, ch).unwrap();
+        let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let per_channel = (resampler.fft_size_out + 1024 + resampler.fft_size_in) * 8 + 1;
+        assert_eq!(fp_fi(1) - fp_fi(0), per_channel);
+        assert_eq!(fp_fi(8) - fp_fi(0), 2 * (fp_fi(4) - fp_fi(0)));
+
+        let fp_fo = |ch| FftFixedOut::<f32>::memory_footprint(44100, 48000, 1024, 2, ch).unwrap();
+        let resampler = FftFixedOut::<f32>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let per_channel = (2 * resampler.fft_size_out + 1024) * 4 + 1;
+        assert_eq!(fp_fo(1) - fp_fo(0), per_channel);
+        assert_eq!(fp_fo(8) - fp_fo(0), 2 * (fp_fo(4) - fp_fo(0)));
+
+        let fp_fio = |ch| FftFixedInOut::<f64>::memory_footprint(44100, 48000, 1024, ch).unwrap();
+        assert_eq!(fp_fio(8) - fp_fio(0), 2 * (fp_fio(4) - fp_fio(0)));
+        assert!(FftFixedInOut::<f64>::memory_footprint(0, 48000, 1024, 2).is_err());
+    }
+
+    #[test]
+    fn large_fft() {
+        assert!(!warn_large_fft(44100, 48000));
+        assert!(!warn_large_fft(44100, 192000));
+        assert!(warn_large_fft(44101, 48000));
+        let resampler = FftFixedInOut::<f32>::new(44100, 48000, 1024, 1).unwrap();
+        assert_eq!(resampler.fft_latency_frames(), 1120 + 560);
+        let resampler = FftFixedIn::<f32>::new(44101, 48000, 1024, 2, 1).unwrap();
+        assert!(resampler.fft_latency_frames() >= 48000);
+        let resampler = FftFixedOut::<f32>::new(44101, 48000, 1024, 2, 1).unwrap();
+        assert!(resampler.fft_latency_frames() >= 48000);
+    }
+
     #[test]
     fn check_fo_output() {
         let mut resampler = FftFixedOut::<f64>::new(44100, 48000, 4096, 4, 2).unwrap();
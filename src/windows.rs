@@ -1,6 +1,9 @@
 use crate::Sample;
 
 /// Different window functions that can be used to window the sinc function.
+///
+/// The parameters of the windows are compared by their bit patterns,
+/// which makes the comparison an equivalence relation also for NaN values.
 #[derive(Debug, Clone, Copy)]
 pub enum WindowFunction {
     /// Blackman. Intermediate rolloff and intermediate attenuation.
@@ -15,8 +18,64 @@ pub enum WindowFunction {
     Hann,
     /// Squared Hann. Slower rolloff and higher attenuation than simple Hann.
     Hann2,
+    /// Kaiser-Bessel-derived, made from a Kaiser window with the given `beta`.
+    /// A larger `beta` gives slower rolloff but higher attenuation.
+    /// The window fulfills the Princen-Bradley condition,
+    /// meaning that the squares of two halves that overlap by 50% sum to one.
+    /// Here it is only used to window the sinc of the anti-aliasing filter, like the other windows,
+    /// and the Princen-Bradley condition has no effect on the result.
+    /// This also applies to the FFT resamplers, where it can be selected with the `window` method
+    /// of their builders, since these multiply the spectrum of the input with that of the filter.
+    /// Compared to the Blackman and Hann windows, the sidelobes close to the main lobe are higher.
+    /// Typical values for `beta` are in the range 10 to 20.
+    KaiserBesselDerived {
+        /// The shape parameter of the Kaiser window.
+        beta: f64,
+    },
+    /// Dolph-Chebyshev, with all sidelobes at the same level, `attenuation_db` below the main lobe.
+    /// For a given sidelobe level, this window has the narrowest possible main lobe.
+    /// A higher attenuation gives slower rolloff.
+    /// The stopband attenuation of the windowed sinc is close to, but not exactly,
+    /// the sidelobe attenuation of the window.
+    /// Typical values for `attenuation_db` are in the range 80 to 160,
+    /// and negative values are treated as zero.
+    DolphChebyshev {
+        /// The attenuation of the sidelobes in dB, as a positive value.
+        attenuation_db: f64,
+    },
 }
 
+impl PartialEq for WindowFunction {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                WindowFunction::KaiserBesselDerived { beta },
+                WindowFunction::KaiserBesselDerived { beta: other_beta },
+            ) => beta.to_bits() == other_beta.to_bits(),
+            (
+                WindowFunction::DolphChebyshev { attenuation_db },
+                WindowFunction::DolphChebyshev {
+                    attenuation_db: other_attenuation_db,
+                },
+            ) => attenuation_db.to_bits() == other_attenuation_db.to_bits(),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for WindowFunction {}
+
+/// The terms of the Blackman-Harris window, as coefficients of `cos(2*pi*k*x/npoints)`.
+const BLACKMAN_HARRIS_TERMS: [f64; 4] = [0.35875, -0.48829, 0.14128, -0.01168];
+/// The terms of the Blackman window.
+const BLACKMAN_TERMS: [f64; 3] = [0.42, -0.5, 0.08];
+/// The terms of the Hann window.
+const HANN_TERMS: [f64; 2] = [0.5, -0.5];
+
+/// The maximum order of the Chebyshev polynomial used for the Dolph-Chebyshev window.
+/// Longer windows are interpolated from the spectrum of a window of this length.
+const DOLPH_CHEBYSHEV_MAX_ORDER: usize = 256;
+
 /// Helper function. Standard Blackman-Harris window.
 // The window created is periodic.
 pub fn blackman_harris<T>(npoints: usize) -> Vec<T>
@@ -29,14 +88,13 @@ where
     let pi4 = T::coerce(4.0) * T::PI;
     let pi6 = T::coerce(6.0) * T::PI;
     let np_f = T::coerce(npoints);
-    let a = T::coerce(0.35875);
-    let b = T::coerce(0.48829);
-    let c = T::coerce(0.14128);
-    let d = T::coerce(0.01168);
+    let [a, b, c, d] = BLACKMAN_HARRIS_TERMS.map(T::coerce);
     for (x, item) in window.iter_mut().enumerate() {
         let x_float = T::coerce(x);
-        *item = a - b * (pi2 * x_float / np_f).cos() + c * (pi4 * x_float / np_f).cos()
-            - d * (pi6 * x_float / np_f).cos();
+        *item = a
+            + b * (pi2 * x_float / np_f).cos()
+            + c * (pi4 * x_float / np_f).cos()
+            + d * (pi6 * x_float / np_f).cos();
     }
     window
 }
@@ -52,12 +110,10 @@ where
     let pi2 = T::coerce(2.0) * T::PI;
     let pi4 = T::coerce(4.0) * T::PI;
     let np_f = T::coerce(npoints);
-    let a = T::coerce(0.42);
-    let b = T::coerce(0.5);
-    let c = T::coerce(0.08);
+    let [a, b, c] = BLACKMAN_TERMS.map(T::coerce);
     for (x, item) in window.iter_mut().enumerate() {
         let x_float = T::coerce(x);
-        *item = a - b * (pi2 * x_float / np_f).cos() + c * (pi4 * x_float / np_f).cos();
+        *item = a + b * (pi2 * x_float / np_f).cos() + c * (pi4 * x_float / np_f).cos();
     }
     window
 }
@@ -72,14 +128,149 @@ where
     let mut window = vec![T::zero(); npoints];
     let pi2 = T::coerce(2.0) * T::PI;
     let np_f = T::coerce(npoints);
-    let a = T::coerce(0.5);
+    let [a, b] = HANN_TERMS.map(T::coerce);
     for (x, item) in window.iter_mut().enumerate() {
         let x_float = T::coerce(x);
-        *item = a - a * (pi2 * x_float / np_f).cos();
+        *item = a + b * (pi2 * x_float / np_f).cos();
     }
     window
 }
 
+/// Helper function. Modified Bessel function of the first kind, order 0.
+/// Evaluated by summing its power series until the terms become negligible.
+fn bessel_i0(x: f64) -> f64 {
+    let half_x = x / 2.0;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    let mut k = 1.0;
+    while term > 1.0e-16 * sum {
+        term *= (half_x / k) * (half_x / k);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+/// Helper function. Kaiser-Bessel-derived window.
+// The first half is the normalized cumulative sum of a Kaiser window of `npoints/2 + 1` points,
+// and the second half is its mirror image. For odd `npoints`, the middle point is one.
+pub fn kaiser_bessel_derived<T>(npoints: usize, beta: f64) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!(
+        "Making a Kaiser-Bessel-derived window with {} points, beta {}",
+        npoints,
+        beta
+    );
+    let half = npoints / 2;
+    let kaiser = (0..=half)
+        .map(|n| {
+            let x = 2.0 * n as f64 / half.max(1) as f64 - 1.0;
+            bessel_i0(beta * (1.0 - x * x).sqrt())
+        })
+        .collect::<Vec<_>>();
+    let total: f64 = kaiser.iter().sum();
+    let mut window = vec![T::one(); npoints];
+    let mut cumulative = 0.0;
+    for n in 0..half {
+        cumulative += kaiser[n];
+        let value = T::coerce((cumulative / total).sqrt());
+        window[n] = value;
+        window[npoints - 1 - n] = value;
+    }
+    window
+}
+
+/// Helper function. Chebyshev polynomial of the first kind, of even `order`.
+fn chebyshev_even(order: usize, x: f64) -> f64 {
+    let order = order as f64;
+    if x.abs() <= 1.0 {
+        (order * x.acos()).cos()
+    } else {
+        (order * x.abs().acosh()).cosh()
+    }
+}
+
+/// Helper function. Dolph-Chebyshev window, with sidelobes `attenuation_db` below the main lobe.
+// The window is calculated from its spectrum, which is the Chebyshev polynomial
+// of order `M - 1` sampled at `M` points, with `M` odd. The spectrum is transformed
+// as a cosine series, which is evaluated at `npoints` points spanning the window.
+// For `npoints` up to the maximum order, the series is evaluated at the points of
+// the standard `M = npoints + 1` window, and the result equals this window without its last point.
+// For longer windows, the series of the maximum order is interpolated,
+// which gives sidelobes that are only approximately at the requested level.
+pub fn dolph_chebyshev<T>(npoints: usize, attenuation_db: f64) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!(
+        "Making a Dolph-Chebyshev window with {} points, attenuation {} dB",
+        npoints,
+        attenuation_db
+    );
+    let order = (npoints.min(DOLPH_CHEBYSHEV_MAX_ORDER) / 2 * 2).max(2);
+    let nbr_coeffs = order + 1;
+    let ratio = 10.0_f64.powf(attenuation_db.max(0.0) / 20.0);
+    let x0 = (ratio.acosh() / order as f64).cosh();
+    let coeffs = (0..=order / 2)
+        .map(|k| {
+            let x = x0 * (std::f64::consts::PI * k as f64 / nbr_coeffs as f64).cos();
+            chebyshev_even(order, x)
+        })
+        .collect::<Vec<_>>();
+    let series = |offset: f64| {
+        coeffs
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(k, coeff)| {
+                let phase = 2.0 * std::f64::consts::PI * k as f64 * offset / nbr_coeffs as f64;
+                2.0 * coeff * phase.cos()
+            })
+            .sum::<f64>()
+            + coeffs[0]
+    };
+    let peak = series(0.0);
+    let center = (npoints / 2) as f64;
+    (0..npoints)
+        .map(|x| {
+            let offset = (x as f64 - center) * order as f64 / npoints as f64;
+            T::coerce(series(offset) / peak)
+        })
+        .collect()
+}
+
+/// Helper function. Estimate the width of the main lobe of a window,
+/// as the position in bins of the first minimum of its spectrum.
+fn main_lobe_half_width(window: &[f64]) -> f64 {
+    let npoints = window.len() as f64;
+    let magnitude = |bins: f64| {
+        let (re, im) = window
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(re, im), (n, w)| {
+                let phase = 2.0 * std::f64::consts::PI * bins * n as f64 / npoints;
+                (re + w * phase.cos(), im - w * phase.sin())
+            });
+        (re * re + im * im).sqrt()
+    };
+    let step = 0.02;
+    let mut bins = step;
+    let mut previous = magnitude(0.0);
+    let mut current = magnitude(bins);
+    while bins < npoints / 2.0 {
+        let next = magnitude(bins + step);
+        if current <= previous && current <= next {
+            return bins;
+        }
+        previous = current;
+        current = next;
+        bins += step;
+    }
+    bins
+}
+
 /// Make the selected window function.
 pub fn make_window<T>(npoints: usize, windowfunc: WindowFunction) -> Vec<T>
 where
@@ -91,6 +282,10 @@ where
         }
         WindowFunction::Blackman | WindowFunction::Blackman2 => blackman::<T>(npoints),
         WindowFunction::Hann | WindowFunction::Hann2 => hann::<T>(npoints),
+        WindowFunction::KaiserBesselDerived { beta } => kaiser_bessel_derived::<T>(npoints, beta),
+        WindowFunction::DolphChebyshev { attenuation_db } => {
+            dolph_chebyshev::<T>(npoints, attenuation_db)
+        }
     };
     match windowfunc {
         WindowFunction::Blackman2 | WindowFunction::BlackmanHarris2 | WindowFunction::Hann2 => {
@@ -101,12 +296,36 @@ where
     window
 }
 
+/// Get the terms of a window that is a sum of cosines, and if the window should be squared.
+/// Returns `None` for the windows that are calculated in other ways.
+pub(crate) fn cosine_terms(windowfunc: WindowFunction) -> Option<(&'static [f64], bool)> {
+    match windowfunc {
+        WindowFunction::BlackmanHarris => Some((&BLACKMAN_HARRIS_TERMS, false)),
+        WindowFunction::BlackmanHarris2 => Some((&BLACKMAN_HARRIS_TERMS, true)),
+        WindowFunction::Blackman => Some((&BLACKMAN_TERMS, false)),
+        WindowFunction::Blackman2 => Some((&BLACKMAN_TERMS, true)),
+        WindowFunction::Hann => Some((&HANN_TERMS, false)),
+        WindowFunction::Hann2 => Some((&HANN_TERMS, true)),
+        WindowFunction::KaiserBesselDerived { .. } | WindowFunction::DolphChebyshev { .. } => None,
+    }
+}
+
 /// Calculate a suitable relative cutoff frequency for the given sinc length using the given window function.
 /// The result is based on an approximation, which gives good results for sinc lengths from 32 to 2048.
+/// For the Kaiser-Bessel-derived and Dolph-Chebyshev windows, where the shape depends on a parameter,
+/// the cutoff is instead estimated from the width of the main lobe of the window spectrum.
 pub fn calculate_cutoff<T>(npoints: usize, windowfunc: WindowFunction) -> T
 where
     T: Sample,
 {
+    if let WindowFunction::KaiserBesselDerived { .. } | WindowFunction::DolphChebyshev { .. } =
+        windowfunc
+    {
+        // The first null of the windowed sinc is placed at the Nyquist frequency.
+        let window = make_window::<f64>(npoints, windowfunc);
+        let width = main_lobe_half_width(&window);
+        return T::coerce(1.0 / (1.0 + 2.0 * width / npoints as f64));
+    }
     // Coefficient values generated by cutoff_fit_cubic.py
     let (k1, k2, k3) = match windowfunc {
         WindowFunction::BlackmanHarris => (
@@ -139,6 +358,9 @@ where
             T::coerce(29.69451915489501),
             T::coerce(184.82117462266237),
         ),
+        WindowFunction::KaiserBesselDerived { .. } | WindowFunction::DolphChebyshev { .. } => {
+            unreachable!()
+        }
     };
     let one = T::one();
     one / (k1 / T::coerce(npoints)
@@ -153,7 +375,9 @@ mod tests {
     use crate::windows::blackman;
     use crate::windows::blackman_harris;
     use crate::windows::calculate_cutoff;
+    use crate::windows::dolph_chebyshev;
     use crate::windows::hann;
+    use crate::windows::kaiser_bessel_derived;
     use crate::windows::make_window;
     use crate::windows::WindowFunction;
     use approx::assert_abs_diff_eq;
@@ -194,6 +418,114 @@ mod tests {
         assert!(wnd[15] < 0.1);
     }
 
+    #[test]
+    fn test_kaiser_bessel_derived() {
+        for beta in [4.0, 12.0, 20.0] {
+            let wnd = kaiser_bessel_derived::<f64>(64, beta);
+            for n in 0..32 {
+                assert_abs_diff_eq!(
+                    wnd[n] * wnd[n] + wnd[n + 32] * wnd[n + 32],
+                    1.0,
+                    epsilon = 1.0e-12
+                );
+                assert_abs_diff_eq!(wnd[n], wnd[63 - n], epsilon = 1.0e-15);
+            }
+            assert!(wnd[..32].windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(wnd[31] > 0.9);
+            assert!(wnd[0] < 0.1);
+        }
+    }
+
+    #[test]
+    fn compare_windows() {
+        let kbd = |beta| WindowFunction::KaiserBesselDerived { beta };
+        assert_eq!(kbd(12.0), kbd(12.0));
+        assert_eq!(kbd(f64::NAN), kbd(f64::NAN));
+        assert_ne!(kbd(12.0), kbd(13.0));
+        assert_ne!(
+            kbd(12.0),
+            WindowFunction::DolphChebyshev {
+                attenuation_db: 12.0
+            }
+        );
+        assert_eq!(WindowFunction::Hann2, WindowFunction::Hann2);
+        assert_ne!(WindowFunction::Hann, WindowFunction::Hann2);
+    }
+
+    /// Get the highest sidelobe of a window relative to the main lobe, in dB.
+    fn sidelobe_level(window: &[f64]) -> f64 {
+        let npoints = window.len();
+        let bins = 16 * npoints;
+        let spectrum = (0..bins / 2)
+            .map(|bin| {
+                let (re, im) = window
+                    .iter()
+                    .enumerate()
+                    .fold((0.0, 0.0), |(re, im), (n, w)| {
+                        let phase = 2.0 * std::f64::consts::PI * (bin * n) as f64 / bins as f64;
+                        (re + w * phase.cos(), im - w * phase.sin())
+                    });
+                (re * re + im * im).sqrt()
+            })
+            .collect::<Vec<f64>>();
+        let first_null = spectrum
+            .windows(2)
+            .position(|pair| pair[1] > pair[0])
+            .unwrap();
+        let sidelobe = spectrum[first_null..].iter().cloned().fold(0.0, f64::max);
+        20.0 * (sidelobe / spectrum[0]).log10()
+    }
+
+    #[test]
+    fn test_dolph_chebyshev() {
+        for attenuation in [60.0, 100.0, 140.0] {
+            let wnd = dolph_chebyshev::<f64>(128, attenuation);
+            assert_abs_diff_eq!(wnd[64], 1.0, epsilon = 1.0e-12);
+            for n in 1..64 {
+                assert_abs_diff_eq!(wnd[64 - n], wnd[64 + n], epsilon = 1.0e-12);
+            }
+            // The window is periodic, adding the first point again gives the symmetric window.
+            let mut symmetric = wnd.clone();
+            symmetric.push(wnd[0]);
+            assert_abs_diff_eq!(sidelobe_level(&symmetric), -attenuation, epsilon = 0.1);
+        }
+        // A long window is interpolated, and keeps the sidelobe level approximately.
+        let wnd = dolph_chebyshev::<f64>(1024, 100.0);
+        assert_abs_diff_eq!(wnd[512], 1.0, epsilon = 1.0e-12);
+        let mut symmetric = wnd.clone();
+        symmetric.push(wnd[0]);
+        assert_abs_diff_eq!(sidelobe_level(&symmetric), -100.0, epsilon = 5.0);
+    }
+
+    #[test]
+    fn test_cutoff_kaiser_bessel_derived() {
+        let narrow =
+            calculate_cutoff::<f64>(128, WindowFunction::KaiserBesselDerived { beta: 4.0 });
+        let wide = calculate_cutoff::<f64>(128, WindowFunction::KaiserBesselDerived { beta: 20.0 });
+        let long = calculate_cutoff::<f64>(256, WindowFunction::KaiserBesselDerived { beta: 20.0 });
+        assert!(narrow > wide);
+        assert!(long > wide);
+        assert!(wide > 0.8 && narrow < 1.0);
+    }
+
+    #[test]
+    fn test_cutoff_dolph_chebyshev() {
+        let low = calculate_cutoff::<f64>(
+            128,
+            WindowFunction::DolphChebyshev {
+                attenuation_db: 80.0,
+            },
+        );
+        let high = calculate_cutoff::<f64>(
+            128,
+            WindowFunction::DolphChebyshev {
+                attenuation_db: 160.0,
+            },
+        );
+        assert!(low > high);
+        assert!(high > 0.8 && low < 1.0);
+    }
+
     #[test]
     fn test_cutoff() {
         let cutoff = calculate_cutoff::<f64>(128, WindowFunction::Blackman);
@@ -0,0 +1,118 @@
+//! Checks that operations intended for realtime use don't allocate.
+//! This uses a counting global allocator, and is therefore kept in a separate test binary.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use rubato::{
+    FastFixedIn, FastFixedOut, PolynomialDegree, Resampler, SincFixedIn, SincFixedOut,
+    SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|count| count.get())
+}
+
+fn assert_ratio_update_allocation_free<R: Resampler<f64>>(resampler: &mut R) {
+    let before = allocations();
+    for n in 0..1000 {
+        let rel_ratio = 1.0 + 0.0001 * (n % 100) as f64;
+        resampler
+            .set_resample_ratio_relative(rel_ratio, n % 2 == 0)
+            .unwrap();
+        resampler
+            .set_resample_ratio(1.0 / rel_ratio, false)
+            .unwrap();
+    }
+    assert_eq!(allocations(), before);
+}
+
+fn params() -> SincInterpolationParameters {
+    SincInterpolationParameters {
+        sinc_len: 64,
+        f_cutoff: 0.95,
+        interpolation: SincInterpolationType::Cubic,
+        oversampling_factor: 16,
+        window: WindowFunction::BlackmanHarris2,
+    }
+}
+
+#[test]
+fn ratio_update_sinc() {
+    let mut resampler = SincFixedIn::<f64>::new(1.0, 1.1, params(), 1024, 2).unwrap();
+    assert_ratio_update_allocation_free(&mut resampler);
+    let mut resampler = SincFixedOut::<f64>::new(1.0, 1.1, params(), 1024, 2).unwrap();
+    assert_ratio_update_allocation_free(&mut resampler);
+}
+
+#[test]
+fn ratio_update_fast() {
+    let mut resampler =
+        FastFixedIn::<f64>::new(1.0, 1.1, PolynomialDegree::Cubic, 1024, 2).unwrap();
+    assert_ratio_update_allocation_free(&mut resampler);
+    let mut resampler =
+        FastFixedOut::<f64>::new(1.0, 1.1, PolynomialDegree::Cubic, 1024, 2).unwrap();
+    assert_ratio_update_allocation_free(&mut resampler);
+}
+
+fn assert_channel_change_allocation_free<R: Resampler<f64>>(
+    resampler: &mut R,
+    set_nbr_channels: impl Fn(&mut R, usize),
+) {
+    let waves_in = vec![vec![0.1f64; resampler.input_frames_max()]; 8];
+    let mut waves_out = vec![vec![0.0f64; resampler.output_frames_max()]; 8];
+    let before = allocations();
+    for nbr_channels in 2..=8 {
+        set_nbr_channels(resampler, nbr_channels);
+        resampler
+            .process_into_buffer(
+                &waves_in[..nbr_channels],
+                &mut waves_out[..nbr_channels],
+                None,
+            )
+            .unwrap();
+    }
+    assert_eq!(allocations(), before);
+}
+
+#[test]
+fn channel_change_sinc() {
+    let mut resampler = SincFixedIn::<f64>::new(1.1, 1.1, params(), 1024, 2).unwrap();
+    resampler.set_max_channels(8);
+    assert_channel_change_allocation_free(&mut resampler, |r, n| r.set_nbr_channels(n).unwrap());
+    let mut resampler = SincFixedOut::<f64>::new(1.1, 1.1, params(), 1024, 2).unwrap();
+    resampler.set_max_channels(8);
+    assert_channel_change_allocation_free(&mut resampler, |r, n| r.set_nbr_channels(n).unwrap());
+}
+
+#[test]
+fn channel_change_fast() {
+    let mut resampler =
+        FastFixedIn::<f64>::new(1.1, 1.1, PolynomialDegree::Cubic, 1024, 2).unwrap();
+    resampler.set_max_channels(8);
+    assert_channel_change_allocation_free(&mut resampler, |r, n| r.set_nbr_channels(n).unwrap());
+    let mut resampler =
+        FastFixedOut::<f64>::new(1.1, 1.1, PolynomialDegree::Cubic, 1024, 2).unwrap();
+    resampler.set_max_channels(8);
+    assert_channel_change_allocation_free(&mut resampler, |r, n| r.set_nbr_channels(n).unwrap());
+}